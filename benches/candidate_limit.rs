@@ -0,0 +1,34 @@
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+mod common;
+
+//Short searches evaluating all insertion options of a part, or only the most promising ones,
+//with the options evaluated sequentially and in parallel.
+
+fn candidate_limit(c: &mut Criterion) {
+    let instance = Arc::new(common::instance());
+
+    let mut group = c.benchmark_group("candidate_limit");
+    group.sample_size(10);
+    for (evaluation, parallel_evaluation_threshold) in [("sequential", None), ("parallel", Some(50))] {
+        for max_candidates_per_step in [None, Some(5), Some(10)] {
+            let name = match max_candidates_per_step {
+                Some(max_candidates) => format!("{}_max_{}", evaluation, max_candidates),
+                None => format!("{}_unlimited", evaluation),
+            };
+            group.bench_function(name, |b| {
+                let config = common::config_with(serde_json::json!({
+                    "maxCandidatesPerStep": max_candidates_per_step,
+                    "parallelEvaluationThreshold": parallel_evaluation_threshold,
+                }));
+                b.iter(|| common::search(&instance, &config))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, candidate_limit);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+//every benchmark only uses some of the fixtures
+#![allow(dead_code)]
+
+use std::sync::Arc;
+use std::sync::mpsc::channel;
+
+use gdrr_2bp::core::empty_node_order::EmptyNodeOrder;
+use gdrr_2bp::core::entities::layout::Layout;
+use gdrr_2bp::core::entities::parttype::PartType;
+use gdrr_2bp::core::entities::sheettype::SheetType;
+use gdrr_2bp::core::kerf::Kerf;
+use gdrr_2bp::core::leftover_valuator;
+use gdrr_2bp::core::orientation::Orientation;
+use gdrr_2bp::optimization::config::Config;
+use gdrr_2bp::optimization::gdrr::GDRR;
+use gdrr_2bp::optimization::instance::Instance;
+use gdrr_2bp::optimization::search_stats::SearchStats;
+use gdrr_2bp::optimization::sol_collectors::local_sol_collector::LocalSolCollector;
+
+//Fixtures shared by the benchmarks
+
+/// Instance with two 1000x1000 sheets and 100 copies of four parttypes, also sets the valuation power as the search would.
+/// The parts fit in a few hundred positions of a sheet, so there are many insertion options per part.
+pub fn instance() -> Instance {
+    leftover_valuator::set_power(2.0);
+    let parts = [(50, 30), (70, 45), (25, 25), (120, 60)].iter().enumerate()
+        .map(|(id, &(w, h))| (PartType::new(id, w, h, None, None, 0.0, Kerf::default()), 100))
+        .collect();
+    let sheets = vec![(SheetType::new(0, 1000, 1000, 1000 * 1000, None, u8::MAX, Kerf::default()), 2)];
+    Instance::new(parts, sheets, 0, false)
+}
+
+/// Layout on the sheet of the instance with 40 copies of every parttype (160 parts), with its cost cached
+pub fn filled_layout(instance: &Instance) -> Layout<'_> {
+    let mut layout = Layout::new(0, instance.get_sheettype(0), Orientation::Horizontal, EmptyNodeOrder::default());
+    for (parttype, _) in instance.parts() {
+        layout.fill_with(parttype, 40, instance);
+    }
+    layout.cost(true);
+    layout
+}
+
+/// Config of a short single threaded search, with the fields of `overrides` (camelCase, as in a config file) replaced
+pub fn config_with(overrides: serde_json::Value) -> Config {
+    let mut config = serde_json::json!({
+        "maxRRIterations": 50,
+        "nThreads": 1,
+        "rotationAllowed": true,
+        "avgNodesRemoved": 6,
+        "blinkRate": 0.01,
+        "leftoverValuationPower": 2,
+        "historyLength": 100,
+        "sheetValuationMode": "area"
+    });
+    config.as_object_mut().unwrap().extend(overrides.as_object().unwrap().clone());
+    serde_json::from_value(config).unwrap()
+}
+
+/// Runs the search on the calling thread, without a global solution collector
+pub fn search(instance: &Arc<Instance>, config: &Config) -> SearchStats {
+    let (_tx_sync, rx_sync) = channel();
+    let (tx_solution_report, _rx_solution_report) = channel();
+    let local_sol_collector = LocalSolCollector::new(instance.clone(), rx_sync, tx_solution_report, gdrr_2bp::COST_COMPARATOR);
+    let mut gdrr = GDRR::new(instance, config, local_sol_collector);
+    gdrr.lahc()
+}
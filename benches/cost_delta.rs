@@ -0,0 +1,47 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+
+use gdrr_2bp::core::cost::Cost;
+use gdrr_2bp::core::insertion::insertion_blueprint::InsertionBlueprint;
+use gdrr_2bp::core::layout_index::LayoutIndex;
+use gdrr_2bp::core::rotation::Rotation;
+use gdrr_2bp::optimization::rr::cache_updates::IOCUpdates;
+
+mod common;
+
+//Cost of a layout after an insertion: recalculated from all nodes, or the cached cost updated with the delta of the insertion
+
+fn cost_after_insertion(c: &mut Criterion) {
+    let instance = common::instance();
+    let layout = common::filled_layout(&instance);
+
+    //insertion of a part in the smallest empty node it fits in
+    let parttype = instance.get_parttype(2);
+    let node_i = *layout.sorted_empty_nodes().iter().rev()
+        .find(|&&n| layout.nodes()[n].insertion_possible(parttype, Rotation::Default))
+        .unwrap();
+    let replacements = layout.nodes()[node_i].generate_insertion_node_blueprints(parttype, Rotation::Default, u8::MAX, vec![]).remove(0);
+    let layout_index = LayoutIndex::Empty(0);
+    let blueprint = InsertionBlueprint::new(layout_index, node_i, replacements, parttype, Cost::empty(), layout.nodes()[node_i].level());
+
+    let mut group = c.benchmark_group("cost_after_insertion");
+    group.bench_function("full_recalculation", |b| b.iter_batched(
+        || layout.clone(),
+        |mut layout| {
+            layout.implement_insertion_blueprint(&blueprint, &instance, &mut IOCUpdates::new(layout_index)).unwrap();
+            layout.cost(false)
+        },
+        BatchSize::SmallInput,
+    ));
+    group.bench_function("cost_delta", |b| b.iter_batched(
+        || layout.clone(),
+        |mut layout| {
+            layout.apply_and_cost_delta(&blueprint, &instance, &mut IOCUpdates::new(layout_index));
+            layout.cost(false)
+        },
+        BatchSize::SmallInput,
+    ));
+    group.finish();
+}
+
+criterion_group!(benches, cost_after_insertion);
+criterion_main!(benches);
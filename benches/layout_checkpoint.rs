@@ -0,0 +1,42 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use generational_arena::Index;
+
+use gdrr_2bp::core::entities::layout::Layout;
+
+mod common;
+
+//Trial removal of a part which is rejected afterwards: undone with a checkpoint, or by restoring a full clone of the layout
+
+/// Some part node of the layout, the removed part is registered again under a new index by restore
+fn part_node(layout: &Layout) -> Index {
+    layout.nodes().iter().find(|(_, n)| n.is_part()).map(|(i, _)| i).unwrap()
+}
+
+fn trial_and_revert(c: &mut Criterion) {
+    let instance = common::instance();
+    let layout = common::filled_layout(&instance);
+
+    let mut group = c.benchmark_group("trial_and_revert");
+    group.bench_function("checkpoint", |b| {
+        let mut layout = layout.clone();
+        b.iter(|| {
+            let node = part_node(&layout);
+            let checkpoint = layout.checkpoint();
+            layout.remove_node(node);
+            layout.restore(checkpoint);
+        })
+    });
+    group.bench_function("full_clone", |b| {
+        let mut layout = layout.clone();
+        b.iter(|| {
+            let node = part_node(&layout);
+            let backup = layout.clone();
+            layout.remove_node(node);
+            layout = backup;
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, trial_and_revert);
+criterion_main!(benches);
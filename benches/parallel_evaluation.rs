@@ -0,0 +1,29 @@
+use std::sync::Arc;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+mod common;
+
+//Short searches evaluating the insertion options sequentially, or in parallel once their number exceeds the threshold.
+//The threshold at which the parallel evaluation starts to pay off is the break-even point.
+
+fn parallel_evaluation(c: &mut Criterion) {
+    let instance = Arc::new(common::instance());
+
+    let mut group = c.benchmark_group("parallel_evaluation");
+    group.sample_size(10);
+    group.bench_function("sequential", |b| {
+        let config = common::config_with(serde_json::json!({}));
+        b.iter(|| common::search(&instance, &config))
+    });
+    for threshold in [0, 50, 200, 800] {
+        group.bench_function(format!("threshold_{}", threshold), |b| {
+            let config = common::config_with(serde_json::json!({"parallelEvaluationThreshold": threshold}));
+            b.iter(|| common::search(&instance, &config))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, parallel_evaluation);
+criterion_main!(benches);
@@ -7,6 +7,8 @@ pub struct Cost {
     pub leftover_value: f32,
     pub part_area_excluded: u64,
     pub part_area_included: u64,
+    /// Highest priority of the parts which are not included, None if all parts are included or the cost is not of a solution
+    pub excluded_priority: Option<u32>,
 }
 
 
@@ -17,11 +19,12 @@ impl Cost {
             leftover_value: 0.0,
             part_area_excluded: 0,
             part_area_included: 0,
+            excluded_priority: None,
         }
     }
 
     pub fn new(material_cost: u64, leftover_value: f32, part_area_excluded: u64, part_area_included: u64) -> Self {
-        Self { material_cost, leftover_value, part_area_excluded, part_area_included }
+        Self { material_cost, leftover_value, part_area_excluded, part_area_included, excluded_priority: None }
     }
 
     pub fn add(mut self, other: &Cost) -> Self {
@@ -29,6 +32,7 @@ impl Cost {
         self.leftover_value += other.leftover_value;
         self.part_area_excluded += other.part_area_excluded;
         self.part_area_included += other.part_area_included;
+        self.excluded_priority = self.excluded_priority.max(other.excluded_priority);
         self
     }
 
@@ -64,6 +68,7 @@ impl Add for Cost {
             leftover_value: self.leftover_value + rhs.leftover_value,
             part_area_excluded: self.part_area_excluded + rhs.part_area_excluded,
             part_area_included: self.part_area_included + rhs.part_area_included,
+            excluded_priority: self.excluded_priority.max(rhs.excluded_priority),
         }
     }
 }
@@ -77,6 +82,7 @@ impl Sub for Cost {
             leftover_value: self.leftover_value - rhs.leftover_value,
             part_area_excluded: self.part_area_excluded - rhs.part_area_excluded,
             part_area_included: self.part_area_included - rhs.part_area_included,
+            excluded_priority: self.excluded_priority,
         }
     }
 }
@@ -0,0 +1,34 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+/// Requirement for a part to be placed near an edge of the sheet, e.g. for access during finishing operations.
+/// The distance is measured from the edge of the sheet (including its margins) to the nearest side of the part.
+/// Only the top and left edge are supported: layouts are built from the top-left corner,
+/// so later modifications of a layout can only move the parts already placed towards these edges, never away from them.
+#[derive(Serialize, Deserialize, Encode, Decode, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct EdgeAffinity {
+    pub edge: Edge,
+    pub max_distance: u64,
+}
+
+#[derive(Serialize, Deserialize, Encode, Decode, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Edge {
+    Top,
+    Left,
+}
+
+impl EdgeAffinity {
+    pub fn new(edge: Edge, max_distance: u64) -> Self {
+        Self { edge, max_distance }
+    }
+
+    /// Whether a part at this position, relative to the top-left corner of the sheet, is near enough to the edge
+    pub fn allows(&self, x: u64, y: u64) -> bool {
+        match self.edge {
+            Edge::Top => y <= self.max_distance,
+            Edge::Left => x <= self.max_distance,
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::entities::node::Node;
+
+/// Defines the order in which the empty nodes of a Layout are kept sorted (descending).
+/// Every variant defines a total preorder, so binary search on the sorted empty nodes remains valid.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum EmptyNodeOrder {
+    /// Descending area
+    #[default]
+    Area,
+    /// Descending largest dimension, ties broken by descending area
+    MaxDimension,
+    /// Descending aspect ratio (long strips first), ties broken by descending area.
+    /// Nodes without area come last.
+    AspectRatio,
+}
+
+impl EmptyNodeOrder {
+    pub fn compare(&self, a: &Node, b: &Node) -> Ordering {
+        match self {
+            EmptyNodeOrder::Area => a.area().cmp(&b.area()),
+            EmptyNodeOrder::MaxDimension => {
+                let max_dim = |n: &Node| u64::max(n.width(), n.height());
+                max_dim(a).cmp(&max_dim(b)).then(a.area().cmp(&b.area()))
+            }
+            EmptyNodeOrder::AspectRatio => {
+                match (a.area() > 0, b.area() > 0) {
+                    (true, true) => {
+                        //compare max_a / min_a with max_b / min_b without floating point division
+                        let (max_a, min_a) = (u64::max(a.width(), a.height()) as u128, u64::min(a.width(), a.height()) as u128);
+                        let (max_b, min_b) = (u64::max(b.width(), b.height()) as u128, u64::min(b.width(), b.height()) as u128);
+                        (max_a * min_b).cmp(&(max_b * min_a)).then(a.area().cmp(&b.area()))
+                    }
+                    (a_has_area, b_has_area) => a_has_area.cmp(&b_has_area)
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::core::insertion::node_blueprint::NodeBlueprint;
+use crate::core::orientation::Orientation;
+
+/// Fixed internal structure of a composite part (e.g. a door with an internal groove layout), in the default rotation of the part.
+/// The part is placed as a single unit, while the structure defines how its region is subdivided when it is cut.
+/// Children are stacked in the y-direction for horizontal cuts and in the x-direction for vertical cuts, leaves have no children.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CompositeStructure {
+    width: u64,
+    height: u64,
+    cut_orient: Orientation,
+    children: Vec<CompositeStructure>,
+}
+
+impl CompositeStructure {
+    pub fn new(width: u64, height: u64, cut_orient: Orientation, children: Vec<CompositeStructure>) -> Self {
+        Self { width, height, cut_orient, children }
+    }
+
+    /// Whether the children exactly subdivide every (sub)region, alternating the cut orientation at every level
+    pub fn is_valid(&self) -> bool {
+        if self.children.is_empty() {
+            return true;
+        }
+        let children_fit = match self.cut_orient {
+            Orientation::Horizontal => {
+                self.children.iter().all(|c| c.width == self.width) &&
+                    self.children.iter().map(|c| c.height).sum::<u64>() == self.height
+            }
+            Orientation::Vertical => {
+                self.children.iter().all(|c| c.height == self.height) &&
+                    self.children.iter().map(|c| c.width).sum::<u64>() == self.width
+            }
+        };
+        let orientations_alternate = self.children.iter()
+            .all(|c| c.children.is_empty() || c.cut_orient == self.cut_orient.rotate());
+
+        children_fit && orientations_alternate && self.children.iter().all(|c| c.is_valid())
+    }
+
+    /// The structure of the part rotated by 90 degrees
+    pub fn rotated(&self) -> Self {
+        Self {
+            width: self.height,
+            height: self.width,
+            cut_orient: self.cut_orient.rotate(),
+            children: self.children.iter().map(|c| c.rotated()).collect(),
+        }
+    }
+
+    /// Expands the structure into the children of a (part) node with the given dimensions and cut orientation.
+    /// If the node is larger than the structure (because of kerf), the difference is added to the last child in every direction.
+    /// An intermediate node is added when the cut orientation of the structure does not match the one of the node.
+    pub fn node_blueprints(&self, width: u64, height: u64, next_cut_orient: Orientation) -> Vec<NodeBlueprint> {
+        if self.children.is_empty() {
+            return vec![];
+        }
+        if self.cut_orient != next_cut_orient {
+            let mut intermediate = NodeBlueprint::new(width, height, None, next_cut_orient.rotate());
+            for child in self.node_blueprints(width, height, next_cut_orient.rotate()) {
+                intermediate.add_child(child);
+            }
+            return vec![intermediate];
+        }
+
+        let last = self.children.len() - 1;
+        self.children.iter().enumerate().map(|(i, child)| {
+            let (child_width, child_height) = match (self.cut_orient, i == last) {
+                (Orientation::Horizontal, false) => (width, child.height),
+                (Orientation::Horizontal, true) => (width, child.height + height - self.height),
+                (Orientation::Vertical, false) => (child.width, height),
+                (Orientation::Vertical, true) => (child.width + width - self.width, height),
+            };
+            let mut child_bp = NodeBlueprint::new(child_width, child_height, None, self.cut_orient.rotate());
+            for grandchild in child.node_blueprints(child_width, child_height, self.cut_orient.rotate()) {
+                child_bp.add_child(grandchild);
+            }
+            child_bp
+        }).collect()
+    }
+
+    pub fn width(&self) -> u64 {
+        self.width
+    }
+
+    pub fn height(&self) -> u64 {
+        self.height
+    }
+
+    pub fn cut_orient(&self) -> Orientation {
+        self.cut_orient
+    }
+
+    pub fn children(&self) -> &Vec<CompositeStructure> {
+        &self.children
+    }
+}
@@ -0,0 +1,23 @@
+use crate::core::orientation::Orientation;
+
+/// A single straight guillotine cut through a node of a layout, starting at (x, y) relative to the top-left corner of the sheet.
+/// Horizontal cuts run in the x-direction, vertical cuts in the y-direction.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cut {
+    pub x: u64,
+    pub y: u64,
+    pub length: u64,
+    pub orientation: Orientation,
+    /// Level of the node which is cut
+    pub level: u8,
+}
+
+/// A cut as a straight segment from start to end (x, y), relative to an origin corner of the sheet, see Layout::cut_paths
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CutPath {
+    pub start: (u64, u64),
+    pub end: (u64, u64),
+    /// Level of the node which is cut
+    pub level: u8,
+}
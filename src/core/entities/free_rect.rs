@@ -0,0 +1,76 @@
+use itertools::Itertools;
+
+/// Empty rectangle in a layout, at (x, y) relative to the top-left corner of the sheet.
+/// Unlike an empty node, a free rectangle can span multiple nodes of the guillotine tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FreeRect {
+    pub x: u64,
+    pub y: u64,
+    pub width: u64,
+    pub height: u64,
+}
+
+impl FreeRect {
+    pub fn new(x: u64, y: u64, width: u64, height: u64) -> Self {
+        Self { x, y, width, height }
+    }
+
+    pub fn area(&self) -> u64 {
+        self.width * self.height
+    }
+
+    pub fn overlaps(&self, other: &FreeRect) -> bool {
+        self.x < other.x + other.width && other.x < self.x + self.width &&
+            self.y < other.y + other.height && other.y < self.y + self.height
+    }
+
+    pub fn contains(&self, other: &FreeRect) -> bool {
+        self.x <= other.x && other.x + other.width <= self.x + self.width &&
+            self.y <= other.y && other.y + other.height <= self.y + self.height
+    }
+
+    /// The parts of this rectangle not covered by the other one: the maximal (overlapping) strips left, right, above and below of it
+    pub fn subtract(&self, other: &FreeRect) -> Vec<FreeRect> {
+        if !self.overlaps(other) {
+            return vec![*self];
+        }
+        let (right, bottom) = (self.x + self.width, self.y + self.height);
+        let (other_right, other_bottom) = (other.x + other.width, other.y + other.height);
+        [
+            FreeRect::new(self.x, self.y, other.x.saturating_sub(self.x), self.height),
+            FreeRect::new(other_right, self.y, right.saturating_sub(other_right), self.height),
+            FreeRect::new(self.x, self.y, self.width, other.y.saturating_sub(self.y)),
+            FreeRect::new(self.x, other_bottom, self.width, bottom.saturating_sub(other_bottom)),
+        ].into_iter().filter(|r| r.area() > 0).collect()
+    }
+}
+
+/// Whether all rectangles (which do not overlap) can be separated from each other by a sequence of guillotine cuts
+pub fn guillotine_separable(rects: &[FreeRect]) -> bool {
+    if rects.len() <= 1 {
+        return true;
+    }
+    //a vertical cut at the right edge of a rectangle which does not cross any other rectangle and leaves rectangles on both sides
+    let vertical_cut = rects.iter().map(|r| r.x + r.width)
+        .find(|&x| rects.iter().all(|r| r.x >= x || r.x + r.width <= x) && rects.iter().any(|r| r.x >= x));
+    if let Some(x) = vertical_cut {
+        let (left, right): (Vec<FreeRect>, Vec<FreeRect>) = rects.iter().partition(|r| r.x < x);
+        return guillotine_separable(&left) && guillotine_separable(&right);
+    }
+    let horizontal_cut = rects.iter().map(|r| r.y + r.height)
+        .find(|&y| rects.iter().all(|r| r.y >= y || r.y + r.height <= y) && rects.iter().any(|r| r.y >= y));
+    if let Some(y) = horizontal_cut {
+        let (top, bottom): (Vec<FreeRect>, Vec<FreeRect>) = rects.iter().partition(|r| r.y < y);
+        return guillotine_separable(&top) && guillotine_separable(&bottom);
+    }
+    false
+}
+
+/// Removes the rectangles contained in another one, keeping one of every set of identical rectangles
+pub fn remove_contained(rects: Vec<FreeRect>) -> Vec<FreeRect> {
+    let rects = rects.into_iter().unique().collect_vec();
+    rects.iter()
+        .filter(|r| !rects.iter().any(|other| other != *r && other.contains(r)))
+        .copied()
+        .collect()
+}
@@ -1,14 +1,29 @@
+use std::cmp::{Ordering, Reverse};
+use std::fmt;
+
 use generational_arena::{Arena, Index};
 use itertools::Itertools;
 use crate::core::{cost::Cost, insertion::insertion_blueprint::InsertionBlueprint};
+use crate::core::insertion::insertion_blueprint::InsertionBlueprintError;
+use crate::core::empty_node_order::EmptyNodeOrder;
+use crate::core::kerf::Kerf;
 use crate::core::entities::node::Node;
 use crate::core::insertion::node_blueprint::NodeBlueprint;
+use crate::core::layout_index::LayoutIndex;
+use crate::core::leftover_valuator;
 use crate::core::orientation::Orientation;
+use crate::core::origin_corner::OriginCorner;
+use crate::core::reading_order::ReadingOrder;
+use crate::core::rotation::Rotation;
+use crate::core::size::Size;
 use crate::optimization::instance::Instance;
 use crate::optimization::rr::cache_updates::IOCUpdates;
 use crate::util::assertions;
 
-use super::{parttype::PartType, sheettype::SheetType};
+use super::{cut::{Cut, CutPath}, free_rect::{self, FreeRect}, parttype::PartType, placed_part::PlacedPart, sheettype::SheetType, spatial_index::SpatialIndex};
+
+/// Objective replacing the built-in cost of a layout, see Instance::with_cost_function
+pub type CostFunction = fn(&Layout) -> Cost;
 
 #[derive(Debug, Clone)]
 pub struct Layout<'a> {
@@ -18,16 +33,30 @@ pub struct Layout<'a> {
     top_node_i: Index,
     cached_cost: Option<Cost>,
     cached_usage: Option<f64>,
-    sorted_empty_nodes: Vec<Index>, //sorted by descending area
+    sorted_empty_nodes: Vec<Index>, //sorted descending according to empty_node_order
+    empty_node_order: EmptyNodeOrder,
+    undo_trail: Option<Vec<TrailEntry<'a>>>, //only recorded while a checkpoint is open
+    spatial_index: Option<SpatialIndex>, //only maintained when enabled
+    position_cache: bool, //whether the nodes contain their positions, see enable_position_cache
+    cost_function: Option<CostFunction>,
+    clustering_weight: f32,
 }
 
 impl<'a> Layout<'a> {
-    pub fn new(id: usize, sheettype: &'a SheetType, first_cut_orientation: Orientation) -> Self {
+    pub fn new(id: usize, sheettype: &'a SheetType, first_cut_orientation: Orientation, empty_node_order: EmptyNodeOrder) -> Self {
+        let mut layout = Layout::with_top_node(id, sheettype, first_cut_orientation, empty_node_order);
+        layout.register_initial_nodes();
+        layout
+    }
+
+    /// Layout consisting of only the top node
+    fn with_top_node(id: usize, sheettype: &'a SheetType, first_cut_orientation: Orientation, empty_node_order: EmptyNodeOrder) -> Self {
         let mut nodes = Arena::new();
-        let top_node = Node::new(0, sheettype.width(), sheettype.height(), first_cut_orientation, None);
+        let layout_size = sheettype.layout_size();
+        let top_node = Node::new(0, layout_size.width(), layout_size.height(), first_cut_orientation, None);
         let top_node_i = nodes.insert(top_node);
 
-        let mut layout = Self {
+        Self {
             id,
             sheettype,
             nodes,
@@ -35,15 +64,104 @@ impl<'a> Layout<'a> {
             cached_cost: None,
             cached_usage: None,
             sorted_empty_nodes: vec![],
-        };
+            empty_node_order,
+            undo_trail: None,
+            spatial_index: None,
+            position_cache: false,
+            cost_function: None,
+            clustering_weight: 0.0,
+        }
+    }
 
-        //The top node cannot be modified, so we register a placeholder node to be able to insert parts
-        let placeholder_node = Node::new(1, sheettype.width(), sheettype.height(), first_cut_orientation.rotate(), None);
-        layout.register_node(placeholder_node, top_node_i, true);
+    /// Registers the children of the top node of a layout without parts
+    fn register_initial_nodes(&mut self) {
+        let layout_size = self.sheettype.layout_size();
+        let (width, height) = (layout_size.width(), layout_size.height());
+        let first_cut_orientation = self.nodes[self.top_node_i].next_cut_orient();
+        match self.sheettype.inset_unusable_regions().is_empty() {
+            true => {
+                //The top node cannot be modified, so we register a placeholder node to be able to insert parts
+                let placeholder_node = Node::new(1, width, height, first_cut_orientation.rotate(), None);
+                self.register_node(placeholder_node, self.top_node_i, true);
+            }
+            false => {
+                //The unusable regions become unusable nodes, separated from the rest of the sheet by the first stages of the layout.
+                //Regions along the far edges of the region inside the margins are enlarged by the kerf, as the region is.
+                let kerf = self.sheettype.kerf();
+                let inset = kerf.deflate(width, height);
+                let unusable_regions = self.sheettype.inset_unusable_regions().iter()
+                    .map(|&(x, y, w, h)| {
+                        let w = if x + w == inset.width() { w + kerf.vertical } else { w };
+                        let h = if y + h == inset.height() { h + kerf.horizontal } else { h };
+                        (x, y, w, h)
+                    })
+                    .collect_vec();
+                let top_blueprint = guillotine_decomposition((0, 0, width, height), &unusable_regions, first_cut_orientation, &mut |region, unusable, orientation| {
+                    match unusable {
+                        true => NodeBlueprint::new_unusable(region.2, region.3, orientation),
+                        false => NodeBlueprint::new(region.2, region.3, None, orientation),
+                    }
+                }).expect("unusable regions cannot be separated by guillotine cuts");
+                for blueprint in top_blueprint.children() {
+                    self.implement_structure_blueprint(self.top_node_i, blueprint);
+                }
+            }
+        }
+    }
 
+    /// Layout with the cutting pattern of the blueprint of a top node (e.g. from NodeBlueprint::from_node), with the parts taken from the instance
+    pub(crate) fn from_blueprint(id: usize, sheettype: &'a SheetType, top_blueprint: &NodeBlueprint, empty_node_order: EmptyNodeOrder, instance: &'a Instance) -> Self {
+        let mut layout = Layout::with_top_node(id, sheettype, top_blueprint.next_cut_orient(), empty_node_order);
+        match top_blueprint.children().is_empty() {
+            true => layout.register_initial_nodes(),
+            false => {
+                let top_node_i = layout.top_node_i;
+                for child in top_blueprint.children() {
+                    layout.implement_node_blueprint(top_node_i, child, instance, &mut vec![]);
+                }
+                debug_assert!(assertions::children_nodes_fit(&top_node_i, &layout.nodes));
+            }
+        }
         layout
     }
 
+    /// Layout reproducing the placements of parts on the sheet (e.g. from another nester, or Layout::placed_parts),
+    /// with their positions relative to the top-left corner of the sheet (including the margins) and their sizes including the kerf.
+    /// The guillotine tree is found by recursively cutting every region edge-to-edge along all part edges which no part straddles.
+    /// Fails if a placement does not match its parttype, lies outside the sheet or overlaps another one,
+    /// or if a region of the sheet cannot be separated by any edge-to-edge cut. The stage limit of the sheettype is not checked.
+    pub fn try_from_placements(id: usize, sheettype: &'a SheetType, placements: &[PlacedPart], empty_node_order: EmptyNodeOrder, instance: &'a Instance) -> Result<Layout<'a>, GuillotineError> {
+        let margins = sheettype.margins();
+        let layout_size = sheettype.layout_size();
+
+        //rectangles of the parts relative to the top node
+        let mut rects = vec![];
+        for (i, p) in placements.iter().enumerate() {
+            let parttype = instance.get_parttype(p.parttype_id);
+            let size = parttype.size_for(p.rotation);
+            if (p.width, p.height) != (size.width(), size.height()) || parttype.fixed_rotation().is_some_and(|r| r != p.rotation) {
+                return Err(GuillotineError::PartMismatch(i));
+            }
+            if p.x < margins.left || p.y < margins.top
+                || p.x - margins.left + p.width > layout_size.width() || p.y - margins.top + p.height > layout_size.height() {
+                return Err(GuillotineError::OutsideSheet(i));
+            }
+            rects.push((p.x - margins.left, p.y - margins.top, p.width, p.height));
+        }
+        let overlap = |a: &Rect, b: &Rect| a.0 < b.0 + b.2 && b.0 < a.0 + a.2 && a.1 < b.1 + b.3 && b.1 < a.1 + a.3;
+        if let Some((i, j)) = (0..rects.len()).tuple_combinations().find(|&(i, j)| overlap(&rects[i], &rects[j])) {
+            return Err(GuillotineError::Overlap(i, j));
+        }
+
+        let orientation = instance.forced_first_cut().or(sheettype.fixed_first_cut_orientation()).unwrap_or(Orientation::Horizontal);
+        let top_blueprint = guillotine_decomposition((0, 0, layout_size.width(), layout_size.height()), &rects, orientation, &mut |region, is_part, orientation| {
+            let parttype = is_part.then(|| instance.get_parttype(placements[rects.iter().position(|r| *r == region).unwrap()].parttype_id));
+            NodeBlueprint::new(region.2, region.3, parttype, orientation)
+        }).map_err(|(x, y, width, height)| GuillotineError::NonGuillotine { x: x + margins.left, y: y + margins.top, width, height })?;
+
+        Ok(Layout::from_blueprint(id, sheettype, &top_blueprint, empty_node_order, instance))
+    }
+
     pub fn clone_with_id(&self, id : usize) -> Self{
         Self {
             id,
@@ -51,31 +169,506 @@ impl<'a> Layout<'a> {
         }
     }
 
-    pub fn implement_insertion_blueprint(&mut self, blueprint: &InsertionBlueprint<'a>, instance: &'a Instance, updates: &mut IOCUpdates) {
+    /// Skeleton of the cutting pattern for reuse: a copy of the layout with the same tree, in which every part node is an empty node.
+    /// Adjacent empty nodes are not merged, so every subdivision of the original pattern is kept.
+    pub fn to_empty_template(&self) -> Layout<'a> {
+        let mut template = self.clone();
+        //modifications of the template cannot be undone by the checkpoints of the layout
+        template.undo_trail = None;
+        let part_nodes = template.nodes.iter().filter(|(_, n)| n.is_part()).map(|(i, _)| i).collect_vec();
+        for node_i in part_nodes {
+            template.nodes[node_i] = template.nodes[node_i].without_part();
+        }
+        let empty_node_order = template.empty_node_order;
+        template.sorted_empty_nodes = template.nodes.iter()
+            .filter(|(_, n)| n.is_empty())
+            .map(|(i, _)| i)
+            .sorted_by(|a, b| empty_node_order.compare(&template.nodes[*a], &template.nodes[*b]).reverse())
+            .collect_vec();
+        template.invalidate_caches();
+        if template.spatial_index.is_some() {
+            template.enable_spatial_index();
+        }
+
+        debug_assert!(assertions::node_arena_valid(&template.nodes, &template.top_node_i));
+        debug_assert!(assertions::cached_sorted_empty_nodes_correct(&template.nodes, &template.sorted_empty_nodes, template.empty_node_order));
+        template
+    }
+
+    /// Records the state of the layout, so that any modifications made afterwards can be undone with restore().
+    /// Instead of copying the node arena, the nodes registered and unregistered from now on are recorded in an undo trail,
+    /// which restore() replays in reverse. The checkpoint needs to be handed back to either restore() or commit(),
+    /// checkpoints can be nested as long as they are handed back in reverse order.
+    pub fn checkpoint(&mut self) -> LayoutCheckpoint {
+        let outermost = self.undo_trail.is_none();
+        let trail = self.undo_trail.get_or_insert_with(Vec::new);
+        LayoutCheckpoint {
+            layout_id: self.id,
+            outermost,
+            trail_len: trail.len(),
+            cached_cost: self.cached_cost.clone(),
+            cached_usage: self.cached_usage,
+        }
+    }
+
+    /// Keeps all modifications made since the checkpoint
+    pub fn commit(&mut self, checkpoint: LayoutCheckpoint) {
+        debug_assert!(checkpoint.layout_id == self.id, "checkpoint of layout {} committed into layout {}", checkpoint.layout_id, self.id);
+        //the outermost checkpoint stops the recording, an enclosing one can still undo the modifications
+        if checkpoint.outermost {
+            self.undo_trail = None;
+        }
+    }
+
+    /// Restores the layout to the state (nodes, caches and sorted empty nodes) of the checkpoint, by undoing the modifications recorded since.
+    /// The arena cannot hand out an index twice, so the nodes unregistered since the checkpoint are registered again under a new index.
+    /// All other nodes keep their index.
+    pub fn restore(&mut self, checkpoint: LayoutCheckpoint) {
+        debug_assert!(checkpoint.layout_id == self.id, "checkpoint of layout {} restored into layout {}", checkpoint.layout_id, self.id);
+        let mut trail = self.undo_trail.take().expect("no checkpoint is open");
+        //new indices of the nodes registered again
+        let mut new_indices: Vec<(Index, Index)> = vec![];
+        let current = |new_indices: &[(Index, Index)], index: Index| new_indices.iter().rev()
+            .find(|(old, _)| *old == index)
+            .map_or(index, |(_, new)| *new);
+
+        for entry in trail.drain(checkpoint.trail_len..).rev() {
+            match entry {
+                TrailEntry::Registered { index, is_empty } => {
+                    let index = current(&new_indices, index);
+                    let node = self.nodes.remove(index).expect("registered node does not exist");
+                    let parent = node.parent().expect("registered node has no parent");
+                    self.nodes[parent].remove_child(index);
+                    if is_empty {
+                        let rank = self.sorted_empty_nodes.iter().position(|&i| i == index).expect("empty node not found in sorted_empty_nodes");
+                        self.sorted_empty_nodes.remove(rank);
+                    }
+                }
+                TrailEntry::Unregistered { index, mut node, rank, empty_rank } => {
+                    //the descendants of the node were unregistered before it, so they are registered again after it
+                    debug_assert!(node.children().is_empty());
+                    let parent = node.parent().map(|p| current(&new_indices, p)).expect("unregistered node has no parent");
+                    node.set_parent(parent);
+                    let new_index = self.nodes.insert(node);
+                    new_indices.push((index, new_index));
+                    self.nodes[parent].insert_child(rank, new_index);
+                    if let Some(empty_rank) = empty_rank {
+                        self.sorted_empty_nodes.insert(empty_rank, new_index);
+                    }
+                }
+                TrailEntry::Flipped(indices) => {
+                    for index in indices {
+                        self.nodes[current(&new_indices, index)].flip_next_cut_orient();
+                    }
+                }
+            }
+        }
+        if !checkpoint.outermost {
+            self.undo_trail = Some(trail);
+        }
+
+        self.cached_cost = checkpoint.cached_cost;
+        self.cached_usage = checkpoint.cached_usage;
+        //the nodes registered again carry the positions (if any) they had when they were unregistered, and their siblings moved back
+        match self.position_cache {
+            true => self.enable_position_cache(),
+            false => self.disable_position_cache(),
+        }
+        if self.spatial_index.is_some() {
+            self.enable_spatial_index();
+        }
+
+        debug_assert!(assertions::node_arena_valid(&self.nodes, &self.top_node_i));
+        debug_assert!(assertions::cached_sorted_empty_nodes_correct(&self.nodes, &self.sorted_empty_nodes, self.empty_node_order));
+    }
+
+    pub fn implement_insertion_blueprint(&mut self, blueprint: &InsertionBlueprint<'a>, instance: &'a Instance, updates: &mut IOCUpdates) -> Result<(), InsertionBlueprintError> {
+        self.validate_insertion_blueprint(blueprint)?;
+        let original = *blueprint.original_node_index();
+
+        let all_created_nodes = self.implement_replacements(original, blueprint.replacements(), instance);
+        updates.add_removed(original);
+        updates.extend_new(all_created_nodes);
+        Ok(())
+    }
+
+    /// Checks whether the InsertionBlueprint can be implemented without corrupting the layout:
+    /// the replacements need to exactly tile the original node along the cut orientation of its parent.
+    /// Performed in all builds, the layout is not modified.
+    pub fn validate_insertion_blueprint(&self, blueprint: &InsertionBlueprint<'a>) -> Result<(), InsertionBlueprintError> {
+        let original = self.nodes.get(*blueprint.original_node_index())
+            .filter(|n| n.is_empty() && n.parent().is_some())
+            .ok_or(InsertionBlueprintError::InvalidOriginalNode)?;
+        let replacements = blueprint.replacements();
+
+        //The replacements become siblings of each other, so they are stacked in the cut orientation of the parent
+        let replacements_tile = match self.nodes[original.parent().unwrap()].next_cut_orient() {
+            Orientation::Horizontal => {
+                replacements.iter().all(|r| r.width() == original.width()) &&
+                    replacements.iter().map(|r| r.height()).sum::<u64>() == original.height()
+            }
+            Orientation::Vertical => {
+                replacements.iter().all(|r| r.height() == original.height()) &&
+                    replacements.iter().map(|r| r.width()).sum::<u64>() == original.width()
+            }
+        };
+        let valid = !replacements.is_empty() && replacements_tile &&
+            replacements.iter().all(|r| r.next_cut_orient() == original.next_cut_orient() && assertions::children_node_blueprints_fit(r));
+
+        match valid {
+            true => Ok(()),
+            false => Err(InsertionBlueprintError::ReplacementsDoNotTile)
+        }
+    }
+
+    /// Implements the InsertionBlueprint and returns the resulting change in cost of the layout.
+    /// The delta is computed from the removed and created nodes only, the cached cost of the layout is updated with it
+    /// instead of being recalculated entirely. An insertion only changes the leftover value, the other fields of the delta are zero.
+    /// A custom cost function cannot be evaluated per node, in which case the cost of the layout is recalculated instead.
+    /// The clustering penalty depends on the positions of all parts of a parttype, it is recalculated entirely if a clustering weight is set.
+    /// The blueprint needs to be valid, see validate_insertion_blueprint.
+    pub fn apply_and_cost_delta(&mut self, blueprint: &InsertionBlueprint<'a>, instance: &'a Instance, updates: &mut IOCUpdates) -> Cost {
+        debug_assert!(self.validate_insertion_blueprint(blueprint).is_ok());
         let original = *blueprint.original_node_index();
+        if self.cost_function.is_some() {
+            let cost_before = self.cost(false);
+            let created_nodes = self.implement_replacements(original, blueprint.replacements(), instance);
+            updates.add_removed(original);
+            updates.extend_new(created_nodes);
+            return self.cost(true) - cost_before;
+        }
+        let cost_before = match cfg!(debug_assertions) {
+            true => Some(self.calculate_cost()),
+            false => None
+        };
+        let cached_cost = self.cached_cost.take();
+
+        let removed_leftover_value = self.nodes[original].calculate_cost().leftover_value;
+        let clustering_penalty_before = self.clustering_penalty();
+        let created_nodes = self.implement_replacements(original, blueprint.replacements(), instance);
+        let created_leftover_value = created_nodes.iter().map(|n| self.nodes[*n].calculate_cost().leftover_value).sum::<f32>();
+        let clustering_penalty_delta = self.clustering_penalty() - clustering_penalty_before;
+        let cost_delta = Cost::empty().add_leftover_value(created_leftover_value - removed_leftover_value - clustering_penalty_delta);
+
+        debug_assert!(assertions::cost_delta_reconciles(cost_before.as_ref().unwrap(), &cost_delta, &self.calculate_cost()));
+
+        self.cached_cost = cached_cost.map(|cost| cost.add(&cost_delta));
+        updates.add_removed(original);
+        updates.extend_new(created_nodes);
+        cost_delta
+    }
+
+    /// Replaces the original node by the replacement node blueprints, returns the indices of all created nodes
+    fn implement_replacements(&mut self, original: Index, replacements: &[NodeBlueprint], instance: &'a Instance) -> Vec<Index> {
         let parent = self.nodes[original].parent().expect("original node has no parent");
+        let rank = self.fixed_rank(original);
 
         //unregister the original node
         self.unregister_node(original, &mut None);
-        updates.add_removed(original);
 
         //create and register the replacements
         let mut all_created_nodes = vec![];
-        for replacement in blueprint.replacements() {
-            self.implement_node_blueprint(parent, replacement, instance, &mut all_created_nodes);
+        for (i, replacement) in replacements.iter().enumerate() {
+            self.implement_node_blueprint_at(parent, rank.map(|r| r + i), replacement, instance, &mut all_created_nodes);
         }
-        updates.extend_new(all_created_nodes);
+        //the replacements are added after the siblings of the original node (unless its rank is fixed), which move into its space
+        self.reindex_parts_below(parent);
 
-        debug_assert!(assertions::children_nodes_fit(&parent, &self.nodes), "{:#?}", blueprint);
+        debug_assert!(assertions::children_nodes_fit(&parent, &self.nodes), "{:#?}", replacements);
         debug_assert!(assertions::node_arena_valid(&self.nodes, &self.top_node_i));
-        debug_assert!(assertions::cached_sorted_empty_nodes_correct(&self.nodes(), &self.sorted_empty_nodes), "{:#?}", self.sorted_empty_nodes.iter().map(|n| &self.nodes[*n]).collect_vec());
+        debug_assert!(assertions::cached_positions_correct(&self.nodes, &self.top_node_i));
+        debug_assert!(assertions::cached_sorted_empty_nodes_correct(self.nodes(), &self.sorted_empty_nodes, self.empty_node_order), "{:#?}", self.sorted_empty_nodes.iter().map(|n| &self.nodes[*n]).collect_vec());
+
+        all_created_nodes
+    }
+
+    /// Greedily inserts up to `count` copies of a parttype into the empty nodes of the layout.
+    /// Every copy is placed in the last empty node (in empty node order) it fits in, using the insertion with the lowest cost.
+    /// With the default order, this is the smallest empty node.
+    /// Only the rotations allowed by the parttype are considered.
+    /// Returns the number of copies which were actually placed.
+    /// The layout is modified directly, registering the placed parts in a Problem is up to the caller.
+    pub fn fill_with(&mut self, parttype: &'a PartType, count: usize, instance: &'a Instance) -> usize {
+        let rotations = match parttype.fixed_rotation() {
+            Some(rotation) => vec![*rotation],
+            None => vec![Rotation::Default, Rotation::Rotated],
+        };
+
+        let mut n_placed = 0;
+        while n_placed < count {
+            //iterate the sorted empty nodes in reverse to find the smallest (by default) suitable node first
+            let selected_insertion = self.sorted_empty_nodes.iter().rev().find_map(|&node_i| {
+                self.cheapest_replacements(node_i, parttype, &rotations, instance)
+                    .map(|replacements| (node_i, replacements))
+            });
+
+            match selected_insertion {
+                Some((node_i, replacements)) => {
+                    self.implement_replacements(node_i, &replacements, instance);
+                    n_placed += 1;
+                }
+                None => break
+            }
+        }
+        n_placed
+    }
+
+    /// Inserts a copy of the parttype in the top-most (then left-most) empty node it fits in, preferring the rotation in which it lies flat.
+    /// Inserting parts by descending height results in a shelf packing, filling the layout row by row.
+    /// Returns whether the part was placed. Registering the placed part in a Problem is up to the caller.
+    pub fn strip_insert(&mut self, parttype: &'a PartType, instance: &'a Instance) -> bool {
+        let rotations = match parttype.fixed_rotation() {
+            Some(rotation) => vec![*rotation],
+            None if parttype.size().height() <= parttype.size().width() => vec![Rotation::Default, Rotation::Rotated],
+            None => vec![Rotation::Rotated, Rotation::Default],
+        };
+        if !self.parttype_allowed(parttype.id(), instance.max_parttypes_per_sheet()) {
+            return false;
+        }
+        let max_stages = self.sheettype.max_stages();
+        let selected_insertion = self.sorted_empty_nodes.iter()
+            .map(|&node_i| (node_i, self.node_position(node_i)))
+            .sorted_by_key(|(_, (x, y))| (*y, *x))
+            .find_map(|(node_i, _)| {
+                let node = &self.nodes[node_i];
+                rotations.iter()
+                    .filter(|&&rotation| node.insertion_possible(parttype, rotation))
+                    .filter(|&&rotation| !instance.homogeneous_strips() || self.strip_allows(node_i, parttype.size_for(rotation)))
+                    .find_map(|&rotation| {
+                        node.generate_insertion_node_blueprints(parttype, rotation, max_stages, vec![]).into_iter()
+                            .find(|replacements| node.waste_allows(replacements, instance) && self.edge_affinity_allows(node_i, replacements, parttype))
+                    })
+                    .map(|replacements| (node_i, replacements))
+            });
+
+        match selected_insertion {
+            Some((node_i, replacements)) => {
+                self.implement_replacements(node_i, &replacements, instance);
+                true
+            }
+            None => false
+        }
+    }
+
+    /// Moves the part in a node to an empty node of the same layout, using the insertion with the lowest cost.
+    /// The target needs to be an empty node which remains intact after removing the part (it is not merged with the freed space),
+    /// and the part needs to fit in it with one of its allowed rotations.
+    /// If the relocation fails, the layout remains unchanged.
+    pub fn relocate_part(&mut self, node_index: Index, target_empty: Index, instance: &'a Instance) -> Result<(), LayoutError> {
+        let part_id = self.nodes.get(node_index).ok_or(LayoutError::NodeNotFound)?
+            .part_id().ok_or(LayoutError::NotAPart)?;
+        let parttype = instance.get_parttype(part_id);
+        let target = self.nodes.get(target_empty).ok_or(LayoutError::NodeNotFound)?;
+        if !target.is_empty() {
+            return Err(LayoutError::NodeNotEmpty);
+        }
+        let rotations = match parttype.fixed_rotation() {
+            Some(rotation) => vec![*rotation],
+            None => vec![Rotation::Default, Rotation::Rotated],
+        };
+        if !rotations.iter().any(|r| target.insertion_possible(parttype, *r)) {
+            return Err(LayoutError::PartDoesNotFit);
+        }
+
+        let checkpoint = self.checkpoint();
+        self.remove_node(node_index);
+
+        let replacements = match self.nodes.contains(target_empty) {
+            true => self.cheapest_replacements(target_empty, parttype, &rotations, instance),
+            false => None, //the target was merged with the freed space
+        };
+        match replacements {
+            Some(replacements) => {
+                self.implement_replacements(target_empty, &replacements, instance);
+                Ok(())
+            }
+            None => {
+                self.restore(checkpoint);
+                Err(LayoutError::PartDoesNotFit)
+            }
+        }
+    }
+
+    /// Replaces the part in a node by a part of another parttype, which is inserted (with the lowest cost) into the empty node
+    /// freed by removing the original part. Returns the parttype id of the removed part.
+    /// If the other part does not fit in the freed space, the layout remains unchanged.
+    pub fn replace_part(&mut self, node_index: Index, parttype: &'a PartType, instance: &'a Instance) -> Result<usize, LayoutError> {
+        let part_id = self.nodes.get(node_index).ok_or(LayoutError::NodeNotFound)?
+            .part_id().ok_or(LayoutError::NotAPart)?;
+        let rotations = match parttype.fixed_rotation() {
+            Some(rotation) => vec![*rotation],
+            None => vec![Rotation::Default, Rotation::Rotated],
+        };
+        let empty_nodes_before = self.sorted_empty_nodes.clone();
+
+        let checkpoint = self.checkpoint();
+        self.remove_node(node_index);
+
+        //the freed space is the only new empty node, the part is possibly merged with an empty sibling which is not adjacent to it
+        let replacements = self.sorted_empty_nodes.iter()
+            .find(|i| !empty_nodes_before.contains(i))
+            .and_then(|&freed| self.cheapest_replacements(freed, parttype, &rotations, instance).map(|r| (freed, r)));
+        match replacements {
+            Some((freed, replacements)) => {
+                self.implement_replacements(freed, &replacements, instance);
+                Ok(part_id)
+            }
+            None => {
+                self.restore(checkpoint);
+                Err(LayoutError::PartDoesNotFit)
+            }
+        }
+    }
+
+    /// Restores the gaps required between the parts when they are cut with the given kerf and separated by an additional spacing,
+    /// e.g. for a layout created with a smaller (or no) kerf. Parts at the edge of the sheet need no gap towards the edge.
+    /// If any pair of adjacent parts is closer than required, the parts are repacked into the sheet with the required gaps,
+    /// as empty nodes between them. Parts which no longer fit are dropped from the layout and reported.
+    /// Registering the dropped parts in a Problem is up to the caller.
+    /// The sheettype cannot have unusable regions (a corner cut or usable regions), which the repacking does not preserve.
+    pub fn repair_spacing(&mut self, kerf: Kerf, spacing: u64, instance: &'a Instance) -> RepairReport {
+        assert!(self.sheettype.inset_unusable_regions().is_empty(), "cannot repair the spacing of a layout with unusable regions");
+        let sheet_kerf = *self.sheettype.kerf();
+        //gap required between the parts (excluding their own kerf), never less than the kerf of the layout itself
+        let gap = Kerf::new(
+            u64::max(kerf.horizontal + spacing, sheet_kerf.horizontal),
+            u64::max(kerf.vertical + spacing, sheet_kerf.vertical),
+        );
+        let n_violations = self.n_spacing_violations(&gap);
+        if n_violations == 0 {
+            return RepairReport { n_violations, dropped_parts: vec![] };
+        }
+
+        //Repack the parts into a scratch copy of the sheet in which all parts are enlarged by the gap
+        let scratch_parts = instance.parts().iter()
+            .map(|(pt, _)| (PartType::new(pt.id(), pt.width(), pt.height(), *pt.fixed_rotation(), *pt.preferred_rotation(), pt.preferred_rotation_penalty(), gap).with_priority(pt.priority()), 0))
+            .collect_vec();
+        let st = self.sheettype;
+        let first_cut_orientation = self.nodes[self.top_node_i].next_cut_orient();
+        let scratch_sheet = SheetType::new(0, st.width(), st.height(), st.value(), Some(first_cut_orientation), st.max_stages(), gap)
+            .with_margins(*st.margins());
+        let scratch_instance = Instance::new(scratch_parts, vec![(scratch_sheet, 1)], 0, false);
+        let mut scratch = Layout::new(0, scratch_instance.get_sheettype(0), first_cut_orientation, self.empty_node_order);
+
+        let mut dropped_parts = vec![];
+        for part_id in self.included_parts_unordered().into_iter().sorted_by_key(|id| Reverse(instance.get_parttype(*id).area())) {
+            if scratch.fill_with(scratch_instance.get_parttype(part_id), 1, &scratch_instance) == 0 {
+                dropped_parts.push(part_id);
+            }
+        }
+
+        //Shrinking every node by the difference between the gap and the kerf of the layout leaves exactly the gaps between siblings
+        let shrink = (gap.vertical - sheet_kerf.vertical, gap.horizontal - sheet_kerf.horizontal);
+        let top_blueprint = shrink_blueprint(&NodeBlueprint::from_node(scratch.top_node_i, &scratch.nodes), shrink, instance);
+
+        let mut repaired = Layout::new(self.id, self.sheettype, first_cut_orientation, self.empty_node_order);
+        repaired.set_cost_function(self.cost_function);
+        repaired.set_clustering_weight(self.clustering_weight);
+        if !top_blueprint.children().is_empty() {
+            //the children of the top node replace the placeholder
+            let placeholder = repaired.nodes[repaired.top_node_i].children()[0];
+            repaired.implement_replacements(placeholder, top_blueprint.children(), instance);
+        }
+        if self.position_cache {
+            repaired.enable_position_cache();
+        }
+        if self.spatial_index.is_some() {
+            repaired.enable_spatial_index();
+        }
+        *self = repaired;
+
+        debug_assert!(self.n_spacing_violations(&gap) == 0);
+        RepairReport { n_violations, dropped_parts }
+    }
+
+    /// Number of pairs of parts facing each other closer than the gap (in the direction of the cut between them), excluding the kerf of the parts
+    fn n_spacing_violations(&self, gap: &Kerf) -> usize {
+        //(x, y, width, height) of all parts, excluding their kerf
+        let parts = self.nodes.iter()
+            .filter_map(|(i, n)| n.parttype().map(|pt| {
+                let (x, y) = self.node_position(i);
+                (x, y, n.width() - pt.kerf().vertical, n.height() - pt.kerf().horizontal)
+            }))
+            .collect_vec();
+
+        parts.iter().tuple_combinations()
+            .filter(|((x_a, y_a, w_a, h_a), (x_b, y_b, w_b, h_b))| {
+                let x_overlap = (x_a + w_a).min(x_b + w_b) as i128 - *x_a.max(x_b) as i128;
+                let y_overlap = (y_a + h_a).min(y_b + h_b) as i128 - *y_a.max(y_b) as i128;
+                (y_overlap > 0 && -x_overlap < gap.vertical as i128) || (x_overlap > 0 && -y_overlap < gap.horizontal as i128)
+            })
+            .count()
+    }
+
+    /// All insertion blueprints of the parttype into the empty nodes of the layout, which is referred to by layout_index in the blueprints.
+    /// Blueprints are generated lazily, node by node in the order of the sorted empty nodes, with the default rotation first.
+    /// The constraints of the instance are applied, see also insertion_options.
+    pub fn insertion_options_iter(&self, layout_index: LayoutIndex, parttype: &'a PartType, instance: &'a Instance) -> impl Iterator<Item = InsertionBlueprint<'a>> + '_ {
+        let rotations = match parttype.fixed_rotation() {
+            Some(rotation) => vec![*rotation],
+            None => vec![Rotation::Default, Rotation::Rotated],
+        };
+        let allowed = self.parttype_allowed(parttype.id(), instance.max_parttypes_per_sheet());
+        let max_stages = self.sheettype.max_stages();
+
+        self.sorted_empty_nodes.iter()
+            .filter(move |_| allowed)
+            .flat_map(move |&node_i| rotations.clone().into_iter().map(move |rotation| (node_i, rotation)))
+            .filter(move |&(node_i, rotation)| self.nodes[node_i].insertion_possible(parttype, rotation))
+            .filter(move |&(node_i, rotation)| !instance.homogeneous_strips() || self.strip_allows(node_i, parttype.size_for(rotation)))
+            .flat_map(move |(node_i, rotation)| {
+                let node = &self.nodes[node_i];
+                node.generate_insertion_node_blueprints(parttype, rotation, max_stages, vec![]).into_iter()
+                    .filter(move |replacements| node.waste_allows(replacements, instance))
+                    .filter(move |replacements| self.edge_affinity_allows(node_i, replacements, parttype))
+                    .map(move |replacements| InsertionBlueprint::from_replacements(layout_index, node_i, node, replacements, parttype, rotation))
+            })
+    }
+
+    /// Eager version of insertion_options_iter, with the blueprints in the same order
+    pub fn insertion_options(&self, layout_index: LayoutIndex, parttype: &'a PartType, instance: &'a Instance) -> Vec<InsertionBlueprint<'a>> {
+        self.insertion_options_iter(layout_index, parttype, instance).collect_vec()
+    }
+
+    /// Replacements (with the lowest cost) to insert the parttype into the empty node, considering the given rotations
+    fn cheapest_replacements(&self, node_index: Index, parttype: &'a PartType, rotations: &[Rotation], instance: &Instance) -> Option<Vec<NodeBlueprint>> {
+        if !self.parttype_allowed(parttype.id(), instance.max_parttypes_per_sheet()) {
+            return None;
+        }
+        let node = &self.nodes[node_index];
+        let max_stages = self.sheettype.max_stages();
+        rotations.iter()
+            .filter(|&&rotation| node.insertion_possible(parttype, rotation))
+            .filter(|&&rotation| !instance.homogeneous_strips() || self.strip_allows(node_index, parttype.size_for(rotation)))
+            .flat_map(|&rotation| {
+                node.generate_insertion_node_blueprints(parttype, rotation, max_stages, vec![])
+                    .into_iter().map(move |replacements| (rotation, replacements))
+            })
+            .filter(|(_, replacements)| node.waste_allows(replacements, instance))
+            .filter(|(_, replacements)| self.edge_affinity_allows(node_index, replacements, parttype))
+            .map(|(rotation, replacements)| {
+                let cost = replacements.iter().map(|r| r.calculate_cost()).sum::<Cost>()
+                    .add_leftover_value(-parttype.rotation_penalty(rotation));
+                (cost, replacements)
+            })
+            .min_by(|(cost_a, _), (cost_b, _)| crate::COST_COMPARATOR(cost_a, cost_b))
+            .map(|(_, replacements)| replacements)
     }
 
     fn implement_node_blueprint(&mut self, parent: Index, blueprint: &NodeBlueprint, instance: &'a Instance, new_nodes: &mut Vec<Index>) {
+        self.implement_node_blueprint_at(parent, None, blueprint, instance, new_nodes);
+    }
+
+    /// Same as implement_node_blueprint, with the node registered at the given rank among the children of the parent (see register_node_at)
+    fn implement_node_blueprint_at(&mut self, parent: Index, rank: Option<usize>, blueprint: &NodeBlueprint, instance: &'a Instance, new_nodes: &mut Vec<Index>) {
         let parttype = blueprint.parttype_id().map(|id| instance.get_parttype(id));
 
-        let node = Node::new(self.nodes[parent].level() + 1, blueprint.width(), blueprint.height(), blueprint.next_cut_orient(), parttype);
-        let node_index = self.register_node(node, parent, blueprint.is_empty());
+        let level = self.nodes[parent].level() + 1;
+        let node = match blueprint.is_unusable() {
+            true => Node::new_unusable(level, blueprint.width(), blueprint.height(), blueprint.next_cut_orient()),
+            false => Node::new(level, blueprint.width(), blueprint.height(), blueprint.next_cut_orient(), parttype),
+        };
+        let node_index = self.register_node_at(node, parent, rank, blueprint.is_empty());
 
         new_nodes.push(node_index);
 
@@ -84,7 +677,29 @@ impl<'a> Layout<'a> {
         }
     }
 
+    /// Same as implement_node_blueprint, for blueprints without parts (e.g. of the unusable regions)
+    fn implement_structure_blueprint(&mut self, parent: Index, blueprint: &NodeBlueprint) {
+        debug_assert!(blueprint.parttype_id().is_none());
+        let level = self.nodes[parent].level() + 1;
+        let node = match blueprint.is_unusable() {
+            true => Node::new_unusable(level, blueprint.width(), blueprint.height(), blueprint.next_cut_orient()),
+            false => Node::new(level, blueprint.width(), blueprint.height(), blueprint.next_cut_orient(), None),
+        };
+        let node_index = self.register_node(node, parent, blueprint.is_empty());
+
+        for child_blueprint in blueprint.children() {
+            self.implement_structure_blueprint(node_index, child_blueprint);
+        }
+    }
+
     pub fn remove_node(&mut self, node_index: Index) -> Vec<usize>{
+        self.remove_node_with(node_index, false)
+    }
+
+    /// Removes a node, see remove_node. With eager_merge, the freed empty node is merged right away with the empty nodes
+    /// which become its siblings, repeatedly up the tree, so no node is left with more than one empty child
+    /// or with a single empty child (except for the top node).
+    pub fn remove_node_with(&mut self, node_index: Index, eager_merge: bool) -> Vec<usize>{
         /*®
            Scenario 1: Empty node present + other child(ren)
             -> expand existing waste piece
@@ -120,12 +735,12 @@ impl<'a> Layout<'a> {
         let parent_node_index = self.nodes[node_index].parent().expect("Cannot remove a node without a parent");
         let parent_node = &self.nodes[parent_node_index];
 
-        //Check if there is an empty_node present
-        let empty_node = parent_node.children().iter().find(|c| { self.nodes[**c].is_empty()});
+        //Check if there is an empty_node present which can be merged with the node
+        let empty_node = parent_node.children().iter().find(|c| { self.nodes[**c].is_empty() && self.mergeable_siblings(node_index, **c) });
 
         let mut removed_parts = Some(vec![]);
 
-        match empty_node {
+        let freed_node_index = match empty_node {
             Some(&empty_node_index) => {
                 //Scenario 1 and 3
                 if parent_node.children().len() > 1 || parent_node.parent().is_none() {
@@ -146,9 +761,12 @@ impl<'a> Layout<'a> {
                     };
 
                     //Replace the empty node and the node to be removed with a enlarged empty node
+                    let rank = self.fixed_rank(node_index).min(self.fixed_rank(empty_node_index));
                     self.unregister_node(empty_node_index, &mut removed_parts);
                     self.unregister_node(node_index, &mut removed_parts);
-                    self.register_node(replacement_node, parent_node_index, true);
+                    let freed_node_index = self.register_node_at(replacement_node, parent_node_index, rank, true);
+                    self.reindex_parts_below(parent_node_index);
+                    freed_node_index
                 } else {
                     //Scenario 3: replace the parent with an empty node
                     let grandparent_index = parent_node.parent().expect("grandparent node needs to be present").clone();
@@ -157,8 +775,11 @@ impl<'a> Layout<'a> {
                     let empty_parent_node = Node::new(parent_node.level(), parent_node.width(), parent_node.height(), parent_node.next_cut_orient(), None);
 
                     //replace
+                    let rank = self.fixed_rank(parent_node_index);
                     self.unregister_node(parent_node_index, &mut removed_parts);
-                    self.register_node(empty_parent_node, grandparent_index, true);
+                    let freed_node_index = self.register_node_at(empty_parent_node, grandparent_index, rank, true);
+                    self.reindex_parts_below(grandparent_index);
+                    freed_node_index
                 }
             }
             None => {
@@ -169,41 +790,245 @@ impl<'a> Layout<'a> {
                 let replacement_node = Node::new(node.level(), node.width(), node.height(), node.next_cut_orient(), None);
 
                 //replace
+                let rank = self.fixed_rank(node_index);
                 self.unregister_node(node_index, &mut removed_parts);
-                self.register_node(replacement_node, parent_node_index, true);
+                let freed_node_index = self.register_node_at(replacement_node, parent_node_index, rank, true);
+                self.reindex_parts_below(parent_node_index);
+                freed_node_index
             }
+        };
+        if eager_merge {
+            self.merge_empty_upwards(freed_node_index);
         }
 
         debug_assert!(assertions::node_arena_valid(&self.nodes, &self.top_node_i));
-        debug_assert!(assertions::cached_sorted_empty_nodes_correct(&self.nodes(), &self.sorted_empty_nodes), "{:#?}", self.sorted_empty_nodes.iter().map(|n| &self.nodes[*n]).collect_vec());
+        debug_assert!(assertions::cached_positions_correct(&self.nodes, &self.top_node_i));
+        debug_assert!(assertions::cached_sorted_empty_nodes_correct(self.nodes(), &self.sorted_empty_nodes, self.empty_node_order), "{:#?}", self.sorted_empty_nodes.iter().map(|n| &self.nodes[*n]).collect_vec());
 
         removed_parts.unwrap()
     }
 
+    /// Merges an empty node with a mergeable empty sibling into a single empty node, and replaces a parent (other than the top node)
+    /// left with only an empty child by an empty node, repeated with the resulting empty node until neither applies
+    fn merge_empty_upwards(&mut self, mut empty_node_index: Index) {
+        let mut removed_parts = None;
+        while let Some(parent_node_index) = *self.nodes[empty_node_index].parent() {
+            let parent_node = &self.nodes[parent_node_index];
+            let empty_sibling = parent_node.children().iter()
+                .find(|&&c| c != empty_node_index && self.nodes[c].is_empty() && self.mergeable_siblings(empty_node_index, c)).copied();
+            empty_node_index = match empty_sibling {
+                Some(empty_sibling_index) => {
+                    let node = &self.nodes[empty_node_index];
+                    let sibling = &self.nodes[empty_sibling_index];
+                    let merged_node = match parent_node.next_cut_orient() {
+                        Orientation::Horizontal => Node::new(node.level(), node.width(), node.height() + sibling.height(), node.next_cut_orient(), None),
+                        Orientation::Vertical => Node::new(node.level(), node.width() + sibling.width(), node.height(), node.next_cut_orient(), None),
+                    };
+                    let rank = self.fixed_rank(empty_node_index).min(self.fixed_rank(empty_sibling_index));
+                    self.unregister_node(empty_sibling_index, &mut removed_parts);
+                    self.unregister_node(empty_node_index, &mut removed_parts);
+                    let merged_node_index = self.register_node_at(merged_node, parent_node_index, rank, true);
+                    self.reindex_parts_below(parent_node_index);
+                    merged_node_index
+                }
+                None => match *parent_node.parent() {
+                    Some(grandparent_index) if parent_node.children().len() == 1 => {
+                        let empty_parent_node = Node::new(parent_node.level(), parent_node.width(), parent_node.height(), parent_node.next_cut_orient(), None);
+                        let rank = self.fixed_rank(parent_node_index);
+                        self.unregister_node(parent_node_index, &mut removed_parts);
+                        let empty_parent_index = self.register_node_at(empty_parent_node, grandparent_index, rank, true);
+                        self.reindex_parts_below(grandparent_index);
+                        empty_parent_index
+                    }
+                    _ => break,
+                }
+            };
+        }
+    }
+
+    /// Flips the cut orientation of a node without children.
+    /// Because the cut orientations alternate between the levels of the tree, all ancestors are flipped as well.
+    /// This is only possible when the node and all of its ancestors are the only child of their parent,
+    /// which is the case for the placeholder node of an empty layout.
+    pub fn flip_node_orientation(&mut self, node_index: Index) -> Result<(), LayoutError> {
+        let node = self.nodes.get(node_index).ok_or(LayoutError::NodeNotFound)?;
+        if !node.children().is_empty() {
+            return Err(LayoutError::NodeHasChildren);
+        }
+
+        //Collect the node and all of its ancestors, which need to be flipped along
+        let mut to_flip = vec![node_index];
+        let mut current = node_index;
+        while let Some(parent) = *self.nodes[current].parent() {
+            if self.nodes[parent].children().len() > 1 {
+                return Err(LayoutError::OrientationFixedByParent);
+            }
+            to_flip.push(parent);
+            current = parent;
+        }
+
+        for &node_i in &to_flip {
+            self.nodes[node_i].flip_next_cut_orient();
+        }
+        if let Some(trail) = self.undo_trail.as_mut() {
+            trail.push(TrailEntry::Flipped(to_flip));
+        }
+        self.invalidate_caches();
+
+        debug_assert!(assertions::node_arena_valid(&self.nodes, &self.top_node_i));
+        debug_assert!(assertions::children_nodes_fit(&self.top_node_i, &self.nodes));
+        Ok(())
+    }
+
     fn invalidate_caches(&mut self) {
         self.cached_cost = None;
         self.cached_usage = None;
     }
 
+    /// Replaces the built-in cost calculation of the layout, None restores the built-in one
+    pub fn set_cost_function(&mut self, cost_function: Option<CostFunction>) {
+        self.cost_function = cost_function;
+        self.invalidate_caches();
+    }
+
+    /// Rewards clustering the parts of the same parttype: the clustering penalty is subtracted from the leftover value of the layout,
+    /// also when a custom cost function is set. 0.0 (the default) disables the penalty.
+    pub fn set_clustering_weight(&mut self, clustering_weight: f32) {
+        self.clustering_weight = clustering_weight;
+        self.invalidate_caches();
+    }
+
+    pub fn clustering_weight(&self) -> f32 {
+        self.clustering_weight
+    }
+
     fn calculate_cost(&self) -> Cost {
+        let cost = match self.cost_function {
+            Some(cost_function) => cost_function(self),
+            None => self.builtin_cost(),
+        };
+        match self.clustering_weight > 0.0 {
+            true => cost.add_leftover_value(-self.clustering_penalty()),
+            false => cost,
+        }
+    }
+
+    /// Spread of the parts of every parttype in the layout: the area of their bounding box which is not covered by the parts themselves,
+    /// sorted by parttype id. Zero for a single part, or for parts forming a rectangle together.
+    pub fn parttype_spreads(&self) -> Vec<(usize, u64)> {
+        self.nodes.iter()
+            .filter_map(|(i, n)| n.part_id().map(|id| (id, self.node_rect(i))))
+            .into_group_map()
+            .into_iter()
+            .map(|(id, rects)| (id, spread(&rects)))
+            .sorted_unstable()
+            .collect_vec()
+    }
+
+    /// Clustering weight times the value of a leftover the size of the spread of every parttype, summed over the parttypes.
+    /// Zero without a clustering weight.
+    pub fn clustering_penalty(&self) -> f32 {
+        if self.clustering_weight <= 0.0 {
+            return 0.0;
+        }
+        self.clustering_weight * self.parttype_spreads().into_iter()
+            .map(|(_, spread)| leftover_valuator::valuate(spread))
+            .sum::<f32>()
+    }
+
+    /// Change of the clustering penalty when the part is inserted by replacing the original node with the replacements.
+    /// Estimated from the current positions of the other parts, an insertion can move some of them towards the top-left corner.
+    pub fn clustering_penalty_delta(&self, original: Index, replacements: &[NodeBlueprint], parttype: &PartType) -> f32 {
+        if self.clustering_weight <= 0.0 {
+            return 0.0;
+        }
+        let inserted_rect = match self.inserted_part_rect(original, replacements, parttype.id()) {
+            Some(rect) => rect,
+            None => return 0.0,
+        };
+        let mut rects = self.nodes.iter()
+            .filter(|(_, n)| n.part_id() == Some(parttype.id()))
+            .map(|(i, _)| self.node_rect(i))
+            .collect_vec();
+        let spread_before = spread(&rects);
+        rects.push(inserted_rect);
+        self.clustering_weight * (leftover_valuator::valuate(spread(&rects)) - leftover_valuator::valuate(spread_before))
+    }
+
+    /// Cost of the layout as calculated by the crate: the value of the sheet and the value of the leftovers
+    pub fn builtin_cost(&self) -> Cost {
         let material_cost = Cost::empty().add_material_cost(self.sheettype.value());
         self.nodes.iter()
             .map(|(_, node)| node.calculate_cost())
             .fold(material_cost, |acc, cost| acc.add(&cost))
     }
 
-    fn calculate_usage(&self) -> f64 {
-        let used_area = self.nodes.iter().map(|(_, node)| {
-            match node.parttype(){
-                Some(_) => node.area(),
-                None => 0
+    /// Cost of the layout after removing the node (see remove_node), without modifying the layout.
+    /// The nodes replaced by the removal are those of the three scenarios of remove_node_with (the node and the empty sibling it merges with,
+    /// the node itself or its parent), their cost is exchanged for the cost of the freed empty node.
+    /// With a custom cost function or a clustering weight, which depend on the entire layout, the removal is performed on a copy instead.
+    /// Panics for the top node, which cannot be removed from a layout.
+    pub fn cost_after_removal(&self, node_index: Index) -> Cost {
+        if self.cost_function.is_some() || self.clustering_weight > 0.0 {
+            let mut copy = self.clone();
+            copy.remove_node(node_index);
+            return copy.cost(true);
+        }
+
+        let parent_node_index = self.nodes[node_index].parent().expect("Cannot remove a node without a parent");
+        let parent_node = &self.nodes[parent_node_index];
+        let node = &self.nodes[node_index];
+        let empty_node = parent_node.children().iter().find(|c| { self.nodes[**c].is_empty() && self.mergeable_siblings(node_index, **c) });
+
+        let (replaced_nodes, freed_node) = match empty_node {
+            Some(&empty_node_index) if parent_node.children().len() > 1 || parent_node.parent().is_none() => {
+                //Scenario 1: the node and the empty sibling are merged
+                let empty_node = &self.nodes[empty_node_index];
+                let freed_node = match parent_node.next_cut_orient() {
+                    Orientation::Horizontal => Node::new(node.level(), node.width(), empty_node.height() + node.height(), node.next_cut_orient(), None),
+                    Orientation::Vertical => Node::new(node.level(), empty_node.width() + node.width(), node.height(), node.next_cut_orient(), None),
+                };
+                (vec![node_index, empty_node_index], freed_node)
             }
-        }).sum::<u64>();
+            //Scenario 3: the parent is converted into an empty node
+            Some(_) => (vec![parent_node_index], Node::new(parent_node.level(), parent_node.width(), parent_node.height(), parent_node.next_cut_orient(), None)),
+            //Scenario 2: the node is converted into an empty node
+            None => (vec![node_index], Node::new(node.level(), node.width(), node.height(), node.next_cut_orient(), None)),
+        };
+
+        replaced_nodes.iter()
+            .map(|&n| self.subtree_cost(n))
+            .fold(self.calculate_cost(), |acc, cost| acc.subtract(&cost))
+            .add(&freed_node.calculate_cost())
+    }
+
+    /// Sum of the built-in costs of the node and all of its descendants
+    fn subtree_cost(&self, node_index: Index) -> Cost {
+        let node = &self.nodes[node_index];
+        node.children().iter()
+            .map(|&c| self.subtree_cost(c))
+            .fold(node.calculate_cost(), |acc, cost| acc.add(&cost))
+    }
+
+    /// Contribution of every node to the built-in cost, in the order builtin_cost sums them.
+    /// Together with the value of the sheet as material cost, they add up to builtin_cost (and cost, unless a custom cost function or a clustering weight is set).
+    pub fn cost_breakdown(&self) -> Vec<(Index, Cost)> {
+        self.nodes.iter()
+            .map(|(i, node)| (i, node.calculate_cost()))
+            .collect_vec()
+    }
 
-        used_area as f64 / self.sheettype.area() as f64
+    fn calculate_usage(&self) -> f64 {
+        self.used_area() as f64 / self.sheettype.usage_area() as f64
     }
 
     fn register_node(&mut self, node: Node<'a>, parent: Index, is_empty: bool) -> Index {
+        self.register_node_at(node, parent, None, is_empty)
+    }
+
+    /// Registers the node at the given rank among the children of the parent (the following siblings move along),
+    /// or after all of its siblings if no rank is given
+    fn register_node_at(&mut self, node: Node<'a>, parent: Index, rank: Option<usize>, is_empty: bool) -> Index {
         self.invalidate_caches();
 
         if let Some(parttype) = node.parttype() {
@@ -213,15 +1038,17 @@ impl<'a> Layout<'a> {
         debug_assert!(node.level() == self.nodes[parent].level() + 1);
 
         let node_index = self.nodes.insert(node);
+        if let Some(trail) = self.undo_trail.as_mut() {
+            trail.push(TrailEntry::Registered { index: node_index, is_empty });
+        }
 
         //All empty nodes need to be added to the sorted empty nodes list
         if is_empty {
             debug_assert!(self.nodes[node_index].is_empty());
-            let node_area = self.nodes[node_index].area();
+            let node = &self.nodes[node_index];
             let result = self.sorted_empty_nodes.binary_search_by(
                 &(|n: &Index| {
-                    let n_area = self.nodes[*n].area();
-                    n_area.cmp(&node_area).reverse()
+                    self.empty_node_order.compare(&self.nodes[*n], node).reverse()
                 })
             );
 
@@ -233,33 +1060,89 @@ impl<'a> Layout<'a> {
 
         //Configure relationship between node and parent
         self.nodes[node_index].set_parent(parent);
-        self.nodes[parent].add_child(node_index);
+        match rank {
+            Some(rank) => self.nodes[parent].insert_child(rank, node_index),
+            None => self.nodes[parent].add_child(node_index),
+        }
+
+        if self.position_cache {
+            match rank {
+                //appended after all siblings, so no other node moves
+                None => {
+                    let position = self.position_from_parent(node_index);
+                    self.nodes[node_index].set_cached_position(Some(position));
+                }
+                //the following siblings move to make room
+                Some(rank) => self.cache_positions_below(parent, rank),
+            }
+        }
 
         debug_assert!(assertions::node_arena_valid(&self.nodes, &self.top_node_i));
         node_index
     }
 
+    /// Rank of the node among the children of its parent
+    fn sibling_rank(&self, node_index: Index) -> usize {
+        let parent = self.nodes[node_index].parent().expect("node has no parent");
+        self.nodes[parent].children().iter().position(|&c| c == node_index).expect("Child not found")
+    }
+
+    /// Rank at which a replacement of the node has to be registered: the siblings of an unusable node (or of a node containing one)
+    /// keep their order, as the unusable regions are fixed to their position on the sheet.
+    /// Otherwise None, replacements are registered after all siblings.
+    fn fixed_rank(&self, node_index: Index) -> Option<usize> {
+        let parent = self.nodes[node_index].parent().expect("node has no parent");
+        let fixed = !self.sheettype.inset_unusable_regions().is_empty()
+            && self.nodes[parent].children().iter().any(|&c| self.contains_unusable_node(c));
+        fixed.then(|| self.sibling_rank(node_index))
+    }
+
+    /// Whether two siblings can be merged into a single node: the siblings in between move to make room,
+    /// which is not possible for unusable nodes as they are fixed to their position on the sheet
+    fn mergeable_siblings(&self, node_index: Index, sibling_index: Index) -> bool {
+        let (rank, sibling_rank) = (self.sibling_rank(node_index), self.sibling_rank(sibling_index));
+        let parent = self.nodes[node_index].parent().expect("node has no parent");
+        self.nodes[parent].children()[rank.min(sibling_rank)..rank.max(sibling_rank)].iter().skip(1)
+            .all(|&c| !self.contains_unusable_node(c))
+    }
+
     fn unregister_node(&mut self, node_index: Index, removed_part_ids: &mut Option<Vec<usize>>) {
+        let parent = *self.nodes[node_index].parent();
+        let sibling_rank = parent.map(|p| self.nodes[p].children().iter().position(|&c| c == node_index).unwrap());
+
+        self.unregister_subtree(node_index, removed_part_ids);
+
+        //the following siblings move into the space of the removed node
+        if let (true, Some(parent), Some(sibling_rank)) = (self.position_cache, parent, sibling_rank) {
+            self.cache_positions_below(parent, sibling_rank);
+        }
+    }
+
+    /// Unregisters the node and all of its descendants, without updating the positions of its siblings
+    fn unregister_subtree(&mut self, node_index: Index, removed_part_ids: &mut Option<Vec<usize>>) {
         self.invalidate_caches();
 
         //All empty nodes need to be removed from the sorted empty nodes list
         let node = &self.nodes[node_index];
+        let mut empty_rank = None;
         if node.is_empty() {
             let lower_index = self.sorted_empty_nodes.partition_point(|n|
-                { self.nodes[*n].area() > node.area() });
+                { self.empty_node_order.compare(&self.nodes[*n], node) == Ordering::Greater });
 
             if self.sorted_empty_nodes[lower_index] == node_index {
                 //We have found the correct node, remove it
                 self.sorted_empty_nodes.remove(lower_index);
+                empty_rank = Some(lower_index);
             } else {
                 let upper_index = self.sorted_empty_nodes.partition_point(|n|
-                    { self.nodes[*n].area() >= node.area() });
+                    { self.empty_node_order.compare(&self.nodes[*n], node) != Ordering::Less });
 
                 let mut node_found = false;
                 for i in lower_index..upper_index {
                     if self.sorted_empty_nodes[i] == node_index {
                         //We have found the correct node, remove it
                         self.sorted_empty_nodes.remove(i);
+                        empty_rank = Some(i);
                         node_found = true;
                         break;
                     }
@@ -272,11 +1155,14 @@ impl<'a> Layout<'a> {
 
         //unregister all children
         for child in node.children().clone() {
-            self.unregister_node(child, removed_part_ids);
+            self.unregister_subtree(child, removed_part_ids);
         }
 
         //remove the node
         let node = self.nodes.remove(node_index).expect("Node to be removed does not exist");
+        if let Some(spatial_index) = self.spatial_index.as_mut() {
+            spatial_index.remove(node_index);
+        }
 
         //unregister part
         if let &Some(parttype) = node.parttype() {
@@ -287,8 +1173,12 @@ impl<'a> Layout<'a> {
         }
 
         //break the relationship with parent
-        if let Some(parent) = node.parent() {
-            self.nodes[*parent].remove_child(node_index);
+        if let Some(parent) = *node.parent() {
+            let rank = self.undo_trail.is_some().then(|| self.nodes[parent].children().iter().position(|&c| c == node_index).expect("Child not found"));
+            self.nodes[parent].remove_child(node_index);
+            if let (Some(trail), Some(rank)) = (self.undo_trail.as_mut(), rank) {
+                trail.push(TrailEntry::Unregistered { index: node_index, node, rank, empty_rank });
+            }
         }
 
         debug_assert!(assertions::node_arena_valid(&self.nodes, &self.top_node_i));
@@ -302,15 +1192,56 @@ impl<'a> Layout<'a> {
         self.invalidate_caches();
     }
 
+    /// Ids of all parts included in the layout, sorted by ascending part id.
     pub fn get_included_parts(&self) -> Vec<usize> {
+        let mut included_parts = self.included_parts_unordered();
+        included_parts.sort_unstable();
+        included_parts
+    }
+
+    /// Number of different parttypes included in the layout
+    pub fn n_distinct_parttypes(&self) -> usize {
+        self.included_parts_unordered().into_iter().unique().count()
+    }
+
+    /// Sum over the parts of their urgency times their area, see Problem::urgency_penalty
+    pub fn urgency_area(&self, instance: &Instance) -> u64 {
+        self.included_parts_unordered().into_iter()
+            .map(|id| instance.get_parttype(id))
+            .map(|pt| pt.urgency() as u64 * pt.area())
+            .sum()
+    }
+
+    /// Whether a part of the parttype can be added without exceeding the maximum number of distinct parttypes in the layout
+    pub fn parttype_allowed(&self, parttype_id: usize, max_parttypes: Option<usize>) -> bool {
+        match max_parttypes {
+            Some(max_parttypes) => {
+                let included = self.included_parts_unordered();
+                included.contains(&parttype_id) || included.into_iter().unique().count() < max_parttypes
+            }
+            None => true,
+        }
+    }
+
+    /// Same as get_included_parts, but in arena order, for internal use where the order does not matter.
+    pub(crate) fn included_parts_unordered(&self) -> Vec<usize> {
         self.nodes.iter()
-            .map(|(_, n)| n.parttype().map(|p| p.id()))
-            .flatten()
+            .filter_map(|(_, n)| n.part_id())
             .collect_vec()
     }
 
+    /// Number of parts in the subtree of the node (including the node itself)
+    pub(crate) fn n_parts_below(&self, node_index: Index) -> usize {
+        let node = &self.nodes[node_index];
+        match node.is_part() {
+            true => 1,
+            false => node.children().iter().map(|c| self.n_parts_below(*c)).sum(),
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.nodes.iter().all(|(_, n)| n.is_empty())
+        //the unusable nodes remain in a layout without parts
+        self.nodes.iter().all(|(_, n)| n.is_empty() || (!self.sheettype.inset_unusable_regions().is_empty() && !n.is_part()))
     }
 
     pub fn cost(&mut self, force_recalc: bool) -> Cost {
@@ -322,7 +1253,7 @@ impl<'a> Layout<'a> {
                 cost
             }
         };
-        debug_assert!(force_recalc || cost == self.cost(true));
+        debug_assert!(force_recalc || assertions::cost_delta_reconciles(&cost, &Cost::empty(), &self.cost(true)));
         cost
     }
 
@@ -334,10 +1265,19 @@ impl<'a> Layout<'a> {
                 cost
             }
         };
-        debug_assert!(force_recalc || cost == self.cost_immut(true));
+        debug_assert!(force_recalc || assertions::cost_delta_reconciles(&cost, &Cost::empty(), &self.cost_immut(true)));
         cost
     }
 
+    /// Cost of the layout after implementing the insertion blueprint, without modifying the layout.
+    /// The insertion is performed on a copy, which also evaluates a custom cost function.
+    pub fn cost_after_insertion(&self, blueprint: &InsertionBlueprint<'a>, instance: &'a Instance) -> Cost {
+        let mut copy = self.clone();
+        copy.implement_insertion_blueprint(blueprint, instance, &mut IOCUpdates::new(*blueprint.layout_index()))
+            .expect("insertion blueprint does not match the layout");
+        copy.cost(true)
+    }
+
     pub fn usage(&mut self, force_recalc: bool) -> f64 {
         let usage = match (self.cached_usage.as_ref(), force_recalc) {
             (Some(usage), false) => *usage,
@@ -363,22 +1303,631 @@ impl<'a> Layout<'a> {
         usage
     }
 
-    pub fn sorted_empty_nodes(&self) -> &Vec<Index> {
-        debug_assert!(assertions::node_arena_valid(&self.nodes, &self.top_node_i), "{:#?}", self.sorted_empty_nodes.iter().map(|n| &self.nodes[*n]).collect_vec());
-        debug_assert!(assertions::cached_sorted_empty_nodes_correct(&self.nodes(), &self.sorted_empty_nodes), "{:#?}", self.sorted_empty_nodes.iter().map(|n| &self.nodes[*n]).collect_vec());
+    /// Total area of the parts in the layout
+    pub fn used_area(&self) -> u64 {
+        self.nodes.iter().map(|(_, node)| {
+            match node.parttype(){
+                Some(parttype) => parttype.area(),
+                None => 0
+            }
+        }).sum::<u64>()
+    }
 
-        &self.sorted_empty_nodes
+    /// Whether the parts cover the entire placeable area of the sheet (compared on the integer areas), the layout cannot be improved any further
+    pub fn is_perfect(&self) -> bool {
+        self.used_area() == self.sheettype.placeable_area()
     }
 
-    pub fn get_removable_nodes(&self) -> Vec<Index> {
-        //All nodes with children or that contain a part are removable
-        self.nodes.iter()
-            .filter(|(_, node)| node.parttype().is_some() || !node.children().is_empty())
-            .map(|(index, _)| index)
-            .collect_vec()
+    /// Total area of the empty nodes (waste) in the layout
+    pub fn waste_area(&self) -> u64 {
+        self.sorted_empty_nodes.iter().map(|n| self.nodes[*n].area()).sum()
     }
 
-    pub fn sheettype(&self) -> &'a SheetType {
+    /// How scattered the waste of the layout is: 1 - (largest empty area / total empty area).
+    /// 0 means all waste is a single piece (or there is no waste at all), values near 1 mean many small pieces.
+    pub fn fragmentation_index(&self) -> f64 {
+        let total = self.waste_area();
+        match total {
+            0 => 0.0,
+            _ => {
+                let largest = self.sorted_empty_nodes.iter().map(|n| self.nodes[*n].area()).max().unwrap_or(0);
+                1.0 - largest as f64 / total as f64
+            }
+        }
+    }
+
+    /// Weight of the waste in the layout, None if the density of the sheettype is unknown
+    pub fn scrap_weight(&self) -> Option<f64> {
+        self.sheettype.density().map(|density| density * self.waste_area() as f64)
+    }
+
+    /// Longest extent of any empty node along the axis (its width for Horizontal, its height for Vertical), 0 if there are no empty nodes.
+    /// Empty nodes are not merged across subtrees, see maximal_free_rects for the free space regardless of the tree.
+    pub fn max_free_dimension(&self, axis: Orientation) -> u64 {
+        self.free_dimensions(axis).max().unwrap_or(0)
+    }
+
+    /// Shortest extent of any empty node along the axis, see max_free_dimension. 0 if there are no empty nodes.
+    pub fn min_free_dimension(&self, axis: Orientation) -> u64 {
+        self.free_dimensions(axis).min().unwrap_or(0)
+    }
+
+    fn free_dimensions(&self, axis: Orientation) -> impl Iterator<Item = u64> + '_ {
+        self.sorted_empty_nodes.iter().map(move |&i| {
+            let node = &self.nodes[i];
+            match axis {
+                Orientation::Horizontal => node.width(),
+                Orientation::Vertical => node.height(),
+            }
+        })
+    }
+
+    pub fn sorted_empty_nodes(&self) -> &Vec<Index> {
+        debug_assert!(assertions::node_arena_valid(&self.nodes, &self.top_node_i), "{:#?}", self.sorted_empty_nodes.iter().map(|n| &self.nodes[*n]).collect_vec());
+        debug_assert!(assertions::cached_sorted_empty_nodes_correct(self.nodes(), &self.sorted_empty_nodes, self.empty_node_order), "{:#?}", self.sorted_empty_nodes.iter().map(|n| &self.nodes[*n]).collect_vec());
+
+        &self.sorted_empty_nodes
+    }
+
+    /// All removable nodes of the layout, sorted by their absolute position (x, then y) and then by level.
+    pub fn get_removable_nodes(&self) -> Vec<Index> {
+        let mut removable_nodes = self.removable_nodes_unordered();
+        removable_nodes.sort_by_cached_key(|node_i| {
+            let (x, y) = self.node_position(*node_i);
+            (x, y, self.nodes[*node_i].level())
+        });
+        removable_nodes
+    }
+
+    /// Same as get_removable_nodes, but in arena order, for internal use where the order does not matter.
+    pub(crate) fn removable_nodes_unordered(&self) -> Vec<Index> {
+        //All nodes with children or that contain a part are removable, except for the nodes around the unusable regions (below the top node)
+        let has_unusable_regions = !self.sheettype.inset_unusable_regions().is_empty();
+        self.nodes.iter()
+            .filter(|(_, node)| node.is_part() || !node.children().is_empty())
+            .filter(|(index, _)| !has_unusable_regions || *index == self.top_node_i || !self.contains_unusable_node(*index))
+            .map(|(index, _)| index)
+            .collect_vec()
+    }
+
+    /// Whether the subtree of the node (including the node itself) contains an unusable node
+    fn contains_unusable_node(&self, node_index: Index) -> bool {
+        let node = &self.nodes[node_index];
+        node.is_unusable() || node.children().iter().any(|c| self.contains_unusable_node(*c))
+    }
+
+    /// Reports which sizes of a catalog of reusable offcuts can be carved from the empty nodes of the layout.
+    /// A catalog size (width, height) matches an empty node if it fits inside the node in either orientation.
+    /// Every match is returned as (catalog width, catalog height, index of the empty node).
+    /// The layout itself is not modified.
+    pub fn reusable_offcuts(&self, catalog: &[(u64, u64)]) -> Vec<(u64, u64, Index)> {
+        self.sorted_empty_nodes.iter()
+            .flat_map(|&node_i| {
+                let node = &self.nodes[node_i];
+                catalog.iter()
+                    .filter(move |&&(w, h)| {
+                        (w <= node.width() && h <= node.height()) || (h <= node.width() && w <= node.height())
+                    })
+                    .map(move |&(w, h)| (w, h, node_i))
+            })
+            .collect_vec()
+    }
+
+    /// Absolute (x, y) position of a node in the layout, relative to the top-left corner of the sheet (including the margins)
+    pub fn node_position(&self, node_index: Index) -> (u64, u64) {
+        let (x, y) = self.layout_position(node_index);
+        let margins = self.sheettype.margins();
+        (x + margins.left, y + margins.top)
+    }
+
+    /// Position of a node relative to the top node, which lies inside the margins of the sheet.
+    /// Constant time when the position cache is enabled, otherwise the ancestors and their preceding siblings are walked.
+    fn layout_position(&self, node_index: Index) -> (u64, u64) {
+        match self.nodes[node_index].cached_position() {
+            Some(position) => position,
+            None => self.position_from_parent(node_index),
+        }
+    }
+
+    /// Position of a node relative to the top node, from the position of its parent and the sizes of its preceding siblings.
+    /// The children of a node with a horizontal cut are stacked along the y-axis, those of a node with a vertical cut along the x-axis.
+    fn position_from_parent(&self, node_index: Index) -> (u64, u64) {
+        match self.nodes[node_index].parent() {
+            None => (0, 0),
+            Some(parent_index) => {
+                let parent = &self.nodes[*parent_index];
+                let (mut x, mut y) = self.layout_position(*parent_index);
+                for sibling in parent.children().iter().take_while(|&&c| c != node_index) {
+                    match parent.next_cut_orient() {
+                        Orientation::Horizontal => y += self.nodes[*sibling].height(),
+                        Orientation::Vertical => x += self.nodes[*sibling].width(),
+                    }
+                }
+                (x, y)
+            }
+        }
+    }
+
+    /// Whether the part is placed near enough to the edge required by its edge affinity (if any) when the original node is replaced by the replacements.
+    /// The replacements are registered after the other children of the parent, or at the rank of the original node if it is fixed (see fixed_rank).
+    pub fn edge_affinity_allows(&self, original: Index, replacements: &[NodeBlueprint], parttype: &PartType) -> bool {
+        let edge_affinity = match parttype.edge_affinity() {
+            Some(edge_affinity) => edge_affinity,
+            None => return true,
+        };
+        match self.inserted_part_rect(original, replacements, parttype.id()) {
+            Some((x, y, _, _)) => {
+                let margins = self.sheettype.margins();
+                edge_affinity.allows(margins.left + x, margins.top + y)
+            }
+            None => true,
+        }
+    }
+
+    /// Rectangle (relative to the top node) of the first part of the parttype within the replacements, if the original node is replaced by them.
+    /// The replacements are registered after the other children of the parent, or at the rank of the original node if it is fixed (see fixed_rank).
+    fn inserted_part_rect(&self, original: Index, replacements: &[NodeBlueprint], parttype_id: usize) -> Option<(u64, u64, u64, u64)> {
+        let parent_index = self.nodes[original].parent().expect("original node has no parent");
+        let parent = &self.nodes[parent_index];
+        let preceding_siblings = match self.fixed_rank(original) {
+            Some(rank) => parent.children()[..rank].to_vec(),
+            None => parent.children().iter().copied().filter(|&c| c != original).collect_vec(),
+        };
+        let (mut x, mut y) = self.layout_position(parent_index);
+        for sibling in preceding_siblings {
+            match parent.next_cut_orient() {
+                Orientation::Horizontal => y += self.nodes[sibling].height(),
+                Orientation::Vertical => x += self.nodes[sibling].width(),
+            }
+        }
+        part_rect(replacements, parent.next_cut_orient(), parttype_id)
+            .map(|(offset_x, offset_y, width, height)| (x + offset_x, y + offset_y, width, height))
+    }
+
+    /// Whether a part of this size can be inserted into the node while keeping the first-level strips homogeneous.
+    /// A first-level strip is a child of the top node, all parts within it need to share the dimension across the strip:
+    /// their height if the first cut is horizontal, their width if it is vertical.
+    /// Inserting into a first-level node is always allowed, as the replacements form new strips.
+    pub fn strip_allows(&self, node_index: Index, part_size: &Size) -> bool {
+        let strip_dimension = |width: u64, height: u64| match self.nodes[self.top_node_i].next_cut_orient() {
+            Orientation::Horizontal => height,
+            Orientation::Vertical => width,
+        };
+
+        //Find the first-level ancestor
+        let mut strip = node_index;
+        while let Some(parent) = *self.nodes[strip].parent() {
+            if parent == self.top_node_i {
+                break;
+            }
+            strip = parent;
+        }
+        if strip == node_index {
+            return true;
+        }
+
+        let part_dimension = strip_dimension(part_size.width(), part_size.height());
+        let mut stack = vec![strip];
+        while let Some(node_i) = stack.pop() {
+            let node = &self.nodes[node_i];
+            if node.is_part() && strip_dimension(node.width(), node.height()) != part_dimension {
+                return false;
+            }
+            stack.extend(node.children());
+        }
+        true
+    }
+
+    /// All parts placed in the layout with their position and rotation, sorted by position (y, then x)
+    pub fn placed_parts(&self) -> Vec<PlacedPart> {
+        self.placed_parts_from(OriginCorner::TopLeft)
+    }
+
+    /// All parts placed in the layout, with their position relative to the given corner of the sheet (including the margins),
+    /// sorted by position (y, then x)
+    pub fn placed_parts_from(&self, origin: OriginCorner) -> Vec<PlacedPart> {
+        let sheet_size = self.sheet_size();
+        self.nodes.iter()
+            .filter_map(|(i, n)| n.part_id().map(|parttype_id| {
+                let (x, y) = origin.transform(self.node_position(i), (n.width(), n.height()), sheet_size);
+                PlacedPart { parttype_id, x, y, width: n.width(), height: n.height(), rotation: n.rotation().unwrap() }
+            }))
+            .sorted_by_key(|p| (p.y, p.x))
+            .collect_vec()
+    }
+
+    /// Order in which the parts are unloaded from the cut sheet, as (sequence number, parttype id) pairs.
+    /// Sequence numbers start at 1 and follow the reading order of the positions of the parts on the sheet.
+    pub fn extraction_order(&self, reading_order: ReadingOrder) -> Vec<(usize, usize)> {
+        self.placed_parts().iter()
+            .sorted_by_key(|p| reading_order.key(p))
+            .enumerate()
+            .map(|(i, p)| (i + 1, p.parttype_id))
+            .collect_vec()
+    }
+
+    /// Size of the top node and the margins around it
+    fn sheet_size(&self) -> (u64, u64) {
+        let top_node = &self.nodes[self.top_node_i];
+        let margins = self.sheettype.margins();
+        (top_node.width() + margins.horizontal(), top_node.height() + margins.vertical())
+    }
+
+    /// Upper contour of the placed parts, seen from the top-left corner of the sheet.
+    /// For Horizontal, the contour runs along the x-axis and every segment (x, height) holds the lowest bottom edge (y + height) of the parts above it.
+    /// For Vertical, it runs along the y-axis and every segment (y, width) holds the rightmost edge (x + width) of the parts next to it.
+    /// A segment extends up to the position of the next one (or the end of the sheet), adjacent segments always differ in height.
+    pub fn skyline(&self, axis: Orientation) -> Vec<(u64, u64)> {
+        let (sheet_width, sheet_height) = self.sheet_size();
+        let length = match axis {
+            Orientation::Horizontal => sheet_width,
+            Orientation::Vertical => sheet_height,
+        };
+        //(start, end, edge) of every part along the axis
+        let spans = self.placed_parts().into_iter()
+            .map(|p| match axis {
+                Orientation::Horizontal => (p.x, p.x + p.width, p.y + p.height),
+                Orientation::Vertical => (p.y, p.y + p.height, p.x + p.width),
+            })
+            .collect_vec();
+        let breakpoints = spans.iter()
+            .flat_map(|(start, end, _)| [*start, *end])
+            .chain([0, length])
+            .sorted()
+            .dedup()
+            .collect_vec();
+
+        let mut skyline: Vec<(u64, u64)> = vec![];
+        for (&start, &end) in breakpoints.iter().tuple_windows() {
+            let edge = spans.iter()
+                .filter(|(s, e, _)| *s < end && start < *e)
+                .map(|(_, _, edge)| *edge)
+                .max().unwrap_or(0);
+            if skyline.last().map(|(_, h)| *h) != Some(edge) {
+                skyline.push((start, edge));
+            }
+        }
+        skyline
+    }
+
+    /// Longest chain of dependent cuts in the layout: a node can only be cut after the cut separating it from its siblings.
+    /// Every node on a path from the top node to a leaf contributes the cut which frees it from its siblings
+    /// (the one following it, or the one preceding it for the last child). Single children do not require a cut.
+    /// Returns the cuts of the path with the largest total cut length, ordered from the top node down.
+    pub fn cut_critical_path(&self) -> Vec<Cut> {
+        self.critical_path_from(self.top_node_i, self.node_position(self.top_node_i)).1
+    }
+
+    /// All cuts of the layout in an order in which they can be performed: the cuts separating the children of a node
+    /// precede the cuts within these children, so every cut runs between the edges of the sheet or earlier cuts.
+    /// The nodes are visited depth-first, every cut lies on the boundary between two consecutive children (the kerf of the cut precedes it),
+    /// relative to the top-left corner of the sheet. Trimming the margins of the sheet is not included.
+    pub fn cut_sequence(&self) -> Vec<Cut> {
+        let margins = self.sheettype.margins();
+        let mut cuts = vec![];
+        self.cut_sequence_from(self.top_node_i, (margins.left, margins.top), &mut cuts);
+        cuts
+    }
+
+    fn cut_sequence_from(&self, node_index: Index, (x, y): (u64, u64), cuts: &mut Vec<Cut>) {
+        let node = &self.nodes[node_index];
+        let mut offset = 0;
+        let mut child_positions = vec![];
+        for (i, &child_index) in node.children().iter().enumerate() {
+            let child = &self.nodes[child_index];
+            if i > 0 {
+                cuts.push(match node.next_cut_orient() {
+                    Orientation::Horizontal => Cut { x, y: y + offset, length: node.width(), orientation: Orientation::Horizontal, level: node.level() },
+                    Orientation::Vertical => Cut { x: x + offset, y, length: node.height(), orientation: Orientation::Vertical, level: node.level() },
+                });
+            }
+            let child_pos = match node.next_cut_orient() {
+                Orientation::Horizontal => (x, y + offset),
+                Orientation::Vertical => (x + offset, y),
+            };
+            child_positions.push((child_index, child_pos));
+            offset += match node.next_cut_orient() {
+                Orientation::Horizontal => child.height(),
+                Orientation::Vertical => child.width(),
+            };
+        }
+        for (child_index, child_pos) in child_positions {
+            self.cut_sequence_from(child_index, child_pos, cuts);
+        }
+    }
+
+    /// The cut sequence (see cut_sequence) as segments between two points relative to the origin corner, e.g. to generate machine instructions.
+    /// The segments are clipped to the sheet, as the top node extends beyond it by the kerf.
+    pub fn cut_paths(&self, origin: OriginCorner) -> Vec<CutPath> {
+        let sheet_size = (self.sheettype.width(), self.sheettype.height());
+        let clip = |(x, y): (u64, u64)| (x.min(sheet_size.0), y.min(sheet_size.1));
+        self.cut_sequence().iter()
+            .map(|cut| {
+                let end = match cut.orientation {
+                    Orientation::Horizontal => (cut.x + cut.length, cut.y),
+                    Orientation::Vertical => (cut.x, cut.y + cut.length),
+                };
+                CutPath {
+                    start: origin.transform(clip((cut.x, cut.y)), (0, 0), sheet_size),
+                    end: origin.transform(clip(end), (0, 0), sheet_size),
+                    level: cut.level,
+                }
+            })
+            .collect_vec()
+    }
+
+    /// Number of cuts needed to cut the layout: every node with k children requires k - 1 cuts to separate them
+    pub fn cut_count(&self) -> usize {
+        self.nodes.iter()
+            .map(|(_, n)| n.children().len().saturating_sub(1))
+            .sum()
+    }
+
+    /// Lower bound on the number of cuts of any cutting pattern of the same parts on this sheet.
+    /// Every guillotine cut splits one piece into two, so separating n parts requires at least n - 1 cuts,
+    /// and one more if the parts do not cover the entire sheet (the waste is at least one additional piece).
+    pub fn min_cut_count(&self) -> usize {
+        let n_parts = self.nodes.iter().filter(|(_, n)| n.is_part()).count();
+        match (n_parts, self.is_perfect()) {
+            (0, _) => 0,
+            (n, true) => n - 1,
+            (n, false) => n,
+        }
+    }
+
+    /// min_cut_count / cut_count, 1.0 means the layout needs no more cuts than strictly necessary for its parts.
+    /// Lower values indicate cuts which only serve to divide the waste into multiple pieces.
+    pub fn cut_efficiency(&self) -> f64 {
+        match self.cut_count() {
+            0 => 1.0,
+            cut_count => self.min_cut_count() as f64 / cut_count as f64,
+        }
+    }
+
+    /// (total cut length, cuts) of the critical path below the node
+    fn critical_path_from(&self, node_index: Index, (x, y): (u64, u64)) -> (u64, Vec<Cut>) {
+        let node = &self.nodes[node_index];
+        let n_children = node.children().len();
+        let mut offset = 0;
+        let mut critical_path = (0, vec![]);
+        for (i, &child_index) in node.children().iter().enumerate() {
+            let child = &self.nodes[child_index];
+            let (child_pos, child_size) = match node.next_cut_orient() {
+                Orientation::Horizontal => ((x, y + offset), child.height()),
+                Orientation::Vertical => ((x + offset, y), child.width()),
+            };
+            let (mut length, mut cuts) = self.critical_path_from(child_index, child_pos);
+            if n_children > 1 {
+                let cut_offset = match i == n_children - 1 {
+                    true => offset,
+                    false => offset + child_size,
+                };
+                let cut = match node.next_cut_orient() {
+                    Orientation::Horizontal => Cut { x, y: y + cut_offset, length: node.width(), orientation: Orientation::Horizontal, level: node.level() },
+                    Orientation::Vertical => Cut { x: x + cut_offset, y, length: node.height(), orientation: Orientation::Vertical, level: node.level() },
+                };
+                length += cut.length;
+                cuts.insert(0, cut);
+            }
+            if length > critical_path.0 || (length == critical_path.0 && cuts.len() > critical_path.1.len()) {
+                critical_path = (length, cuts);
+            }
+            offset += child_size;
+        }
+        critical_path
+    }
+
+    /// Builds a spatial index of the parts, which is kept up to date with all further modifications of the layout.
+    /// Speeds up parts_at and parts_in_rect, at the expense of some overhead for every insertion and removal.
+    pub fn enable_spatial_index(&mut self) {
+        let top_node = &self.nodes[self.top_node_i];
+        let mut spatial_index = SpatialIndex::new(top_node.width(), top_node.height());
+        for (i, _) in self.nodes.iter().filter(|(_, n)| n.is_part()) {
+            spatial_index.insert(i, self.node_rect(i));
+        }
+        self.spatial_index = Some(spatial_index);
+    }
+
+    pub fn disable_spatial_index(&mut self) {
+        self.spatial_index = None;
+    }
+
+    /// Stores the position (relative to the top node) in every node, which is kept up to date with all further modifications of the layout.
+    /// Makes node_position (and the queries based on it, such as parts_in_rect and adjacencies) a lookup instead of a walk up the tree.
+    /// Registering a node after its siblings only computes its own position, registering it at a fixed rank or removing one
+    /// moves the subtrees of its following siblings.
+    pub fn enable_position_cache(&mut self) {
+        self.position_cache = true;
+        self.nodes[self.top_node_i].set_cached_position(Some((0, 0)));
+        self.cache_positions_below(self.top_node_i, 0);
+    }
+
+    pub fn disable_position_cache(&mut self) {
+        self.position_cache = false;
+        for (_, node) in self.nodes.iter_mut() {
+            node.set_cached_position(None);
+        }
+    }
+
+    /// Caches the positions of the children of a node (with a correct position), from the child at the given rank onwards, and of all their descendants
+    fn cache_positions_below(&mut self, node_index: Index, first_rank: usize) {
+        let (mut x, mut y) = self.layout_position(node_index);
+        let orientation = self.nodes[node_index].next_cut_orient();
+        for (rank, child) in self.nodes[node_index].children().clone().into_iter().enumerate() {
+            if rank >= first_rank {
+                self.nodes[child].set_cached_position(Some((x, y)));
+                self.cache_positions_below(child, 0);
+            }
+            match orientation {
+                Orientation::Horizontal => y += self.nodes[child].height(),
+                Orientation::Vertical => x += self.nodes[child].width(),
+            }
+        }
+    }
+
+    /// Part nodes containing the point (relative to the top-left corner of the sheet, including the margins), sorted by position (y, then x).
+    /// Points on the boundary between two parts belong to the part to the right or below.
+    pub fn parts_at(&self, x: u64, y: u64) -> Vec<Index> {
+        let margins = self.sheettype.margins();
+        match (x.checked_sub(margins.left), y.checked_sub(margins.top), self.spatial_index.as_ref()) {
+            (Some(layout_x), Some(layout_y), Some(spatial_index)) => spatial_index.at(layout_x, layout_y),
+            (Some(_), Some(_), None) => self.parts_in_rect(x, y, 1, 1),
+            _ => vec![],
+        }
+    }
+
+    /// Part nodes overlapping the rectangle (relative to the top-left corner of the sheet, including the margins) with a nonzero area,
+    /// sorted by position (y, then x)
+    pub fn parts_in_rect(&self, x: u64, y: u64, width: u64, height: u64) -> Vec<Index> {
+        //the nodes are positioned relative to the top node, the part of the rectangle inside the margins is left
+        let margins = self.sheettype.margins();
+        let (x_end, y_end) = ((x + width).saturating_sub(margins.left), (y + height).saturating_sub(margins.top));
+        let (x, y) = (x.saturating_sub(margins.left), y.saturating_sub(margins.top));
+        if x >= x_end || y >= y_end {
+            return vec![];
+        }
+        let (width, height) = (x_end - x, y_end - y);
+        match self.spatial_index.as_ref() {
+            Some(spatial_index) => spatial_index.in_rect((x, y, width, height)),
+            None => self.nodes.iter()
+                .filter(|(_, n)| n.is_part())
+                .map(|(i, _)| (i, self.node_rect(i)))
+                .filter(|(_, (n_x, n_y, n_w, n_h))| *n_x < x + width && x < n_x + n_w && *n_y < y + height && y < n_y + n_h)
+                .sorted_by_key(|(_, (n_x, n_y, _, _))| (*n_y, *n_x))
+                .map(|(i, _)| i)
+                .collect_vec(),
+        }
+    }
+
+    /// Updates the spatial index for all parts below a node, whose children were added, removed or reordered
+    fn reindex_parts_below(&mut self, node_index: Index) {
+        if self.spatial_index.is_none() {
+            return;
+        }
+        let mut stack = vec![node_index];
+        let mut parts = vec![];
+        while let Some(i) = stack.pop() {
+            match self.nodes[i].is_part() {
+                true => parts.push((i, self.node_rect(i))),
+                false => stack.extend(self.nodes[i].children().iter().copied()),
+            }
+        }
+        if let Some(spatial_index) = self.spatial_index.as_mut() {
+            for (i, rect) in parts {
+                spatial_index.remove(i);
+                spatial_index.insert(i, rect);
+            }
+        }
+    }
+
+    /// All maximal empty rectangles of the layout, in absolute coordinates (including the margins) and regardless of the nodes of the guillotine tree,
+    /// so adjacent empty nodes in different subtrees are merged into the larger rectangles they form.
+    /// Only rectangles which can still be cut out with guillotine cuts, together with the parts already placed, are returned.
+    /// Sorted by position (y, then x).
+    pub fn maximal_free_rects(&self) -> Vec<FreeRect> {
+        let top_node = &self.nodes[self.top_node_i];
+        let margins = self.sheettype.margins();
+        let parts = self.nodes.iter()
+            .filter(|(_, n)| n.is_part())
+            .map(|(i, _)| {
+                let (x, y, width, height) = self.node_rect(i);
+                FreeRect::new(x, y, width, height)
+            })
+            .collect_vec();
+
+        let mut free_rects = vec![FreeRect::new(0, 0, top_node.width(), top_node.height())];
+        for part in &parts {
+            let split = free_rects.iter().flat_map(|r| r.subtract(part)).collect_vec();
+            free_rects = free_rect::remove_contained(split);
+        }
+
+        free_rects.into_iter()
+            .filter(|r| {
+                let mut rects = parts.clone();
+                rects.push(*r);
+                free_rect::guillotine_separable(&rects)
+            })
+            .map(|r| FreeRect::new(r.x + margins.left, r.y + margins.top, r.width, r.height))
+            .sorted_by_key(|r| (r.y, r.x))
+            .collect_vec()
+    }
+
+    /// Rectangle of a node relative to the top node, as stored in the spatial index
+    fn node_rect(&self, node_index: Index) -> (u64, u64, u64, u64) {
+        let (x, y) = self.layout_position(node_index);
+        let node = &self.nodes[node_index];
+        (x, y, node.width(), node.height())
+    }
+
+    /// Pairs of parttype ids of the parts which share a boundary segment of nonzero length, touching corners do not count.
+    /// Every pair of touching parts is reported once, as (lowest id, highest id), sorted ascending.
+    pub fn adjacencies(&self) -> Vec<(usize, usize)> {
+        //(x, y, width, height, parttype id) of all parts, sorted by x to sweep along the x-axis
+        let parts = self.nodes.iter()
+            .filter_map(|(i, n)| n.part_id().map(|id| {
+                let (x, y) = self.node_position(i);
+                (x, y, n.width(), n.height(), id)
+            }))
+            .sorted_by_key(|p| p.0)
+            .collect_vec();
+
+        let mut adjacencies = vec![];
+        for (i, &(x_a, y_a, w_a, h_a, id_a)) in parts.iter().enumerate() {
+            //parts starting beyond the right edge of this part cannot touch it
+            for &(x_b, y_b, w_b, h_b, id_b) in parts[i + 1..].iter().take_while(|p| p.0 <= x_a + w_a) {
+                let x_overlap = (x_a + w_a).min(x_b + w_b) as i128 - x_a.max(x_b) as i128;
+                let y_overlap = (y_a + h_a).min(y_b + h_b) as i128 - y_a.max(y_b) as i128;
+                let touching = (x_overlap == 0 && y_overlap > 0) || (y_overlap == 0 && x_overlap > 0);
+                if touching {
+                    adjacencies.push((id_a.min(id_b), id_a.max(id_b)));
+                }
+            }
+        }
+        adjacencies.sort_unstable();
+        adjacencies
+    }
+
+    /// Nested JSON representation of the complete node tree, starting from the top node.
+    /// Every node contains its dimensions, next cut orientation (H or V), parttype id (or null) and children.
+    /// The dimensions include the kerf of the cuts which follow a node, except beyond the edge of the sheet:
+    /// the top node is the region of the sheet inside its margins, which the leaves tile exactly, so the areas of the leaves sum to the area of that region.
+    pub fn tree_to_json(&self) -> serde_json::Value {
+        let kerf = self.sheettype.kerf();
+        let top_node = &self.nodes[self.top_node_i];
+        let bounds = kerf.deflate(top_node.width(), top_node.height());
+        self.node_to_json(self.top_node_i, (0, 0), (bounds.width(), bounds.height()))
+    }
+
+    fn node_to_json(&self, node_index: Index, (x, y): (u64, u64), bounds: (u64, u64)) -> serde_json::Value {
+        let node = &self.nodes[node_index];
+        let orientation = match node.next_cut_orient() {
+            Orientation::Horizontal => "H",
+            Orientation::Vertical => "V",
+        };
+        let (mut child_x, mut child_y) = (x, y);
+        let mut children = vec![];
+        for child in node.children() {
+            children.push(self.node_to_json(*child, (child_x, child_y), bounds));
+            match node.next_cut_orient() {
+                Orientation::Horizontal => child_y += self.nodes[*child].height(),
+                Orientation::Vertical => child_x += self.nodes[*child].width(),
+            }
+        }
+
+        serde_json::json!({
+            "width": u64::min(node.width(), bounds.0.saturating_sub(x)),
+            "height": u64::min(node.height(), bounds.1.saturating_sub(y)),
+            "orientation": orientation,
+            "parttype": node.part_id(),
+            "children": children,
+        })
+    }
+
+    pub fn empty_node_order(&self) -> EmptyNodeOrder {
+        self.empty_node_order
+    }
+
+    pub fn sheettype(&self) -> &'a SheetType {
         self.sheettype
     }
 
@@ -393,4 +1942,1268 @@ impl<'a> Layout<'a> {
     pub fn id(&self) -> usize {
         self.id
     }
-}
\ No newline at end of file
+}
+
+/// State of a Layout at a certain moment, created by Layout::checkpoint().
+/// Only contains the caches and the length of the undo trail, the modifications themselves are recorded by the layout.
+#[derive(Debug)]
+#[must_use = "a checkpoint needs to be restored or committed"]
+pub struct LayoutCheckpoint {
+    layout_id: usize,
+    outermost: bool,
+    trail_len: usize,
+    cached_cost: Option<Cost>,
+    cached_usage: Option<f64>,
+}
+
+/// Modification of the node tree, recorded while a checkpoint is open
+#[derive(Debug, Clone)]
+enum TrailEntry<'a> {
+    /// The node was registered as a child of its parent, and in the sorted empty nodes if it was registered as empty
+    Registered { index: Index, is_empty: bool },
+    /// The node was unregistered at the given rank among the children of its parent,
+    /// and at the given rank in the sorted empty nodes if it was empty
+    Unregistered { index: Index, node: Node<'a>, rank: usize, empty_rank: Option<usize> },
+    /// The next cut orientation of the nodes was flipped
+    Flipped(Vec<Index>),
+}
+
+/// Outcome of Layout::repair_spacing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepairReport {
+    /// Number of pairs of parts which were closer than required
+    pub n_violations: usize,
+    /// Parttype ids of the parts which were removed from the layout because they no longer fit
+    pub dropped_parts: Vec<usize>,
+}
+
+/// Reasons why placements cannot be reproduced by a layout, see Layout::try_from_placements.
+/// Placements are referred to by their index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GuillotineError {
+    /// The size of the placement does not match its parttype in the given rotation, or the rotation is not allowed
+    PartMismatch(usize),
+    /// The placement extends beyond the region of the sheet inside the margins
+    OutsideSheet(usize),
+    /// The two placements overlap
+    Overlap(usize, usize),
+    /// The parts in the region (relative to the top-left corner of the sheet) cannot be separated by any edge-to-edge cut
+    NonGuillotine { x: u64, y: u64, width: u64, height: u64 },
+}
+
+impl fmt::Display for GuillotineError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GuillotineError::PartMismatch(i) => write!(f, "placement {} does not match its parttype", i),
+            GuillotineError::OutsideSheet(i) => write!(f, "placement {} lies outside the sheet", i),
+            GuillotineError::Overlap(i, j) => write!(f, "placements {} and {} overlap", i, j),
+            GuillotineError::NonGuillotine { x, y, width, height } =>
+                write!(f, "region ({}, {}, {}x{}) cannot be separated by a guillotine cut", x, y, width, height),
+        }
+    }
+}
+
+impl std::error::Error for GuillotineError {}
+
+/// Reasons why an operation on a Layout is rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutError {
+    /// The node does not exist in the layout
+    NodeNotFound,
+    /// The node has children, modifying it would invalidate their geometry
+    NodeHasChildren,
+    /// The node has siblings, so its cut orientation is determined by its parent
+    OrientationFixedByParent,
+    /// The node does not contain a part
+    NotAPart,
+    /// The node is not empty
+    NodeNotEmpty,
+    /// The part cannot be inserted into the node
+    PartDoesNotFit,
+    /// The operation requires nodes of two different layouts
+    SameLayout,
+    /// The orientation of the first cut of the layout is fixed
+    FirstCutOrientationFixed,
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::NodeNotFound => write!(f, "node not found in layout"),
+            LayoutError::NodeHasChildren => write!(f, "node has children"),
+            LayoutError::OrientationFixedByParent => write!(f, "cut orientation of the node is fixed by its parent"),
+            LayoutError::NotAPart => write!(f, "node does not contain a part"),
+            LayoutError::NodeNotEmpty => write!(f, "node is not empty"),
+            LayoutError::PartDoesNotFit => write!(f, "part does not fit in the node"),
+            LayoutError::SameLayout => write!(f, "nodes are in the same layout"),
+            LayoutError::FirstCutOrientationFixed => write!(f, "orientation of the first cut is fixed"),
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {}
+
+type Rect = (u64, u64, u64, u64);
+
+/// Guillotine decomposition of the region (x, y, width, height) into the strips between the cuts of the orientation which do not cross any of the rectangles,
+/// recursively with the cuts of the other orientation, until every strip is either one of the rectangles or does not overlap any of them.
+/// The rectangles cannot overlap and lie inside the region. The leaves are created by leaf(region, whether it is one of the rectangles, orientation).
+/// Returns the blueprint of the region, or the first region in which the rectangles cannot be separated by guillotine cuts.
+pub(crate) fn guillotine_decomposition(region: Rect, rects: &[Rect], orientation: Orientation, leaf: &mut impl FnMut(Rect, bool, Orientation) -> NodeBlueprint) -> Result<NodeBlueprint, Rect> {
+    let inside = rects.iter().copied()
+        .filter(|r| r.0 >= region.0 && r.1 >= region.1 && r.0 + r.2 <= region.0 + region.2 && r.1 + r.3 <= region.1 + region.3)
+        .collect_vec();
+    match inside.as_slice() {
+        [] => return Ok(leaf(region, false, orientation)),
+        [r] if *r == region => return Ok(leaf(region, true, orientation)),
+        _ => {}
+    }
+    let mut node = NodeBlueprint::new(region.2, region.3, None, orientation);
+    let strips = guillotine_strips(region, &inside, orientation);
+    match strips.len() {
+        //no cut of this orientation, a single child is cut in the other orientation
+        1 if guillotine_strips(region, &inside, orientation.rotate()).len() > 1 => node.add_child(guillotine_decomposition(region, &inside, orientation.rotate(), leaf)?),
+        1 => return Err(region),
+        _ => for strip in strips {
+            node.add_child(guillotine_decomposition(strip, &inside, orientation.rotate(), leaf)?);
+        }
+    }
+    Ok(node)
+}
+
+/// The strips of the region between all cuts of the orientation which do not cross any of the rectangles
+fn guillotine_strips(region: Rect, rects: &[Rect], orientation: Orientation) -> Vec<Rect> {
+    //(start, end) of a rectangle along the axis in which the cuts of the orientation separate rectangles
+    let span = |&(x, y, width, height): &Rect| match orientation {
+        Orientation::Horizontal => (y, y + height),
+        Orientation::Vertical => (x, x + width),
+    };
+    let (start, end) = span(&region);
+    let cuts = rects.iter()
+        .flat_map(|r| { let (a, b) = span(r); [a, b] })
+        .filter(|&c| c > start && c < end)
+        .filter(|&c| rects.iter().all(|r| { let (a, b) = span(r); c <= a || c >= b }))
+        .sorted_unstable()
+        .dedup();
+
+    [start].into_iter().chain(cuts).chain([end])
+        .tuple_windows()
+        .map(|(a, b)| match orientation {
+            Orientation::Horizontal => (region.0, a, region.2, b - a),
+            Orientation::Vertical => (a, region.1, b - a, region.3),
+        })
+        .collect_vec()
+}
+
+
+/// Copy of a blueprint with every node shrunk by (width, height), inserting empty space of this size between the children
+/// to keep them adding up. Adjacent empty nodes and the empty space between them are merged, which absorbs empty nodes smaller than the shrinkage.
+/// Parts are taken from the instance.
+fn shrink_blueprint(blueprint: &NodeBlueprint, (d_width, d_height): (u64, u64), instance: &Instance) -> NodeBlueprint {
+    let (width, height) = (blueprint.width() - d_width, blueprint.height() - d_height);
+    let parttype = blueprint.parttype_id().map(|id| instance.get_parttype(id));
+    let mut shrunk = NodeBlueprint::new(width, height, parttype, blueprint.next_cut_orient());
+    if parttype.is_some() {
+        return shrunk;
+    }
+
+    let child_orient = blueprint.next_cut_orient().rotate();
+    //length along the axis in which the children are stacked
+    let (d_length, length_of) = match blueprint.next_cut_orient() {
+        Orientation::Horizontal => (d_height as i128, NodeBlueprint::height as fn(&NodeBlueprint) -> u64),
+        Orientation::Vertical => (d_width as i128, NodeBlueprint::width as fn(&NodeBlueprint) -> u64),
+    };
+    let empty_node = |length: u64| match blueprint.next_cut_orient() {
+        Orientation::Horizontal => NodeBlueprint::new(width, length, None, child_orient),
+        Orientation::Vertical => NodeBlueprint::new(length, height, None, child_orient),
+    };
+
+    let mut children = vec![];
+    //length of the run of empty space since the last non-empty child
+    let mut empty_run: i128 = 0;
+    for (i, child) in blueprint.children().iter().enumerate() {
+        if i > 0 {
+            empty_run += d_length;
+        }
+        match child.is_empty() {
+            true => empty_run += length_of(child) as i128 - d_length,
+            false => {
+                debug_assert!(empty_run >= 0);
+                if empty_run > 0 {
+                    children.push(empty_node(empty_run as u64));
+                }
+                empty_run = 0;
+                children.push(shrink_blueprint(child, (d_width, d_height), instance));
+            }
+        }
+    }
+    debug_assert!(empty_run >= 0);
+    //the node stays empty if it contains no parts
+    if !children.is_empty() {
+        if empty_run > 0 {
+            children.push(empty_node(empty_run as u64));
+        }
+        children.into_iter().for_each(|c| shrunk.add_child(c));
+    }
+    shrunk
+}
+
+/// Rectangle of the (first) part of the parttype relative to the first blueprint, with the blueprints stacked in the cut orientation of their parent
+fn part_rect(blueprints: &[NodeBlueprint], parent_orientation: Orientation, parttype_id: usize) -> Option<(u64, u64, u64, u64)> {
+    let (mut x, mut y) = (0, 0);
+    for blueprint in blueprints {
+        if blueprint.parttype_id() == Some(parttype_id) {
+            return Some((x, y, blueprint.width(), blueprint.height()));
+        }
+        if let Some((child_x, child_y, width, height)) = part_rect(blueprint.children(), blueprint.next_cut_orient(), parttype_id) {
+            return Some((x + child_x, y + child_y, width, height));
+        }
+        match parent_orientation {
+            Orientation::Horizontal => y += blueprint.height(),
+            Orientation::Vertical => x += blueprint.width(),
+        }
+    }
+    None
+}
+
+/// Area of the bounding box of the rectangles (x, y, width, height) which is not covered by them, the rectangles do not overlap
+fn spread(rects: &[(u64, u64, u64, u64)]) -> u64 {
+    let min_x = rects.iter().map(|r| r.0).min().unwrap_or(0);
+    let min_y = rects.iter().map(|r| r.1).min().unwrap_or(0);
+    let max_x = rects.iter().map(|r| r.0 + r.2).max().unwrap_or(0);
+    let max_y = rects.iter().map(|r| r.1 + r.3).max().unwrap_or(0);
+    let covered = rects.iter().map(|r| r.2 * r.3).sum::<u64>();
+    (max_x - min_x) * (max_y - min_y) - covered
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use generational_arena::Index;
+    use itertools::Itertools;
+    use serde_json::json;
+
+    use crate::core::cost::Cost;
+    use crate::core::edge_affinity::{Edge, EdgeAffinity};
+    use crate::core::empty_node_order::EmptyNodeOrder;
+    use crate::core::entities::cut::{Cut, CutPath};
+    use crate::core::entities::free_rect::FreeRect;
+    use crate::core::entities::layout::{GuillotineError, Layout, LayoutError};
+    use crate::core::entities::parttype::PartType;
+    use crate::core::insertion::insertion_blueprint::{InsertionBlueprint, InsertionBlueprintError};
+    use crate::core::insertion::insertion_option::InsertionOption;
+    use crate::core::insertion::node_blueprint::NodeBlueprint;
+    use crate::core::kerf::Kerf;
+    use crate::core::layout_index::LayoutIndex;
+    use crate::core::leftover_valuator;
+    use crate::core::margins::Margins;
+    use crate::core::orientation::Orientation;
+    use crate::core::origin_corner::OriginCorner;
+    use crate::core::reading_order::ReadingOrder;
+    use crate::core::rotation::Rotation;
+    use crate::io::instance_error::InstanceError;
+    use crate::io::json_format::{JsonCornerCut, JsonRegion};
+    use crate::io::parser;
+    use crate::optimization::config::Config;
+    use crate::optimization::instance::Instance;
+    use crate::optimization::rr::cache_updates::IOCUpdates;
+    use crate::optimization::solutions::solution::Solution;
+    use crate::optimization::solutions::solution_diff::Placement;
+    use crate::util::assertions;
+    use crate::util::test_util;
+
+    /// Insertion of the parttype in its default rotation in the smallest empty node it fits in
+    fn insertion<'a>(layout: &Layout<'a>, parttype: &'a PartType) -> InsertionBlueprint<'a> {
+        let node_i = *layout.sorted_empty_nodes().iter().rev()
+            .find(|&&n| layout.nodes()[n].insertion_possible(parttype, Rotation::Default))
+            .unwrap();
+        let replacements = layout.nodes()[node_i].generate_insertion_node_blueprints(parttype, Rotation::Default, layout.sheettype().max_stages(), vec![]).remove(0);
+        InsertionBlueprint::new(LayoutIndex::Empty(0), node_i, replacements, parttype, Cost::empty(), layout.nodes()[node_i].level())
+    }
+
+    #[test]
+    fn reusable_offcuts_match_in_either_orientation() {
+        let instance = test_util::instance(&[(60, 100, 1)], &[(100, 100, 1)]);
+        let layout = test_util::layout(&instance, 0, &[(0, 0, 0)]);
+        //the only empty node is the 40x100 strip next to the part
+        let empty_node = layout.sorted_empty_nodes()[0];
+        let catalog = [(30, 90), (90, 30), (50, 50), (40, 100), (100, 40), (41, 101)];
+
+        let offcuts = layout.reusable_offcuts(&catalog);
+        assert_eq!(offcuts.iter().map(|&(w, h, _)| (w, h)).collect_vec(), vec![(30, 90), (90, 30), (40, 100), (100, 40)]);
+        assert!(offcuts.iter().all(|&(_, _, node)| node == empty_node));
+    }
+
+    #[test]
+    fn included_parts_and_removable_nodes_are_sorted() {
+        let instance = test_util::instance(&[(50, 50, 2), (30, 40, 1)], &[(100, 100, 1)]);
+        let layout = test_util::layout(&instance, 0, &[(1, 0, 0), (0, 50, 0), (0, 0, 50)]);
+        let reordered = test_util::layout(&instance, 0, &[(0, 0, 50), (0, 50, 0), (1, 0, 0)]);
+
+        assert_eq!(layout.get_included_parts(), vec![0, 0, 1]);
+
+        let key = |layout: &Layout, node: Index| {
+            let (x, y) = layout.node_position(node);
+            (x, y, layout.nodes()[node].level())
+        };
+        let keys = layout.get_removable_nodes().into_iter().map(|n| key(&layout, n)).collect_vec();
+        assert!(keys.is_sorted());
+        assert_eq!(keys.len(), layout.removable_nodes_unordered().len());
+        //the order only depends on the geometry, not on the order in which the nodes were created
+        let reordered_keys = reordered.get_removable_nodes().into_iter().map(|n| key(&reordered, n)).collect_vec();
+        assert_eq!(keys, reordered_keys);
+    }
+
+    #[test]
+    fn fill_with_fills_a_strip() {
+        let instance = test_util::instance(&[(100, 80, 1), (10, 20, 30)], &[(100, 100, 1)]);
+        //leaves a 100x20 strip at the bottom
+        let mut layout = test_util::layout(&instance, 0, &[(0, 0, 0)]);
+        let empty_area = |layout: &Layout| layout.sorted_empty_nodes().iter().map(|n| layout.nodes()[*n].area()).sum::<u64>();
+
+        assert_eq!(layout.fill_with(instance.get_parttype(1), 4, &instance), 4);
+        assert_eq!(empty_area(&layout), 100 * 20 - 4 * 10 * 20);
+        assert_eq!(layout.fill_with(instance.get_parttype(1), 30, &instance), 6);
+        assert_eq!(layout.get_included_parts(), vec![0, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1]);
+        assert_eq!(empty_area(&layout), 0);
+        assert!(layout.sorted_empty_nodes().is_empty());
+    }
+
+    #[test]
+    fn fill_with_respects_kerf_and_rotation() {
+        //a vertical kerf of 2: every part (and the sheet) is 2 wider, the strip fits 102 / 12 = 8 parts
+        let instance = test_util::instance_with_kerf(&[(100, 80, 1), (10, 20, 30)], &[(100, 100, 1)], Kerf::new(0, 2));
+        let mut layout = test_util::layout(&instance, 0, &[(0, 0, 0)]);
+
+        assert_eq!(layout.fill_with(instance.get_parttype(1), 30, &instance), 8);
+        let leftovers = layout.sorted_empty_nodes().iter().map(|n| (layout.nodes()[*n].width(), layout.nodes()[*n].height())).collect_vec();
+        assert_eq!(leftovers, vec![(6, 20)]);
+
+        //a part which only fits rotated is not placed if its rotation is fixed
+        let parts = vec![
+            (test_util::parttype(0, 100, 80), 1),
+            (PartType::new(1, 20, 10, Some(Rotation::Default), None, 0.0, Kerf::default()), 5),
+            (test_util::parttype(2, 25, 20), 1),
+        ];
+        let instance = Instance::new(parts, vec![(test_util::sheettype(0, 100, 100), 1)], 0, false);
+        let mut layout = test_util::layout(&instance, 0, &[(0, 0, 0), (2, 0, 80)]);
+        //the remaining 75x20 strip only fits the (20x10) parts in their nominal orientation, stacked in pairs
+        assert_eq!(layout.fill_with(instance.get_parttype(1), 5, &instance), 5);
+        assert!(layout.nodes().iter().filter(|(_, n)| n.parttype().is_some_and(|p| p.id() == 1)).all(|(_, n)| n.width() == 20));
+    }
+
+    #[test]
+    fn horizontal_and_vertical_kerf_differ() {
+        //horizontal cuts consume 3 of height, vertical cuts 2 of width: a 2x2 grid of 40x40 parts fits in 102x103
+        let kerf = Kerf::new(3, 2);
+        let instance = test_util::instance_with_kerf(&[(40, 40, 10)], &[(100, 100, 1)], kerf);
+        let mut layout = Layout::new(0, instance.get_sheettype(0), Orientation::Horizontal, EmptyNodeOrder::default());
+
+        assert_eq!(layout.fill_with(instance.get_parttype(0), 10, &instance), 4);
+        let positions = layout.nodes().iter()
+            .filter(|(_, n)| n.parttype().is_some())
+            .map(|(i, _)| layout.node_position(i))
+            .collect_vec();
+        let xs = positions.iter().map(|&(x, _)| x).sorted().dedup().collect_vec();
+        let ys = positions.iter().map(|&(_, y)| y).sorted().dedup().collect_vec();
+        assert_eq!(xs, vec![0, 42]);
+        assert_eq!(ys, vec![0, 43]);
+
+        //the usage of the blueprint excludes the kerf, as the usage of the layout does
+        let blueprint = NodeBlueprint::from_node(*layout.top_node_index(), layout.nodes());
+        assert_eq!(blueprint.part_area(&kerf), 4 * 40 * 40);
+        assert_eq!(blueprint.calculate_usage(&kerf), layout.usage(true));
+    }
+
+    #[test]
+    fn cost_delta_reconciles_with_the_recalculated_cost() {
+        let instance = test_util::instance(&[(30, 20, 5), (25, 45, 5)], &[(100, 100, 1)]);
+        let mut layout = Layout::new(0, instance.get_sheettype(0), Orientation::Horizontal, EmptyNodeOrder::default());
+        for parttype_id in [0, 1, 0, 1, 0] {
+            let cost_before = layout.cost(true);
+            let blueprint = insertion(&layout, instance.get_parttype(parttype_id));
+            let delta = layout.apply_and_cost_delta(&blueprint, &instance, &mut IOCUpdates::new(LayoutIndex::Empty(0)));
+            assert!(assertions::cost_delta_reconciles(&cost_before, &delta, &layout.cost(true)));
+        }
+    }
+
+    #[test]
+    fn clustering_penalty_is_included_in_the_cost_delta_and_the_cost_after_removal() {
+        let instance = test_util::instance(&[(30, 20, 5), (25, 45, 5)], &[(100, 100, 1)]);
+        let mut layout = Layout::new(0, instance.get_sheettype(0), Orientation::Horizontal, EmptyNodeOrder::default());
+        layout.set_clustering_weight(0.5);
+        for parttype_id in [0, 1, 0, 1, 0] {
+            let cost_before = layout.cost(true);
+            let blueprint = insertion(&layout, instance.get_parttype(parttype_id));
+            let delta = layout.apply_and_cost_delta(&blueprint, &instance, &mut IOCUpdates::new(LayoutIndex::Empty(0)));
+            assert!(assertions::cost_delta_reconciles(&cost_before, &delta, &layout.cost(true)));
+        }
+
+        let part = layout.nodes().iter().find(|(_, n)| n.part_id() == Some(0)).unwrap().0;
+        let dry_run = layout.cost_after_removal(part);
+        layout.remove_node(part);
+        assert!(assertions::cost_delta_reconciles(&dry_run, &Cost::empty(), &layout.cost(true)));
+    }
+
+    #[test]
+    fn parttype_spreads_are_the_uncovered_areas_of_the_bounding_boxes() {
+        let instance = test_util::instance(&[(30, 20, 2), (25, 45, 1)], &[(100, 100, 1)]);
+        //the two 30x20 parts span an 80x20 box, the single 25x45 part covers its own box
+        let mut layout = test_util::layout(&instance, 0, &[(0, 0, 0), (0, 50, 0), (1, 0, 20)]);
+        assert_eq!(layout.parttype_spreads(), vec![(0, 80 * 20 - 2 * 30 * 20), (1, 0)]);
+
+        assert_eq!(layout.clustering_penalty(), 0.0);
+        let cost = layout.cost(true);
+        layout.set_clustering_weight(2.0);
+        assert_eq!(layout.clustering_penalty(), 2.0 * leftover_valuator::valuate(400));
+        assert_eq!(layout.cost(true), cost.add_leftover_value(-layout.clustering_penalty()));
+    }
+
+    #[test]
+    fn cost_delta_keeps_the_cached_cost() {
+        let instance = test_util::instance(&[(30, 20, 5)], &[(100, 100, 1)]);
+        let mut layout = Layout::new(0, instance.get_sheettype(0), Orientation::Horizontal, EmptyNodeOrder::default());
+        let cost_before = layout.cost(false);
+
+        let blueprint = insertion(&layout, instance.get_parttype(0));
+        let delta = layout.apply_and_cost_delta(&blueprint, &instance, &mut IOCUpdates::new(LayoutIndex::Empty(0)));
+        assert_eq!(layout.cached_cost, Some(cost_before.add(&delta)));
+        //implement_insertion_blueprint leaves the cost to be recalculated
+        let blueprint = insertion(&layout, instance.get_parttype(0));
+        layout.implement_insertion_blueprint(&blueprint, &instance, &mut IOCUpdates::new(LayoutIndex::Empty(0))).unwrap();
+        assert_eq!(layout.cached_cost, None);
+    }
+
+    #[test]
+    fn cost_delta_is_recalculated_with_a_cost_function() {
+        let instance = test_util::instance(&[(30, 20, 2)], &[(100, 100, 1)]);
+        let mut layout = Layout::new(0, instance.get_sheettype(0), Orientation::Horizontal, EmptyNodeOrder::default());
+        //a cost function which cannot be evaluated per node: the number of parts
+        layout.set_cost_function(Some(|l: &Layout| Cost::empty().add_material_cost(l.get_included_parts().len() as u64)));
+        let cost_before = layout.cost(false);
+
+        let blueprint = insertion(&layout, instance.get_parttype(0));
+        let delta = layout.apply_and_cost_delta(&blueprint, &instance, &mut IOCUpdates::new(LayoutIndex::Empty(0)));
+        assert_eq!(delta.material_cost, 1);
+        assert!(assertions::cost_delta_reconciles(&cost_before, &delta, &layout.cost(true)));
+    }
+
+    fn leaf_area(node: &serde_json::Value) -> u64 {
+        match node["children"].as_array().unwrap().as_slice() {
+            [] => node["width"].as_u64().unwrap() * node["height"].as_u64().unwrap(),
+            children => children.iter().map(leaf_area).sum(),
+        }
+    }
+
+    #[test]
+    fn tree_leaves_tile_the_sheet() {
+        let instance = test_util::instance_with_kerf(&[(40, 40, 10), (15, 70, 3)], &[(100, 100, 1)], Kerf::new(3, 2));
+        let mut layout = Layout::new(0, instance.get_sheettype(0), Orientation::Horizontal, EmptyNodeOrder::default());
+        layout.fill_with(instance.get_parttype(0), 3, &instance);
+        layout.fill_with(instance.get_parttype(1), 3, &instance);
+
+        let tree = layout.tree_to_json();
+        assert_eq!((tree["width"].as_u64(), tree["height"].as_u64()), (Some(100), Some(100)));
+        assert_eq!(leaf_area(&tree), 100 * 100);
+    }
+
+    #[test]
+    fn empty_node_order_changes_the_best_fit() {
+        let parts = [(40, 60, 1), (10, 10, 1)];
+        let empty_node_sizes = |layout: &Layout| layout.sorted_empty_nodes().iter()
+            .map(|n| (layout.nodes()[*n].width(), layout.nodes()[*n].height()))
+            .collect_vec();
+        //the smallest empty node by area (10x60) is not the one with the smallest largest dimension (50x40)
+        for (order, filled_empty_node) in [(EmptyNodeOrder::Area, (10, 60)), (EmptyNodeOrder::MaxDimension, (50, 40))] {
+            let instance = test_util::instance(&parts, &[(50, 100, 1)]);
+            let mut layout = Layout::new(0, instance.get_sheettype(0), Orientation::Horizontal, order);
+            layout.fill_with(instance.get_parttype(0), 1, &instance);
+            let mut empty_nodes = empty_node_sizes(&layout);
+            empty_nodes.sort();
+            assert_eq!(empty_nodes, vec![(10, 60), (50, 40)]);
+
+            //the part is placed in the last empty node in the order it fits in
+            assert_eq!(layout.fill_with(instance.get_parttype(1), 1, &instance), 1);
+            assert!(!empty_node_sizes(&layout).contains(&filled_empty_node));
+        }
+    }
+
+    #[test]
+    fn non_tiling_blueprint_is_rejected() {
+        let instance = test_util::instance(&[(60, 100, 1), (40, 50, 1)], &[(100, 100, 1)]);
+        let mut layout = test_util::layout(&instance, 0, &[(0, 0, 0)]);
+        let layout_index = LayoutIndex::Existing(Index::from_raw_parts(0, 0));
+        let empty_node_index = layout.sorted_empty_nodes()[0];
+        let empty_node = &layout.nodes()[empty_node_index];
+        assert_eq!((empty_node.width(), empty_node.height()), (40, 100));
+
+        //the 40x50 part alone leaves half of the 40x100 empty node uncovered
+        let parttype = instance.get_parttype(1);
+        let replacements = vec![NodeBlueprint::new(40, 50, Some(parttype), empty_node.next_cut_orient())];
+        let blueprint = InsertionBlueprint::new(layout_index, empty_node_index, replacements, parttype, Cost::empty(), layout.nodes()[empty_node_index].level());
+        let cost_before = layout.cost(true);
+
+        let result = layout.implement_insertion_blueprint(&blueprint, &instance, &mut IOCUpdates::new(layout_index));
+        assert_eq!(result, Err(InsertionBlueprintError::ReplacementsDoNotTile));
+        assert_eq!(layout.get_included_parts(), vec![0]);
+        assert_eq!(layout.sorted_empty_nodes(), &[empty_node_index]);
+        assert_eq!(layout.cost(true), cost_before);
+    }
+
+    #[test]
+    fn rotation_penalty_only_suppresses_profitable_rotations_when_high() {
+        //placed upright, the 25x40 part leaves a single 75x40 leftover in the 100x40 strip, placed as preferred (rotated) it fragments the strip
+        let part_width = |penalty: f32| {
+            let parts = vec![
+                (test_util::parttype(0, 100, 60), 1),
+                (PartType::new(1, 25, 40, None, Some(Rotation::Rotated), penalty, Kerf::default()), 1),
+            ];
+            leftover_valuator::set_power(2.0);
+            let instance = Instance::new(parts, vec![(test_util::sheettype(0, 100, 100), 1)], 0, false);
+            let mut layout = test_util::layout(&instance, 0, &[(0, 0, 0)]);
+            assert_eq!(layout.fill_with(instance.get_parttype(1), 1, &instance), 1);
+            layout.nodes().iter().find(|(_, n)| n.parttype().is_some_and(|p| p.id() == 1)).unwrap().1.width()
+        };
+
+        assert_eq!(part_width(1.0), 25);
+        assert_eq!(part_width(10.0), 40);
+    }
+
+    #[test]
+    fn flip_node_orientation_only_accepts_leaf_nodes() {
+        let instance = test_util::instance(&[(60, 100, 1)], &[(100, 100, 1)]);
+        let mut layout = Layout::new(0, instance.get_sheettype(0), Orientation::Horizontal, EmptyNodeOrder::default());
+        let top_node = *layout.top_node_index();
+        assert_eq!(layout.flip_node_orientation(top_node), Err(LayoutError::NodeHasChildren));
+
+        //the placeholder of an empty layout is a leaf and the only child of the top node
+        let placeholder = layout.sorted_empty_nodes()[0];
+        let orientation = layout.nodes()[placeholder].next_cut_orient();
+        assert_eq!(layout.flip_node_orientation(placeholder), Ok(()));
+        assert_ne!(layout.nodes()[placeholder].next_cut_orient(), orientation);
+        assert_ne!(layout.nodes()[top_node].next_cut_orient(), layout.nodes()[placeholder].next_cut_orient());
+
+        //next to a part, the orientation of an empty node is fixed by its parent
+        let layout = &mut test_util::layout(&instance, 0, &[(0, 0, 0)]);
+        let empty_node = layout.sorted_empty_nodes()[0];
+        assert_eq!(layout.flip_node_orientation(empty_node), Err(LayoutError::OrientationFixedByParent));
+    }
+
+    #[test]
+    fn adjacencies_only_report_parts_sharing_an_edge() {
+        let instance = test_util::instance(&[(50, 50, 1), (50, 50, 1), (50, 50, 1)], &[(100, 100, 1)]);
+        //L arrangement: 1 below 0 and 2 right of 1, 0 and 2 only share a corner
+        let layout = test_util::layout(&instance, 0, &[(0, 0, 0), (1, 0, 50), (2, 50, 50)]);
+
+        assert_eq!(layout.adjacencies(), vec![(0, 1), (1, 2)]);
+    }
+
+    /// Everything restore() needs to reproduce: the tree, the caches and the sorted empty nodes (by size and position)
+    type LayoutState = (NodeBlueprint, Option<Cost>, Vec<(u64, u64, u64, u64)>);
+
+    fn layout_state(layout: &Layout) -> LayoutState {
+        let empty_nodes = layout.sorted_empty_nodes().iter()
+            .map(|&i| {
+                let (x, y) = layout.node_position(i);
+                (x, y, layout.nodes()[i].width(), layout.nodes()[i].height())
+            })
+            .collect_vec();
+        (NodeBlueprint::from_node(*layout.top_node_index(), layout.nodes()), layout.cached_cost.clone(), empty_nodes)
+    }
+
+    #[test]
+    fn restore_undoes_all_modifications_since_the_checkpoint() {
+        let instance = test_util::instance(&[(40, 30, 2), (30, 30, 2), (20, 50, 2)], &[(100, 100, 1)]);
+        let mut layout = test_util::layout(&instance, 0, &[(0, 0, 0), (1, 40, 0), (2, 0, 30)]);
+        layout.cost(false);
+        let state = layout_state(&layout);
+        let (kept_part, removed_part) = {
+            let part_node = |id: usize| layout.nodes().iter().find(|(_, n)| n.part_id() == Some(id)).unwrap().0;
+            (part_node(0), part_node(1))
+        };
+
+        let checkpoint = layout.checkpoint();
+        layout.remove_node(removed_part);
+        //a nested checkpoint kept by its own caller, but still undone by the outer one
+        let nested = layout.checkpoint();
+        assert_eq!(layout.fill_with(instance.get_parttype(2), 2, &instance), 2);
+        layout.commit(nested);
+        let nested = layout.checkpoint();
+        layout.fill_with(instance.get_parttype(1), 2, &instance);
+        layout.restore(nested);
+        layout.flip_node_orientation(layout.sorted_empty_nodes()[0]).ok();
+        layout.cost(false);
+        assert_ne!(layout_state(&layout), state);
+
+        layout.restore(checkpoint);
+        assert_eq!(layout_state(&layout), state);
+        assert_eq!(layout.cost(true), state.1.unwrap());
+        //nodes which were not unregistered keep their index
+        assert_eq!(layout.nodes()[kept_part].part_id(), Some(0));
+        assert!(layout.undo_trail.is_none());
+    }
+
+    #[test]
+    fn half_turn_only_parts_are_never_rotated_by_90_degrees() {
+        let mut json_instance = test_util::json_instance(&[(40, 20, 4), (40, 20, 4)], &[(50, 100, 4)]);
+        json_instance.parttypes[0].half_turn_only = Some(true);
+        let config = test_util::config();
+        let instance = Arc::new(parser::generate_instance(&mut json_instance, &config).unwrap());
+        assert_eq!(instance.get_parttype(0).fixed_rotation(), &Some(Rotation::Default));
+        assert_eq!(instance.get_parttype(1).fixed_rotation(), &None);
+
+        let solution = test_util::solve(instance.clone(), &config);
+        let placements = solution.layouts().iter().enumerate()
+            .flat_map(|(i, l)| Placement::extract(i, l.sheettype_id(), l.top_node()))
+            .filter(|p| p.parttype_id == 0)
+            .collect_vec();
+        assert_eq!(placements.len(), 4);
+        assert!(placements.iter().all(|p| (p.width, p.height) == (40, 20)));
+
+        let layout = test_util::layout(&instance, 0, &[(0, 0, 0), (1, 0, 20)]);
+        assert!(layout.placed_parts().iter().all(|p| p.rotation == Rotation::Default));
+    }
+
+    #[test]
+    fn relocating_a_part_preserves_the_usage_and_fails_without_changes() {
+        //a 100x30 strip with both parts and a 30x30 leftover, above a 100x70 empty node
+        let instance = test_util::instance(&[(40, 30, 1), (30, 30, 1)], &[(100, 100, 1)]);
+        let mut layout = test_util::layout(&instance, 0, &[(0, 0, 0), (1, 40, 0)]);
+        let usage = layout.usage(true);
+        let part_node = |layout: &Layout, id: usize| layout.nodes().iter().find(|(_, n)| n.part_id() == Some(id)).unwrap().0;
+        let empty_node_at = |layout: &Layout, position: (u64, u64)| *layout.sorted_empty_nodes().iter().find(|&&i| layout.node_position(i) == position).unwrap();
+
+        layout.relocate_part(part_node(&layout, 0), empty_node_at(&layout, (0, 30)), &instance).unwrap();
+        assert_eq!(layout.usage(true), usage);
+        assert_eq!(layout.node_position(part_node(&layout, 0)), (0, 30));
+        assert!(assertions::node_arena_valid(layout.nodes(), layout.top_node_index()));
+        assert!(assertions::children_nodes_fit(layout.top_node_index(), layout.nodes()));
+        //the following siblings shifted into the freed space
+        assert_eq!(layout.node_position(part_node(&layout, 1)), (0, 0));
+
+        //the 40x30 part does not fit in the 30x30 leftover below it
+        let state = layout_state(&layout);
+        assert_eq!(layout.relocate_part(part_node(&layout, 0), empty_node_at(&layout, (0, 70)), &instance), Err(LayoutError::PartDoesNotFit));
+        assert_eq!(layout_state(&layout), state);
+
+        //removing the 30x30 part merges the leftover next to it with the freed space
+        assert_eq!(layout.relocate_part(part_node(&layout, 1), empty_node_at(&layout, (30, 0)), &instance), Err(LayoutError::PartDoesNotFit));
+        assert_eq!(layout_state(&layout), state);
+        assert_eq!(layout.usage(true), usage);
+    }
+
+    #[test]
+    fn bottom_left_origin_only_flips_the_y_coordinates() {
+        let instance = test_util::instance(&[(40, 30, 1), (30, 50, 1), (20, 20, 1)], &[(100, 100, 1)]);
+        let layout = test_util::layout(&instance, 0, &[(0, 0, 0), (1, 40, 0), (2, 0, 30)]);
+        let by_part = |origin: OriginCorner| layout.placed_parts_from(origin).into_iter().sorted_by_key(|p| p.parttype_id).collect_vec();
+
+        for (top_left, bottom_left) in by_part(OriginCorner::TopLeft).into_iter().zip(by_part(OriginCorner::BottomLeft)) {
+            assert_eq!(bottom_left.y, 100 - top_left.y - top_left.height);
+            assert_eq!((bottom_left.x, bottom_left.width, bottom_left.height), (top_left.x, top_left.width, top_left.height));
+            assert_eq!((bottom_left.parttype_id, bottom_left.rotation), (top_left.parttype_id, top_left.rotation));
+        }
+    }
+
+    #[test]
+    fn scrap_weight_reconciles_with_the_waste_area() {
+        let parts = vec![(test_util::parttype(0, 40, 30), 1), (test_util::parttype(1, 30, 50), 1)];
+        let sheets = vec![(test_util::sheettype(0, 100, 100).with_density(0.5), 1)];
+        let instance = Instance::new(parts, sheets, 0, false);
+        leftover_valuator::set_power(2.0);
+        let layout = test_util::layout(&instance, 0, &[(0, 0, 0), (1, 40, 0)]);
+
+        assert_eq!(layout.waste_area(), 10000 - 1200 - 1500);
+        assert_eq!(layout.scrap_weight(), Some(0.5 * 7300.0));
+        let without_density = test_util::instance(&[(40, 30, 1)], &[(100, 100, 1)]);
+        assert_eq!(test_util::layout(&without_density, 0, &[(0, 0, 0)]).scrap_weight(), None);
+    }
+
+    #[test]
+    fn spatial_index_follows_insertions_and_shifted_siblings() {
+        //a 100x30 strip with the 40x30 and 30x30 parts and a 30x30 leftover, above a 100x70 empty node
+        let instance = test_util::instance(&[(40, 30, 1), (30, 30, 1), (50, 50, 1)], &[(100, 100, 1)]);
+        let mut layout = test_util::layout(&instance, 0, &[(0, 0, 0), (1, 40, 0)]);
+        layout.enable_spatial_index();
+        let part_node = |layout: &Layout, id: usize| layout.nodes().iter().find(|(_, n)| n.part_id() == Some(id)).unwrap().0;
+        let scanned = |layout: &Layout, rect: (u64, u64, u64, u64)| {
+            let mut without_index = layout.clone();
+            without_index.disable_spatial_index();
+            without_index.parts_in_rect(rect.0, rect.1, rect.2, rect.3)
+        };
+
+        assert_eq!(layout.parts_at(50, 10), vec![part_node(&layout, 1)]);
+        assert!(layout.parts_at(50, 50).is_empty());
+
+        assert_eq!(layout.fill_with(instance.get_parttype(2), 1, &instance), 1);
+        assert_eq!(layout.parts_at(10, 40), vec![part_node(&layout, 2)]);
+
+        //the 30x30 part moves into the space of the removed 40x30 part
+        layout.remove_node(part_node(&layout, 0));
+        assert_eq!(layout.parts_at(10, 10), vec![part_node(&layout, 1)]);
+        assert!(layout.parts_at(50, 10).is_empty());
+        for rect in [(0, 0, 100, 100), (0, 0, 35, 30), (35, 0, 65, 30), (0, 30, 100, 70)] {
+            assert_eq!(layout.parts_in_rect(rect.0, rect.1, rect.2, rect.3), scanned(&layout, rect));
+        }
+    }
+
+    #[test]
+    fn corner_cut_leaves_a_guillotine_l_shaped_region() {
+        let mut json_instance = test_util::json_instance(&[(40, 30, 6), (25, 60, 4), (30, 20, 8)], &[(100, 100, 10)]);
+        json_instance.sheettypes[0].corner_cut = Some(JsonCornerCut { length: 45, height: 35 });
+        let config = test_util::config();
+        let instance = Arc::new(parser::generate_instance(&mut json_instance, &config).unwrap());
+        let unusable_rects = |layout: &Layout| layout.nodes().iter()
+            .filter(|(_, n)| n.is_unusable())
+            .map(|(i, n)| (layout.node_position(i), n.width(), n.height()))
+            .collect_vec();
+
+        //the first stage cuts the corner away, the L-shaped region around it is free
+        for orientation in [Orientation::Horizontal, Orientation::Vertical] {
+            let layout = Layout::new(0, instance.get_sheettype(0), orientation, EmptyNodeOrder::default());
+            assert_eq!(unusable_rects(&layout), vec![((0, 0), 45, 35)]);
+            assert_eq!(layout.waste_area(), 100 * 100 - 45 * 35);
+            assert!(layout.is_empty());
+            assert_eq!(layout.removable_nodes_unordered(), vec![*layout.top_node_index()]);
+        }
+
+        //the corner survives the ruins of the search, everything below the first stage is a guillotine pattern
+        let solution = test_util::solve(instance.clone(), &config);
+        assert!(solution.parttype_qtys().iter().all(|q| *q == 0));
+        for sendable_layout in solution.layouts() {
+            assert!(assertions::children_node_blueprints_fit(sendable_layout.top_node()));
+            let layout = Layout::from_blueprint(0, instance.get_sheettype(0), sendable_layout.top_node(), EmptyNodeOrder::default(), &instance);
+            assert_eq!(unusable_rects(&layout), vec![((0, 0), 45, 35)]);
+            assert!(layout.placed_parts().iter().all(|p| p.x >= 45 || p.y >= 35));
+        }
+
+        //the corner cut has to leave an L-shaped region
+        json_instance.sheettypes[0].corner_cut = Some(JsonCornerCut { length: 100, height: 35 });
+        assert_eq!(parser::generate_instance(&mut json_instance, &config).err(), Some(InstanceError::InvalidCornerCut(0)));
+    }
+
+    #[test]
+    fn critical_path_follows_the_deepest_chain_of_cuts() {
+        let cut = |x: u64, y: u64, length: u64, orientation: Orientation, level: u8| Cut { x, y, length, orientation, level };
+
+        //a 2x2 grid: one cut across the sheet, followed by a cut within a strip
+        let instance = test_util::instance(&[(50, 50, 4)], &[(100, 100, 1)]);
+        let balanced = test_util::layout(&instance, 0, &[(0, 0, 0), (0, 50, 0), (0, 0, 50), (0, 50, 50)]);
+        assert_eq!(balanced.cut_critical_path(), vec![
+            cut(0, 50, 100, Orientation::Horizontal, 0),
+            cut(50, 0, 50, Orientation::Vertical, 1),
+        ]);
+
+        //the bottom strip is split further, which adds a third cut to the chain
+        let instance = test_util::instance(&[(100, 50, 1), (50, 50, 1), (50, 25, 2)], &[(100, 100, 1)]);
+        let skewed = test_util::layout(&instance, 0, &[(0, 0, 0), (1, 0, 50), (2, 50, 50), (2, 50, 75)]);
+        assert_eq!(skewed.cut_critical_path(), vec![
+            cut(0, 50, 100, Orientation::Horizontal, 0),
+            cut(50, 50, 50, Orientation::Vertical, 1),
+            cut(50, 75, 50, Orientation::Horizontal, 2),
+        ]);
+    }
+
+    #[test]
+    fn maximal_free_rects_merge_empty_nodes_of_different_subtrees() {
+        //the first cut separates the empty node right of the first part from the one left of the second part
+        let instance = test_util::instance(&[(30, 40, 1), (40, 40, 1)], &[(100, 80, 1)]);
+        let layout = test_util::layout(&instance, 0, &[(0, 0, 0), (1, 60, 40)]);
+        let empty_nodes = layout.sorted_empty_nodes().iter().map(|&i| layout.node_rect(i)).sorted().collect_vec();
+        assert_eq!(empty_nodes, vec![(0, 40, 60, 40), (30, 0, 70, 40)]);
+
+        assert_eq!(layout.maximal_free_rects(), vec![FreeRect::new(30, 0, 30, 80), FreeRect::new(30, 0, 70, 40), FreeRect::new(0, 40, 60, 40)]);
+    }
+
+    #[test]
+    fn scattered_waste_is_more_fragmented_than_consolidated_waste() {
+        let instance = test_util::instance(&[(100, 50, 1), (50, 50, 2), (100, 100, 1)], &[(100, 100, 1)]);
+        let consolidated = test_util::layout(&instance, 0, &[(0, 0, 0)]);
+        let scattered = test_util::layout(&instance, 0, &[(1, 0, 0), (1, 50, 50)]);
+        let full = test_util::layout(&instance, 0, &[(2, 0, 0)]);
+
+        assert_eq!(consolidated.waste_area(), scattered.waste_area());
+        assert_eq!(consolidated.fragmentation_index(), 0.0);
+        assert_eq!(scattered.fragmentation_index(), 0.5);
+        assert_eq!(full.fragmentation_index(), 0.0);
+    }
+
+    #[test]
+    fn max_parttypes_per_sheet_limits_the_distinct_parttypes_of_every_layout() {
+        fn parttype_ids(pattern: &NodeBlueprint) -> Vec<usize> {
+            pattern.parttype_id().into_iter().chain(pattern.children().iter().flat_map(parttype_ids)).collect()
+        }
+        let n_distinct_parttypes = |config: &Config| {
+            let mut json_instance = test_util::json_instance(&[(40, 30, 2), (25, 35, 2), (30, 20, 2), (20, 20, 2), (15, 45, 2)], &[(100, 100, 10)]);
+            let instance = Arc::new(parser::generate_instance(&mut json_instance, config).unwrap());
+            let solution = test_util::solve(instance, config);
+            assert!(solution.is_complete());
+            solution.layouts().iter()
+                .map(|l| parttype_ids(l.top_node()).into_iter().unique().count())
+                .collect_vec()
+        };
+
+        //all parts fit on a single sheet
+        assert_eq!(n_distinct_parttypes(&test_util::config()), vec![5]);
+        let limited = n_distinct_parttypes(&test_util::config_with(json!({"maxParttypesPerSheet": 2})));
+        assert!(limited.len() >= 3 && limited.iter().all(|n| *n <= 2));
+    }
+
+    #[test]
+    fn lazy_and_eager_insertion_options_agree() {
+        let instance = test_util::instance(&[(40, 30, 1), (30, 60, 1), (25, 20, 1)], &[(100, 100, 1)]);
+        let problem = test_util::problem(&instance, &[(0, &[(0, 0, 0), (1, 0, 30)])]);
+        let layout_index = LayoutIndex::Existing(problem.layouts().iter().next().unwrap().0);
+        let layout = problem.get_layout(&layout_index);
+        let parttype = instance.get_parttype(2);
+        let key = |bp: &InsertionBlueprint| (*bp.original_node_index(), bp.replacements().clone(), bp.cost().clone());
+
+        let eager = layout.insertion_options(layout_index, parttype, &instance).iter().map(key).collect_vec();
+        let lazy = layout.insertion_options_iter(layout_index, parttype, &instance).map(|bp| key(&bp)).collect_vec();
+        assert!(eager.len() > layout.sorted_empty_nodes().len());
+        assert_eq!(lazy, eager);
+
+        //the same candidates as the insertion options of the search, node by node
+        let options = layout.sorted_empty_nodes().iter()
+            .flat_map(|&node_i| [Rotation::Default, Rotation::Rotated].map(|rotation| (node_i, rotation)))
+            .filter(|&(node_i, rotation)| layout.nodes()[node_i].insertion_possible(parttype, rotation))
+            .flat_map(|(node_i, rotation)| InsertionOption::new(layout_index, node_i, parttype, Some(rotation)).generate_blueprints(&problem))
+            .map(|bp| key(&bp))
+            .collect_vec();
+        assert_eq!(options, eager);
+    }
+
+    #[test]
+    fn parts_stay_within_the_usable_regions() {
+        let region = |x: u64, y: u64, length: u64, height: u64| JsonRegion { x, y, length, height };
+        let mut json_instance = test_util::json_instance(&[(40, 30, 6), (25, 35, 4), (30, 20, 8)], &[(100, 100, 10)]);
+        json_instance.sheettypes[0].usable_regions = Some(vec![region(0, 0, 100, 40), region(0, 40, 50, 60)]);
+        let config = test_util::config();
+        let instance = Arc::new(parser::generate_instance(&mut json_instance, &config).unwrap());
+        assert_eq!(instance.get_sheettype(0).unusable_regions(), &[(50, 40, 50, 60)]);
+        assert_eq!(instance.get_sheettype(0).usage_area(), 7000);
+
+        //the parts avoid the bottom right of the sheet, the usage is relative to the usable area
+        let solution = test_util::solve(instance.clone(), &config);
+        assert!(solution.parttype_qtys().iter().all(|q| *q == 0));
+        for sendable_layout in solution.layouts() {
+            let mut layout = Layout::from_blueprint(0, instance.get_sheettype(0), sendable_layout.top_node(), EmptyNodeOrder::default(), &instance);
+            let placed_parts = layout.placed_parts();
+            assert!(placed_parts.iter().all(|p| p.x + p.width <= 50 || p.y + p.height <= 40));
+            let part_area = placed_parts.iter().map(|p| p.width * p.height).sum::<u64>();
+            assert_eq!(layout.usage(true), part_area as f64 / 7000.0);
+        }
+
+        //the regions have to be separable by guillotine cuts, without overlapping or leaving the sheet
+        for regions in [
+            vec![],
+            vec![region(0, 0, 60, 60), region(50, 50, 50, 50)],
+            vec![region(0, 0, 100, 40), region(60, 40, 50, 60)],
+            vec![region(0, 0, 60, 40), region(60, 0, 40, 60), region(40, 60, 60, 40), region(0, 40, 40, 60)],
+        ] {
+            json_instance.sheettypes[0].usable_regions = Some(regions);
+            assert!(matches!(parser::generate_instance(&mut json_instance, &config), Err(InstanceError::InvalidUsableRegions(0, _))));
+        }
+    }
+
+    #[test]
+    fn the_only_empty_child_of_a_node_can_be_removed() {
+        let instance = test_util::instance(&[(50, 60, 1), (50, 40, 1), (50, 100, 1)], &[(100, 100, 1)]);
+        let mut layout = test_util::layout(&instance, 0, &[(0, 0, 0), (1, 0, 60), (2, 50, 0)]);
+        let part_node = |layout: &Layout, parttype_id: usize| layout.nodes().iter().find(|(_, n)| n.part_id() == Some(parttype_id)).unwrap().0;
+        let strip = layout.nodes()[part_node(&layout, 0)].parent().unwrap();
+        for parttype_id in [0, 1] {
+            let part = part_node(&layout, parttype_id);
+            layout.remove_node(part);
+        }
+        //the node is compared with itself when looking for a sibling to merge with
+        let [empty_child] = layout.nodes()[strip].children()[..] else { panic!("the strip has more than one child") };
+        layout.remove_node(empty_child);
+        assert!(layout.nodes().get(strip).is_none());
+        assert_eq!(layout.nodes().iter().filter(|(_, n)| n.is_empty() && (n.width(), n.height()) == (50, 100)).count(), 1);
+    }
+
+    #[test]
+    fn empty_template_keeps_the_structure_without_the_parts() {
+        let instance = test_util::instance(&[(40, 30, 2), (60, 50, 1)], &[(100, 100, 1)]);
+        let layout = test_util::layout(&instance, 0, &[(0, 0, 0), (0, 40, 0), (1, 0, 30)]);
+        let shape = |layout: &Layout| layout.nodes().iter()
+            .map(|(i, n)| (layout.node_position(i), n.width(), n.height(), n.children().len()))
+            .sorted()
+            .collect_vec();
+
+        let mut template = layout.to_empty_template();
+        assert_eq!(template.nodes().len(), layout.nodes().len());
+        assert_eq!(shape(&template), shape(&layout));
+        assert!(template.nodes().iter().all(|(_, n)| !n.is_part()));
+        assert!(template.placed_parts().is_empty());
+        assert_eq!(template.usage(true), 0.0);
+        assert_eq!(template.sorted_empty_nodes().len(), layout.sorted_empty_nodes().len() + 3);
+        assert!(assertions::node_arena_valid(template.nodes(), template.top_node_index()));
+        assert!(assertions::children_nodes_fit(template.top_node_index(), template.nodes()));
+        assert!(assertions::cached_sorted_empty_nodes_correct(template.nodes(), template.sorted_empty_nodes(), EmptyNodeOrder::default()));
+    }
+
+    #[test]
+    fn cut_efficiency_relates_the_cuts_to_their_lower_bound() {
+        //a single part needs a single cut to separate it from the waste
+        let instance = test_util::instance(&[(60, 100, 1)], &[(100, 100, 1)]);
+        let single = test_util::layout(&instance, 0, &[(0, 0, 0)]);
+        assert_eq!((single.cut_count(), single.min_cut_count(), single.cut_efficiency()), (1, 1, 1.0));
+
+        //a perfect 2x2 grid needs one cut less than it has parts
+        let instance = test_util::instance(&[(50, 50, 4)], &[(100, 100, 1)]);
+        let grid = test_util::layout(&instance, 0, &[(0, 0, 0), (0, 50, 0), (0, 0, 50), (0, 50, 50)]);
+        assert_eq!((grid.cut_count(), grid.min_cut_count(), grid.cut_efficiency()), (3, 3, 1.0));
+
+        //parts in opposite corners divide the waste into three pieces
+        let instance = test_util::instance(&[(30, 30, 2)], &[(100, 100, 1)]);
+        let fragmented = test_util::layout(&instance, 0, &[(0, 0, 0), (0, 70, 70)]);
+        assert_eq!((fragmented.cut_count(), fragmented.min_cut_count(), fragmented.cut_efficiency()), (4, 2, 0.5));
+    }
+
+    #[test]
+    fn skyline_follows_a_staircase_and_coalesces_equal_heights() {
+        let instance = test_util::instance(&[(40, 60, 1), (30, 30, 2)], &[(100, 100, 1)]);
+        let staircase = test_util::layout(&instance, 0, &[(0, 0, 0), (1, 40, 0)]);
+        assert_eq!(staircase.skyline(Orientation::Horizontal), vec![(0, 60), (40, 30), (70, 0)]);
+        assert_eq!(staircase.skyline(Orientation::Vertical), vec![(0, 70), (30, 40), (60, 0)]);
+
+        //the two 30x30 parts side by side form a single segment
+        let coalesced = test_util::layout(&instance, 0, &[(0, 0, 0), (1, 40, 0), (1, 70, 0)]);
+        assert_eq!(coalesced.skyline(Orientation::Horizontal), vec![(0, 60), (40, 30)]);
+        assert_eq!(coalesced.skyline(Orientation::Vertical), vec![(0, 100), (30, 40), (60, 0)]);
+    }
+
+    #[test]
+    fn asymmetric_margins_keep_the_parts_in_the_inset_region() {
+        let mut json_instance = test_util::json_instance(&[(40, 30, 6), (25, 60, 4), (30, 20, 8)], &[(100, 100, 10)]);
+        json_instance.sheettypes[0].margins = Some(Margins::new(0, 0, 20, 0));
+        let config = test_util::config();
+        let instance = Arc::new(parser::generate_instance(&mut json_instance, &config).unwrap());
+
+        //the layouts only cover the region right of the margin, the usage is still relative to the entire sheet
+        let layout = Layout::new(0, instance.get_sheettype(0), Orientation::Horizontal, EmptyNodeOrder::default());
+        let top_node = &layout.nodes()[*layout.top_node_index()];
+        assert_eq!((top_node.width(), top_node.height()), (80, 100));
+        let solution = test_util::solve(instance.clone(), &config);
+        assert!(solution.parttype_qtys().iter().all(|q| *q == 0));
+        for sendable_layout in solution.layouts() {
+            let layout = Layout::from_blueprint(0, instance.get_sheettype(0), sendable_layout.top_node(), EmptyNodeOrder::default(), &instance);
+            assert!(layout.placed_parts().iter().all(|p| p.x >= 20 && p.x + p.width <= 100));
+            assert_eq!(layout.usage_immut(true), layout.used_area() as f64 / 10000.0);
+        }
+
+        //positions and queries are relative to the sheet, not to the region inside the margins
+        let layout = test_util::layout(&instance, 0, &[(0, 0, 0)]);
+        let part_node = layout.parts_at(20, 0)[0];
+        assert_eq!(layout.node_position(part_node), (20, 0));
+        assert!(layout.parts_at(10, 0).is_empty());
+        assert_eq!(layout.parts_in_rect(0, 0, 21, 1), vec![part_node]);
+        assert!(layout.parts_in_rect(0, 0, 20, 100).is_empty());
+        assert_eq!(layout.maximal_free_rects(), vec![FreeRect::new(60, 0, 40, 100), FreeRect::new(20, 30, 80, 70)]);
+
+        //a corner cut is relative to the sheet as well, only the part of it inside the margins is unusable
+        let sheettype = test_util::sheettype(0, 100, 100).with_margins(Margins::new(0, 0, 20, 0)).with_corner_cut(30, 10);
+        assert_eq!(sheettype.inset_unusable_regions(), &[(0, 0, 10, 10)]);
+        assert_eq!(sheettype.placeable_area(), 80 * 100 - 10 * 10);
+
+        //margins without a usable region in between are rejected instead of panicking
+        for margins in [Margins::new(0, 0, 100, 0), Margins::new(0, 0, 40, 60), Margins::new(50, 50, 0, 0)] {
+            json_instance.sheettypes[0].margins = Some(margins);
+            let error = parser::generate_instance(&mut json_instance, &config).err();
+            assert_eq!(error, Some(InstanceError::InvalidMargins(0)));
+            assert_eq!(error.unwrap().to_string(), "margins of object 0 leave no usable region");
+        }
+    }
+
+    #[test]
+    fn extraction_order_follows_the_reading_order() {
+        //a 2x2 grid with a different parttype in every cell
+        let instance = test_util::instance(&[(50, 50, 1), (50, 50, 1), (50, 50, 1), (50, 50, 1)], &[(100, 100, 1)]);
+        let layout = test_util::layout(&instance, 0, &[(0, 0, 0), (1, 50, 0), (2, 0, 50), (3, 50, 50)]);
+
+        assert_eq!(layout.extraction_order(ReadingOrder::RowMajor), vec![(1, 0), (2, 1), (3, 2), (4, 3)]);
+        assert_eq!(layout.extraction_order(ReadingOrder::ColumnMajor), vec![(1, 0), (2, 2), (3, 1), (4, 3)]);
+    }
+
+    #[test]
+    fn repairing_a_zero_kerf_layout_restores_the_gaps_or_drops_parts() {
+        let instance = test_util::instance(&[(40, 40, 4), (50, 100, 2)], &[(100, 100, 2)]);
+
+        let mut grid = test_util::layout(&instance, 0, &[(0, 0, 0), (0, 40, 0), (0, 0, 40), (0, 40, 40)]);
+        let report = grid.repair_spacing(Kerf::new(3, 3), 2, &instance);
+        //every pair of neighbours was touching
+        assert_eq!(report.n_violations, 4);
+        assert!(report.dropped_parts.is_empty());
+        assert!(assertions::node_arena_valid(&grid.nodes, &grid.top_node_i));
+        let parts = grid.placed_parts();
+        assert_eq!(parts.len(), 4);
+        for (a, b) in parts.iter().tuple_combinations() {
+            let x_gap = i64::max(a.x as i64 - (b.x + b.width) as i64, b.x as i64 - (a.x + a.width) as i64);
+            let y_gap = i64::max(a.y as i64 - (b.y + b.height) as i64, b.y as i64 - (a.y + a.height) as i64);
+            assert!(x_gap >= 5 || y_gap >= 5);
+        }
+
+        //the halves no longer fit next to each other
+        let mut halves = test_util::layout(&instance, 0, &[(1, 0, 0), (1, 50, 0)]);
+        let report = halves.repair_spacing(Kerf::new(1, 1), 0, &instance);
+        assert_eq!((report.n_violations, report.dropped_parts), (1, vec![1]));
+        assert_eq!(halves.placed_parts().len(), 1);
+
+        //nothing to repair
+        assert_eq!(halves.repair_spacing(Kerf::new(1, 1), 0, &instance).n_violations, 0);
+    }
+
+    #[test]
+    fn cost_breakdown_reconciles_with_the_cost() {
+        let instance = test_util::instance(&[(40, 30, 3), (25, 60, 2)], &[(100, 100, 1)]);
+        let mut layout = test_util::layout(&instance, 0, &[(0, 0, 0), (0, 40, 0), (1, 0, 30), (1, 25, 30), (0, 50, 30)]);
+
+        let breakdown = layout.cost_breakdown();
+        assert_eq!(breakdown.len(), layout.nodes.len());
+        //only the empty nodes contribute leftover value
+        assert!(breakdown.iter().filter(|(_, cost)| cost.leftover_value != 0.0).all(|(i, _)| layout.nodes[*i].is_empty()));
+        let total = breakdown.iter().fold(Cost::empty().add_material_cost(instance.get_sheettype(0).value()), |acc, (_, cost)| acc.add(cost));
+        assert_eq!(total, layout.cost(true));
+    }
+
+    #[test]
+    fn pinwheels_cannot_be_rebuilt_but_grids_can() {
+        let instance = test_util::instance(&[(60, 40, 2), (40, 60, 2), (50, 50, 4)], &[(100, 100, 1)]);
+        let from_placements = |parts: &[(usize, u64, u64)]| {
+            let placements = parts.iter().map(|&(id, x, y)| test_util::placed(&instance, id, x, y)).collect_vec();
+            Layout::try_from_placements(0, instance.get_sheettype(0), &placements, EmptyNodeOrder::default(), &instance)
+        };
+
+        //four parts around an empty square in the center, no cut crosses the sheet without cutting a part
+        let pinwheel = [(0, 0, 0), (1, 60, 0), (0, 40, 60), (1, 0, 40)];
+        assert_eq!(from_placements(&pinwheel).err(), Some(GuillotineError::NonGuillotine { x: 0, y: 0, width: 100, height: 100 }));
+
+        let grid = [(2, 0, 0), (2, 50, 0), (2, 0, 50), (2, 50, 50)];
+        let layout = from_placements(&grid).unwrap();
+        assert!(assertions::node_arena_valid(&layout.nodes, &layout.top_node_i));
+        assert_eq!(layout.placed_parts(), grid.iter().map(|&(id, x, y)| test_util::placed(&instance, id, x, y)).collect_vec());
+
+        assert_eq!(from_placements(&[(2, 0, 0), (2, 40, 0)]).err(), Some(GuillotineError::Overlap(0, 1)));
+        assert_eq!(from_placements(&[(2, 60, 0)]).err(), Some(GuillotineError::OutsideSheet(0)));
+    }
+
+    #[test]
+    fn eager_and_lazy_merges_leave_the_same_geometry_after_defragmenting() {
+        let instance = test_util::instance(&[(30, 10, 4), (40, 40, 2)], &[(100, 100, 1)]);
+        //a column of small parts next to a large part in the first strip, another large part in the second strip
+        let parts = [(0, 0, 0), (0, 0, 10), (0, 0, 20), (0, 0, 30), (1, 30, 0), (1, 30, 40)];
+        //the tree of the layout, up to the order of the siblings (which the removals do not preserve either)
+        fn geometry(layout: &Layout, node_index: Index) -> String {
+            let node = &layout.nodes()[node_index];
+            let children = node.children().iter().map(|c| geometry(layout, *c)).sorted().join(" ");
+            format!("{}x{}{:?}[{}]", node.width(), node.height(), node.part_id(), children)
+        }
+        //merges the empty nodes until no more merges are possible
+        let defragment = |layout: &mut Layout| loop {
+            let n_nodes = layout.nodes().len();
+            for i in layout.nodes().iter().filter(|(_, n)| n.is_empty()).map(|(i, _)| i).collect_vec() {
+                if layout.nodes().get(i).is_some_and(|n| n.is_empty()) {
+                    layout.merge_empty_upwards(i);
+                }
+            }
+            if layout.nodes().len() == n_nodes {
+                break;
+            }
+        };
+        //the large part of the first strip, then all small parts, which empties the first strip
+        let remove = |eager_merge: bool| {
+            let mut layout = test_util::layout(&instance, 0, &parts);
+            let large_part = layout.nodes().iter().find(|(i, n)| n.part_id() == Some(1) && layout.node_position(*i).1 == 0).unwrap().0;
+            layout.remove_node_with(large_part, eager_merge);
+            while let Some((small_part, _)) = layout.nodes().iter().find(|(_, n)| n.part_id() == Some(0)) {
+                layout.remove_node_with(small_part, eager_merge);
+                assert!(assertions::node_arena_valid(&layout.nodes, &layout.top_node_i));
+                assert!(assertions::cached_sorted_empty_nodes_correct(&layout.nodes, &layout.sorted_empty_nodes, layout.empty_node_order));
+            }
+            layout
+        };
+        let n_empty_nodes = |layout: &Layout| layout.nodes().iter().filter(|(_, n)| n.is_empty()).count();
+
+        let (mut lazy, mut eager) = (remove(false), remove(true));
+        //the emptied strip is merged with the empty strip below right away
+        assert!(n_empty_nodes(&eager) < n_empty_nodes(&lazy));
+        defragment(&mut eager);
+        defragment(&mut lazy);
+        //built from the placement: the remaining part next to a single empty node in its strip, the rest of the sheet in another one
+        let expected = test_util::layout(&instance, 0, &[(1, 0, 0)]);
+        assert_eq!(geometry(&eager, eager.top_node_i), geometry(&expected, expected.top_node_i));
+        assert_eq!(geometry(&lazy, lazy.top_node_i), geometry(&expected, expected.top_node_i));
+        assert_eq!(lazy.placed_parts(), eager.placed_parts());
+    }
+
+    #[test]
+    fn free_dimensions_report_the_extents_of_the_empty_nodes() {
+        let instance = test_util::instance(&[(100, 90, 1), (30, 30, 1), (100, 100, 1)], &[(100, 100, 1)]);
+        let extents = |layout: &Layout| [Orientation::Horizontal, Orientation::Vertical]
+            .map(|axis| (layout.min_free_dimension(axis), layout.max_free_dimension(axis)));
+
+        //a long thin 100x10 strip remains below the large part
+        assert_eq!(extents(&test_util::layout(&instance, 0, &[(0, 0, 0)])), [(100, 100), (10, 10)]);
+        //a 70x30 empty node next to the small part, above a 100x70 one
+        assert_eq!(extents(&test_util::layout(&instance, 0, &[(1, 0, 0)])), [(70, 100), (30, 70)]);
+        assert_eq!(extents(&test_util::layout(&instance, 0, &[(2, 0, 0)])), [(0, 0), (0, 0)]);
+    }
+
+    #[test]
+    fn cost_after_removal_matches_the_cost_after_removing_in_all_three_scenarios() {
+        let instance = test_util::instance(&[(50, 60, 1), (50, 40, 1), (50, 100, 1)], &[(100, 100, 1)]);
+        //a strip with two parts on top of each other, next to a strip filled by a single part
+        let mut layout = test_util::layout(&instance, 0, &[(0, 0, 0), (1, 0, 60), (2, 50, 0)]);
+        let part_node = |layout: &Layout, parttype_id: usize| layout.nodes().iter().find(|(_, n)| n.part_id() == Some(parttype_id)).unwrap().0;
+        let remove = |layout: &mut Layout, node_index: Index| {
+            let dry_run = layout.cost_after_removal(node_index);
+            layout.remove_node(node_index);
+            let committed = layout.cost(true);
+            assert!(assertions::cost_delta_reconciles(&dry_run, &Cost::empty(), &committed), "{:?} != {:?}", dry_run, committed);
+        };
+
+        //scenario 2: the first part has no empty sibling and is replaced by an empty node
+        let strip = layout.nodes()[part_node(&layout, 0)].parent().unwrap();
+        let (first_part, n_nodes) = (part_node(&layout, 0), layout.nodes().len());
+        remove(&mut layout, first_part);
+        assert_eq!(layout.nodes().len(), n_nodes);
+
+        //scenario 1: the second part is merged with the empty node, which leaves it as the only child of the strip
+        let second_part = part_node(&layout, 1);
+        remove(&mut layout, second_part);
+        let strip_children = layout.nodes()[strip].children().clone();
+        assert_eq!(strip_children.len(), 1);
+        assert!(layout.nodes()[strip_children[0]].is_empty());
+
+        //scenario 3: the only (empty) child of the strip is removed, which converts the strip into an empty node
+        remove(&mut layout, strip_children[0]);
+        let freed_strip = layout.nodes().iter().find(|(_, n)| n.is_empty() && (n.width(), n.height()) == (50, 100)).unwrap().1;
+        assert!(freed_strip.children().is_empty());
+        assert!(layout.nodes().get(strip).is_none());
+    }
+
+    #[test]
+    fn parts_with_an_edge_affinity_are_only_inserted_near_the_edge() {
+        let mut json_instance = test_util::json_instance(&[(50, 50, 10), (100, 60, 1)], &[(100, 300, 1)]);
+        json_instance.parttypes[0].edge_affinity = Some(EdgeAffinity::new(Edge::Top, 100));
+        let instance = parser::generate_instance(&mut json_instance, &test_util::config()).unwrap();
+        leftover_valuator::set_power(2.0);
+        let parttype = instance.get_parttype(0);
+        let layout_index = LayoutIndex::Existing(Index::from_raw_parts(0, 0));
+        let near_the_top = |layout: &Layout| layout.placed_parts().iter().filter(|p| p.parttype_id == 0).all(|p| p.y <= 100);
+        //a strip at the top leaves an empty node starting 60 from the top edge
+        let mut layout = test_util::layout(&instance, 0, &[(1, 0, 0)]);
+
+        let options = layout.insertion_options(layout_index, parttype, &instance);
+        assert!(!options.is_empty());
+        for blueprint in options {
+            let mut copy = layout.clone();
+            copy.apply_and_cost_delta(&blueprint, &instance, &mut IOCUpdates::new(layout_index));
+            assert!(near_the_top(&copy));
+        }
+
+        //only a single row of parts fits within 100 of the top edge
+        assert_eq!(layout.fill_with(parttype, 10, &instance), 2);
+        assert!(near_the_top(&layout));
+        assert!(layout.insertion_options(layout_index, parttype, &instance).is_empty());
+        assert!(!layout.insertion_options(layout_index, instance.get_parttype(1), &instance).is_empty());
+    }
+
+    #[test]
+    fn cut_paths_run_between_the_sheet_edges_and_earlier_cuts() {
+        let instance = test_util::instance(&[(60, 30, 1), (40, 30, 1), (30, 70, 1), (70, 40, 1)], &[(100, 100, 1)]);
+        let layout = test_util::layout(&instance, 0, &[(0, 0, 0), (1, 60, 0), (2, 0, 30), (3, 30, 30)]);
+        let on_segment = |(x, y): (u64, u64), path: &CutPath| {
+            let (x_range, y_range) = ((path.start.0.min(path.end.0), path.start.0.max(path.end.0)), (path.start.1.min(path.end.1), path.start.1.max(path.end.1)));
+            (x_range.0..=x_range.1).contains(&x) && (y_range.0..=y_range.1).contains(&y)
+        };
+
+        let paths = layout.cut_paths(OriginCorner::TopLeft);
+        assert_eq!(paths.len(), layout.cut_count());
+        for (i, path) in paths.iter().enumerate() {
+            //straight segments within the sheet
+            assert!(path.start.0 == path.end.0 || path.start.1 == path.end.1);
+            assert!([path.start, path.end].iter().all(|&(x, y)| x <= 100 && y <= 100));
+            for point in [path.start, path.end] {
+                let on_sheet_edge = point.0 == 0 || point.0 == 100 || point.1 == 0 || point.1 == 100;
+                assert!(on_sheet_edge || paths[..i].iter().any(|earlier| on_segment(point, earlier)), "{:?} of cut {} is not on an earlier cut", point, i);
+            }
+        }
+        //no cut crosses a part
+        for part in layout.placed_parts() {
+            let inside = |(x, y): (u64, u64)| x > part.x && x < part.x + part.width && y > part.y && y < part.y + part.height;
+            assert!(paths.iter().all(|p| !inside(p.start) && !inside(p.end) && !inside(((p.start.0 + p.end.0) / 2, (p.start.1 + p.end.1) / 2))));
+        }
+
+        //relative to the bottom-left corner, the same segments in the same order are mirrored vertically
+        let mirrored = paths.iter().map(|p| ((p.start.0, 100 - p.start.1), (p.end.0, 100 - p.end.1))).collect_vec();
+        assert_eq!(layout.cut_paths(OriginCorner::BottomLeft).iter().map(|p| (p.start, p.end)).collect_vec(), mirrored);
+    }
+
+    #[test]
+    fn cached_positions_match_a_walk_of_the_tree_after_inserts_and_removes() {
+        //a corner cut keeps the siblings of its unusable node at a fixed rank, so insertions do not only append
+        let parts = [(30, 20), (25, 45), (10, 10)].iter().enumerate().map(|(id, &(w, h))| (test_util::parttype(id, w, h), 10)).collect_vec();
+        let sheets = vec![(test_util::sheettype(0, 100, 100), 1), (test_util::sheettype(1, 100, 100).with_corner_cut(30, 20), 1)];
+        let instance = Instance::new(parts, sheets, 0, false);
+        leftover_valuator::set_power(2.0);
+        //positions found by walking down the tree from the top node
+        fn walk(layout: &Layout, node_index: Index, (mut x, mut y): (u64, u64), positions: &mut Vec<(Index, (u64, u64))>) {
+            positions.push((node_index, (x, y)));
+            let node = &layout.nodes()[node_index];
+            for &child in node.children() {
+                walk(layout, child, (x, y), positions);
+                match node.next_cut_orient() {
+                    Orientation::Horizontal => y += layout.nodes()[child].height(),
+                    Orientation::Vertical => x += layout.nodes()[child].width(),
+                }
+            }
+        }
+        let assert_positions_correct = |cached: &Layout, walked: &Layout| {
+            let mut positions = vec![];
+            walk(cached, cached.top_node_i, (0, 0), &mut positions);
+            assert_eq!(positions.len(), cached.nodes().len());
+            for (node_index, position) in positions {
+                assert_eq!(cached.nodes()[node_index].cached_position(), Some(position));
+                assert_eq!(walked.node_position(node_index), position);
+            }
+        };
+
+        for sheettype_id in [0, 1] {
+            let mut cached = Layout::new(0, instance.get_sheettype(sheettype_id), Orientation::Horizontal, EmptyNodeOrder::default());
+            cached.enable_position_cache();
+            let mut walked = cached.clone();
+            walked.disable_position_cache();
+            assert_positions_correct(&cached, &walked);
+
+            for round in 0..6 {
+                for parttype_id in [1, 0, 2] {
+                    for layout in [&mut cached, &mut walked] {
+                        layout.fill_with(instance.get_parttype(parttype_id), 4, &instance);
+                    }
+                    assert_positions_correct(&cached, &walked);
+                }
+                //remove every other part, merging the freed nodes eagerly in every other round
+                let parts = cached.nodes().iter().filter(|(_, n)| n.is_part()).map(|(i, _)| i).sorted_by_key(|&i| cached.node_position(i)).step_by(2).collect_vec();
+                for part in parts {
+                    for layout in [&mut cached, &mut walked] {
+                        layout.remove_node_with(part, round % 2 == 0);
+                    }
+                    assert_positions_correct(&cached, &walked);
+                }
+                //modifications undone by a checkpoint restore
+                let checkpoint = cached.checkpoint();
+                let part = cached.nodes().iter().find(|(_, n)| n.is_part()).map(|(i, _)| i).unwrap();
+                cached.remove_node_with(part, true);
+                cached.fill_with(instance.get_parttype(2), 4, &instance);
+                cached.restore(checkpoint);
+                walked = cached.clone();
+                walked.disable_position_cache();
+                assert_positions_correct(&cached, &walked);
+            }
+        }
+    }
+}
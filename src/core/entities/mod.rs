@@ -1,5 +1,10 @@
+pub mod composite_structure;
+pub mod cut;
+pub mod free_rect;
 pub mod layout;
 pub mod node;
 pub mod parttype;
+pub mod placed_part;
 pub mod sheettype;
+pub mod spatial_index;
 pub mod sendable_layout;
\ No newline at end of file
@@ -1,4 +1,4 @@
-use generational_arena::{Index};
+use generational_arena::{Arena, Index};
 
 use crate::core::cost::Cost;
 use crate::core::entities::parttype::PartType;
@@ -6,6 +6,7 @@ use crate::core::insertion::node_blueprint::NodeBlueprint;
 use crate::core::leftover_valuator;
 use crate::core::orientation::Orientation;
 use crate::core::rotation::Rotation;
+use crate::optimization::instance::Instance;
 
 #[derive(Debug, Clone)]
 pub struct Node<'a> {
@@ -16,6 +17,8 @@ pub struct Node<'a> {
     parent: Option<Index>,
     parttype: Option<&'a PartType>,
     next_cut_orient: Orientation,
+    unusable: bool,
+    position: Option<(u64, u64)>, //relative to the top node, only maintained when the position cache of the layout is enabled
 }
 
 
@@ -29,6 +32,16 @@ impl<'a> Node<'a> {
             parent: None,
             parttype,
             next_cut_orient,
+            unusable: false,
+            position: None,
+        }
+    }
+
+    /// Leaf covering a region of the sheet which cannot be used (e.g. a corner cut), it is neither a part nor free space
+    pub fn new_unusable(level: u8, width: u64, height: u64, next_cut_orient: Orientation) -> Node<'a> {
+        Node {
+            unusable: true,
+            ..Node::new(level, width, height, next_cut_orient, None)
         }
     }
 
@@ -36,10 +49,22 @@ impl<'a> Node<'a> {
         self.parent = Some(parent);
     }
 
+    pub fn flip_next_cut_orient(&mut self) {
+        self.next_cut_orient = self.next_cut_orient.rotate();
+    }
+
     pub fn add_child(&mut self, child: Index) {
         self.children.push(child);
     }
 
+    pub fn insert_child(&mut self, rank: usize, child: Index) {
+        self.children.insert(rank, child);
+    }
+
+    pub fn set_cached_position(&mut self, position: Option<(u64, u64)>) {
+        self.position = position;
+    }
+
     pub fn remove_child(&mut self, old_child: Index) {
         let old_child_index = self.children.iter().position(|c| *c == old_child).expect("Child not found");
         self.children.remove(old_child_index);
@@ -62,7 +87,7 @@ impl<'a> Node<'a> {
              ---*****          ---*****
 
              -> node gets replaced by one node on same level
-             -> = Scenario 2
+             -> = Scenario 2, without the remainder node (an empty node without area is not created)
          */
 
         /*
@@ -80,17 +105,23 @@ impl<'a> Node<'a> {
         if self.next_cut_orient == Orientation::Horizontal && self.height == part_size.height() {
             let remainder_width = self.width - part_size.width();
             let part_node = NodeBlueprint::new(part_size.width(), self.height, Some(parttype), self.next_cut_orient);
-            let remainder_node = NodeBlueprint::new(remainder_width, self.height, None, self.next_cut_orient);
+            let replacement = match remainder_width {
+                0 => vec![part_node],
+                _ => vec![part_node, NodeBlueprint::new(remainder_width, self.height, None, self.next_cut_orient)],
+            };
 
-            insertion_replacements.push(vec![part_node, remainder_node]);
+            insertion_replacements.push(replacement);
             return insertion_replacements;
         }
         if self.next_cut_orient == Orientation::Vertical && self.width == part_size.width() {
             let remainder_height = self.height - part_size.height();
             let part_node = NodeBlueprint::new(self.width, part_size.height(), Some(parttype), self.next_cut_orient);
-            let remainder_node = NodeBlueprint::new(self.width, remainder_height, None, self.next_cut_orient);
+            let replacement = match remainder_height {
+                0 => vec![part_node],
+                _ => vec![part_node, NodeBlueprint::new(self.width, remainder_height, None, self.next_cut_orient)],
+            };
 
-            insertion_replacements.push(vec![part_node, remainder_node]);
+            insertion_replacements.push(replacement);
             return insertion_replacements;
         }
 
@@ -230,6 +261,41 @@ impl<'a> Node<'a> {
         insertion_replacements
     }
 
+    /// Copy of this (leaf) node without its part, keeping its position in the tree
+    pub fn without_part(&self) -> Node<'a> {
+        debug_assert!(self.children.is_empty());
+        Node {
+            parttype: None,
+            ..self.clone()
+        }
+    }
+
+    /// Whether replacing this node respects the waste constraints of the instance:
+    /// no empty node is created which is thinner than the minimum waste dimension, or more elongated than the maximum waste aspect ratio.
+    /// Empty nodes which inherit a (thin) dimension from this node are allowed, as the sliver exists regardless of the insertion.
+    pub fn waste_allows(&self, replacements: &[NodeBlueprint], instance: &Instance) -> bool {
+        let dimension_allowed = match instance.min_waste_dimension() {
+            Some(min_waste_dimension) => replacements.iter()
+                .filter_map(|r| r.min_new_waste_dimension((self.width, self.height)))
+                .all(|dimension| dimension >= min_waste_dimension),
+            None => true,
+        };
+        let aspect_ratio_allowed = match instance.max_waste_aspect_ratio() {
+            Some(max_aspect_ratio) => !self.creates_elongated_waste(replacements, max_aspect_ratio),
+            None => true,
+        };
+        dimension_allowed && aspect_ratio_allowed
+    }
+
+    /// Whether replacing this node creates an empty node with an aspect ratio (long side / short side) above the maximum.
+    /// Empty nodes which are not more elongated than this node itself are not considered, as they only inherit its shape.
+    pub fn creates_elongated_waste(&self, replacements: &[NodeBlueprint], max_aspect_ratio: f64) -> bool {
+        let own_aspect_ratio = aspect_ratio(self.width, self.height).unwrap_or(f64::INFINITY);
+        replacements.iter()
+            .filter_map(|r| r.max_waste_aspect_ratio())
+            .any(|ratio| ratio > max_aspect_ratio && ratio > own_aspect_ratio)
+    }
+
     pub fn insertion_possible(&self, parttype: &PartType, rotation: Rotation) -> bool {
         debug_assert!(*parttype.fixed_rotation() == None || *parttype.fixed_rotation() == Some(rotation));
         debug_assert!(self.children.is_empty() && self.parttype.is_none());
@@ -243,16 +309,67 @@ impl<'a> Node<'a> {
     }
 
     pub fn calculate_cost(&self) -> Cost {
+        if self.unusable {
+            return Cost::empty();
+        }
         match (self.parttype, self.children.is_empty()) {
-            (Some(_), true) => Cost::empty(), // part-node
+            (Some(parttype), true) => Cost::empty().add_leftover_value(-parttype.rotation_penalty(self.rotation().unwrap())), // part-node
             (None, false) => Cost::empty(), // structure-node
             (None, true) => Cost::empty().add_leftover_value(leftover_valuator::valuate(self.area())), //leftover node
             (Some(_), false) => panic!("Parttype set on node with children"),
         }
     }
 
+    /// Rotation of the part placed in this node, None if it does not contain a part
+    pub fn rotation(&self) -> Option<Rotation> {
+        self.parttype.map(|parttype| {
+            match (self.width, self.height) == (parttype.size().width(), parttype.size().height()) {
+                true => Rotation::Default,
+                false => Rotation::Rotated
+            }
+        })
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.parttype.is_none() && self.children.is_empty()
+        self.parttype.is_none() && self.children.is_empty() && !self.unusable
+    }
+
+    /// Whether this node covers a region of the sheet which cannot be used, see new_unusable
+    pub fn is_unusable(&self) -> bool {
+        self.unusable
+    }
+
+    /// Id of the parttype placed in this node, if any
+    pub fn part_id(&self) -> Option<usize> {
+        self.parttype.map(|p| p.id())
+    }
+
+    /// Whether a part is placed in this node
+    pub fn is_part(&self) -> bool {
+        self.parttype.is_some()
+    }
+
+    /// Whether this node is a leftover: a leaf without a part.
+    /// Structure nodes (with children) are neither parts nor waste.
+    /// Waste nodes are exactly the empty nodes, this is the name to use when the leftover material is meant rather than free space.
+    pub fn is_waste(&self) -> bool {
+        self.is_empty()
+    }
+
+    /// Length along the cut axis (height for horizontal cuts, width for vertical cuts) not consumed by non-empty children.
+    /// Empty children are free space, so this is the room left for another sibling if the empty children were merged.
+    pub fn remaining_along_cut(&self, nodes: &Arena<Node>) -> u64 {
+        let extent = |width: u64, height: u64| match self.next_cut_orient {
+            Orientation::Horizontal => height,
+            Orientation::Vertical => width,
+        };
+        let consumed = self.children.iter()
+            .map(|c| &nodes[*c])
+            .filter(|c| !c.is_empty())
+            .map(|c| extent(c.width, c.height))
+            .sum::<u64>();
+
+        extent(self.width, self.height) - consumed
     }
 
     pub fn width(&self) -> u64 {
@@ -279,7 +396,126 @@ impl<'a> Node<'a> {
     pub fn level(&self) -> u8 {
         self.level
     }
+    /// Position of the node relative to the top node of its layout, if the layout maintains a position cache (see Layout::enable_position_cache)
+    pub fn cached_position(&self) -> Option<(u64, u64)> {
+        self.position
+    }
+}
+
+/// Long side / short side of a rectangle, None if it has no area
+pub fn aspect_ratio(width: u64, height: u64) -> Option<f64> {
+    match u64::min(width, height) {
+        0 => None,
+        short_side => Some(u64::max(width, height) as f64 / short_side as f64),
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use crate::core::entities::node;
+    use crate::core::insertion::node_blueprint::NodeBlueprint;
+    use crate::core::orientation::Orientation;
+    use crate::core::rotation::Rotation;
+    use crate::io::parser;
+    use crate::optimization::config::Config;
+    use crate::optimization::solutions::solution::Solution;
+    use crate::util::test_util;
+
+    #[test]
+    fn parts_waste_and_structure_nodes_are_distinct() {
+        let instance = test_util::instance(&[(60, 100, 1)], &[(100, 100, 1)]);
+        let layout = test_util::layout(&instance, 0, &[(0, 0, 0)]);
+        let nodes = layout.nodes();
+
+        let top_node = &nodes[*layout.top_node_index()];
+        assert!(!top_node.is_part() && !top_node.is_waste() && !top_node.is_empty());
+        assert_eq!(top_node.part_id(), None);
+
+        let part_node = nodes.iter().map(|(_, n)| n).find(|n| n.is_part()).unwrap();
+        assert!(!part_node.is_waste() && !part_node.is_empty());
+        assert_eq!(part_node.part_id(), Some(0));
+
+        let empty_node = &nodes[layout.sorted_empty_nodes()[0]];
+        assert!(empty_node.is_waste() && empty_node.is_empty() && !empty_node.is_part());
+        assert_eq!(empty_node.part_id(), None);
+    }
+
+    #[test]
+    fn remaining_along_cut_excludes_the_children_with_parts() {
+        let instance = test_util::instance(&[(30, 100, 2)], &[(100, 100, 1)]);
+        let layout = test_util::layout(&instance, 0, &[(0, 0, 0), (0, 30, 0)]);
+        let nodes = layout.nodes();
+
+        let part_node = nodes.iter().map(|(_, n)| n).find(|n| n.is_part()).unwrap();
+        let parent = &nodes[part_node.parent().unwrap()];
+        assert_eq!(parent.next_cut_orient(), Orientation::Vertical);
+        assert_eq!(parent.children().len(), 3);
+        assert_eq!(parent.remaining_along_cut(nodes), 40);
+    }
 
+    #[test]
+    fn min_waste_dimension_forbids_thin_leftovers() {
+        fn min_waste_dimension(pattern: &NodeBlueprint) -> Option<u64> {
+            match pattern.is_empty() {
+                true => Some(u64::min(pattern.width(), pattern.height())),
+                false => pattern.children().iter().filter_map(min_waste_dimension).min(),
+            }
+        }
+        let min_waste_dimensions = |config: &Config| {
+            let mut json_instance = test_util::json_instance(&[(30, 30, 6)], &[(100, 100, 10)]);
+            let instance = Arc::new(parser::generate_instance(&mut json_instance, config).unwrap());
+            let solution = test_util::solve(instance, config);
+            assert!(solution.is_complete());
+            solution.layouts().iter().filter_map(|l| min_waste_dimension(l.top_node())).min()
+        };
 
+        //every complete solution on a single sheet has 3 parts side by side, leaving a 10 wide sliver
+        assert!(min_waste_dimensions(&test_util::config()).unwrap() < 20);
+        assert!(min_waste_dimensions(&test_util::config_with(json!({"minWasteDimension": 20}))).unwrap() >= 20);
+    }
+
+    #[test]
+    fn exact_fits_do_not_create_empty_nodes() {
+        let instance = test_util::instance(&[(100, 40, 1), (100, 60, 1)], &[(100, 100, 1)]);
+        let mut layout = test_util::layout(&instance, 0, &[(0, 0, 0)]);
+        let empty_node_i = layout.sorted_empty_nodes()[0];
+        let empty_node = &layout.nodes()[empty_node_i];
+        assert_eq!((empty_node.width(), empty_node.height()), (100, 60));
+
+        //the only replacement of the empty node is the part itself
+        let parttype = instance.get_parttype(1);
+        let replacements = empty_node.generate_insertion_node_blueprints(parttype, Rotation::Default, u8::MAX, vec![]);
+        assert!(replacements.iter().all(|r| r.len() == 1 && r[0].parttype_id() == Some(1)));
+
+        assert_eq!(layout.fill_with(parttype, 1, &instance), 1);
+        assert!(layout.sorted_empty_nodes().is_empty());
+        assert!(layout.nodes().iter().all(|(_, n)| n.width() * n.height() > 0));
+        assert_eq!(layout.usage(true), 1.0);
+    }
+
+    #[test]
+    fn hard_max_waste_aspect_ratio_forbids_elongated_leftovers() {
+        fn max_aspect_ratio(pattern: &NodeBlueprint) -> Option<f64> {
+            match pattern.is_empty() {
+                true => node::aspect_ratio(pattern.width(), pattern.height()),
+                false => pattern.children().iter().filter_map(max_aspect_ratio).max_by(|a, b| a.total_cmp(b)),
+            }
+        }
+        let max_aspect_ratios = |config: &Config| {
+            let mut json_instance = test_util::json_instance(&[(30, 30, 6)], &[(100, 100, 10)]);
+            let instance = Arc::new(parser::generate_instance(&mut json_instance, config).unwrap());
+            let solution = test_util::solve(instance, config);
+            assert!(solution.is_complete());
+            solution.layouts().iter().filter_map(|l| max_aspect_ratio(l.top_node())).max_by(|a, b| a.total_cmp(b))
+        };
+
+        //the 10 wide sliver next to 3 parts side by side is at least 3 times as long
+        assert!(max_aspect_ratios(&test_util::config()).unwrap() >= 3.0);
+        let hard = test_util::config_with(json!({"maxWasteAspectRatio": 2.5, "wasteAspectRatioMode": "hard"}));
+        assert!(max_aspect_ratios(&hard).unwrap() <= 2.5);
+    }
+}
@@ -1,30 +1,74 @@
 use std::hash::{Hash, Hasher};
+use crate::core::edge_affinity::EdgeAffinity;
+use crate::core::entities::composite_structure::CompositeStructure;
+use crate::core::kerf::Kerf;
+use crate::core::leftover_valuator;
 use crate::core::rotation::Rotation;
 
 use crate::core::size::Size;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PartType {
     id: usize,
     width: u64,
     height: u64,
     fixed_rotation: Option<Rotation>,
+    preferred_rotation: Option<Rotation>,
+    rotation_penalty: f32,
+    priority: u32,
+    kerf: Kerf,
     size: Size,
     rotated_size: Size,
+    composite: Option<CompositeStructure>,
+    edge_affinity: Option<EdgeAffinity>,
+    urgency: u32,
 }
 
 impl PartType {
-    pub fn new(id: usize, width: u64, height: u64, fixed_rotation: Option<Rotation>) -> PartType {
+    pub fn new(id: usize, width: u64, height: u64, fixed_rotation: Option<Rotation>, preferred_rotation: Option<Rotation>, rotation_penalty: f32, kerf: Kerf) -> PartType {
         PartType {
             id,
             width,
             height,
             fixed_rotation,
-            size: Size::new(width, height),
-            rotated_size: Size::new(height, width),
+            preferred_rotation,
+            rotation_penalty,
+            priority: 0,
+            kerf,
+            size: kerf.inflate(width, height),
+            rotated_size: kerf.inflate(height, width),
+            composite: None,
+            edge_affinity: None,
+            urgency: 0,
         }
     }
 
+    /// Parts with a higher priority are inserted first during recreate, and are the last to be left out of a solution
+    pub fn with_priority(mut self, priority: u32) -> PartType {
+        self.priority = priority;
+        self
+    }
+
+    /// Turns the parttype into a composite part, which is placed as a single unit but cut according to the given structure
+    pub fn with_composite(mut self, composite: CompositeStructure) -> PartType {
+        assert!((composite.width(), composite.height()) == (self.width, self.height), "composite structure does not match the dimensions of part {}", self.id);
+        assert!(composite.is_valid(), "composite structure of part {} does not subdivide the part", self.id);
+        self.composite = Some(composite);
+        self
+    }
+
+    /// Only allows the part to be placed near an edge of the sheet
+    pub fn with_edge_affinity(mut self, edge_affinity: EdgeAffinity) -> PartType {
+        self.edge_affinity = Some(edge_affinity);
+        self
+    }
+
+    /// Parts with a higher urgency are preferably placed on the first layouts to be cut, see Problem::urgency_penalty
+    pub fn with_urgency(mut self, urgency: u32) -> PartType {
+        self.urgency = urgency;
+        self
+    }
+
     pub fn id(&self) -> usize {
         self.id
     }
@@ -41,16 +85,70 @@ impl PartType {
         &self.fixed_rotation
     }
 
+    /// Rotation the part prefers to be placed in, placing it otherwise is allowed but penalized
+    pub fn preferred_rotation(&self) -> &Option<Rotation> {
+        &self.preferred_rotation
+    }
+
+    /// Penalty for placing the part against its preferred rotation,
+    /// as a fraction of the value its own area would have as a leftover
+    pub fn preferred_rotation_penalty(&self) -> f32 {
+        self.rotation_penalty
+    }
+
+    /// Reduction of the leftover value when the part is placed with this rotation.
+    /// Expressed as a fraction of the value its own area would have as a leftover.
+    pub fn rotation_penalty(&self, rotation: Rotation) -> f32 {
+        match self.preferred_rotation {
+            Some(preferred) if preferred != rotation => self.rotation_penalty * leftover_valuator::valuate(self.area()),
+            _ => 0.0
+        }
+    }
+
+    /// Parts with a higher priority are inserted first during recreate, and are the last to be left out of a solution
+    pub fn priority(&self) -> u32 {
+        self.priority
+    }
+
+    /// Internal structure of the part, if it is a composite part
+    pub fn composite(&self) -> &Option<CompositeStructure> {
+        &self.composite
+    }
+
+    /// Edge of the sheet the part needs to be placed near, if any
+    pub fn edge_affinity(&self) -> Option<EdgeAffinity> {
+        self.edge_affinity
+    }
+
+    pub fn urgency(&self) -> u32 {
+        self.urgency
+    }
+
+    pub fn kerf(&self) -> &Kerf {
+        &self.kerf
+    }
+
+    /// Size the part occupies in a layout, including kerf
     pub fn size(&self) -> &Size {
         &self.size
     }
 
+    /// Size the part occupies in a layout when rotated, including kerf
     pub fn rotated_size(&self) -> &Size {
         &self.rotated_size
     }
 
+    /// Size the part occupies in a layout with the given rotation, including kerf
+    pub fn size_for(&self, rotation: Rotation) -> &Size {
+        match rotation {
+            Rotation::Default => &self.size,
+            Rotation::Rotated => &self.rotated_size,
+        }
+    }
+
+    /// Area of the part itself, excluding kerf
     pub fn area(&self) -> u64 {
-        self.size.area()
+        self.width * self.height
     }
 }
 
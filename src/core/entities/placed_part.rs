@@ -0,0 +1,15 @@
+use crate::core::rotation::Rotation;
+
+/// A part placed in a layout, with its absolute position relative to the corner of the sheet.
+/// The width and height are those of the node it occupies (including kerf).
+/// A Default rotation means the part is placed in its nominal orientation, which for a rectangle is geometrically identical to a 180° turn.
+/// A Rotated part is turned 90°, which is never the case for parts with a fixed rotation (such as half-turn-only parts).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlacedPart {
+    pub parttype_id: usize,
+    pub x: u64,
+    pub y: u64,
+    pub width: u64,
+    pub height: u64,
+    pub rotation: Rotation,
+}
@@ -1,4 +1,5 @@
 use crate::core::cost::Cost;
+use crate::core::empty_node_order::EmptyNodeOrder;
 use crate::core::entities::layout::Layout;
 use crate::core::insertion::node_blueprint::NodeBlueprint;
 use crate::optimization::instance::Instance;
@@ -23,8 +24,9 @@ impl SendableLayout {
         }
     }
 
-    pub fn convert_to_layout<'a>(&self, _instance: &'a Instance) -> Layout<'a> {
-        todo!();
+    /// Rebuilds the layout for the instance, which needs to contain the sheettype and parttypes of this one under the same ids
+    pub fn convert_to_layout<'a>(&self, id: usize, instance: &'a Instance, empty_node_order: EmptyNodeOrder) -> Layout<'a> {
+        Layout::from_blueprint(id, instance.get_sheettype(self.sheettype_id), &self.top_node, empty_node_order, instance)
     }
 
     pub fn sheettype_id(&self) -> usize {
@@ -39,4 +41,9 @@ impl SendableLayout {
     pub fn usage(&self) -> f64 {
         self.usage
     }
+
+    /// Hash of the sheettype and the complete cutting pattern, identical for structurally identical layouts
+    pub fn structural_key(&self) -> u64 {
+        fxhash::hash64(&(self.sheettype_id, &self.top_node))
+    }
 }
\ No newline at end of file
@@ -1,7 +1,16 @@
 use std::hash::{Hash, Hasher};
+
+use itertools::Itertools;
+use ordered_float::NotNan;
+
+use crate::core::entities::layout;
+use crate::core::insertion::node_blueprint::NodeBlueprint;
+use crate::core::kerf::Kerf;
+use crate::core::margins::Margins;
 use crate::core::orientation::Orientation;
+use crate::core::size::Size;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SheetType {
     id: usize,
     width: u64,
@@ -9,10 +18,17 @@ pub struct SheetType {
     value: u64,
     fixed_first_cut_orientation: Option<Orientation>,
     max_stages: u8,
+    kerf: Kerf,
+    margins: Margins,
+    density: Option<NotNan<f64>>,
+    corner_cut: Option<(u64, u64)>,
+    usable_regions: Option<Vec<(u64, u64, u64, u64)>>,
+    unusable_regions: Vec<(u64, u64, u64, u64)>,
+    inset_unusable_regions: Vec<(u64, u64, u64, u64)>,
 }
 
 impl SheetType {
-    pub fn new(id: usize, width: u64, height: u64, value: u64, fixed_first_cut_orientation: Option<Orientation>, max_stages: u8) -> SheetType {
+    pub fn new(id: usize, width: u64, height: u64, value: u64, fixed_first_cut_orientation: Option<Orientation>, max_stages: u8, kerf: Kerf) -> SheetType {
         SheetType {
             id,
             width,
@@ -20,9 +36,83 @@ impl SheetType {
             value,
             fixed_first_cut_orientation,
             max_stages,
+            kerf,
+            margins: Margins::default(),
+            density: None,
+            corner_cut: None,
+            usable_regions: None,
+            unusable_regions: vec![],
+            inset_unusable_regions: vec![],
         }
     }
 
+    /// Sets the mass per unit of area of the sheet material
+    pub fn with_density(mut self, density: f64) -> SheetType {
+        self.density = Some(NotNan::new(density).expect("density is NaN"));
+        self
+    }
+
+    /// Cuts a rectangle (width, height) away from the top-left corner of the sheet before the guillotine cuts start,
+    /// which leaves an L-shaped region for the parts
+    pub fn with_corner_cut(mut self, width: u64, height: u64) -> SheetType {
+        assert!(width > 0 && height > 0, "empty corner cut on sheettype {}", self.id);
+        assert!(width < self.width && height < self.height, "corner cut on sheettype {} does not leave an L-shaped region", self.id);
+        self.corner_cut = Some((width, height));
+        self.unusable_regions = vec![(0, 0, width, height)];
+        self.clip_unusable_regions();
+        self
+    }
+
+    /// Restricts the layouts to the union of the regions (x, y, width, height) relative to the top-left corner of the sheet,
+    /// e.g. for reclaimed boards which are not rectangular. The rest of the sheet becomes unusable
+    /// and the usage of the layouts is measured against the area of the regions.
+    /// Fails if there are no regions, if a region is empty, lies outside the sheet or overlaps another one,
+    /// or if the regions cannot be separated by guillotine cuts.
+    pub fn with_usable_regions(mut self, regions: Vec<(u64, u64, u64, u64)>) -> Result<SheetType, &'static str> {
+        if regions.is_empty() {
+            return Err("no usable region");
+        }
+        if regions.iter().any(|&(x, y, width, height)| width == 0 || height == 0 || x + width > self.width || y + height > self.height) {
+            return Err("region is empty or lies outside the sheet");
+        }
+        if regions.iter().tuple_combinations().any(|(a, b)| a.0 < b.0 + b.2 && b.0 < a.0 + a.2 && a.1 < b.1 + b.3 && b.1 < a.1 + a.3) {
+            return Err("regions overlap");
+        }
+        //the unusable regions are the leaves in between the usable regions
+        let mut unusable_regions = vec![];
+        layout::guillotine_decomposition((0, 0, self.width, self.height), &regions, Orientation::Horizontal, &mut |region, usable, orientation| {
+            if !usable {
+                unusable_regions.push(region);
+            }
+            NodeBlueprint::new(region.2, region.3, None, orientation)
+        }).map_err(|_| "regions cannot be separated by guillotine cuts")?;
+
+        self.usable_regions = Some(regions);
+        self.unusable_regions = unusable_regions;
+        self.clip_unusable_regions();
+        Ok(self)
+    }
+
+    /// Reserves strips along the edges of the sheet in which no parts can be placed.
+    /// The corner cut and the usable regions remain relative to the top-left corner of the sheet.
+    pub fn with_margins(mut self, margins: Margins) -> SheetType {
+        assert!(margins.horizontal() < self.width && margins.vertical() < self.height, "margins leave no usable region on sheettype {}", self.id);
+        self.margins = margins;
+        self.clip_unusable_regions();
+        self
+    }
+
+    /// Clips the unusable regions to the region inside the margins, relative to its top-left corner
+    fn clip_unusable_regions(&mut self) {
+        let (left, top) = (self.margins.left, self.margins.top);
+        let (right, bottom) = (self.width - self.margins.right, self.height - self.margins.bottom);
+        self.inset_unusable_regions = self.unusable_regions.iter()
+            .map(|&(x, y, width, height)| (x.max(left), y.max(top), (x + width).min(right), (y + height).min(bottom)))
+            .filter(|&(x_start, y_start, x_end, y_end)| x_start < x_end && y_start < y_end)
+            .map(|(x_start, y_start, x_end, y_end)| (x_start - left, y_start - top, x_end - x_start, y_end - y_start))
+            .collect();
+    }
+
     pub fn id(&self) -> usize {
         self.id
     }
@@ -43,6 +133,14 @@ impl SheetType {
         self.width * self.height
     }
 
+    /// Area the usage of a layout is measured against: the area of the usable regions if they are declared, the entire sheet otherwise
+    pub fn usage_area(&self) -> u64 {
+        match &self.usable_regions {
+            Some(regions) => regions.iter().map(|&(_, _, width, height)| width * height).sum(),
+            None => self.area(),
+        }
+    }
+
     pub fn fixed_first_cut_orientation(&self) -> Option<Orientation> {
         self.fixed_first_cut_orientation
     }
@@ -50,6 +148,51 @@ impl SheetType {
     pub fn max_stages(&self) -> u8 {
         self.max_stages
     }
+
+    pub fn kerf(&self) -> &Kerf {
+        &self.kerf
+    }
+
+    pub fn margins(&self) -> &Margins {
+        &self.margins
+    }
+
+    /// Area in which parts can be placed: the region inside the margins, without the unusable regions
+    pub fn placeable_area(&self) -> u64 {
+        let inset_area = (self.width - self.margins.horizontal()) * (self.height - self.margins.vertical());
+        inset_area - self.inset_unusable_regions.iter().map(|&(_, _, width, height)| width * height).sum::<u64>()
+    }
+
+    /// Mass per unit of area of the sheet material, if known
+    pub fn density(&self) -> Option<f64> {
+        self.density.map(|d| d.into_inner())
+    }
+
+    /// Size (width, height) of the rectangle cut away from the top-left corner of the sheet, see with_corner_cut
+    pub fn corner_cut(&self) -> Option<(u64, u64)> {
+        self.corner_cut
+    }
+
+    /// Declared regions (x, y, width, height) of the sheet in which parts can be placed, see with_usable_regions
+    pub fn usable_regions(&self) -> Option<&[(u64, u64, u64, u64)]> {
+        self.usable_regions.as_deref()
+    }
+
+    /// Regions (x, y, width, height) of the sheet in which no parts can be placed: the corner cut, or the rest of the sheet outside the usable regions
+    pub fn unusable_regions(&self) -> &[(u64, u64, u64, u64)] {
+        &self.unusable_regions
+    }
+
+    /// The unusable regions clipped to the region inside the margins, relative to its top-left corner (as the nodes of a layout are)
+    pub fn inset_unusable_regions(&self) -> &[(u64, u64, u64, u64)] {
+        &self.inset_unusable_regions
+    }
+
+    /// Size of the top node of a layout of this sheettype: the region inside the margins.
+    /// Enlarged by the kerf, as the parts at the edge of the sheet do not need a trailing cut.
+    pub fn layout_size(&self) -> Size {
+        self.kerf.inflate(self.width - self.margins.horizontal(), self.height - self.margins.vertical())
+    }
 }
 
 impl Hash for SheetType {
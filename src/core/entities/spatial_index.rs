@@ -0,0 +1,78 @@
+use fxhash::FxHashMap;
+use generational_arena::Index;
+use itertools::Itertools;
+
+/// Uniform grid over a layout, registering the rectangle (x, y, width, height) of every part node in all the cells it overlaps.
+/// Used to answer point and rectangle queries without scanning all nodes of the layout.
+#[derive(Debug, Clone)]
+pub struct SpatialIndex {
+    cell_size: u64,
+    cells: FxHashMap<(u64, u64), Vec<Index>>,
+    rects: FxHashMap<Index, (u64, u64, u64, u64)>,
+}
+
+impl SpatialIndex {
+    const CELLS_PER_SIDE: u64 = 32;
+
+    pub fn new(width: u64, height: u64) -> Self {
+        Self {
+            cell_size: (u64::max(width, height) / SpatialIndex::CELLS_PER_SIDE).max(1),
+            cells: FxHashMap::default(),
+            rects: FxHashMap::default(),
+        }
+    }
+
+    pub fn insert(&mut self, node_index: Index, rect: (u64, u64, u64, u64)) {
+        for cell in self.overlapping_cells(rect) {
+            self.cells.entry(cell).or_default().push(node_index);
+        }
+        self.rects.insert(node_index, rect);
+    }
+
+    pub fn remove(&mut self, node_index: Index) {
+        if let Some(rect) = self.rects.remove(&node_index) {
+            for cell in self.overlapping_cells(rect) {
+                if let Some(nodes) = self.cells.get_mut(&cell) {
+                    nodes.retain(|n| *n != node_index);
+                }
+            }
+        }
+    }
+
+    /// Nodes whose rectangle contains the point, sorted by position (y, then x)
+    pub fn at(&self, x: u64, y: u64) -> Vec<Index> {
+        let cell = (x / self.cell_size, y / self.cell_size);
+        self.cells.get(&cell).into_iter().flatten()
+            .filter(|n| {
+                let (n_x, n_y, n_w, n_h) = self.rects[*n];
+                (n_x..n_x + n_w).contains(&x) && (n_y..n_y + n_h).contains(&y)
+            })
+            .copied()
+            .sorted_by_key(|n| (self.rects[n].1, self.rects[n].0))
+            .collect()
+    }
+
+    /// Nodes whose rectangle overlaps the given one with a nonzero area, sorted by position (y, then x)
+    pub fn in_rect(&self, rect: (u64, u64, u64, u64)) -> Vec<Index> {
+        let (x, y, w, h) = rect;
+        self.overlapping_cells(rect).into_iter()
+            .flat_map(|cell| self.cells.get(&cell).into_iter().flatten())
+            .filter(|n| {
+                let (n_x, n_y, n_w, n_h) = self.rects[*n];
+                n_x < x + w && x < n_x + n_w && n_y < y + h && y < n_y + n_h
+            })
+            .copied()
+            .unique()
+            .sorted_by_key(|n| (self.rects[n].1, self.rects[n].0))
+            .collect()
+    }
+
+    fn overlapping_cells(&self, (x, y, w, h): (u64, u64, u64, u64)) -> Vec<(u64, u64)> {
+        if w == 0 || h == 0 {
+            return vec![];
+        }
+        let (x_min, x_max) = (x / self.cell_size, (x + w - 1) / self.cell_size);
+        let (y_min, y_max) = (y / self.cell_size, (y + h - 1) / self.cell_size);
+        (x_min..=x_max).cartesian_product(y_min..=y_max).collect()
+    }
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::insertion::insertion_blueprint::InsertionBlueprint;
+
+/// Classic 2D packing rules to rank the insertions of a part into existing layouts during recreate,
+/// instead of the cost (leftover value) of the insertions. Which rule performs best is instance dependent.
+/// Every rule scores an insertion by the empty node it uses and the (rotated) size of the part, lower scores are better.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum FitMetric {
+    /// Smallest leftover area of the node, ties broken by the short side fit
+    BestAreaFit,
+    /// Smallest remaining length along the shorter leftover side of the node, ties broken by the long side
+    BestShortSideFit,
+    /// Smallest remaining length along the longer leftover side of the node, ties broken by the short side
+    BestLongSideFit,
+    /// Smallest area of new empty nodes in which no part of the instance fits,
+    /// because one of their dimensions is smaller than the smallest part dimension. Ties broken by the area fit.
+    BestAreaWaste,
+}
+
+impl FitMetric {
+    /// Score of inserting into an empty node of the given size, with min_part_dimension the smallest dimension of all parts
+    pub fn score(&self, node_size: (u64, u64), blueprint: &InsertionBlueprint, min_part_dimension: u64) -> (u64, u64) {
+        let (node_width, node_height) = node_size;
+        let (part_width, part_height) = blueprint.part_size();
+        let (leftover_width, leftover_height) = (node_width - part_width, node_height - part_height);
+        let short_side = u64::min(leftover_width, leftover_height);
+        let long_side = u64::max(leftover_width, leftover_height);
+        let area_fit = node_width * node_height - part_width * part_height;
+
+        match self {
+            FitMetric::BestAreaFit => (area_fit, short_side),
+            FitMetric::BestShortSideFit => (short_side, long_side),
+            FitMetric::BestLongSideFit => (long_side, short_side),
+            FitMetric::BestAreaWaste => {
+                let dead_area = blueprint.replacements().iter().map(|r| r.dead_area(min_part_dimension)).sum();
+                (dead_area, area_fit)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use crate::core::fit_metric::FitMetric;
+    use crate::core::insertion::insertion_option::InsertionOption;
+    use crate::core::layout_index::LayoutIndex;
+    use crate::core::rotation::Rotation;
+    use crate::optimization::problem::Problem;
+    use crate::util::test_util;
+
+    #[test]
+    fn short_and_long_side_fit_select_different_empty_nodes() {
+        //the part leaves 35x100 to its right and 95x60 below it
+        let instance = test_util::instance(&[(60, 100, 1), (30, 30, 1)], &[(95, 160, 1)]);
+        let mut problem = Problem::new(&instance, Default::default());
+        let layout_index = LayoutIndex::Existing(problem.register_layout(test_util::layout(&instance, 0, &[(0, 0, 0)])));
+        let layout = problem.get_layout(&layout_index);
+        let blueprints = layout.sorted_empty_nodes().iter()
+            .flat_map(|&n| InsertionOption::new(layout_index, n, instance.get_parttype(1), Some(Rotation::Default)).generate_blueprints(&problem))
+            .collect_vec();
+
+        let selected_node_size = |fit_metric: FitMetric| {
+            let node_size = |i| (layout.nodes()[i].width(), layout.nodes()[i].height());
+            let best = blueprints.iter().min_by_key(|bp| fit_metric.score(node_size(*bp.original_node_index()), bp, 30)).unwrap();
+            node_size(*best.original_node_index())
+        };
+        //leftovers of 5 and 70 in the narrow node, 65 and 30 in the wide one
+        assert_eq!(selected_node_size(FitMetric::BestShortSideFit), (35, 100));
+        assert_eq!(selected_node_size(FitMetric::BestLongSideFit), (95, 60));
+    }
+}
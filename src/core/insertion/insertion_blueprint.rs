@@ -1,9 +1,13 @@
+use std::fmt;
+
 use generational_arena::Index;
 
 use crate::core::cost::Cost;
+use crate::core::entities::node::Node;
 use crate::core::entities::parttype::PartType;
 use crate::core::insertion::node_blueprint::NodeBlueprint;
 use crate::core::layout_index::LayoutIndex;
+use crate::core::rotation::Rotation;
 
 /// Representation of how a part can be inserted into a Node of a Layout
 /// Layouts can use InsertionBlueprints to insert parts
@@ -15,20 +19,37 @@ pub struct InsertionBlueprint<'a> {
     replacements: Vec<NodeBlueprint>,
     parttype: &'a PartType,
     cost: Cost,
+    max_level: u8,
 }
 
 
 impl<'a> InsertionBlueprint<'a> {
-    pub fn new(layout_i: LayoutIndex, original_node_i: Index, replacements: Vec<NodeBlueprint>, parttype: &'a PartType, cost: Cost) -> Self {
+    pub fn new(layout_i: LayoutIndex, original_node_i: Index, replacements: Vec<NodeBlueprint>, parttype: &'a PartType, cost: Cost, original_level: u8) -> Self {
+        let max_level = original_level + replacements.iter().map(|r| r.depth()).max().unwrap_or(0);
         Self {
             layout_i,
             original_node_i,
             replacements,
             parttype,
-            cost
+            cost,
+            max_level,
         }
     }
 
+    /// Adds a term to the leftover value of the insertion cost, e.g. for effects on the layout beyond the replaced node
+    pub fn add_leftover_value(mut self, leftover_value: f32) -> Self {
+        self.cost = self.cost.add_leftover_value(leftover_value);
+        self
+    }
+
+    /// Blueprint replacing the original (empty) node, with the cost difference of the replacements and the rotation penalty of the part
+    pub fn from_replacements(layout_i: LayoutIndex, original_node_i: Index, original_node: &Node, replacements: Vec<NodeBlueprint>, parttype: &'a PartType, rotation: Rotation) -> Self {
+        let new_cost = replacements.iter().map(|replacement| replacement.calculate_cost()).sum::<Cost>()
+            .add_leftover_value(-parttype.rotation_penalty(rotation));
+        let insertion_cost = new_cost.subtract(&original_node.calculate_cost());
+        InsertionBlueprint::new(layout_i, original_node_i, replacements, parttype, insertion_cost, original_node.level())
+    }
+
     pub fn replacements(&self) -> &Vec<NodeBlueprint> {
         &self.replacements
     }
@@ -49,4 +70,43 @@ impl<'a> InsertionBlueprint<'a> {
         &self.original_node_i
     }
 
-}
\ No newline at end of file
+    /// Width and height of the part node, as placed
+    pub fn part_size(&self) -> (u64, u64) {
+        let mut stack = self.replacements.iter().collect::<Vec<_>>();
+        while let Some(node) = stack.pop() {
+            if node.parttype_id().is_some() {
+                return (node.width(), node.height());
+            }
+            stack.extend(node.children());
+        }
+        panic!("Insertion blueprint without part node");
+    }
+
+    /// Level of the deepest node in the layout after implementing the blueprint
+    pub fn max_level(&self) -> u8 {
+        self.max_level
+    }
+
+}
+
+/// Reasons why an InsertionBlueprint cannot be implemented in a Layout
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InsertionBlueprintError {
+    /// The original node does not exist, has no parent or is not empty
+    InvalidOriginalNode,
+    /// The replacements do not exactly tile the original node along the cut orientation of its parent,
+    /// or their children do not exactly tile them
+    ReplacementsDoNotTile,
+}
+
+impl fmt::Display for InsertionBlueprintError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InsertionBlueprintError::InvalidOriginalNode => write!(f, "original node of the blueprint is not a valid empty node"),
+            InsertionBlueprintError::ReplacementsDoNotTile => write!(f, "replacements of the blueprint do not tile the original node"),
+        }
+    }
+}
+
+impl std::error::Error for InsertionBlueprintError {}
@@ -3,7 +3,6 @@ use std::fmt::Debug;
 use generational_arena::Index;
 use itertools::Itertools;
 
-use crate::core::cost::Cost;
 use crate::core::entities::parttype::PartType;
 use crate::core::insertion::insertion_blueprint::InsertionBlueprint;
 use crate::core::layout_index::LayoutIndex;
@@ -35,24 +34,30 @@ impl<'a> InsertionOption<'a> {
 
     pub fn generate_blueprints(&self, problem: &Problem) -> Vec<InsertionBlueprint<'a>> {
         let layout = problem.get_layout(&self.layout_i);
+        if !layout.parttype_allowed(self.parttype.id(), problem.instance().max_parttypes_per_sheet()) {
+            return vec![];
+        }
         let original_node = &layout.nodes()[self.original_node_i];
         let max_stages = layout.sheettype().max_stages();
-        let node_blueprints = match self.rotation {
-            Some(rotation) => {
-                original_node.generate_insertion_node_blueprints(self.parttype, rotation, max_stages, vec![])
-            }
-            None => {
-                let node_blueprints = original_node.generate_insertion_node_blueprints(self.parttype, Rotation::Default, max_stages, vec![]);
-                original_node.generate_insertion_node_blueprints(self.parttype, Rotation::Rotated, max_stages, node_blueprints)
-            }
-        };
-        let original_cost = original_node.calculate_cost();
+        let rotations = match self.rotation {
+            Some(rotation) => vec![rotation],
+            None => vec![Rotation::Default, Rotation::Rotated]
+        }.into_iter()
+            .filter(|&rotation| !problem.instance().homogeneous_strips() || layout.strip_allows(self.original_node_i, self.parttype.size_for(rotation)))
+            .collect_vec();
+        let node_blueprints = rotations.into_iter().flat_map(|rotation| {
+            original_node.generate_insertion_node_blueprints(self.parttype, rotation, max_stages, vec![])
+                .into_iter().map(move |nbs| (rotation, nbs))
+        })
+            .filter(|(_, nbs)| original_node.waste_allows(nbs, problem.instance()))
+            .filter(|(_, nbs)| layout.edge_affinity_allows(self.original_node_i, nbs, self.parttype))
+            .collect_vec();
 
         //Convert the node blueprints into insertion blueprints
-        node_blueprints.into_iter().map(|nbs| {
-            let new_cost = nbs.iter().map(|replacement| replacement.calculate_cost()).sum::<Cost>();
-            let insertion_cost = new_cost.subtract(&original_cost);
-            InsertionBlueprint::new(self.layout_i, self.original_node_i, nbs, self.parttype, insertion_cost)
+        node_blueprints.into_iter().map(|(rotation, nbs)| {
+            let clustering_penalty_delta = layout.clustering_penalty_delta(self.original_node_i, &nbs, self.parttype);
+            InsertionBlueprint::from_replacements(self.layout_i, self.original_node_i, original_node, nbs, self.parttype, rotation)
+                .add_leftover_value(-clustering_penalty_delta)
         }).collect_vec()
     }
 
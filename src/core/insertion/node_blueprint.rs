@@ -2,20 +2,24 @@ use generational_arena::{Arena, Index};
 use itertools::Itertools;
 
 use crate::core::cost::Cost;
-use crate::core::entities::node::Node;
+use crate::core::entities::node::{self, Node};
 use crate::core::entities::parttype::PartType;
+use crate::core::kerf::Kerf;
 use crate::core::leftover_valuator;
 use crate::core::orientation::Orientation;
+use crate::core::rotation::Rotation;
+use crate::core::size::Size;
 
 /// Represents a node in an InsertionBlueprint
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct NodeBlueprint {
     width: u64,
     height: u64,
     children: Vec<NodeBlueprint>,
     parttype_id: Option<usize>,
     next_cut_orient: Orientation,
+    unusable: bool,
 }
 
 impl NodeBlueprint {
@@ -25,7 +29,12 @@ impl NodeBlueprint {
             Some(parttype) => Some(parttype.id()),
             None => None,
         };
-        Self { width, height, children, parttype_id, next_cut_orient }
+        Self { width, height, children, parttype_id, next_cut_orient, unusable: false }
+    }
+
+    /// Blueprint of an unusable node, see Node::new_unusable
+    pub fn new_unusable(width: u64, height: u64, next_cut_orient: Orientation) -> Self {
+        Self { unusable: true, ..Self::new(width, height, None, next_cut_orient) }
     }
 
     pub fn from_node(node_index: Index, nodes: &Arena<Node>) -> Self {
@@ -33,15 +42,19 @@ impl NodeBlueprint {
 
         let (width, height) = (node.width(), node.height());
         let next_cut_orient = node.next_cut_orient();
-        let parttype_id = match node.parttype() {
-            Some(pt) => Some(pt.id()),
-            None => None
+        let parttype_id = node.part_id();
+        let children = match node.parttype().and_then(|p| p.composite().as_ref()) {
+            //the structure of a composite part is expanded, the part remains a single node in the layout
+            Some(composite) => match node.rotation() {
+                Some(Rotation::Rotated) => composite.rotated().node_blueprints(width, height, next_cut_orient),
+                _ => composite.node_blueprints(width, height, next_cut_orient),
+            },
+            None => node.children().iter()
+                .map(|child_index| NodeBlueprint::from_node(*child_index, nodes))
+                .collect_vec(),
         };
-        let children = node.children().iter()
-            .map(|child_index| NodeBlueprint::from_node(*child_index, nodes))
-            .collect_vec();
 
-        Self { width, height, parttype_id, children, next_cut_orient }
+        Self { width, height, parttype_id, children, next_cut_orient, unusable: node.is_unusable() }
     }
 
     pub fn add_child(&mut self, child: NodeBlueprint) {
@@ -49,7 +62,7 @@ impl NodeBlueprint {
     }
 
     pub fn calculate_cost(&self) -> Cost {
-        if self.parttype_id.is_some() {
+        if self.parttype_id.is_some() || self.unusable {
             return Cost::new(0, 0.0, 0, 0);
         } else if self.children.is_empty() {
             return Cost::new(0, leftover_valuator::valuate(self.area()), 0, 0);
@@ -62,24 +75,131 @@ impl NodeBlueprint {
         }
     }
 
-    pub fn calculate_usage(&self) -> f64 {
-        if self.parttype_id.is_some() {
-            1.0
-        } else if self.children.is_empty() {
-            0.0
-        } else {
-            let mut usage = 0.0;
-            for child in &self.children {
-                usage += child.area() as f64 * child.calculate_usage();
+    /// Fraction of this node covered by parts, both without the kerf of the cuts which follow them, as in Layout::calculate_usage
+    pub fn calculate_usage(&self, kerf: &Kerf) -> f64 {
+        match kerf.deflate(self.width, self.height).area() {
+            0 => 0.0,
+            area => {
+                let usage = self.part_area(kerf) as f64 / area as f64;
+                debug_assert!(usage <= 1.0);
+                usage
             }
-            usage /= self.area() as f64;
-            debug_assert!(usage <= 1.0);
-            usage
         }
     }
 
+    /// Total area of the parts in this (sub)tree, without the kerf
+    pub fn part_area(&self, kerf: &Kerf) -> u64 {
+        match self.parttype_id {
+            Some(_) => kerf.deflate(self.width, self.height).area(),
+            None => self.children.iter().map(|c| c.part_area(kerf)).sum(),
+        }
+    }
+
+    /// Total area of the empty nodes in this (sub)tree
+    pub fn waste_area(&self) -> u64 {
+        match (self.parttype_id, self.children.is_empty()) {
+            _ if self.unusable => 0,
+            (Some(_), _) => 0,
+            (None, true) => self.area(),
+            (None, false) => self.children.iter().map(|c| c.waste_area()).sum(),
+        }
+    }
+
+    /// (x, y, width, height) of the empty nodes in this (sub)tree, relative to the top-left corner of this node
+    pub fn waste_rects(&self) -> Vec<(u64, u64, u64, u64)> {
+        let mut rects = vec![];
+        self.collect_waste_rects((0, 0), &mut rects);
+        rects
+    }
+
+    fn collect_waste_rects(&self, (x, y): (u64, u64), rects: &mut Vec<(u64, u64, u64, u64)>) {
+        match (self.parttype_id, self.children.is_empty()) {
+            (Some(_), _) => {}
+            (None, true) => rects.push((x, y, self.width, self.height)),
+            (None, false) => {
+                let (mut child_x, mut child_y) = (x, y);
+                for child in &self.children {
+                    child.collect_waste_rects((child_x, child_y), rects);
+                    match self.next_cut_orient {
+                        Orientation::Horizontal => child_y += child.height(),
+                        Orientation::Vertical => child_x += child.width(),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Area of the largest empty node in this (sub)tree
+    pub fn largest_waste_area(&self) -> u64 {
+        match (self.parttype_id, self.children.is_empty()) {
+            (Some(_), _) => 0,
+            (None, true) => self.area(),
+            (None, false) => self.children.iter().map(|c| c.largest_waste_area()).max().unwrap_or(0),
+        }
+    }
+
+    /// Number of cuts needed to separate all nodes in this (sub)tree, see Layout::cut_count
+    pub fn cut_count(&self) -> usize {
+        self.children.len().saturating_sub(1) + self.children.iter().map(|c| c.cut_count()).sum::<usize>()
+    }
+
+    /// Number of parts which fit in the empty nodes in this (sub)tree when every empty node is filled with a grid of parts,
+    /// all in the same size (out of the given sizes, the one resulting in the most parts per empty node)
+    pub fn grid_capacity(&self, part_sizes: &[&Size]) -> usize {
+        match (self.parttype_id, self.children.is_empty()) {
+            (Some(_), _) => 0,
+            (None, true) => part_sizes.iter()
+                .map(|s| ((self.width / s.width()) * (self.height / s.height())) as usize)
+                .max().unwrap_or(0),
+            (None, false) => self.children.iter().map(|c| c.grid_capacity(part_sizes)).sum(),
+        }
+    }
+
+    /// Total area of the empty nodes in this (sub)tree with a width or height smaller than min_dimension
+    pub fn dead_area(&self, min_dimension: u64) -> u64 {
+        match (self.parttype_id, self.children.is_empty()) {
+            (Some(_), _) => 0,
+            (None, true) if self.width < min_dimension || self.height < min_dimension => self.area(),
+            (None, true) => 0,
+            (None, false) => self.children.iter().map(|c| c.dead_area(min_dimension)).sum(),
+        }
+    }
+
+    /// Smallest dimension of the empty nodes in this (sub)tree which is smaller than the corresponding dimension (width or height) of the bounds.
+    /// Dimensions equal to the bounds are inherited from the node being replaced, and not created by the replacement.
+    /// Empty nodes without area are no material and are ignored.
+    pub fn min_new_waste_dimension(&self, bounds: (u64, u64)) -> Option<u64> {
+        match (self.parttype_id, self.children.is_empty()) {
+            (Some(_), _) => None,
+            (None, true) if self.area() == 0 => None,
+            (None, true) => [(self.width, bounds.0), (self.height, bounds.1)].into_iter()
+                .filter(|(dimension, bound)| dimension < bound)
+                .map(|(dimension, _)| dimension)
+                .min(),
+            (None, false) => self.children.iter().filter_map(|c| c.min_new_waste_dimension(bounds)).min(),
+        }
+    }
+
+    /// Largest aspect ratio (long side / short side) of the empty nodes in this (sub)tree, empty nodes without area are ignored
+    pub fn max_waste_aspect_ratio(&self) -> Option<f64> {
+        match (self.parttype_id, self.children.is_empty()) {
+            (Some(_), _) => None,
+            (None, true) => node::aspect_ratio(self.width, self.height),
+            (None, false) => self.children.iter().filter_map(|c| c.max_waste_aspect_ratio()).max_by(|a, b| a.total_cmp(b)),
+        }
+    }
+
+    /// Number of levels of nodes below this one
+    pub fn depth(&self) -> u8 {
+        self.children.iter().map(|c| c.depth() + 1).max().unwrap_or(0)
+    }
+
     pub fn is_empty(&self) -> bool {
-        self.parttype_id.is_none() && self.children.is_empty()
+        self.parttype_id.is_none() && self.children.is_empty() && !self.unusable
+    }
+
+    pub fn is_unusable(&self) -> bool {
+        self.unusable
     }
 
     pub fn area(&self) -> u64 {
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::size::Size;
+
+/// Material lost by the saw blade during a cut.
+/// A horizontal cut consumes height, a vertical cut consumes width.
+///
+/// The kerf is modelled by enlarging every part (and sheet) by the kerf of the cuts which follow it.
+/// This way, the sizes of the children of a node still add up exactly to the size of the node.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Kerf {
+    pub horizontal: u64,
+    pub vertical: u64,
+}
+
+impl Kerf {
+    pub fn new(horizontal: u64, vertical: u64) -> Self {
+        Self { horizontal, vertical }
+    }
+
+    pub fn uniform(kerf: u64) -> Self {
+        Self::new(kerf, kerf)
+    }
+
+    /// Size of a rectangle including the kerf of the cuts which separate it from its neighbours
+    pub fn inflate(&self, width: u64, height: u64) -> Size {
+        Size::new(width + self.vertical, height + self.horizontal)
+    }
+
+    /// Size of a rectangle without the kerf of the cuts which follow it, the inverse of inflate
+    pub fn deflate(&self, width: u64, height: u64) -> Size {
+        Size::new(width.saturating_sub(self.vertical), height.saturating_sub(self.horizontal))
+    }
+}
@@ -10,6 +10,11 @@ pub fn set_power(power: f32) {
     })
 }
 
+/// Valuation power set for this thread, if any
+pub fn power() -> Option<f32> {
+    VALUATION_POWER.with(|p| *p.borrow())
+}
+
 pub fn valuate(area: u64) -> f32 {
     VALUATION_POWER.with(|p| {
         let power = p.borrow().expect("valuation power not set for this thread!");
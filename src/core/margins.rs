@@ -0,0 +1,29 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+/// Strips along the edges of a sheet in which no parts can be placed, e.g. where the sheet is clamped to the table.
+/// Layouts only cover the usable region inside the margins, but the usage is still relative to the entire sheet.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Encode, Decode)]
+#[serde(default)]
+pub struct Margins {
+    pub top: u64,
+    pub bottom: u64,
+    pub left: u64,
+    pub right: u64,
+}
+
+impl Margins {
+    pub fn new(top: u64, bottom: u64, left: u64, right: u64) -> Self {
+        Self { top, bottom, left, right }
+    }
+
+    /// Total width of the margins along the x-axis
+    pub fn horizontal(&self) -> u64 {
+        self.left + self.right
+    }
+
+    /// Total height of the margins along the y-axis
+    pub fn vertical(&self) -> u64 {
+        self.top + self.bottom
+    }
+}
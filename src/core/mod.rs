@@ -6,3 +6,10 @@ pub mod entities;
 pub mod rotation;
 pub mod leftover_valuator;
 pub mod layout_index;
+pub mod kerf;
+pub mod margins;
+pub mod empty_node_order;
+pub mod fit_metric;
+pub mod origin_corner;
+pub mod reading_order;
+pub mod edge_affinity;
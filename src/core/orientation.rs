@@ -1,6 +1,10 @@
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Orientation {
+    #[serde(rename = "H")]
     Horizontal,
+    #[serde(rename = "V")]
     Vertical,
 }
 
@@ -0,0 +1,28 @@
+use serde::{Deserialize, Serialize};
+
+/// Corner of the sheet used as the origin of exported coordinates, with the axes pointing into the sheet.
+/// All computations use TopLeft (y increases downwards), the other corners are only applied when exporting.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum OriginCorner {
+    #[default]
+    TopLeft,
+    BottomLeft,
+    TopRight,
+    BottomRight,
+}
+
+impl OriginCorner {
+    /// Position of the rectangle at (x, y) relative to the top-left corner of a sheet, relative to this corner instead.
+    /// The returned position is the corner of the rectangle closest to the origin.
+    pub fn transform(&self, (x, y): (u64, u64), (width, height): (u64, u64), (sheet_width, sheet_height): (u64, u64)) -> (u64, u64) {
+        let flipped_x = sheet_width - x - width;
+        let flipped_y = sheet_height - y - height;
+        match self {
+            OriginCorner::TopLeft => (x, y),
+            OriginCorner::BottomLeft => (x, flipped_y),
+            OriginCorner::TopRight => (flipped_x, y),
+            OriginCorner::BottomRight => (flipped_x, flipped_y),
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use serde::{Deserialize, Serialize};
+
+use crate::core::entities::placed_part::PlacedPart;
+
+/// Order in which the parts of a sheet are read, based on their position relative to the top-left corner of the sheet.
+/// RowMajor reads top-to-bottom and left-to-right within a row, ColumnMajor left-to-right and top-to-bottom within a column.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ReadingOrder {
+    #[default]
+    RowMajor,
+    ColumnMajor,
+}
+
+impl ReadingOrder {
+    /// Sort key of a placed part, parts with a smaller key are read first
+    pub fn key(&self, part: &PlacedPart) -> (u64, u64) {
+        match self {
+            ReadingOrder::RowMajor => (part.y, part.x),
+            ReadingOrder::ColumnMajor => (part.x, part.y),
+        }
+    }
+}
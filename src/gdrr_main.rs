@@ -10,9 +10,13 @@ use once_cell::sync::Lazy;
 
 use gdrr_2bp::io::html_export::generate_solution;
 use gdrr_2bp::io::json_format::JsonInstance;
+use gdrr_2bp::io::{csv_instance, packlib, snapping};
 use gdrr_2bp::io::parser;
 use gdrr_2bp::optimization::config::Config;
+use gdrr_2bp::optimization::gdrr;
 use gdrr_2bp::optimization::gdrr::GDRR;
+use gdrr_2bp::optimization::instance::Instance;
+use gdrr_2bp::optimization::solutions::solution::Solution;
 use gdrr_2bp::optimization::sol_collectors::global_sol_collector::GlobalSolCollector;
 use gdrr_2bp::optimization::sol_collectors::local_sol_collector::LocalSolCollector;
 use gdrr_2bp::timed_println;
@@ -46,18 +50,92 @@ fn main() {
     let input_file = File::open(&input_file_path).expect("input file could not be opened");
     let config_file = File::open(&config_file_path).expect("config file could not be opened");
 
-    let mut json_instance: JsonInstance = serde_json::from_reader(BufReader::new(&input_file)).unwrap();
+    let mut json_instance: JsonInstance = match input_file_path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_reader(BufReader::new(&input_file)).unwrap(),
+        Some("csv") => csv_instance::read_csv(&input_file_path).unwrap_or_else(|e| panic!("could not read CSV instance: {}", e)),
+        Some("txt" | "ins2D") => packlib::read_2dpacklib(&input_file_path).unwrap_or_else(|e| panic!("could not read 2DPackLib instance: {}", e)),
+        _ => panic!("unsupported input file extension (expected .json, .csv, or .txt or .ins2D for 2DPackLib): {}", input_file_path.display()),
+    };
     let config: Config = serde_json::from_reader(BufReader::new(&config_file)).unwrap();
+    config.validate().unwrap_or_else(|e| panic!("invalid config: {}", e));
 
     timed_println!("Config file loaded: {}", serde_json::to_string(&config).unwrap());
 
-    let instance = parser::generate_instance(&mut json_instance, &config);
+    if let Some(snap_to) = config.snap_to {
+        for adjustment in snapping::snap_dimensions(&mut json_instance, snap_to) {
+            timed_println!("Snapped {:?}: {} -> {}", adjustment.dimension, adjustment.original, adjustment.snapped);
+        }
+    }
+
+    let instance = parser::generate_instance(&mut json_instance, &config).unwrap_or_else(|e| panic!("could not generate instance: {}", e));
+    let precheck = instance.precheck();
+    for warning in &precheck.warnings {
+        timed_println!("Precheck warning: {:?}", warning);
+    }
+    for error in &precheck.errors {
+        timed_println!("Precheck error, no complete solution exists: {:?}", error);
+    }
     timed_println!("Starting optimization of {} parts of {} different types for {} seconds", instance.total_part_qty(), instance.parts().len(), config.max_run_time.unwrap_or(usize::MAX));
     timed_println!("Press Ctrl+C to terminate manually");
 
     let instance = Arc::new(instance);
+    let origin_corner = config.origin_corner.unwrap_or_default();
     let config = Arc::new(config);
 
+    let fast_path_solution = match config.single_parttype_fast_path.unwrap_or(true) {
+        true => gdrr::optimize_single_parttype(instance.clone(), &config),
+        false => None,
+    };
+
+    let json_solution = match fast_path_solution {
+        Some(solution) => {
+            timed_println!("Single item type: cut {} sheets in strip patterns, no search needed", solution.n_layouts());
+            Some(parser::generate_json_solution(&json_instance, &solution, &config_file_path))
+        }
+        None => {
+            let global_sol_collector = search(instance, config);
+            match (global_sol_collector.best_complete_solution().as_ref(), global_sol_collector.best_incomplete_solution().as_ref()) {
+                (Some(best_complete_solution), _) => {
+                    Some(parser::generate_json_solution(&json_instance, best_complete_solution, &config_file_path))
+                }
+                (None, Some(best_incomplete_solution)) => {
+                    Some(parser::generate_json_solution(&json_instance, best_incomplete_solution, &config_file_path))
+                }
+                (None, None) => {
+                    None
+                }
+            }
+        }
+    };
+
+    if let Some(solution) = &json_solution {
+        if let Some(json_solution_path) = json_solution_path {
+            let mut json_file = File::create(&json_solution_path).expect("JSON solution file could not be created");
+            match json_solution_path.extension().is_some_and(|ext| ext == "bin") {
+                true => {
+                    json_file.write_all(&solution.to_bytes()).expect("could not write binary solution");
+                    timed_println!("Binary solution written to {}", json_solution_path.display());
+                }
+                false => {
+                    let json_string = serde_json::to_string_pretty(solution).expect("could not serialize JSON solution");
+                    write!(json_file, "{}", json_string).expect("could not write JSON solution");
+                    timed_println!("JSON solution written to {}", json_solution_path.display());
+                }
+            }
+        }
+        if let Some(html_solution_path) = html_solution_path {
+            let mut html_file = File::create(&html_solution_path).expect("HTML solution file could not be created");
+            let html_string = generate_solution(solution, origin_corner);
+            write!(html_file, "{}", html_string).expect("could not write HTML solution");
+            timed_println!("HTML solution written to {}", html_solution_path.display());
+        }
+    } else {
+        timed_println!("No solution available");
+    }
+}
+
+/// Runs the search on config.n_threads threads until the global solution collector terminates it
+fn search(instance: Arc<Instance>, config: Arc<Config>) -> GlobalSolCollector {
     let mut gdrr_thread_handlers = Vec::new();
 
 
@@ -76,7 +154,7 @@ fn main() {
         let handle = thread::Builder::new().name(thread_name).spawn(move || {
             let local_sol_collector = LocalSolCollector::new(instance_thread.clone(), rx_sync, tx_solution_report_thread, COST_COMPARATOR);
             let mut gdrr = GDRR::new(&instance_thread, &config_thread, local_sol_collector);
-            gdrr.lahc();
+            gdrr.lahc()
         });
         gdrr_thread_handlers.push(handle.expect("could not spawn thread"));
     }
@@ -84,33 +162,12 @@ fn main() {
     let mut global_sol_collector = GlobalSolCollector::new(instance, config, tx_syncs, rx_solution_report, COST_COMPARATOR);
 
     global_sol_collector.monitor(gdrr_thread_handlers);
-
-    let json_solution = match (global_sol_collector.best_complete_solution().as_ref(), global_sol_collector.best_incomplete_solution().as_ref()) {
-        (Some(best_complete_solution), _) => {
-            Some(parser::generate_json_solution(&json_instance, best_complete_solution, &config_file_path))
-        }
-        (None, Some(best_incomplete_solution)) => {
-            Some(parser::generate_json_solution(&json_instance, best_incomplete_solution, &config_file_path))
+    for (i, stats) in global_sol_collector.search_stats().iter().enumerate() {
+        timed_println!("[T{}]\t{} iterations, {} node operations", i, stats.n_iterations, stats.n_node_ops);
+        if let Some(parttype_id) = stats.insertion_failure {
+            timed_println!("[T{}]\tItem {} cannot be placed in any sheet", i, parttype_id);
         }
-        (None, None) => {
-            None
-        }
-    };
-
-    if json_solution.is_some() {
-        if let Some(json_solution_path) = json_solution_path {
-            let mut json_file = File::create(&json_solution_path).expect("JSON solution file could not be created");
-            let json_string = serde_json::to_string_pretty(json_solution.as_ref().unwrap()).expect("could not serialize JSON solution");
-            write!(json_file, "{}", json_string).expect("could not write JSON solution");
-            timed_println!("JSON solution written to {}", json_solution_path.display());
-        }
-        if let Some(html_solution_path) = html_solution_path {
-            let mut html_file = File::create(&html_solution_path).expect("HTML solution file could not be created");
-            let html_string = generate_solution(json_solution.as_ref().unwrap());
-            write!(html_file, "{}", html_string).expect("could not write HTML solution");
-            timed_println!("HTML solution written to {}", html_solution_path.display());
-        }
-    } else {
-        timed_println!("No solution available");
     }
+
+    global_sol_collector
 }
@@ -0,0 +1,256 @@
+//! Compact binary representation of a JsonSolution, for archiving large amounts of solutions.
+//! The format starts with a magic sequence followed by a version byte, the solution itself is encoded with bincode.
+//! The cutting patterns are decoded with a limited nesting depth, so malformed input cannot overflow the stack.
+
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bincode::de::Decoder;
+use bincode::error::DecodeError;
+use bincode::{Decode, impl_borrow_decode_with_context};
+
+use crate::io::json_format::{JsonCPNode, JsonCompositeNode, JsonInstance, JsonSolution};
+use crate::io::parser;
+use crate::optimization::instance::Instance;
+use crate::optimization::solutions::sendable_solution::SendableSolution;
+
+const MAGIC: &[u8; 4] = b"GDRR";
+pub const FORMAT_VERSION: u8 = 10;
+
+/// Maximum nesting of the nodes of a cutting pattern
+pub const MAX_NESTING_DEPTH: usize = 128;
+
+/// Upper bound on the memory claimed while decoding, guards against corrupted lengths
+const DECODE_LIMIT: usize = 1 << 30;
+
+const NESTING_TOO_DEEP: &str = "nesting too deep";
+
+/// Reasons why bytes cannot be decoded into a JsonSolution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BinaryFormatError {
+    /// The bytes do not start with the magic sequence
+    InvalidMagic,
+    /// The bytes were written in a format version which is not supported
+    UnsupportedVersion(u8),
+    /// The bytes ended before the solution was complete
+    UnexpectedEnd,
+    /// The bytes contain a value which cannot be decoded
+    InvalidValue,
+    /// The nodes are nested deeper than MAX_NESTING_DEPTH
+    NestingTooDeep,
+    /// The cutting patterns do not match the instance the solution is rebuilt for
+    InstanceMismatch,
+}
+
+impl fmt::Display for BinaryFormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BinaryFormatError::InvalidMagic => write!(f, "not a binary solution"),
+            BinaryFormatError::UnsupportedVersion(version) => write!(f, "unsupported binary solution version: {}", version),
+            BinaryFormatError::UnexpectedEnd => write!(f, "unexpected end of binary solution"),
+            BinaryFormatError::InvalidValue => write!(f, "invalid value in binary solution"),
+            BinaryFormatError::NestingTooDeep => write!(f, "nodes nested deeper than {} levels in binary solution", MAX_NESTING_DEPTH),
+            BinaryFormatError::InstanceMismatch => write!(f, "binary solution does not match the instance"),
+        }
+    }
+}
+
+impl std::error::Error for BinaryFormatError {}
+
+impl From<DecodeError> for BinaryFormatError {
+    fn from(error: DecodeError) -> Self {
+        match error {
+            DecodeError::UnexpectedEnd { .. } => BinaryFormatError::UnexpectedEnd,
+            DecodeError::Other(NESTING_TOO_DEEP) => BinaryFormatError::NestingTooDeep,
+            _ => BinaryFormatError::InvalidValue,
+        }
+    }
+}
+
+/// Decoding context tracking the nesting depth of the nodes currently being decoded
+#[derive(Debug, Default)]
+pub struct DecodeDepth(usize);
+
+impl DecodeDepth {
+    fn enter(&mut self) -> Result<(), DecodeError> {
+        self.0 += 1;
+        match self.0 > MAX_NESTING_DEPTH {
+            true => Err(DecodeError::Other(NESTING_TOO_DEEP)),
+            false => Ok(()),
+        }
+    }
+
+    fn leave(&mut self) {
+        self.0 -= 1;
+    }
+}
+
+//The nodes are decoded by hand (in the order of their fields, as derived by Encode) to keep track of the depth
+
+impl Decode<DecodeDepth> for JsonCPNode {
+    fn decode<D: Decoder<Context = DecodeDepth>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        decoder.context().enter()?;
+        let node = JsonCPNode {
+            length: Decode::decode(decoder)?,
+            height: Decode::decode(decoder)?,
+            orientation: Decode::decode(decoder)?,
+            node_type: Decode::decode(decoder)?,
+            item: Decode::decode(decoder)?,
+            children: Decode::decode(decoder)?,
+        };
+        decoder.context().leave();
+        Ok(node)
+    }
+}
+impl_borrow_decode_with_context!(JsonCPNode, DecodeDepth);
+
+impl Decode<DecodeDepth> for JsonCompositeNode {
+    fn decode<D: Decoder<Context = DecodeDepth>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        decoder.context().enter()?;
+        let node = JsonCompositeNode {
+            length: Decode::decode(decoder)?,
+            height: Decode::decode(decoder)?,
+            orientation: Decode::decode(decoder)?,
+            children: Decode::decode(decoder)?,
+        };
+        decoder.context().leave();
+        Ok(node)
+    }
+}
+impl_borrow_decode_with_context!(JsonCompositeNode, DecodeDepth);
+
+impl JsonSolution {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(FORMAT_VERSION);
+        bincode::encode_into_std_write(self, &mut bytes, bincode::config::standard())
+            .expect("writing to a vector cannot fail");
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, BinaryFormatError> {
+        if bytes.len() < MAGIC.len() + 1 || &bytes[..MAGIC.len()] != MAGIC {
+            return Err(BinaryFormatError::InvalidMagic);
+        }
+        let version = bytes[MAGIC.len()];
+        if version != FORMAT_VERSION {
+            return Err(BinaryFormatError::UnsupportedVersion(version));
+        }
+
+        let content = &bytes[MAGIC.len() + 1..];
+        let config = bincode::config::standard().with_limit::<DECODE_LIMIT>();
+        let (solution, read) = bincode::decode_from_slice_with_context(content, config, DecodeDepth::default())?;
+        match read == content.len() {
+            true => Ok(solution),
+            false => Err(BinaryFormatError::InvalidValue),
+        }
+    }
+}
+
+impl SendableSolution {
+    /// Binary counterpart of the JSON solution generated by parser::generate_json_solution
+    pub fn to_bytes(&self, json_instance: &JsonInstance, config_path: &PathBuf) -> Vec<u8> {
+        parser::generate_json_solution(json_instance, self, config_path).to_bytes()
+    }
+
+    /// Rebuilds a solution written by SendableSolution::to_bytes, for the instance it was generated from
+    pub fn from_bytes(bytes: &[u8], instance: Arc<Instance>) -> Result<Self, BinaryFormatError> {
+        let json_solution = JsonSolution::from_bytes(bytes)?;
+        parser::convert_json_solution(&json_solution, instance).ok_or(BinaryFormatError::InstanceMismatch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use itertools::Itertools;
+
+    use crate::io::binary_format::{BinaryFormatError, MAX_NESTING_DEPTH};
+    use crate::io::json_format::{JsonCP, JsonCPNode, JsonCPNodeType, JsonOrientation, JsonSolution, JsonSolutionStats};
+    use crate::io::parser;
+    use crate::optimization::solutions::sendable_solution::SendableSolution;
+    use crate::optimization::solutions::solution::Solution;
+    use crate::optimization::solutions::solution_diff::Placement;
+    use crate::util::test_util;
+
+    fn placements(solution: &SendableSolution) -> Vec<Placement> {
+        solution.layouts().iter().enumerate()
+            .flat_map(|(i, l)| Placement::extract(i, l.sheettype_id(), l.top_node()))
+            .collect_vec()
+    }
+
+    #[test]
+    fn binary_and_json_round_trips_rebuild_the_same_solution() {
+        let mut json_instance = test_util::json_instance(&[(40, 30, 5), (25, 60, 3), (70, 20, 2)], &[(100, 100, 10)]);
+        let config = test_util::config();
+        let instance = Arc::new(parser::generate_instance(&mut json_instance, &config).unwrap());
+        let solution = test_util::solve(instance.clone(), &config);
+        let config_path = PathBuf::from("config.json");
+
+        let bytes = solution.to_bytes(&json_instance, &config_path);
+        let from_bytes = SendableSolution::from_bytes(&bytes, instance.clone()).unwrap();
+
+        let json_string = serde_json::to_string(&parser::generate_json_solution(&json_instance, &solution, &config_path)).unwrap();
+        let json_solution = serde_json::from_str::<JsonSolution>(&json_string).unwrap();
+        let from_json = parser::convert_json_solution(&json_solution, instance.clone()).unwrap();
+
+        assert!(bytes.len() < json_string.len());
+        for rebuilt in [&from_bytes, &from_json] {
+            assert_eq!(rebuilt.cost(), solution.cost());
+            assert_eq!(rebuilt.usage(), solution.usage());
+            assert_eq!(rebuilt.parttype_qtys(), solution.parttype_qtys());
+        }
+        assert_eq!(placements(&from_bytes), placements(&from_json));
+    }
+
+    #[test]
+    fn other_instances_are_rejected() {
+        let mut json_instance = test_util::json_instance(&[(40, 30, 5)], &[(100, 100, 10)]);
+        let config = test_util::config();
+        let instance = Arc::new(parser::generate_instance(&mut json_instance, &config).unwrap());
+        let bytes = test_util::solve(instance, &config).to_bytes(&json_instance, &PathBuf::from("config.json"));
+
+        let mut other_json_instance = test_util::json_instance(&[(30, 30, 5)], &[(100, 100, 10)]);
+        let other_instance = Arc::new(parser::generate_instance(&mut other_json_instance, &config).unwrap());
+        assert_eq!(SendableSolution::from_bytes(&bytes, other_instance).unwrap_err(), BinaryFormatError::InstanceMismatch);
+    }
+
+    #[test]
+    fn deeply_nested_nodes_are_rejected() {
+        let bytes = |depth: usize| {
+            let leaf = JsonCPNode { length: 1, height: 1, orientation: None, node_type: JsonCPNodeType::Leftover, item: None, children: vec![] };
+            let root = (1..depth).fold(leaf, |child, _| JsonCPNode {
+                length: 1, height: 1, orientation: Some(JsonOrientation::H), node_type: JsonCPNodeType::Structure, item: None, children: vec![child],
+            });
+            let statistics = JsonSolutionStats {
+                usage_pct: 0.0, part_area_included_pct: 0.0, n_objects_used: 1, material_cost: 1, run_time_ms: 0, config_path: String::new(),
+            };
+            let json_instance = test_util::json_instance(&[], &[(1, 1, 1)]);
+            JsonSolution {
+                name: json_instance.name,
+                unit: None,
+                sheettypes: json_instance.sheettypes,
+                parttypes: vec![],
+                cutting_patterns: vec![JsonCP { object: 0, usage: 0.0, root }],
+                statistics,
+            }.to_bytes()
+        };
+
+        assert!(JsonSolution::from_bytes(&bytes(MAX_NESTING_DEPTH)).is_ok());
+        assert_eq!(JsonSolution::from_bytes(&bytes(MAX_NESTING_DEPTH + 1)).err(), Some(BinaryFormatError::NestingTooDeep));
+    }
+
+    #[test]
+    fn truncated_and_foreign_bytes_are_rejected() {
+        let mut json_instance = test_util::json_instance(&[(40, 30, 5)], &[(100, 100, 10)]);
+        let config = test_util::config();
+        let instance = Arc::new(parser::generate_instance(&mut json_instance, &config).unwrap());
+        let bytes = test_util::solve(instance, &config).to_bytes(&json_instance, &PathBuf::from("config.json"));
+
+        assert_eq!(JsonSolution::from_bytes(b"{\"Name\": \"test\"}").err(), Some(BinaryFormatError::InvalidMagic));
+        assert_eq!(JsonSolution::from_bytes(&bytes[..bytes.len() - 1]).err(), Some(BinaryFormatError::UnexpectedEnd));
+    }
+}
@@ -0,0 +1,134 @@
+use std::fs;
+use std::path::Path;
+
+use crate::io::instance_error::InstanceError;
+use crate::io::json_format::{JsonInstance, JsonPartType, JsonSheetType};
+
+pub const CSV_HEADER: &str = "kind,length,height,quantity,value";
+
+/// Reads an instance from a CSV file with the header `kind,length,height,quantity,value`, optionally followed by a `unit` column,
+/// and a line per object type (`object`) and item type (`item`).
+/// The quantity is the stock of an object (unlimited if left empty) or the demand of an item,
+/// the value is the cost of an object or the value of an item (its area if left empty).
+/// The units are validated when the instance is generated, see parser::validate_units.
+pub fn read_csv(path: &Path) -> Result<JsonInstance, InstanceError> {
+    let content = fs::read_to_string(path).map_err(|e| InstanceError::Io(e.to_string()))?;
+    let name = path.file_stem().map_or(String::new(), |s| s.to_string_lossy().to_string());
+    parse_csv(&content, name)
+}
+
+pub fn parse_csv(content: &str, name: String) -> Result<JsonInstance, InstanceError> {
+    //(line number, content) of all non-empty lines
+    let mut lines = content.lines().enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| (i + 1, l));
+
+    let with_unit = match lines.next().map(|(_, header)| header.split(',').map(|f| f.trim().to_lowercase()).collect::<Vec<_>>()) {
+        Some(columns) if columns.iter().eq(CSV_HEADER.split(',')) => false,
+        Some(columns) if columns.iter().eq(CSV_HEADER.split(',').chain(["unit"])) => true,
+        _ => return Err(InstanceError::MissingHeader),
+    };
+
+    let mut sheettypes = vec![];
+    let mut parttypes = vec![];
+    for (line, content) in lines {
+        let invalid_line = || InstanceError::InvalidLine(line, content.to_string());
+        let fields = content.split(',').map(|f| f.trim()).collect::<Vec<_>>();
+        let (kind, length, height, quantity, value, unit) = match (fields.as_slice(), with_unit) {
+            ([kind, length, height, quantity, value], false) => (kind, length, height, quantity, value, None),
+            ([kind, length, height, quantity, value, unit], true) => (kind, length, height, quantity, value, Some(unit.to_string()).filter(|u| !u.is_empty())),
+            _ => return Err(invalid_line()),
+        };
+        let optional = |field: &str| match field.is_empty() {
+            true => Ok(None),
+            false => field.parse::<u64>().map(Some).map_err(|_| invalid_line()),
+        };
+        let (length, height) = match (optional(length)?, optional(height)?) {
+            (Some(length), Some(height)) if length > 0 && height > 0 => (length, height),
+            _ => return Err(invalid_line()),
+        };
+        let (quantity, value) = (optional(quantity)?, optional(value)?.unwrap_or(length * height));
+
+        match *kind {
+            "object" => sheettypes.push(JsonSheetType {
+                length,
+                height,
+                stock: quantity.map(|q| q as usize),
+                cost: value,
+                density: None,
+                corner_cut: None,
+                usable_regions: None,
+                unit: unit.clone(),
+                margins: None,
+                reference: None,
+            }),
+            "item" => parttypes.push(JsonPartType {
+                length,
+                height,
+                demand: quantity.ok_or_else(invalid_line)? as usize,
+                value,
+                preferred_rotation: None,
+                priority: None,
+                half_turn_only: None,
+                composite: None,
+                unit,
+                edge_affinity: None,
+                urgency: None,
+                reference: None,
+            }),
+            _ => return Err(invalid_line()),
+        }
+    }
+    if sheettypes.is_empty() {
+        return Err(InstanceError::MissingObject);
+    }
+
+    Ok(JsonInstance {
+        name,
+        unit: None,
+        sheettypes,
+        parttypes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io::{csv_instance, parser};
+    use crate::io::instance_error::InstanceError;
+    use crate::util::test_util;
+
+    #[test]
+    fn objects_and_items_are_read_with_their_defaults() {
+        let content = "kind,length,height,quantity,value\nobject,100,80,,\nobject,50,50,3,2000\n\nitem,30,20,4,\nitem,45,35,2,1000\n";
+        let instance = csv_instance::parse_csv(content, "test".to_string()).unwrap();
+
+        let sheets = instance.sheettypes.iter().map(|s| (s.length, s.height, s.stock, s.cost)).collect::<Vec<_>>();
+        assert_eq!(sheets, vec![(100, 80, None, 8000), (50, 50, Some(3), 2000)]);
+        let parts = instance.parttypes.iter().map(|p| (p.length, p.height, p.demand, p.value)).collect::<Vec<_>>();
+        assert_eq!(parts, vec![(30, 20, 4, 600), (45, 35, 2, 1000)]);
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        let parse = |content: &str| csv_instance::parse_csv(content, "test".to_string()).err();
+
+        assert_eq!(parse("object,100,80,,\n"), Some(InstanceError::MissingHeader));
+        assert_eq!(parse("kind,length,height,quantity,value\nitem,30,20,4,\n"), Some(InstanceError::MissingObject));
+        assert_eq!(parse("kind,length,height,quantity,value\nobject,100,80,,\nitem,30,20,,\n"), Some(InstanceError::InvalidLine(3, "item,30,20,,".to_string())));
+        assert_eq!(parse("kind,length,height,quantity,value\nsheet,100,80,,\n"), Some(InstanceError::InvalidLine(2, "sheet,100,80,,".to_string())));
+        assert_eq!(parse("kind,length,height,quantity,value\nobject,100,0,,\n"), Some(InstanceError::InvalidLine(2, "object,100,0,,".to_string())));
+    }
+
+    #[test]
+    fn instances_with_mixed_units_are_rejected() {
+        let generate = |content: &str| {
+            let mut json_instance = csv_instance::parse_csv(content, "test".to_string()).unwrap();
+            parser::generate_instance(&mut json_instance, &test_util::config()).map(|instance| instance.unit().map(|u| u.to_string()))
+        };
+
+        assert_eq!(generate("kind,length,height,quantity,value,unit\nobject,100,80,,,mm\nitem,30,20,4,,\n"), Ok(Some("mm".to_string())));
+        let error = generate("kind,length,height,quantity,value,unit\nobject,100,80,,,mm\nitem,30,20,4,,in\n").unwrap_err();
+        assert_eq!(error, InstanceError::MixedUnits("mm".to_string(), "in".to_string()));
+        assert_eq!(error.to_string(), "items and objects are declared in different units: mm and in");
+    }
+}
@@ -4,9 +4,12 @@ use horrorshow::prelude::*;
 use svg::node::element::{Group, Rectangle, Text};
 use svg::Document;
 
+use crate::core::origin_corner::OriginCorner;
 use crate::io::json_format::{JsonCP, JsonCPNode, JsonCPNodeType, JsonOrientation, JsonSolution};
 
-pub fn generate_solution(json_solution: &JsonSolution) -> String {
+/// The coordinates of the rectangles in the SVG drawings are relative to the origin corner,
+/// the drawings themselves always show the sheets as seen from the top-left corner.
+pub fn generate_solution(json_solution: &JsonSolution, origin: OriginCorner) -> String {
     let html = format!(
         "{}",
         html! {
@@ -71,6 +74,16 @@ pub fn generate_solution(json_solution: &JsonSolution) -> String {
                                 : format!{"{}",  json_solution.statistics.config_path};
                             }
                         }
+                        @ if let Some(unit) = &json_solution.unit {
+                            tr {
+                                th(style="text-align:left") {
+                                    : "Unit";
+                                }
+                                td {
+                                    : unit;
+                                }
+                            }
+                        }
                     }
                     h2 {
                         : format!{"{}", "Cutting Patterns"}
@@ -86,7 +99,7 @@ pub fn generate_solution(json_solution: &JsonSolution) -> String {
                             );
                         }
                         div(style="width:1000px;") {
-                            : Raw(generate_cutting_pattern(&json_solution.cutting_patterns[i]))
+                            : Raw(generate_cutting_pattern(&json_solution.cutting_patterns[i], origin))
                         }
 
                     }
@@ -98,7 +111,7 @@ pub fn generate_solution(json_solution: &JsonSolution) -> String {
     html
 }
 
-pub fn generate_cutting_pattern(json_cp: &JsonCP) -> String {
+pub fn generate_cutting_pattern(json_cp: &JsonCP, origin: OriginCorner) -> String {
     let stroke_width = 0.002 * u64::max(json_cp.root.height, json_cp.root.length) as f64;
     let mut document = Document::new()
         .set("width", "100%")
@@ -112,15 +125,24 @@ pub fn generate_cutting_pattern(json_cp: &JsonCP) -> String {
                 json_cp.root.height as f64 + stroke_width * 2.0,
             ),
         );
-    let mut group = Group::new();
+    let sheet_size = (json_cp.root.length, json_cp.root.height);
+    //maps the coordinates relative to the origin corner back onto the drawing, the labels are placed on the drawing directly
+    let mut group = match frame_transform(origin, sheet_size) {
+        Some(transform) => Group::new().set("transform", transform),
+        None => Group::new(),
+    };
+    let mut labels = Group::new();
 
-    let mut subgroups = Vec::new();
-
-    generate_node(&json_cp.root, (0, 0), &mut subgroups, stroke_width);
-    for rect in subgroups {
+    let mut rects = Vec::new();
+    let mut texts = Vec::new();
+    generate_node(&json_cp.root, (0, 0), origin, sheet_size, &mut rects, &mut texts, stroke_width);
+    for rect in rects {
         group = group.add(rect);
     }
-    document = document.add(group);
+    for text in texts {
+        labels = labels.add(text);
+    }
+    document = document.add(group).add(labels);
 
     let mut write_buffer = Vec::new();
     {
@@ -132,31 +154,46 @@ pub fn generate_cutting_pattern(json_cp: &JsonCP) -> String {
         .to_string()
 }
 
+/// SVG transform from the coordinates relative to the origin corner to those of the drawing
+fn frame_transform(origin: OriginCorner, (width, height): (u64, u64)) -> Option<String> {
+    match origin {
+        OriginCorner::TopLeft => None,
+        OriginCorner::BottomLeft => Some(format!("matrix(1 0 0 -1 0 {})", height)),
+        OriginCorner::TopRight => Some(format!("matrix(-1 0 0 1 {} 0)", width)),
+        OriginCorner::BottomRight => Some(format!("matrix(-1 0 0 -1 {} {})", width, height)),
+    }
+}
+
 fn generate_node(
     json_cp_node: &JsonCPNode,
     reference: (u64, u64),
-    groups: &mut Vec<Group>,
+    origin: OriginCorner,
+    sheet_size: (u64, u64),
+    rects: &mut Vec<Rectangle>,
+    texts: &mut Vec<Text>,
     stroke_width: f64,
 ) {
-    match json_cp_node.children.is_empty() {
+    //the internal structure of composite items is not drawn
+    match json_cp_node.children.is_empty() || matches!(json_cp_node.node_type, JsonCPNodeType::Item) {
         true => {
             let color = match json_cp_node.node_type {
                 JsonCPNodeType::Structure => panic!("Structure node should have children"),
                 JsonCPNodeType::Item => "#BFBFBF",
                 JsonCPNodeType::Leftover => "#A9D18E",
+                JsonCPNodeType::Unusable => "#595959",
             };
             let (x, y) = (reference.0 as f64, reference.1 as f64);
             let (width, height) = (json_cp_node.length as f64, json_cp_node.height as f64);
-            let mut group = Group::new();
+            let (origin_x, origin_y) = origin.transform(reference, (json_cp_node.length, json_cp_node.height), sheet_size);
             let rect = Rectangle::new()
-                .set("x", x)
-                .set("y", y)
+                .set("x", origin_x as f64)
+                .set("y", origin_y as f64)
                 .set("width", width)
                 .set("height", height)
                 .set("fill", color)
                 .set("stroke", "black")
                 .set("stroke-width", stroke_width.to_string());
-            group = group.add(rect);
+            rects.push(rect);
 
             match json_cp_node.node_type {
                 JsonCPNodeType::Item => {
@@ -184,16 +221,15 @@ fn generate_node(
                     );
                     text = text.set("font-size", format!("{}em", font_size));
 
-                    group = group.add(text);
+                    texts.push(text);
                 }
                 _ => {}
             };
-            groups.push(group);
         }
         false => {
             let mut reference = reference;
             for child in &json_cp_node.children {
-                generate_node(child, reference, groups, stroke_width);
+                generate_node(child, reference, origin, sheet_size, rects, texts, stroke_width);
                 match json_cp_node.orientation {
                     Some(JsonOrientation::H) => {
                         reference.1 += child.height;
@@ -209,3 +245,51 @@ fn generate_node(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::core::origin_corner::OriginCorner;
+    use crate::io::html_export;
+    use crate::io::json_format::{JsonCP, JsonCPNode, JsonCPNodeType, JsonOrientation};
+
+    fn attribute(element: &str, name: &str) -> u64 {
+        let start = element.find(&format!(" {}=\"", name)).unwrap() + name.len() + 3;
+        element[start..].split('"').next().unwrap().parse().unwrap()
+    }
+
+    /// (x, y, width, height) of all rectangles in the SVG
+    fn rects(svg: &str) -> Vec<(u64, u64, u64, u64)> {
+        svg.lines()
+            .filter(|line| line.starts_with("<rect"))
+            .map(|rect| (attribute(rect, "x"), attribute(rect, "y"), attribute(rect, "width"), attribute(rect, "height")))
+            .collect()
+    }
+
+    #[test]
+    fn svg_coordinates_are_relative_to_the_origin_corner() {
+        //a 60x30 item in the top-left corner of a 100x100 sheet, next to and above leftovers
+        let node = |length: u64, height: u64, node_type: JsonCPNodeType, item: Option<usize>| JsonCPNode {
+            length, height, orientation: None, node_type, item, children: vec![],
+        };
+        let strip = JsonCPNode {
+            length: 100, height: 30, orientation: Some(JsonOrientation::V), node_type: JsonCPNodeType::Structure, item: None,
+            children: vec![node(60, 30, JsonCPNodeType::Item, Some(0)), node(40, 30, JsonCPNodeType::Leftover, None)],
+        };
+        let root = JsonCPNode {
+            length: 100, height: 100, orientation: Some(JsonOrientation::H), node_type: JsonCPNodeType::Structure, item: None,
+            children: vec![strip, node(100, 70, JsonCPNodeType::Leftover, None)],
+        };
+        let cp = JsonCP { object: 0, usage: 0.18, root };
+
+        let top_left = html_export::generate_cutting_pattern(&cp, OriginCorner::TopLeft);
+        let bottom_left = html_export::generate_cutting_pattern(&cp, OriginCorner::BottomLeft);
+        let bottom_right = html_export::generate_cutting_pattern(&cp, OriginCorner::BottomRight);
+        assert_eq!(rects(&top_left), vec![(0, 0, 60, 30), (60, 0, 40, 30), (0, 30, 100, 70)]);
+        assert_eq!(rects(&bottom_left), vec![(0, 70, 60, 30), (60, 70, 40, 30), (0, 0, 100, 70)]);
+        assert_eq!(rects(&bottom_right), vec![(40, 70, 60, 30), (0, 70, 40, 30), (0, 0, 100, 70)]);
+        //the drawing is mapped back onto the sheet as seen from the top-left corner
+        assert!(!top_left.contains("transform=\"matrix"));
+        assert!(bottom_left.contains("transform=\"matrix(1 0 0 -1 0 100)\""));
+        assert!(bottom_right.contains("transform=\"matrix(-1 0 0 -1 100 100)\""));
+    }
+}
@@ -0,0 +1,48 @@
+use std::fmt;
+
+/// Reasons why an instance cannot be read from a file or generated from its JSON representation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstanceError {
+    /// The file could not be read
+    Io(String),
+    /// The file does not start with its header: the number of item types and the bin dimensions (2DPackLib), or the column names (CSV)
+    MissingHeader,
+    /// The file does not declare any object type
+    MissingObject,
+    /// The line (1-based) cannot be parsed
+    InvalidLine(usize, String),
+    /// The number of item types in the header (first) does not match the number of item lines (second)
+    ItemCountMismatch(usize, usize),
+    /// The composite structure of the item type (index) cannot be used, for the given reason
+    InvalidComposite(usize, String),
+    /// The margins of the object type (index) do not leave a usable region
+    InvalidMargins(usize),
+    /// The corner cut of the object type (index) does not leave an L-shaped region
+    InvalidCornerCut(usize),
+    /// The usable regions of the object type (index) cannot be used, for the given reason
+    InvalidUsableRegions(usize, String),
+    /// Items and objects of the instance are declared in different units (the first two distinct units)
+    MixedUnits(String, String),
+    /// The initialSheets of the config refer to an object type (index) which does not exist
+    InvalidInitialSheet(usize),
+}
+
+impl fmt::Display for InstanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstanceError::Io(e) => write!(f, "could not read instance: {}", e),
+            InstanceError::MissingHeader => write!(f, "missing header"),
+            InstanceError::MissingObject => write!(f, "no object type declared"),
+            InstanceError::InvalidLine(line, content) => write!(f, "invalid line {}: {}", line, content),
+            InstanceError::ItemCountMismatch(expected, found) => write!(f, "expected {} item types, found {}", expected, found),
+            InstanceError::InvalidComposite(item, reason) => write!(f, "invalid composite structure of item {}: {}", item, reason),
+            InstanceError::InvalidMargins(object) => write!(f, "margins of object {} leave no usable region", object),
+            InstanceError::InvalidCornerCut(object) => write!(f, "corner cut of object {} does not leave an L-shaped region", object),
+            InstanceError::InvalidUsableRegions(object, reason) => write!(f, "invalid usable regions of object {}: {}", object, reason),
+            InstanceError::MixedUnits(a, b) => write!(f, "items and objects are declared in different units: {} and {}", a, b),
+            InstanceError::InvalidInitialSheet(object) => write!(f, "initial sheet refers to object {} which does not exist", object),
+        }
+    }
+}
+
+impl std::error::Error for InstanceError {}
@@ -1,19 +1,28 @@
+use bincode::{Decode, Encode};
 use serde::{Deserialize, Serialize};
 
+use crate::core::edge_affinity::EdgeAffinity;
+use crate::core::margins::Margins;
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct JsonInstance {
     #[serde(rename = "Name")]
     pub name: String,
+    #[serde(rename = "Unit", default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
     #[serde(rename = "Objects")]
     pub sheettypes: Vec<JsonSheetType>,
     #[serde(rename = "Items")]
     pub parttypes: Vec<JsonPartType>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Encode, Decode)]
+#[bincode(decode_context = "crate::io::binary_format::DecodeDepth")]
 pub struct JsonSolution {
     #[serde(rename = "Name")]
     pub name: String,
+    #[serde(rename = "Unit", default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
     #[serde(rename = "Objects")]
     pub sheettypes: Vec<JsonSheetType>,
     #[serde(rename = "Items")]
@@ -25,29 +34,91 @@ pub struct JsonSolution {
 
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Encode, Decode)]
 #[serde(rename_all = "PascalCase")]
 pub struct JsonSheetType {
     pub length: u64,
     pub height: u64,
     pub stock: Option<usize>,
     pub cost: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub density: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub corner_cut: Option<JsonCornerCut>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usable_regions: Option<Vec<JsonRegion>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub margins: Option<Margins>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reference: Option<usize>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+/// Rectangle cut away from the top-left corner of an object
+#[derive(Serialize, Deserialize, Clone, Copy, Encode, Decode)]
+#[serde(rename_all = "PascalCase")]
+pub struct JsonCornerCut {
+    pub length: u64,
+    pub height: u64,
+}
+
+/// Rectangle relative to the top-left corner of an object
+#[derive(Serialize, Deserialize, Clone, Copy, Encode, Decode)]
+#[serde(rename_all = "PascalCase")]
+pub struct JsonRegion {
+    pub x: u64,
+    pub y: u64,
+    pub length: u64,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Encode, Decode)]
+#[bincode(decode_context = "crate::io::binary_format::DecodeDepth")]
 #[serde(rename_all = "PascalCase")]
 pub struct JsonPartType {
     pub length: u64,
     pub height: u64,
     pub demand: usize,
     pub value: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preferred_rotation: Option<JsonRotation>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub half_turn_only: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub composite: Option<JsonCompositeNode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edge_affinity: Option<EdgeAffinity>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub urgency: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub reference: Option<usize>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Encode)]
+#[serde(rename_all = "PascalCase")]
+pub struct JsonCompositeNode {
+    pub length: u64,
+    pub height: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<JsonOrientation>,
+    #[serde(default)]
+    pub children: Vec<JsonCompositeNode>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Encode, Decode)]
+#[serde(rename_all = "PascalCase")]
+pub enum JsonRotation {
+    Default,
+    Rotated,
+}
+
+#[derive(Serialize, Deserialize, Clone, Encode, Decode)]
+#[bincode(decode_context = "crate::io::binary_format::DecodeDepth")]
 #[serde(rename_all = "PascalCase")]
 pub struct JsonCP {
     pub object: usize,
@@ -55,7 +126,7 @@ pub struct JsonCP {
     pub root: JsonCPNode,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Encode)]
 #[serde(rename_all = "PascalCase")]
 pub struct JsonCPNode {
     pub length: u64,
@@ -69,22 +140,23 @@ pub struct JsonCPNode {
     pub children: Vec<JsonCPNode>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Encode, Decode)]
 #[serde(rename_all = "PascalCase")]
 pub enum JsonOrientation {
     H,
     V,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Encode, Decode)]
 #[serde(rename_all = "PascalCase")]
 pub enum JsonCPNodeType {
     Structure,
     Item,
     Leftover,
+    Unusable,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Encode, Decode)]
 #[serde(rename_all = "PascalCase")]
 pub struct JsonSolutionStats {
     pub usage_pct: f32,
@@ -1,3 +1,8 @@
 pub mod parser;
 pub mod json_format;
+pub mod binary_format;
+pub mod packlib;
+pub mod csv_instance;
+pub mod instance_error;
+pub mod snapping;
 pub mod html_export;
@@ -0,0 +1,110 @@
+use std::fs;
+use std::path::Path;
+
+use crate::io::instance_error::InstanceError;
+use crate::io::json_format::{JsonInstance, JsonPartType, JsonSheetType};
+
+/// Reads an instance in the 2DPackLib (ESICUP) text format:
+/// a line with the number of item types, a line with the width and height of the bin,
+/// followed by a line per item type, containing `width height demand` (optionally preceded by an index).
+/// The bin is available in unlimited quantity, both bins and items are valued by their area.
+pub fn read_2dpacklib(path: &Path) -> Result<JsonInstance, InstanceError> {
+    let content = fs::read_to_string(path).map_err(|e| InstanceError::Io(e.to_string()))?;
+    let name = path.file_stem().map_or(String::new(), |s| s.to_string_lossy().to_string());
+    parse_2dpacklib(&content, name)
+}
+
+pub fn parse_2dpacklib(content: &str, name: String) -> Result<JsonInstance, InstanceError> {
+    //(line number, content, values) of all non-empty lines
+    let mut lines = content.lines().enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| (i + 1, l, l.split_whitespace().map(|v| v.parse::<u64>()).collect::<Result<Vec<_>, _>>().unwrap_or_default()));
+
+    let n_parttypes = match lines.next() {
+        Some((_, _, values)) if values.len() == 1 => values[0] as usize,
+        Some((line, content, _)) => return Err(InstanceError::InvalidLine(line, content.to_string())),
+        None => return Err(InstanceError::MissingHeader),
+    };
+    let (bin_width, bin_height) = match lines.next() {
+        Some((_, _, values)) if values.len() == 2 && values.iter().all(|v| *v > 0) => (values[0], values[1]),
+        Some((line, content, _)) => return Err(InstanceError::InvalidLine(line, content.to_string())),
+        None => return Err(InstanceError::MissingHeader),
+    };
+
+    let mut parttypes = vec![];
+    for (line, content, values) in lines {
+        //unparsable lines result in an empty list of values
+        let (width, height, demand) = match values.as_slice() {
+            [w, h, d] | [_, w, h, d] if *w > 0 && *h > 0 => (*w, *h, *d as usize),
+            _ => return Err(InstanceError::InvalidLine(line, content.to_string())),
+        };
+        parttypes.push(JsonPartType {
+            length: width,
+            height,
+            demand,
+            value: width * height,
+            preferred_rotation: None,
+            priority: None,
+            half_turn_only: None,
+            composite: None,
+            unit: None,
+            edge_affinity: None,
+            urgency: None,
+            reference: None,
+        });
+    }
+    if parttypes.len() != n_parttypes {
+        return Err(InstanceError::ItemCountMismatch(n_parttypes, parttypes.len()));
+    }
+
+    let sheettype = JsonSheetType {
+        length: bin_width,
+        height: bin_height,
+        stock: None,
+        cost: bin_width * bin_height,
+        density: None,
+        corner_cut: None,
+        usable_regions: None,
+        unit: None,
+        margins: None,
+        reference: None,
+    };
+
+    Ok(JsonInstance {
+        name,
+        unit: None,
+        sheettypes: vec![sheettype],
+        parttypes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::io::instance_error::InstanceError;
+    use crate::io::packlib;
+    use crate::optimization::instance::Instance;
+
+    #[test]
+    fn fixture_parses_to_its_parts_and_bin() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/small_example_2dpacklib.txt");
+        let instance = Instance::from_2dpacklib(&path).unwrap();
+
+        assert_eq!(instance.parts().len(), 3);
+        assert_eq!(instance.parts().iter().map(|(_, demand)| demand).sum::<usize>(), 9);
+        assert_eq!(instance.sheets().len(), 1);
+        let (sheettype, stock) = &instance.sheets()[0];
+        assert_eq!((sheettype.width(), sheettype.height(), *stock), (100, 80, usize::MAX));
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        let parse = |content: &str| packlib::parse_2dpacklib(content, "test".to_string()).err();
+
+        assert_eq!(parse(""), Some(InstanceError::MissingHeader));
+        assert_eq!(parse("1\n100 80 5\n30 20 4\n"), Some(InstanceError::InvalidLine(2, "100 80 5".to_string())));
+        assert_eq!(parse("1\n100 80\n30 x 4\n"), Some(InstanceError::InvalidLine(3, "30 x 4".to_string())));
+        assert_eq!(parse("2\n100 80\n30 20 4\n"), Some(InstanceError::ItemCountMismatch(2, 1)));
+    }
+}
@@ -1,20 +1,29 @@
 use std::path::PathBuf;
+use std::sync::Arc;
 
 use itertools::Itertools;
+use crate::core::entities::layout::Layout;
+use crate::core::empty_node_order::EmptyNodeOrder;
+use crate::core::entities::composite_structure::CompositeStructure;
 use crate::core::entities::parttype::PartType;
 
 use crate::core::entities::sendable_layout::SendableLayout;
 use crate::core::entities::sheettype::SheetType;
 use crate::core::insertion::node_blueprint::NodeBlueprint;
+use crate::core::kerf::Kerf;
 use crate::core::orientation::Orientation;
 use crate::core::rotation::Rotation;
-use crate::io::json_format::{JsonCP, JsonCPNode, JsonCPNodeType, JsonInstance, JsonOrientation, JsonSolution, JsonSolutionStats};
-use crate::optimization::config::{Config, SheetValuationMode};
+use crate::io::instance_error::InstanceError;
+use crate::io::json_format::{JsonCP, JsonCPNode, JsonCPNodeType, JsonCompositeNode, JsonInstance, JsonOrientation, JsonRotation, JsonSolution, JsonSolutionStats};
+use crate::optimization::config::{Config, SheetValuationMode, WasteAspectRatioMode};
 use crate::optimization::instance::Instance;
+use crate::optimization::problem::Problem;
 use crate::optimization::solutions::sendable_solution::SendableSolution;
 use crate::optimization::solutions::solution::Solution;
 
-pub fn generate_instance(json_instance: &mut JsonInstance, config: &Config) -> Instance {
+pub fn generate_instance(json_instance: &mut JsonInstance, config: &Config) -> Result<Instance, InstanceError> {
+    let kerf = config.kerf.unwrap_or_default();
+
     let mut part_id = 0;
     let mut parts = Vec::new();
     for json_part in json_instance.parttypes.iter_mut() {
@@ -23,8 +32,36 @@ pub fn generate_instance(json_instance: &mut JsonInstance, config: &Config) -> I
             part_id,
             json_part.length,
             json_part.height,
-            if config.rotation_allowed { None } else { Some(Rotation::Default) },
-        );
+            match config.rotation_allowed && !json_part.half_turn_only.unwrap_or(false) {
+                true => None,
+                false => Some(Rotation::Default),
+            },
+            json_part.preferred_rotation.map(|r| match r {
+                JsonRotation::Default => Rotation::Default,
+                JsonRotation::Rotated => Rotation::Rotated,
+            }),
+            config.rotation_penalty.unwrap_or(0.0),
+            kerf,
+        ).with_priority(json_part.priority.unwrap_or(0));
+        let parttype = match &json_part.composite {
+            Some(json_composite) => {
+                let composite = convert_json_composite_node(json_composite)
+                    .map_err(|reason| InstanceError::InvalidComposite(part_id, reason.to_string()))?;
+                if (composite.width(), composite.height()) != (json_part.length, json_part.height) {
+                    return Err(InstanceError::InvalidComposite(part_id, "structure does not match the dimensions of the item".to_string()));
+                }
+                if !composite.is_valid() {
+                    return Err(InstanceError::InvalidComposite(part_id, "structure does not subdivide the item".to_string()));
+                }
+                parttype.with_composite(composite)
+            }
+            None => parttype,
+        };
+        let parttype = match json_part.edge_affinity {
+            Some(edge_affinity) => parttype.with_edge_affinity(edge_affinity),
+            None => parttype,
+        };
+        let parttype = parttype.with_urgency(json_part.urgency.unwrap_or(0));
         let demand = json_part.demand;
         parts.push((parttype, demand));
         part_id += 1;
@@ -47,25 +84,133 @@ pub fn generate_instance(json_instance: &mut JsonInstance, config: &Config) -> I
             json_sheet.height,
             sheet_value,
             None,
-            max_stages
+            max_stages,
+            kerf,
         );
+        let sheettype = match json_sheet.density {
+            Some(density) => sheettype.with_density(density),
+            None => sheettype,
+        };
+        let sheettype = match json_sheet.margins {
+            Some(margins) => {
+                //the margins have to leave a usable region
+                if margins.horizontal() >= sheettype.width() || margins.vertical() >= sheettype.height() {
+                    return Err(InstanceError::InvalidMargins(sheet_id));
+                }
+                sheettype.with_margins(margins)
+            }
+            None => sheettype,
+        };
+        let sheettype = match json_sheet.corner_cut {
+            Some(corner_cut) => {
+                //the corner cut has to leave an L-shaped region of the sheet
+                if corner_cut.length == 0 || corner_cut.height == 0 || corner_cut.length >= sheettype.width() || corner_cut.height >= sheettype.height() {
+                    return Err(InstanceError::InvalidCornerCut(sheet_id));
+                }
+                sheettype.with_corner_cut(corner_cut.length, corner_cut.height)
+            }
+            None => sheettype,
+        };
+        let sheettype = match &json_sheet.usable_regions {
+            Some(regions) => {
+                //the corner cut is a special case of the usable regions, they cannot be combined
+                if json_sheet.corner_cut.is_some() {
+                    return Err(InstanceError::InvalidUsableRegions(sheet_id, "declared along with a corner cut".to_string()));
+                }
+                let regions = regions.iter().map(|r| (r.x, r.y, r.length, r.height)).collect_vec();
+                sheettype.with_usable_regions(regions).map_err(|reason| InstanceError::InvalidUsableRegions(sheet_id, reason.to_string()))?
+            }
+            None => sheettype,
+        };
 
         let stock = json_sheet.stock.unwrap_or(usize::MAX);
         sheets.push((sheettype, stock));
         sheet_id += 1;
     }
+    if let Some(&sheet_id) = config.initial_sheets.iter().flatten().find(|&&id| id >= sheets.len()) {
+        return Err(InstanceError::InvalidInitialSheet(sheet_id));
+    }
+
+    let instance = Instance::new(parts, sheets, config.sheettype_penalty.unwrap_or(0), config.homogeneous_strips.unwrap_or(false));
+    let instance = match config.sheet_open_penalty {
+        Some(sheet_open_penalty) => instance.with_sheet_open_penalty(sheet_open_penalty),
+        None => instance,
+    };
+    let instance = match config.clustering_weight {
+        Some(clustering_weight) => instance.with_clustering_weight(clustering_weight),
+        None => instance,
+    };
+    let instance = match config.urgency_weight {
+        Some(urgency_weight) => instance.with_urgency_weight(urgency_weight),
+        None => instance,
+    };
+    let instance = match config.min_waste_dimension {
+        Some(min_waste_dimension) => instance.with_min_waste_dimension(min_waste_dimension),
+        None => instance,
+    };
+    let instance = match (config.max_waste_aspect_ratio, config.waste_aspect_ratio_mode.unwrap_or_default()) {
+        (Some(max_waste_aspect_ratio), WasteAspectRatioMode::Hard) => instance.with_max_waste_aspect_ratio(max_waste_aspect_ratio),
+        _ => instance,
+    };
+    let instance = match config.max_parttypes_per_sheet {
+        Some(max_parttypes_per_sheet) => instance.with_max_parttypes_per_sheet(max_parttypes_per_sheet),
+        None => instance,
+    };
+    let instance = match config.first_sheettype {
+        Some(first_sheettype) => instance.with_first_sheettype(first_sheettype),
+        None => instance,
+    };
+    let instance = match config.forced_first_cut {
+        Some(orientation) => instance.with_forced_first_cut(orientation),
+        None => instance,
+    };
+    Ok(match validate_units(json_instance)? {
+        Some(unit) => instance.with_unit(unit),
+        None => instance,
+    })
+}
+
+/// The unit of the instance, which needs to be the same for the instance and all items and objects which declare a unit.
+/// Returns None if no unit is declared at all.
+pub fn validate_units(json_instance: &JsonInstance) -> Result<Option<String>, InstanceError> {
+    let declared = json_instance.unit.iter()
+        .chain(json_instance.sheettypes.iter().filter_map(|s| s.unit.as_ref()))
+        .chain(json_instance.parttypes.iter().filter_map(|p| p.unit.as_ref()))
+        .unique()
+        .collect_vec();
+
+    match declared.as_slice() {
+        [] => Ok(None),
+        [unit] => Ok(Some(unit.to_string())),
+        [a, b, ..] => Err(InstanceError::MixedUnits(a.to_string(), b.to_string())),
+    }
+}
 
-    Instance::new(parts, sheets)
+fn convert_json_composite_node(json_node: &JsonCompositeNode) -> Result<CompositeStructure, &'static str> {
+    let cut_orient = match (&json_node.orientation, json_node.children.is_empty()) {
+        (Some(JsonOrientation::H), _) => Orientation::Horizontal,
+        (Some(JsonOrientation::V), _) => Orientation::Vertical,
+        (None, true) => Orientation::Horizontal,
+        (None, false) => return Err("node with children has no orientation"),
+    };
+    let children = json_node.children.iter().map(convert_json_composite_node).collect::<Result<Vec<_>, _>>()?;
+
+    Ok(CompositeStructure::new(json_node.length, json_node.height, cut_orient, children))
 }
 
 pub fn generate_json_solution(json_instance: &JsonInstance, solution: &SendableSolution, config_path: &PathBuf) -> JsonSolution {
     let name = json_instance.name.clone();
+    let unit = solution.instance().unit().map(|u| u.to_string());
     let sheettypes = json_instance.sheettypes.clone();
     let parttypes = json_instance.parttypes.clone();
 
+    let first_sheettype = solution.instance().first_sheettype();
     let cutting_patterns = solution.layouts().iter()
-        .sorted_by(|a, b| { a.usage().partial_cmp(&b.usage()).unwrap().reverse() })
-        .map(|l| { convert_layout_to_json_cp(l) }
+        .sorted_by(|a, b| {
+            let pinned = |l: &SendableLayout| Some(l.sheettype_id()) == first_sheettype;
+            pinned(b).cmp(&pinned(a)).then(a.usage().partial_cmp(&b.usage()).unwrap().reverse())
+        })
+        .map(|l| { convert_layout_to_json_cp(l, solution.instance().get_sheettype(l.sheettype_id()).kerf()) }
         ).collect::<Vec<JsonCP>>();
 
     let statistics = JsonSolutionStats {
@@ -79,6 +224,7 @@ pub fn generate_json_solution(json_instance: &JsonInstance, solution: &SendableS
 
     JsonSolution {
         name,
+        unit,
         sheettypes,
         parttypes,
         cutting_patterns,
@@ -86,9 +232,9 @@ pub fn generate_json_solution(json_instance: &JsonInstance, solution: &SendableS
     }
 }
 
-pub fn convert_layout_to_json_cp(layout: &SendableLayout) -> JsonCP {
+pub fn convert_layout_to_json_cp(layout: &SendableLayout, kerf: &Kerf) -> JsonCP {
     let object = layout.sheettype_id();
-    let root = convert_node_bp_to_json_cp_node(layout.top_node());
+    let root = convert_node_bp_to_json_cp_node(layout.top_node(), kerf);
     let usage = layout.usage();
 
     JsonCP {
@@ -98,27 +244,40 @@ pub fn convert_layout_to_json_cp(layout: &SendableLayout) -> JsonCP {
     }
 }
 
-pub fn convert_node_bp_to_json_cp_node(node: &NodeBlueprint) -> JsonCPNode {
+pub fn convert_node_bp_to_json_cp_node(node: &NodeBlueprint, kerf: &Kerf) -> JsonCPNode {
+    convert_node_bp(node, kerf, false)
+}
+
+/// Items with children are composite items, the nodes of their internal structure are all converted to structure nodes
+fn contains_unusable(node: &NodeBlueprint) -> bool {
+    node.is_unusable() || node.children().iter().any(contains_unusable)
+}
+
+fn convert_node_bp(node: &NodeBlueprint, kerf: &Kerf, in_composite: bool) -> JsonCPNode {
     let mut json_children = Vec::new();
-    for child in node.children().iter().sorted_by(|a, b| a.calculate_usage().partial_cmp(&b.calculate_usage()).unwrap().reverse()) {
-        json_children.push(convert_node_bp_to_json_cp_node(child));
+    //the children keep their order if any of them contains an unusable region, as those are fixed to their position on the sheet
+    let children = match node.children().iter().any(contains_unusable) {
+        true => node.children().iter().collect_vec(),
+        false => node.children().iter().sorted_by(|a, b| a.calculate_usage(kerf).partial_cmp(&b.calculate_usage(kerf)).unwrap().reverse()).collect_vec(),
+    };
+    for child in children {
+        json_children.push(convert_node_bp(child, kerf, in_composite || node.parttype_id().is_some()));
     }
     let length = node.width();
     let height = node.height();
 
     let node_type = match (node.parttype_id(), node.children().is_empty()) {
+        (None, _) if in_composite => JsonCPNodeType::Structure,
+        (None, true) if node.is_unusable() => JsonCPNodeType::Unusable,
         (None, true) => JsonCPNodeType::Leftover,
         (None, false) => JsonCPNodeType::Structure,
-        (Some(_), true) => JsonCPNodeType::Item,
-        (Some(_), false) => {
-            panic!("This should not happen")
-        }
+        (Some(_), _) => JsonCPNodeType::Item,
     };
 
-    let orientation = match (&node_type, node.next_cut_orient()) {
-        (JsonCPNodeType::Structure, Orientation::Horizontal) => Some(JsonOrientation::H),
-        (JsonCPNodeType::Structure, Orientation::Vertical) => Some(JsonOrientation::V),
-        (_, _) => None,
+    let orientation = match (node.children().is_empty(), node.next_cut_orient()) {
+        (false, Orientation::Horizontal) => Some(JsonOrientation::H),
+        (false, Orientation::Vertical) => Some(JsonOrientation::V),
+        (true, _) => None,
     };
 
     let item = match &node_type {
@@ -134,4 +293,171 @@ pub fn convert_node_bp_to_json_cp_node(node: &NodeBlueprint) -> JsonCPNode {
         item,
         children: json_children,
     }
-}
\ No newline at end of file
+}
+
+/// Rebuilds the solution described by the cutting patterns of a JSON solution, for the instance it was generated from.
+/// Returns None if the cutting patterns do not match the instance (unknown ids, sizes which do not tile the sheets or parts,
+/// or more sheets or parts than available).
+pub fn convert_json_solution(json_solution: &JsonSolution, instance: Arc<Instance>) -> Option<SendableSolution> {
+    let mut sheettype_qtys = instance.sheets().iter().map(|(_, stock)| *stock).collect_vec();
+    let mut parttype_qtys = instance.parts().iter().map(|(_, demand)| *demand).collect_vec();
+    let mut problem = Problem::new(&instance, EmptyNodeOrder::default());
+
+    for cp in &json_solution.cutting_patterns {
+        let (sheettype, _) = instance.sheets().get(cp.object)?;
+        sheettype_qtys[cp.object] = sheettype_qtys[cp.object].checked_sub(1)?;
+        let layout_size = sheettype.layout_size();
+        if (cp.root.length, cp.root.height) != (layout_size.width(), layout_size.height()) {
+            return None;
+        }
+        let top_node = convert_json_cp_node(&cp.root, Orientation::Horizontal, &instance, &mut parttype_qtys)?;
+        let id = problem.next_layout_id();
+        let layout = Layout::from_blueprint(id, sheettype, &top_node, EmptyNodeOrder::default(), &instance);
+        problem.register_layout(layout);
+    }
+
+    let problem_solution = problem.create_solution(&None, None);
+    Some(SendableSolution::new(instance.clone(), &problem_solution))
+}
+
+/// The orientation of leaves is not part of the JSON format, they get the orientation following the one of their parent
+fn convert_json_cp_node(json_node: &JsonCPNode, default_orient: Orientation, instance: &Instance, parttype_qtys: &mut [usize]) -> Option<NodeBlueprint> {
+    let next_cut_orient = match json_node.orientation {
+        Some(JsonOrientation::H) => Orientation::Horizontal,
+        Some(JsonOrientation::V) => Orientation::Vertical,
+        None => default_orient,
+    };
+
+    match (&json_node.node_type, json_node.item) {
+        (JsonCPNodeType::Item, Some(item)) => {
+            let (parttype, _) = instance.parts().get(item)?;
+            parttype_qtys[item] = parttype_qtys[item].checked_sub(1)?;
+            let fits = [Rotation::Default, Rotation::Rotated].into_iter()
+                .filter(|r| parttype.fixed_rotation().is_none_or(|fixed| fixed == *r))
+                .map(|r| match r {
+                    Rotation::Default => parttype.size(),
+                    Rotation::Rotated => parttype.rotated_size(),
+                })
+                .any(|size| size.width() == json_node.length && size.height() == json_node.height);
+            fits.then(|| NodeBlueprint::new(json_node.length, json_node.height, Some(parttype), next_cut_orient))
+        }
+        (JsonCPNodeType::Item, None) => None,
+        (JsonCPNodeType::Unusable, _) => json_node.children.is_empty()
+            .then(|| NodeBlueprint::new_unusable(json_node.length, json_node.height, next_cut_orient)),
+        (_, _) => {
+            let mut node = NodeBlueprint::new(json_node.length, json_node.height, None, next_cut_orient);
+            if !json_node.children.is_empty() {
+                json_node.orientation.as_ref()?;
+                //the children are stacked along the cut orientation and span the node in the other direction
+                let (stacked, spanned) = match next_cut_orient {
+                    Orientation::Horizontal => (json_node.children.iter().map(|c| c.height).sum::<u64>(), json_node.children.iter().all(|c| c.length == json_node.length)),
+                    Orientation::Vertical => (json_node.children.iter().map(|c| c.length).sum::<u64>(), json_node.children.iter().all(|c| c.height == json_node.height)),
+                };
+                let length = match next_cut_orient {
+                    Orientation::Horizontal => json_node.height,
+                    Orientation::Vertical => json_node.length,
+                };
+                if stacked != length || !spanned {
+                    return None;
+                }
+                for child in &json_node.children {
+                    node.add_child(convert_json_cp_node(child, next_cut_orient.rotate(), instance, parttype_qtys)?);
+                }
+            }
+            Some(node)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use itertools::Itertools;
+
+    use crate::io::json_format::{JsonCPNode, JsonCPNodeType, JsonCompositeNode, JsonOrientation};
+    use crate::io::instance_error::InstanceError;
+    use crate::io::parser;
+    use crate::util::test_util;
+
+    fn composite_node(length: u64, height: u64, orientation: Option<JsonOrientation>, children: Vec<JsonCompositeNode>) -> JsonCompositeNode {
+        JsonCompositeNode { length, height, orientation, children }
+    }
+
+    //60x40 door: a 60x10 strip above a 60x30 region split into 20x30 and 40x30
+    fn door() -> JsonCompositeNode {
+        composite_node(60, 40, Some(JsonOrientation::H), vec![
+            composite_node(60, 10, None, vec![]),
+            composite_node(60, 30, Some(JsonOrientation::V), vec![composite_node(20, 30, None, vec![]), composite_node(40, 30, None, vec![])]),
+        ])
+    }
+
+    fn leaves(node: &JsonCPNode) -> Vec<&JsonCPNode> {
+        match node.children.is_empty() {
+            true => vec![node],
+            false => node.children.iter().flat_map(leaves).collect(),
+        }
+    }
+
+    fn items<'a>(node: &'a JsonCPNode, found: &mut Vec<&'a JsonCPNode>) {
+        match node.node_type {
+            JsonCPNodeType::Item => found.push(node),
+            _ => node.children.iter().for_each(|c| items(c, found)),
+        }
+    }
+
+    #[test]
+    fn composite_parts_are_placed_as_a_unit_with_their_structure() {
+        let mut json_instance = test_util::json_instance(&[(60, 40, 2), (30, 20, 4)], &[(100, 100, 5)]);
+        json_instance.parttypes[0].composite = Some(door());
+        let config = test_util::config();
+        let instance = Arc::new(parser::generate_instance(&mut json_instance, &config).unwrap());
+        let solution = test_util::solve(instance, &config);
+        let json_solution = parser::generate_json_solution(&json_instance, &solution, &PathBuf::from("config.json"));
+
+        let mut found = vec![];
+        json_solution.cutting_patterns.iter().for_each(|cp| items(&cp.root, &mut found));
+        let doors = found.iter().filter(|n| n.item == Some(0)).collect_vec();
+        assert_eq!(doors.len(), 2);
+        for door in doors {
+            let door_leaves = leaves(door);
+            assert_eq!(door_leaves.len(), 3);
+            assert!(door_leaves.iter().all(|l| matches!(l.node_type, JsonCPNodeType::Structure) && l.item.is_none()));
+            assert_eq!(door_leaves.iter().map(|l| l.length * l.height).sum::<u64>(), 60 * 40);
+        }
+        assert!(found.iter().filter(|n| n.item == Some(1)).all(|n| n.children.is_empty()));
+    }
+
+    #[test]
+    fn invalid_composite_structures_are_rejected() {
+        let parse = |composite: JsonCompositeNode| {
+            let mut json_instance = test_util::json_instance(&[(30, 20, 1), (60, 40, 1)], &[(100, 100, 5)]);
+            json_instance.parttypes[1].composite = Some(composite);
+            parser::generate_instance(&mut json_instance, &test_util::config()).err()
+        };
+        let invalid = |error: Option<InstanceError>| matches!(error, Some(InstanceError::InvalidComposite(1, _)));
+
+        assert!(parse(door()).is_none());
+        let mut without_orientation = door();
+        without_orientation.orientation = None;
+        assert!(invalid(parse(without_orientation)));
+        let mut not_subdividing = door();
+        not_subdividing.children[1].children.pop();
+        assert!(invalid(parse(not_subdividing)));
+        assert!(invalid(parse(composite_node(40, 60, Some(JsonOrientation::V), vec![composite_node(40, 60, None, vec![])]))));
+    }
+
+    #[test]
+    fn initial_sheets_of_unknown_objects_are_rejected() {
+        let mut json_instance = test_util::json_instance(&[(40, 30, 4)], &[(100, 100, 10), (50, 50, 10)]);
+        let mut generate = |initial_sheets: serde_json::Value| {
+            parser::generate_instance(&mut json_instance, &test_util::config_with(serde_json::json!({"initialSheets": initial_sheets}))).err()
+        };
+
+        assert_eq!(generate(serde_json::json!([0, 1, 1])), None);
+        let error = generate(serde_json::json!([1, 2]));
+        assert_eq!(error, Some(InstanceError::InvalidInitialSheet(2)));
+        assert_eq!(error.unwrap().to_string(), "initial sheet refers to object 2 which does not exist");
+    }
+}
@@ -0,0 +1,73 @@
+use crate::io::json_format::JsonInstance;
+
+/// A dimension of an item or object of an instance which was changed by snapping
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DimensionAdjustment {
+    pub dimension: SnappedDimension,
+    pub original: u64,
+    pub snapped: u64,
+}
+
+/// Dimension of an item or object, identified by its index in the instance
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnappedDimension {
+    ItemLength(usize),
+    ItemHeight(usize),
+    ObjectLength(usize),
+    ObjectHeight(usize),
+}
+
+/// Rounds the dimensions of all items and objects to the nearest multiple of snap_to (halfway values are rounded up),
+/// to remove measurement noise which would otherwise result in thin slivers of leftover material.
+/// Dimensions are never rounded down to 0. Composite items are left unchanged, as their internal structure would no longer fit.
+/// Returns all the dimensions which were changed.
+pub fn snap_dimensions(json_instance: &mut JsonInstance, snap_to: u64) -> Vec<DimensionAdjustment> {
+    assert!(snap_to > 0, "dimensions cannot be snapped to multiples of 0");
+    let mut adjustments = vec![];
+
+    for (i, item) in json_instance.parttypes.iter_mut().enumerate().filter(|(_, item)| item.composite.is_none()) {
+        snap(&mut item.length, snap_to, SnappedDimension::ItemLength(i), &mut adjustments);
+        snap(&mut item.height, snap_to, SnappedDimension::ItemHeight(i), &mut adjustments);
+    }
+    for (i, object) in json_instance.sheettypes.iter_mut().enumerate() {
+        snap(&mut object.length, snap_to, SnappedDimension::ObjectLength(i), &mut adjustments);
+        snap(&mut object.height, snap_to, SnappedDimension::ObjectHeight(i), &mut adjustments);
+    }
+    adjustments
+}
+
+fn snap(value: &mut u64, snap_to: u64, dimension: SnappedDimension, adjustments: &mut Vec<DimensionAdjustment>) {
+    let snapped = u64::max((*value + snap_to / 2) / snap_to * snap_to, snap_to);
+    if snapped != *value {
+        adjustments.push(DimensionAdjustment { dimension, original: *value, snapped });
+        *value = snapped;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::io::json_format::JsonCompositeNode;
+    use crate::io::snapping::{self, DimensionAdjustment, SnappedDimension};
+    use crate::util::test_util;
+
+    #[test]
+    fn dimensions_snap_to_the_nearest_multiple_and_are_reported() {
+        let mut json_instance = test_util::json_instance(&[(299, 300, 1), (304, 305, 1), (3, 40, 1)], &[(2501, 1249, 1)]);
+        json_instance.parttypes.push(json_instance.parttypes[0].clone());
+        json_instance.parttypes[3].composite = Some(JsonCompositeNode { length: 299, height: 300, orientation: None, children: vec![] });
+
+        let adjustments = snapping::snap_dimensions(&mut json_instance, 10);
+        let adjustment = |dimension: SnappedDimension, original: u64, snapped: u64| DimensionAdjustment { dimension, original, snapped };
+        assert_eq!(adjustments, vec![
+            adjustment(SnappedDimension::ItemLength(0), 299, 300),
+            adjustment(SnappedDimension::ItemLength(1), 304, 300),
+            adjustment(SnappedDimension::ItemHeight(1), 305, 310),
+            adjustment(SnappedDimension::ItemLength(2), 3, 10),
+            adjustment(SnappedDimension::ObjectLength(0), 2501, 2500),
+            adjustment(SnappedDimension::ObjectHeight(0), 1249, 1250),
+        ]);
+        let item_sizes = json_instance.parttypes.iter().map(|p| (p.length, p.height)).collect::<Vec<_>>();
+        assert_eq!(item_sizes, vec![(300, 300), (300, 310), (10, 40), (299, 300)]);
+        assert_eq!((json_instance.sheettypes[0].length, json_instance.sheettypes[0].height), (2500, 1250));
+    }
+}
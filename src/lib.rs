@@ -11,7 +11,8 @@ pub mod core;
 
 pub static EPOCH: Lazy<Instant> = Lazy::new(Instant::now);
 pub const COST_COMPARATOR: fn(&Cost, &Cost) -> Ordering = |a: &Cost, b: &Cost| {
-    match a.part_area_excluded.cmp(&b.part_area_excluded) {
+    //leaving out a part of a higher priority is worse than leaving out any number of parts of lower priorities
+    match a.excluded_priority.cmp(&b.excluded_priority).then(a.part_area_excluded.cmp(&b.part_area_excluded)) {
         Ordering::Equal => a.leftover_value.partial_cmp(&b.leftover_value).unwrap().reverse(),
         other => other
     }
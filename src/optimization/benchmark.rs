@@ -0,0 +1,292 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::channel;
+use std::time::Instant;
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::io::json_format::JsonInstance;
+use crate::io::{csv_instance, packlib, parser};
+use crate::optimization::config::Config;
+use crate::optimization::gdrr::GDRR;
+use crate::optimization::sol_collectors::local_sol_collector::LocalSolCollector;
+use crate::optimization::solutions::sendable_solution::SendableSolution;
+use crate::optimization::solutions::solution::Solution;
+use crate::util::messages::SolutionReportMessage;
+
+/// Seed and budget of every benchmark run, fixed to make the runs reproducible,
+/// and the best known usages the results are compared against (the bundled table by default)
+#[derive(Debug, Clone)]
+pub struct BenchmarkSettings {
+    pub seed: u64,
+    pub max_rr_iterations: usize,
+    pub best_known: Option<BestKnownTable>,
+}
+
+impl Default for BenchmarkSettings {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            max_rr_iterations: 10_000,
+            best_known: Some(BestKnownTable::bundled()),
+        }
+    }
+}
+
+/// Best known usage of a named instance, e.g. from the literature
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BestKnown {
+    pub name: String,
+    pub usage: f64,
+    /// Where the value was published
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// Best known usages keyed by instance name: the instance name in the JSON file, the file name for 2DPackLib instances.
+/// The bundled table only contains the example instances of this repository, with the usage of their published results.
+/// Other values can be loaded from a JSON file with a list of entries, e.g. `[{"name": "cgcut1", "usage": 0.92, "source": "..."}]`,
+/// which replace the entries of the table with the same name.
+#[derive(Debug, Clone, Default)]
+pub struct BestKnownTable {
+    entries: HashMap<String, BestKnown>,
+}
+
+impl BestKnownTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bundled() -> Self {
+        let entries: Vec<BestKnown> = serde_json::from_str(include_str!("best_known.json")).expect("bundled best known table is invalid");
+        let mut table = Self::new();
+        table.extend(entries);
+        table
+    }
+
+    /// Adds the entries of the JSON file to the table, fails with InvalidData (adding none of them) if a usage is not in (0, 1]
+    pub fn load(&mut self, path: &Path) -> io::Result<()> {
+        let file = File::open(path)?;
+        let entries: Vec<BestKnown> = serde_json::from_reader(BufReader::new(file))?;
+        if let Some(entry) = entries.iter().find(|entry| !BestKnownTable::valid_usage(entry.usage)) {
+            let message = format!("best known usage of {} is not in (0, 1]: {}", entry.name, entry.usage);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+        self.extend(entries);
+        Ok(())
+    }
+
+    pub fn extend(&mut self, entries: impl IntoIterator<Item=BestKnown>) {
+        for entry in entries {
+            assert!(BestKnownTable::valid_usage(entry.usage), "best known usage of {} is not in (0, 1]: {}", entry.name, entry.usage);
+            self.entries.insert(entry.name.clone(), entry);
+        }
+    }
+
+    pub fn get(&self, instance_name: &str) -> Option<&BestKnown> {
+        self.entries.get(instance_name)
+    }
+
+    fn valid_usage(usage: f64) -> bool {
+        usage > 0.0 && usage <= 1.0
+    }
+}
+
+/// Outcome of solving a single instance file: the stats of the best solution found, or why the file could not be solved
+#[derive(Debug, Clone)]
+pub struct BenchmarkResult {
+    pub path: PathBuf,
+    pub outcome: Result<BenchmarkRun, String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BenchmarkRun {
+    pub instance_name: String,
+    pub material_cost: u64,
+    pub usage: f64,
+    pub n_sheets: usize,
+    pub complete: bool,
+    pub elapsed_ms: u128,
+    /// Best known usage of the instance, if it is in the table of the settings
+    pub best_known_usage: Option<f64>,
+}
+
+impl BenchmarkResult {
+    pub const CSV_HEADER: &'static str = "path,instance,material_cost,usage,sheets,complete,elapsed_ms,error";
+
+    /// The result as a row matching CSV_HEADER, the fields which are unknown for failed runs are left empty
+    pub fn to_csv_row(&self) -> String {
+        let path = csv_field(&self.path.display().to_string());
+        match &self.outcome {
+            Ok(run) => format!("{},{},{},{:.5},{},{},{},", path, csv_field(&run.instance_name), run.material_cost,
+                               run.usage, run.n_sheets, run.complete, run.elapsed_ms),
+            Err(error) => format!("{},,,,,,,{}", path, csv_field(error)),
+        }
+    }
+
+    /// Relative gap between the usage of the run and the best known usage of the instance: (best - usage) / best,
+    /// positive if the run is worse. None if the instance is not in the table, or the run failed or did not place all parts.
+    pub fn gap_to_best(&self) -> Option<f64> {
+        let run = self.outcome.as_ref().ok().filter(|run| run.complete)?;
+        let best_usage = run.best_known_usage?;
+        Some((best_usage - run.usage) / best_usage)
+    }
+}
+
+/// Solves every instance file in the directory (JSON, CSV, and 2DPackLib files with a .txt or .ins2D extension),
+/// one after the other on the calling thread, with the seed and iteration budget of the settings.
+/// Files which cannot be read or for which no solution is found are reported in the results, instead of aborting the benchmark.
+/// The results are sorted by path.
+pub fn run_dir(path: &Path, config: &Config, settings: &BenchmarkSettings) -> io::Result<Vec<BenchmarkResult>> {
+    let mut config = config.clone();
+    config.max_run_time = None;
+    config.max_rr_iterations = Some(settings.max_rr_iterations);
+
+    let files = std::fs::read_dir(path)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<io::Result<Vec<PathBuf>>>()?
+        .into_iter()
+        .filter(|p| p.is_file())
+        .sorted()
+        .collect_vec();
+
+    let results = files.into_iter()
+        .map(|file| {
+            let outcome = run_file(&file, &config, settings.seed, settings.best_known.as_ref());
+            BenchmarkResult { path: file, outcome }
+        })
+        .collect();
+
+    Ok(results)
+}
+
+fn run_file(path: &Path, config: &Config, seed: u64, best_known: Option<&BestKnownTable>) -> Result<BenchmarkRun, String> {
+    let mut json_instance = read_instance(path)?;
+    let instance = Arc::new(parser::generate_instance(&mut json_instance, config).map_err(|e| format!("invalid instance: {}", e))?);
+
+    let start = Instant::now();
+    let (_tx_sync, rx_sync) = channel();
+    let (tx_solution_report, rx_solution_report) = channel();
+    let local_sol_collector = LocalSolCollector::new(instance.clone(), rx_sync, tx_solution_report, crate::COST_COMPARATOR);
+    let mut gdrr = GDRR::new(&instance, config, local_sol_collector);
+    gdrr.reseed(seed);
+    gdrr.lahc();
+    let elapsed_ms = start.elapsed().as_millis();
+
+    //Solutions are reported in order of improvement, full incomplete solutions only as long as no complete solution exists
+    let mut best_complete: Option<SendableSolution> = None;
+    let mut best_incomplete: Option<SendableSolution> = None;
+    for message in rx_solution_report.try_iter() {
+        match message {
+            SolutionReportMessage::NewCompleteSolution(_, solution) => best_complete = Some(solution),
+            SolutionReportMessage::NewIncompleteSolution(_, solution) => best_incomplete = Some(solution),
+            SolutionReportMessage::NewIncompleteStats(..) => {}
+        }
+    }
+
+    match best_complete.or(best_incomplete) {
+        Some(solution) => Ok(BenchmarkRun {
+            instance_name: json_instance.name.clone(),
+            material_cost: solution.cost().material_cost,
+            usage: solution.usage(),
+            n_sheets: solution.n_layouts(),
+            complete: solution.is_complete(),
+            elapsed_ms,
+            best_known_usage: best_known.and_then(|table| table.get(&json_instance.name)).map(|b| b.usage),
+        }),
+        None => Err("no solution found".to_string()),
+    }
+}
+
+fn read_instance(path: &Path) -> Result<JsonInstance, String> {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => {
+            let file = File::open(path).map_err(|e| e.to_string())?;
+            serde_json::from_reader(BufReader::new(file)).map_err(|e| format!("could not read JSON instance: {}", e))
+        }
+        Some("csv") => csv_instance::read_csv(path).map_err(|e| format!("could not read CSV instance: {}", e)),
+        Some("txt" | "ins2D") => packlib::read_2dpacklib(path).map_err(|e| format!("could not read 2DPackLib instance: {}", e)),
+        _ => Err("unsupported file extension".to_string()),
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\n']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io;
+
+    use crate::optimization::benchmark::{self, BenchmarkSettings, BestKnownTable};
+    use crate::util::test_util;
+
+    #[test]
+    fn every_supported_file_is_solved_and_unreadable_files_are_reported() {
+        let dir = std::env::temp_dir().join(format!("gdrr_benchmark_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let json_instance = test_util::json_instance(&[(30, 20, 4)], &[(100, 80, 10)]);
+        fs::write(dir.join("a.json"), serde_json::to_string(&json_instance).unwrap()).unwrap();
+        fs::write(dir.join("b.csv"), "kind,length,height,quantity,value\nobject,100,80,,\nitem,30,20,4,\n").unwrap();
+        fs::write(dir.join("c.txt"), "1\n100 80\n30 20 4\n").unwrap();
+        fs::write(dir.join("d.csv"), "object,100,80,,\n").unwrap();
+        fs::write(dir.join("e.csv"), "kind,length,height,quantity,value,unit\nobject,100,80,,,mm\nitem,30,20,4,,in\n").unwrap();
+        fs::write(dir.join("f.xml"), "<instance/>").unwrap();
+
+        let settings = BenchmarkSettings { seed: 0, max_rr_iterations: 100, best_known: None };
+        let results = benchmark::run_dir(&dir, &test_util::config(), &settings).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        let file_names = results.iter().map(|r| r.path.file_name().unwrap().to_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(file_names, vec!["a.json", "b.csv", "c.txt", "d.csv", "e.csv", "f.xml"]);
+        for result in &results[..3] {
+            let run = result.outcome.as_ref().unwrap();
+            assert!(run.complete);
+            assert_eq!((run.n_sheets, run.material_cost), (1, 8000));
+        }
+        assert!(results[3].outcome.as_ref().unwrap_err().starts_with("could not read CSV instance"));
+        assert!(results[3].to_csv_row().ends_with("missing header"));
+        assert_eq!(results[4].outcome.as_ref().unwrap_err(), "invalid instance: items and objects are declared in different units: mm and in");
+        assert_eq!(results[5].outcome.as_ref().unwrap_err(), "unsupported file extension");
+    }
+
+    #[test]
+    fn gap_to_best_is_relative_to_the_best_known_usage() {
+        let dir = std::env::temp_dir().join(format!("gdrr_best_known_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        //a usage of 4 * 30 * 20 / (100 * 80) = 0.3
+        let mut json_instance = test_util::json_instance(&[(30, 20, 4)], &[(100, 80, 10)]);
+        fs::write(dir.join("a.json"), serde_json::to_string(&json_instance).unwrap()).unwrap();
+        json_instance.name = "unknown".to_string();
+        fs::write(dir.join("b.json"), serde_json::to_string(&json_instance).unwrap()).unwrap();
+        let table_path = dir.join("best_known.table");
+        fs::write(&table_path, r#"[{"name": "test", "usage": 0.4, "source": "reference"}, {"name": "small_example", "usage": 0.95}]"#).unwrap();
+
+        let mut best_known = BestKnownTable::bundled();
+        assert!(best_known.get("small_example").is_some());
+        best_known.load(&table_path).unwrap();
+        assert_eq!(best_known.get("small_example").unwrap().usage, 0.95);
+
+        //a table with an invalid usage is rejected entirely
+        fs::write(&table_path, r#"[{"name": "test", "usage": 0.5}, {"name": "other", "usage": 1.5}]"#).unwrap();
+        assert_eq!(best_known.load(&table_path).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(best_known.get("test").unwrap().usage, 0.4);
+        fs::remove_file(&table_path).unwrap();
+
+        let settings = BenchmarkSettings { seed: 0, max_rr_iterations: 100, best_known: Some(best_known) };
+        let results = benchmark::run_dir(&dir, &test_util::config(), &settings).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(results[0].outcome.as_ref().unwrap().usage, 0.3);
+        assert!((results[0].gap_to_best().unwrap() - 0.25).abs() < 1e-9);
+        assert_eq!(results[1].gap_to_best(), None);
+    }
+}
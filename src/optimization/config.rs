@@ -1,8 +1,18 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
+use crate::core::empty_node_order::EmptyNodeOrder;
+use crate::core::fit_metric::FitMetric;
+use crate::core::kerf::Kerf;
+use crate::core::orientation::Orientation;
+use crate::core::origin_corner::OriginCorner;
+use crate::optimization::solutions::objective::Objective;
+use crate::optimization::solutions::tiebreak::Tiebreak;
+
 /// Contains all the configurable parameters of the algorithm
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Config {
     pub avg_nodes_removed: usize,
@@ -10,17 +20,224 @@ pub struct Config {
     pub max_run_time: Option<usize>,
     #[serde(rename = "maxRRIterations")]
     pub max_rr_iterations: Option<usize>,
+    pub max_node_ops: Option<u64>,
     pub leftover_valuation_power: f32,
     pub history_length: usize,
     pub rotation_allowed: bool,
     pub n_threads: usize,
     pub sheet_valuation_mode : SheetValuationMode,
     pub max_stages: Option<u8>,
+    pub kerf: Option<Kerf>,
+    pub no_insertion_policy: Option<NoInsertionPolicy>,
+    pub sheettype_priority: Option<Vec<usize>>,
+    pub min_layouts: Option<usize>,
+    pub max_layouts: Option<usize>,
+    pub sheettype_penalty: Option<u64>,
+    pub sheet_open_penalty: Option<u64>,
+    pub empty_node_order: Option<EmptyNodeOrder>,
+    pub fit_metric: Option<FitMetric>,
+    pub rotation_penalty: Option<f32>,
+    pub target_usage: Option<f64>,
+    pub recreate_strategy: Option<RecreateStrategy>,
+    pub warm_start: Option<bool>,
+    pub homogeneous_strips: Option<bool>,
+    pub min_waste_dimension: Option<u64>,
+    pub max_waste_aspect_ratio: Option<f64>,
+    pub waste_aspect_ratio_mode: Option<WasteAspectRatioMode>,
+    pub max_parttypes_per_sheet: Option<usize>,
+    pub first_sheettype: Option<usize>,
+    pub forced_first_cut: Option<Orientation>,
+    pub stagnation_window: Option<usize>,
+    pub stop_on_stagnation: Option<bool>,
+    pub restart_every: Option<u64>,
+    pub restart_seeds: Option<Vec<u64>>,
+    pub prefer_shallow_insertions: Option<bool>,
+    pub origin_corner: Option<OriginCorner>,
+    pub snap_to: Option<u64>,
+    pub diverse_cost_tolerance: Option<f64>,
+    pub diverse_min_distance: Option<usize>,
+    pub tiebreaks: Option<Vec<Tiebreak>>,
+    pub pareto_objectives: Option<Vec<Objective>>,
+    pub parallel_evaluation_threshold: Option<usize>,
+    pub initial_sheets: Option<Vec<usize>>,
+    pub insertion_order: Option<InsertionOrder>,
+    pub single_parttype_fast_path: Option<bool>,
+    pub clustering_weight: Option<f32>,
+    pub max_candidates_per_step: Option<usize>,
+    pub urgency_weight: Option<f32>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Copy)]
 #[serde(rename_all = "camelCase")]
 pub enum SheetValuationMode {
     Area,
     Cost
-}
\ No newline at end of file
+}
+
+impl Config {
+    /// Priority of a sheettype when opening new sheets, lower is preferred.
+    /// Sheettypes absent from the configured priority order all share the lowest priority.
+    pub fn sheettype_priority(&self, sheettype_id: usize) -> usize {
+        match &self.sheettype_priority {
+            Some(priority) => priority.iter().position(|id| *id == sheettype_id).unwrap_or(priority.len()),
+            None => 0
+        }
+    }
+
+    /// Checks the values which cannot be expressed by the types of the fields
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.restart_every == Some(0) {
+            return Err(ConfigError::ZeroRestartInterval);
+        }
+        if self.snap_to == Some(0) {
+            return Err(ConfigError::ZeroSnap);
+        }
+        if self.max_waste_aspect_ratio.is_some_and(|ratio| ratio.is_nan() || ratio < 1.0) {
+            return Err(ConfigError::WasteAspectRatioBelowOne);
+        }
+        if self.clustering_weight.is_some_and(|weight| weight.is_nan() || weight < 0.0) {
+            return Err(ConfigError::NegativeClusteringWeight);
+        }
+        if self.urgency_weight.is_some_and(|weight| weight.is_nan() || weight < 0.0) {
+            return Err(ConfigError::NegativeUrgencyWeight);
+        }
+        if self.pareto_objectives.as_ref().is_some_and(|objectives| objectives.is_empty()) {
+            return Err(ConfigError::NoParetoObjectives);
+        }
+        Ok(())
+    }
+}
+
+/// Reasons why a config cannot be used for a search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// restartEvery is 0, the search would restart before every iteration
+    ZeroRestartInterval,
+    /// snapTo is 0, dimensions cannot be rounded to multiples of 0
+    ZeroSnap,
+    /// maxWasteAspectRatio is below 1, which no rectangle satisfies
+    WasteAspectRatioBelowOne,
+    /// clusteringWeight is negative, which would reward spreading the parts of a parttype
+    NegativeClusteringWeight,
+    /// urgencyWeight is negative, which would reward spreading the urgent parts over the layouts
+    NegativeUrgencyWeight,
+    /// paretoObjectives is empty, no solution would dominate another one
+    NoParetoObjectives,
+    /// A search on the calling thread (e.g. optimize_diverse) is not limited by maxRRIterations, maxNodeOps, targetUsage or stopOnStagnation,
+    /// without a global solution collector it would never terminate
+    UnboundedSearch,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::ZeroRestartInterval => write!(f, "restartEvery must be at least 1"),
+            ConfigError::ZeroSnap => write!(f, "snapTo must be at least 1"),
+            ConfigError::WasteAspectRatioBelowOne => write!(f, "maxWasteAspectRatio must be at least 1"),
+            ConfigError::NegativeClusteringWeight => write!(f, "clusteringWeight cannot be negative"),
+            ConfigError::NegativeUrgencyWeight => write!(f, "urgencyWeight cannot be negative"),
+            ConfigError::NoParetoObjectives => write!(f, "paretoObjectives must contain at least one objective"),
+            ConfigError::UnboundedSearch => write!(f, "the search needs to be limited by maxRRIterations, maxNodeOps, targetUsage or stopOnStagnation"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Defines how the parts are inserted during recreate
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum RecreateStrategy {
+    /// Parts are selected randomly (with a bias towards hard to place parts) and inserted at the cheapest position
+    #[default]
+    BestFit,
+    /// Parts are inserted by descending height, filling every sheet row by row (shelf packing)
+    Strip,
+}
+
+/// Defines in which order the parttypes are selected for insertion during a (best fit) recreate.
+/// Only the parttypes with the highest priority are eligible, and the selection blinks (see blink_rate) for all orders except Random.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum InsertionOrder {
+    /// Parttypes with the fewest insertion options first, ties in random order
+    #[default]
+    FewestOptions,
+    /// Parttypes with the largest area first
+    LargestAreaFirst,
+    /// Parttypes with the largest perimeter first
+    LargestPerimeterFirst,
+    /// Uniformly random, determined by the seed of the problem
+    Random,
+    /// Only the priority decides, parttypes with equal priority are inserted in the order they are listed
+    ByPriority,
+}
+
+/// Defines how the maximum aspect ratio of the waste pieces is enforced
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum WasteAspectRatioMode {
+    /// Insertions creating a too elongated empty node are never considered
+    #[default]
+    Hard,
+    /// Insertions creating a too elongated empty node remain possible, but are ranked behind all other insertions in existing layouts
+    Soft,
+}
+
+/// Defines what happens during recreate when a part does not fit in any of the existing layouts
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum NoInsertionPolicy {
+    /// Open a new sheet of the cheapest sheettype which is in stock, fits the part and is within the material budget
+    OpenNewSheet,
+    /// Leave the part unplaced, never open a new sheet
+    DropPart,
+    /// Open a new sheet as usual, but stop the search if the part cannot be placed in a new sheet of any sheettype in stock either,
+    /// reported by SearchStats::insertion_failure
+    Fail,
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::optimization::config::ConfigError;
+    use crate::util::test_util;
+
+    #[test]
+    fn a_restart_interval_of_zero_is_rejected() {
+        assert_eq!(test_util::config_with(json!({"restartEvery": 0})).validate(), Err(ConfigError::ZeroRestartInterval));
+        assert_eq!(test_util::config_with(json!({"restartEvery": 1})).validate(), Ok(()));
+        assert_eq!(test_util::config().validate(), Ok(()));
+    }
+
+    #[test]
+    fn snapping_to_zero_is_rejected() {
+        assert_eq!(test_util::config_with(json!({"snapTo": 0})).validate(), Err(ConfigError::ZeroSnap));
+        assert_eq!(test_util::config_with(json!({"snapTo": 5})).validate(), Ok(()));
+    }
+
+    #[test]
+    fn a_waste_aspect_ratio_below_one_is_rejected() {
+        assert_eq!(test_util::config_with(json!({"maxWasteAspectRatio": 0.5})).validate(), Err(ConfigError::WasteAspectRatioBelowOne));
+        assert_eq!(test_util::config_with(json!({"maxWasteAspectRatio": 1.0})).validate(), Ok(()));
+    }
+
+    #[test]
+    fn a_negative_clustering_weight_is_rejected() {
+        assert_eq!(test_util::config_with(json!({"clusteringWeight": -0.5})).validate(), Err(ConfigError::NegativeClusteringWeight));
+        assert_eq!(test_util::config_with(json!({"clusteringWeight": 0.0})).validate(), Ok(()));
+    }
+
+    #[test]
+    fn a_negative_urgency_weight_is_rejected() {
+        assert_eq!(test_util::config_with(json!({"urgencyWeight": -1.0})).validate(), Err(ConfigError::NegativeUrgencyWeight));
+        assert_eq!(test_util::config_with(json!({"urgencyWeight": 1.0})).validate(), Ok(()));
+    }
+
+    #[test]
+    fn an_empty_list_of_pareto_objectives_is_rejected() {
+        assert_eq!(test_util::config_with(json!({"paretoObjectives": []})).validate(), Err(ConfigError::NoParetoObjectives));
+        assert_eq!(test_util::config_with(json!({"paretoObjectives": [{"metric": "cutCount", "direction": "maximize"}]})).validate(), Ok(()));
+    }
+}
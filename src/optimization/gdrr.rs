@@ -1,30 +1,65 @@
-use std::cmp::Ordering;
-use std::collections::VecDeque;
+use std::cmp::{Ordering, Reverse};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::sync::mpsc::channel;
 
 use colored::*;
+use generational_arena::Index;
 use itertools::Itertools;
+use log::Level;
+use once_cell::sync::Lazy;
 use ordered_float::NotNan;
 use rand::prelude::{IndexedRandom, SliceRandom};
 use rand::Rng;
 use rand::rngs::SmallRng;
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 
 use crate::core::cost::Cost;
 use crate::core::entities::parttype::PartType;
+use crate::core::entities::layout::Layout;
+use crate::core::entities::placed_part::PlacedPart;
 use crate::core::insertion::insertion_blueprint::InsertionBlueprint;
+use crate::core::insertion::insertion_option::InsertionOption;
 use crate::core::layout_index::LayoutIndex;
 use crate::core::leftover_valuator;
-use crate::optimization::config::Config;
+use crate::core::orientation::Orientation;
+use crate::core::rotation::Rotation;
+use crate::core::size::Size;
+use crate::optimization::config::{Config, ConfigError, InsertionOrder, NoInsertionPolicy, RecreateStrategy, WasteAspectRatioMode};
 use crate::optimization::instance::Instance;
+use crate::optimization::move_log::{InsertionTarget, MoveLog, MoveStep, RRMove};
 use crate::optimization::problem::Problem;
+use crate::optimization::rr::cache_updates::IOCUpdates;
 use crate::optimization::rr::insertion_option_cache::InsertionOptionCache;
+use crate::optimization::search_stats::SearchStats;
+use crate::optimization::sol_collectors::diverse_archive::DiverseArchive;
+use crate::optimization::sol_collectors::sheet_count_archive::SheetCountArchive;
 use crate::optimization::sol_collectors::local_sol_collector::LocalSolCollector;
+use crate::optimization::sol_collectors::pareto_archive::ParetoArchive;
+use crate::optimization::sol_collectors::solution_archive::SolutionArchive;
+use crate::optimization::solutions::objective::{Direction, Metric, Objective};
 use crate::optimization::solutions::problem_solution::ProblemSolution;
+use crate::optimization::solutions::sendable_solution::SendableSolution;
 use crate::optimization::solutions::solution::Solution;
+use crate::optimization::solutions::tiebreak;
 use crate::util::{assertions, blink};
 use crate::util::biased_sampler::{BiasedSampler, BiasMode};
 use crate::timed_thread_println;
 use crate::util::util;
 
+/// Threads evaluating the insertion options in parallel (see parallelEvaluationThreshold), one per core.
+/// Created on first use and shared by all searches, so no threads are spawned per evaluation.
+static EVALUATION_POOL: Lazy<ThreadPool> = Lazy::new(|| {
+    ThreadPoolBuilder::new()
+        .thread_name(|i| format!("evaluation-{}", i))
+        .build()
+        .expect("failed to create the evaluation thread pool")
+});
+
 /// Goal-Driven Ruin and Recreate algorithm
 
 pub struct GDRR<'a> {
@@ -33,12 +68,18 @@ pub struct GDRR<'a> {
     problem: Problem<'a>,
     cost_comparator: fn(&Cost, &Cost) -> Ordering,
     local_sol_collector: LocalSolCollector<'a>,
+    insertion_failure: Option<usize>,
+    archive: Option<Rc<RefCell<dyn SolutionArchive>>>,
+    cancellation_flag: Option<Arc<AtomicBool>>,
+    move_log: Option<Rc<RefCell<MoveLog>>>,
+    pending_steps: Vec<MoveStep>,
 }
 
 
 impl<'a> GDRR<'a> {
-    pub fn new(instance: &'a Instance, config: &'a Config, local_sol_collector: LocalSolCollector<'a>) -> Self {
-        let problem = Problem::new(instance);
+    pub fn new(instance: &'a Instance, config: &'a Config, mut local_sol_collector: LocalSolCollector<'a>) -> Self {
+        local_sol_collector.set_tiebreaks(config.tiebreaks.clone().unwrap_or_default());
+        let problem = Problem::new(instance, config.empty_node_order.unwrap_or_default());
         leftover_valuator::set_power(config.leftover_valuation_power);
         let cost_comparator = crate::COST_COMPARATOR;
         Self {
@@ -47,26 +88,91 @@ impl<'a> GDRR<'a> {
             problem,
             cost_comparator,
             local_sol_collector,
+            insertion_failure: None,
+            archive: None,
+            cancellation_flag: None,
+            move_log: None,
+            pending_steps: vec![],
         }
     }
 
+    /// Replaces the random seed of the search
+    pub fn reseed(&mut self, seed: u64) {
+        self.problem.reseed(seed);
+    }
+
+    /// Offers every accepted solution of the search to the archive
+    pub fn set_archive(&mut self, archive: Rc<RefCell<dyn SolutionArchive>>) {
+        self.archive = Some(archive);
+    }
+
+    /// Registers a flag which stops the search once set, checked once per iteration.
+    /// The best solutions found so far remain available through the solution collector.
+    pub fn set_cancellation_flag(&mut self, flag: Arc<AtomicBool>) {
+        self.cancellation_flag = Some(flag);
+    }
+
+    /// Records the accepted moves of the search in the move log, which can replay them to reproduce the best solution
+    pub fn set_move_log(&mut self, move_log: Rc<RefCell<MoveLog>>) {
+        self.move_log = Some(move_log);
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancellation_flag.as_ref().is_some_and(|flag| flag.load(AtomicOrdering::Relaxed))
+    }
+
     // Late Acceptance Hill Climbing metaheuristic
-    pub fn lahc(&'a mut self) {
+    pub fn lahc(&'a mut self) -> SearchStats {
         let start_time = std::time::Instant::now();
 
         let max_rr_iterations = self.config.max_rr_iterations.unwrap_or(usize::MAX);
+        let max_node_ops = self.config.max_node_ops.unwrap_or(u64::MAX);
+        let target_usage = self.config.target_usage.unwrap_or(f64::INFINITY);
+        let stagnation_window = self.config.stagnation_window.unwrap_or(usize::MAX);
+        let mut n_restarts = 0;
 
-        let empty_problem_cost = Cost::new(0, 0.0, self.instance.total_part_area(), 0);
+        let empty_problem_cost = Cost {
+            excluded_priority: self.instance.parts().iter().filter(|(_, qty)| *qty > 0).map(|(pt, _)| pt.priority()).max(),
+            ..Cost::new(0, 0.0, self.instance.total_part_area(), 0)
+        };
 
         let mut lahc_history: VecDeque<Cost> = VecDeque::with_capacity(self.config.history_length);
         lahc_history.push_back(empty_problem_cost.clone());
         let mut n_iterations = 0;
         let mut n_accepted = 0;
         let mut n_improved = 0;
+        let mut last_improvement_iteration = 0;
         let mut mat_limit = self.local_sol_collector.material_limit();
         let mut local_optimum: Option<ProblemSolution> = None;
 
-        while n_iterations < max_rr_iterations && !self.local_sol_collector.terminate() {
+        self.open_initial_sheets();
+        if self.config.warm_start.unwrap_or(true) {
+            //Start the search from a deterministic greedy solution instead of an empty one
+            let mat_limit_budget = mat_limit as i128 - 1 - self.problem.cost().material_cost as i128;
+            self.recreate_with(mat_limit_budget, u64::MAX, true);
+            if let Some(parttype_id) = self.insertion_failure {
+                timed_thread_println!("Search stopped: part {} cannot be placed in any layout", parttype_id);
+            }
+            self.release_empty_layouts();
+            let cost = self.problem.cost();
+            local_optimum = Some(self.problem.create_solution(&None, Some(cost.clone())));
+            if let Some(archive) = self.archive.as_ref() {
+                archive.borrow_mut().offer(local_optimum.as_ref().unwrap());
+            }
+            self.local_sol_collector.report_problem_solution(local_optimum.as_ref().unwrap());
+            self.log_accepted_move(None, local_optimum.as_ref().unwrap());
+            if log::log_enabled!(Level::Debug) {
+                log::debug!("warm start: (excl: {}, leftover: {:.3}, mat: {})", cost.part_area_excluded, cost.leftover_value, cost.material_cost);
+            }
+            lahc_history.clear();
+            lahc_history.push_back(cost);
+        }
+
+        while n_iterations < max_rr_iterations && self.problem.n_node_ops() < max_node_ops && !self.local_sol_collector.terminate() && self.insertion_failure.is_none() {
+            if self.cancelled() {
+                timed_thread_println!("Search cancelled");
+                break;
+            }
             let mat_limit_budget: i128 = match local_optimum.as_ref() {
                 Some(solution) => mat_limit as i128 - 1 - solution.cost().material_cost as i128,
                 None => mat_limit as i128 - 1 - self.problem.cost().material_cost as i128,
@@ -78,15 +184,60 @@ impl<'a> GDRR<'a> {
                 None => lahc_history.front().unwrap().part_area_excluded
             };
 
-            self.recreate(mat_limit_budget, max_part_area_not_included);
+            match self.config.recreate_strategy.unwrap_or_default() {
+                RecreateStrategy::BestFit => self.recreate(mat_limit_budget, max_part_area_not_included),
+                RecreateStrategy::Strip => self.recreate_strip(mat_limit_budget),
+            }
+            if let Some(parttype_id) = self.insertion_failure {
+                timed_thread_println!("Search stopped: part {} cannot be placed in any layout", parttype_id);
+                break;
+            }
+            if n_iterations == 0 {
+                //initial sheets which remained unused after the first recreate
+                self.release_empty_layouts();
+            }
 
             let cost = self.problem.cost();
 
-            if (self.cost_comparator)(&cost, lahc_history.front().unwrap()) <= Ordering::Equal ||
-                (local_optimum.is_some() && (self.cost_comparator)(&cost, local_optimum.as_ref().unwrap().cost()) <= Ordering::Equal) {
+            //solution of the move, created in advance to compare it to a local optimum of equal cost by the tiebreaks
+            let mut candidate = None;
+            let accepted = (self.cost_comparator)(&cost, lahc_history.front().unwrap()) <= Ordering::Equal || match &local_optimum {
+                Some(optimum) => match (self.cost_comparator)(&cost, optimum.cost()) {
+                    Ordering::Less => true,
+                    Ordering::Equal => {
+                        //moves of equal cost are accepted, unless the configured tiebreaks prefer the local optimum
+                        let tiebreaks = self.config.tiebreaks.as_deref().unwrap_or_default();
+                        let solution = self.problem.create_solution(&local_optimum, Some(cost.clone()));
+                        let preferred = tiebreak::compare_chain(tiebreaks, &solution, optimum) != Ordering::Greater;
+                        candidate = Some(solution);
+                        preferred
+                    }
+                    Ordering::Greater => false,
+                },
+                None => false,
+            };
+
+            if log::log_enabled!(Level::Trace) {
+                let reference = lahc_history.front().unwrap();
+                log::trace!("move {}: {} (excl: {:+}, leftover: {:+.3}, mat: {:+})", n_iterations,
+                    if accepted { "accepted" } else { "rejected" },
+                    cost.part_area_excluded as i128 - reference.part_area_excluded as i128,
+                    cost.leftover_value - reference.leftover_value,
+                    cost.material_cost as i128 - reference.material_cost as i128);
+            }
+
+            if accepted {
                 //Solution is better or equivalent to the last entry in the history queue or the local optimum.
 
-                local_optimum = Some(self.problem.create_solution(&local_optimum, Some(cost.clone())));
+                let tied_with_local_optimum = candidate.is_some() && self.config.tiebreaks.as_ref().is_some_and(|tiebreaks| !tiebreaks.is_empty());
+                local_optimum = Some(match candidate {
+                    Some(candidate) => candidate,
+                    None => self.problem.create_solution(&local_optimum, Some(cost.clone())),
+                });
+                if let Some(archive) = self.archive.as_ref() {
+                    archive.borrow_mut().offer(local_optimum.as_ref().unwrap());
+                }
+                self.log_accepted_move(Some(n_iterations), local_optimum.as_ref().unwrap());
 
                 lahc_history.pop_front();
 
@@ -96,8 +247,18 @@ impl<'a> GDRR<'a> {
                         lahc_history.push_back(cost.clone());
                     }
                     self.local_sol_collector.report_problem_solution(local_optimum.as_ref().unwrap());
+                    self.mark_best_move(local_optimum.as_ref().unwrap());
+                    if log::log_enabled!(Level::Debug) {
+                        log::debug!("improvement {}: (excl: {}, leftover: {:.3}, mat: {})", n_iterations,
+                            cost.part_area_excluded, cost.leftover_value, cost.material_cost);
+                    }
                     n_improved += 1;
+                    last_improvement_iteration = n_iterations;
                 } else {
+                    if tied_with_local_optimum {
+                        //the collector decides whether the tiebreaks prefer it over its best solution
+                        self.local_sol_collector.report_problem_solution(local_optimum.as_ref().unwrap());
+                    }
                     //Current local optimum is not better, add the best cost to the history queue
                     for _ in 0..(self.config.history_length - lahc_history.len()) {
                         let best = lahc_history.back().unwrap_or(&empty_problem_cost).clone();
@@ -107,6 +268,7 @@ impl<'a> GDRR<'a> {
                 n_accepted += 1;
             } else {
                 self.problem.restore_from_problem_solution(local_optimum.as_ref().unwrap());
+                self.pending_steps.clear();
             }
 
             if self.local_sol_collector.material_limit() < mat_limit {
@@ -114,11 +276,32 @@ impl<'a> GDRR<'a> {
                 local_optimum = None;
                 lahc_history.clear();
                 lahc_history.push_back(empty_problem_cost.clone());
+                //the search restarts from scratch under the new material limit
+                last_improvement_iteration = n_iterations;
             }
             n_iterations += 1;
             if n_iterations % 100 == 0 {
                 self.local_sol_collector.rx_sync()
             }
+            if self.config.restart_every.is_some_and(|every| (n_iterations as u64).checked_rem(every) == Some(0)) {
+                //Restart the search from the local optimum with a new seed and an empty history, the best solutions are kept by the collector
+                let seed = match self.config.restart_seeds.as_ref().filter(|seeds| !seeds.is_empty()) {
+                    Some(seeds) => seeds[n_restarts % seeds.len()],
+                    None => n_restarts as u64,
+                };
+                self.problem.reseed(seed);
+                lahc_history.clear();
+                lahc_history.push_back(empty_problem_cost.clone());
+                n_restarts += 1;
+            }
+            if self.local_sol_collector.best_complete_solution().as_ref().is_some_and(|s| s.usage() >= target_usage) {
+                timed_thread_println!("Target usage reached");
+                break;
+            }
+            if self.config.stop_on_stagnation.unwrap_or(false) && n_iterations - last_improvement_iteration > stagnation_window {
+                timed_thread_println!("Search stagnated");
+                break;
+            }
 
             debug_assert!(lahc_history.len() <= self.config.history_length, "{}", lahc_history.len());
         }
@@ -135,6 +318,99 @@ impl<'a> GDRR<'a> {
                 }
                 None => "()".to_string()
             });
+
+        let iterations_since_improvement = n_iterations - last_improvement_iteration;
+        SearchStats::new(n_iterations, n_accepted, n_improved, self.problem.n_node_ops(), self.insertion_failure,
+                         iterations_since_improvement, iterations_since_improvement > stagnation_window)
+    }
+
+    /// Constructs a single solution greedily with the configured recreate strategy, without any ruin or search.
+    /// Returns None if not all parts can be placed within the stock of the sheettypes.
+    pub fn first_feasible(&mut self) -> Option<ProblemSolution<'a>> {
+        self.open_initial_sheets();
+        match self.config.recreate_strategy.unwrap_or_default() {
+            //stop as soon as a part cannot be placed
+            RecreateStrategy::BestFit => self.recreate(i128::MAX, 0),
+            RecreateStrategy::Strip => self.recreate_strip(i128::MAX),
+        }
+        self.release_empty_layouts();
+        let cost = self.problem.cost();
+        match cost.part_area_excluded == 0 {
+            true => Some(self.problem.create_solution(&None, Some(cost))),
+            false => None
+        }
+    }
+
+    /// Opens the `initialSheets` before the first recreate, so the parts are spread over them from the start.
+    /// Sheets of sheettypes which are out of stock are skipped.
+    fn open_initial_sheets(&mut self) {
+        for &sheettype_id in self.config.initial_sheets.iter().flatten() {
+            assert!(sheettype_id < self.instance.sheets().len(), "initial sheet {} does not exist", sheettype_id);
+            let opened = self.problem.open_layout(sheettype_id);
+            if let Some(index) = opened.filter(|_| self.move_log.is_some()) {
+                let layout_id = self.problem.layouts()[index].id();
+                self.pending_steps.push(MoveStep::OpenSheet { sheettype_id, layout_id });
+            }
+        }
+    }
+
+    fn release_empty_layouts(&mut self) {
+        self.problem.release_empty_layouts();
+        if self.move_log.is_some() {
+            self.pending_steps.push(MoveStep::ReleaseEmptyLayouts);
+        }
+    }
+
+    fn remove_node(&mut self, node_index: Index, layout_index: Index) -> Option<Layout<'a>> {
+        if self.move_log.is_some() {
+            let layout_id = self.problem.layouts()[layout_index].id();
+            self.pending_steps.push(MoveStep::Remove { layout_id, node: node_index });
+        }
+        self.problem.remove_node(node_index, LayoutIndex::Existing(layout_index))
+    }
+
+    fn implement_insertion_blueprint(&mut self, blueprint: &InsertionBlueprint<'a>) -> IOCUpdates {
+        let updates = self.problem.implement_insertion_blueprint(blueprint).expect("generated blueprint is invalid");
+        if self.move_log.is_some() {
+            let layout_id = match updates.layout_index() {
+                LayoutIndex::Existing(index) => self.problem.layouts()[*index].id(),
+                LayoutIndex::Empty(_) => unreachable!("insertions always result in an existing layout"),
+            };
+            let target = match blueprint.layout_index() {
+                LayoutIndex::Existing(_) => InsertionTarget::Existing { layout_id },
+                LayoutIndex::Empty(empty_layout) => InsertionTarget::New { empty_layout: *empty_layout, layout_id },
+            };
+            self.pending_steps.push(MoveStep::Insert {
+                target,
+                parttype_id: blueprint.parttype().id(),
+                original_node: *blueprint.original_node_index(),
+                replacements: blueprint.replacements().clone(),
+            });
+        }
+        updates
+    }
+
+    /// Moves the steps since the last accepted move to the move log
+    fn log_accepted_move(&mut self, iteration: Option<usize>, solution: &ProblemSolution<'a>) {
+        if let Some(move_log) = self.move_log.as_ref() {
+            let steps = std::mem::take(&mut self.pending_steps);
+            move_log.borrow_mut().push_move(RRMove { iteration, steps });
+            if iteration.is_none() {
+                //the warm start is reported to the solution collector right away
+                self.mark_best_move(solution);
+            }
+        }
+    }
+
+    /// Marks the last accepted move as the best one if its solution was accepted by the solution collector
+    fn mark_best_move(&self, solution: &ProblemSolution<'a>) {
+        if let Some(move_log) = self.move_log.as_ref() {
+            let best = self.local_sol_collector.best_complete_solution().as_ref()
+                .or(self.local_sol_collector.best_incomplete_solution().as_ref());
+            if best.is_some_and(|best| best.id() == solution.id()) {
+                move_log.borrow_mut().mark_best(solution);
+            }
+        }
     }
 
     fn ruin(&mut self, mut mat_limit_budget: i128) -> i128 {
@@ -143,8 +419,10 @@ impl<'a> GDRR<'a> {
         if mat_limit_budget >= 0 {
             for _i in 0..n_nodes_to_remove {
                 //The bias sampler allows us to select a random layout for removing a node, but with a bias towards layouts with a low usage.
-                //This is done to preserve 'good' layouts and give 'bad' layouts more opportunity to improve
+                //This is done to preserve 'good' layouts and give 'bad' layouts more opportunity to improve.
+                //Layouts which fully use their sheet cannot improve and are never ruined.
                 let entries = self.problem.layouts_mut().iter_mut()
+                    .filter(|(_, l)| !l.is_perfect())
                     .map(|(i, l)| (i, NotNan::new(l.usage(false)).expect("layout usage is NaN")))
                     .collect_vec();
                 let biased_sampler = BiasedSampler::new_default(entries, BiasMode::Low);
@@ -152,12 +430,19 @@ impl<'a> GDRR<'a> {
 
                 match selected_layout {
                     Some(layout_index) => {
-                        let removable_nodes = self.problem.layouts()[*layout_index].get_removable_nodes();
-                        let selected_node = removable_nodes.choose(&mut self.problem.rng()).unwrap();
-
-                        let removed_layout = self.problem.remove_node(*selected_node, LayoutIndex::Existing(*layout_index));
-                        if let Some(removed_layout) = removed_layout {
-                            mat_limit_budget += removed_layout.sheettype().value() as i128;
+                        let layout = &self.problem.layouts()[*layout_index];
+                        let mut removable_nodes = layout.removable_nodes_unordered();
+                        if GDRR::sole_first_sheettype_layout(&self.problem) == Some(*layout_index) {
+                            //The only layout of the pinned first sheettype keeps at least one part
+                            let n_parts = layout.included_parts_unordered().len();
+                            removable_nodes.retain(|n| layout.n_parts_below(*n) < n_parts);
+                        }
+                        if let Some(&selected_node) = removable_nodes.choose(&mut self.problem.rng()) {
+                            let layout_index = *layout_index;
+                            let removed_layout = self.remove_node(selected_node, layout_index);
+                            if let Some(removed_layout) = removed_layout {
+                                mat_limit_budget += self.problem.layout_material_cost(removed_layout.sheettype()) as i128;
+                            }
                         }
                     }
                     None => {
@@ -167,8 +452,13 @@ impl<'a> GDRR<'a> {
             }
         } else {
             while mat_limit_budget < 0 {
-                //Search the lowest usage layout
+                //Search the lowest usage layout, the only layout of the pinned first sheettype is released last
+                let protected_layout = match self.problem.layouts().len() {
+                    1 => None,
+                    _ => GDRR::sole_first_sheettype_layout(&self.problem),
+                };
                 let min_usage_layout_index = self.problem.layouts_mut().iter_mut()
+                    .filter(|(i, _)| Some(*i) != protected_layout)
                     .map(|(i, l)| (i, l.usage(false)))
                     .min_by(|(_, a), (_, b)| {
                         a.partial_cmp(b).unwrap()
@@ -180,9 +470,9 @@ impl<'a> GDRR<'a> {
                         let top_node = self.problem.layouts()[min_usage_layout_index].top_node_index().clone();
 
                         //release it and update mat_limit_exceedance
-                        let removed_layout = self.problem.remove_node(top_node, LayoutIndex::Existing(min_usage_layout_index));
+                        let removed_layout = self.remove_node(top_node, min_usage_layout_index);
                         if let Some(removed_layout) = removed_layout {
-                            mat_limit_budget += removed_layout.sheettype().value() as i128;
+                            mat_limit_budget += self.problem.layout_material_cost(removed_layout.sheettype()) as i128;
                         } else {
                             panic!("Top node should remove entire layout!");
                         }
@@ -196,7 +486,13 @@ impl<'a> GDRR<'a> {
         mat_limit_budget
     }
 
-    fn recreate(&mut self, mut mat_limit_budget: i128, max_part_area_excluded: u64) {
+    fn recreate(&mut self, mat_limit_budget: i128, max_part_area_excluded: u64) {
+        self.recreate_with(mat_limit_budget, max_part_area_excluded, false)
+    }
+
+    /// Recreate, greedy recreates are best-fit-decreasing: the parttypes are inserted by descending area (within the highest priority),
+    /// each at its cheapest position without blinking, independent of the random seed
+    fn recreate_with(&mut self, mut mat_limit_budget: i128, max_part_area_excluded: u64, greedy: bool) {
         let mut parttypes_to_consider: Vec<&PartType> = self.problem.parttype_qtys().iter().enumerate()
             .filter(|(_i, q)| { **q > 0 })
             .map(|(i, _q)| -> &PartType { self.problem.instance().get_parttype(i) }).collect();
@@ -218,17 +514,27 @@ impl<'a> GDRR<'a> {
         debug_assert!(assertions::insertion_option_cache_is_valid(&self.problem, &insertion_option_cache, &parttypes_to_consider));
 
         while !parttypes_to_consider.is_empty() && part_area_not_included <= max_part_area_excluded {
-            let elected_parttype = GDRR::select_next_parttype(&parttypes_to_consider, &insertion_option_cache, self.problem.rng(), &self.config);
-            let elected_blueprint = GDRR::select_insertion_blueprint(elected_parttype, &insertion_option_cache, mat_limit_budget, &mut self.problem, &self.config, &self.cost_comparator);
+            let elected_parttype = match greedy {
+                true => *parttypes_to_consider.iter()
+                    .min_by_key(|pt| (std::cmp::Reverse(pt.priority()), std::cmp::Reverse(pt.area()), pt.id()))
+                    .unwrap(),
+                false => GDRR::select_next_parttype(&parttypes_to_consider, &insertion_option_cache, self.problem.rng(), self.config),
+            };
+            let elected_blueprint = GDRR::select_insertion_blueprint(elected_parttype, &insertion_option_cache, mat_limit_budget, &mut self.problem, self.config, &self.cost_comparator, greedy);
 
             if let Some(elected_blueprint) = elected_blueprint.as_ref() {
-                let cache_updates = self.problem.implement_insertion_blueprint(elected_blueprint);
+                //material cost of opening a new layout needs to be determined before the blueprint is implemented
+                let new_layout_material_cost = match elected_blueprint.layout_index() {
+                    LayoutIndex::Empty(index) => self.problem.layout_material_cost(self.problem.empty_layouts()[*index].sheettype()),
+                    LayoutIndex::Existing(_) => 0
+                };
+                let cache_updates = self.implement_insertion_blueprint(elected_blueprint);
                 insertion_option_cache.update_cache(&cache_updates, &parttypes_to_consider, &self.problem);
 
                 if let LayoutIndex::Empty(index) = elected_blueprint.layout_index() {
                     //update mat_limit_budget
                     let empty_layout = &self.problem.empty_layouts()[*index];
-                    mat_limit_budget -= empty_layout.sheettype().value() as i128;
+                    mat_limit_budget -= new_layout_material_cost as i128;
                     let sheettype_id = empty_layout.sheettype().id();
 
                     if self.problem.sheettype_qtys()[sheettype_id] == 0 {
@@ -251,6 +557,11 @@ impl<'a> GDRR<'a> {
 
                 debug_assert!(assertions::insertion_option_cache_is_valid(&self.problem, &insertion_option_cache, &parttypes_to_consider), "{:#?}\n{:#?}", elected_blueprint, cache_updates);
             } else {
+                if self.config.no_insertion_policy == Some(NoInsertionPolicy::Fail) && GDRR::fits_no_new_layout(elected_parttype, &self.problem) {
+                    //the search is stopped, see SearchStats::insertion_failure
+                    self.insertion_failure = Some(elected_parttype.id());
+                    break;
+                }
                 //if there is no insertion blueprint, the part cannot be added to the problem
                 part_area_not_included += *self.problem.parttype_qtys().get(elected_parttype.id()).unwrap() as u64
                     * elected_parttype.area();
@@ -262,63 +573,350 @@ impl<'a> GDRR<'a> {
         }
     }
 
+    /// Whether the part fits no existing layout (it has no insertion blueprint) and no new layout of a sheettype in stock either,
+    /// regardless of the material budget
+    fn fits_no_new_layout(parttype: &'a PartType, problem: &Problem<'a>) -> bool {
+        let empty_layouts = problem.empty_layouts().iter().enumerate()
+            .filter(|(_, l)| problem.sheettype_qtys()[l.sheettype().id()] > 0)
+            .map(|(i, l)| (LayoutIndex::Empty(i), l))
+            .collect_vec();
+        let mut insertion_option_cache = InsertionOptionCache::new(problem.instance());
+        insertion_option_cache.add_for_parttypes(&[parttype], &empty_layouts);
+        insertion_option_cache.get_for_parttype(parttype).is_none_or(|options| options.is_empty())
+    }
+
+    /// Deterministic alternative to recreate: shelf packing.
+    /// Parts are inserted by descending height (lying flat if rotation is allowed),
+    /// each in the first empty node (per layout, row by row) it fits in.
+    /// If a part does not fit in any row, a new sheet is opened. If that is not possible either, the part is left out.
+    fn recreate_strip(&mut self, mut mat_limit_budget: i128) {
+        let strip_height = |pt: &PartType| match pt.fixed_rotation() {
+            Some(_) => pt.size().height(),
+            None => u64::min(pt.size().width(), pt.size().height()),
+        };
+        let parttypes = self.problem.parttype_qtys().iter().enumerate()
+            .filter(|(_, q)| **q > 0)
+            .map(|(i, _)| self.instance.get_parttype(i))
+            .sorted_by_key(|pt| (std::cmp::Reverse(strip_height(pt)), std::cmp::Reverse(pt.area()), pt.id()))
+            .collect_vec();
+
+        for parttype in parttypes {
+            while self.problem.parttype_qtys()[parttype.id()] > 0 {
+                let blueprint = match GDRR::select_strip_blueprint(parttype, &self.problem, self.config, mat_limit_budget) {
+                    Some(blueprint) => blueprint,
+                    None => break
+                };
+                if let LayoutIndex::Empty(index) = blueprint.layout_index() {
+                    mat_limit_budget -= self.problem.layout_material_cost(self.problem.empty_layouts()[*index].sheettype()) as i128;
+                }
+                self.implement_insertion_blueprint(&blueprint);
+            }
+        }
+    }
+
+    fn select_strip_blueprint(parttype: &'a PartType, problem: &Problem<'a>, config: &Config, mat_limit_budget: i128) -> Option<InsertionBlueprint<'a>> {
+        //Try the flat rotation first
+        let rotations = match parttype.fixed_rotation() {
+            Some(rotation) => vec![*rotation],
+            None if parttype.size().height() <= parttype.size().width() => vec![Rotation::Default, Rotation::Rotated],
+            None => vec![Rotation::Rotated, Rotation::Default],
+        };
+        let first_blueprint = |layout_i: LayoutIndex, layout: &Layout<'a>| {
+            layout.sorted_empty_nodes().iter()
+                .map(|&node_i| (node_i, layout.node_position(node_i)))
+                .sorted_by_key(|(_, (x, y))| (*y, *x))
+                .find_map(|(node_i, _)| {
+                    let node = &layout.nodes()[node_i];
+                    rotations.iter()
+                        .filter(|&&r| node.insertion_possible(parttype, r))
+                        .find_map(|&r| InsertionOption::new(layout_i, node_i, parttype, Some(r)).generate_blueprints(problem).into_iter().next())
+                })
+        };
+
+        let existing_blueprint = problem.layouts().iter()
+            .sorted_by_key(|(_, l)| l.id())
+            .find_map(|(i, l)| first_blueprint(LayoutIndex::Existing(i), l));
+        if existing_blueprint.is_some() {
+            return existing_blueprint;
+        }
+
+        //Open a new layout
+        let new_layout_allowed = problem.layouts().len() < config.max_layouts.unwrap_or(usize::MAX)
+            && config.no_insertion_policy != Some(NoInsertionPolicy::DropPart);
+        if !new_layout_allowed {
+            return None;
+        }
+        let first_sheettype = GDRR::pending_first_sheettype(problem);
+        problem.empty_layouts().iter().enumerate()
+            .filter(|(_, l)| problem.sheettype_qtys()[l.sheettype().id()] > 0)
+            .filter(|(_, l)| mat_limit_budget >= problem.layout_material_cost(l.sheettype()) as i128)
+            .sorted_by_key(|(_, l)| (Some(l.sheettype().id()) != first_sheettype, config.sheettype_priority(l.sheettype().id()), problem.layout_material_cost(l.sheettype())))
+            .find_map(|(i, l)| first_blueprint(LayoutIndex::Empty(i), l))
+    }
+
+    /// The pinned first sheettype of the instance, if the problem does not contain a layout of it yet
+    fn pending_first_sheettype(problem: &Problem) -> Option<usize> {
+        problem.instance().first_sheettype()
+            .filter(|id| problem.layouts().iter().all(|(_, l)| l.sheettype().id() != *id))
+    }
+
+    /// Index of the only layout of the pinned first sheettype of the instance, if there is exactly one
+    fn sole_first_sheettype_layout(problem: &Problem) -> Option<Index> {
+        let first_sheettype = problem.instance().first_sheettype()?;
+        problem.layouts().iter()
+            .filter(|(_, l)| l.sheettype().id() == first_sheettype)
+            .map(|(i, _)| i)
+            .exactly_one().ok()
+    }
+
     fn select_next_parttype(parttypes: &[&'a PartType], insertion_option_cache: &InsertionOptionCache<'a>, rand: &mut SmallRng, config: &Config) -> &'a PartType {
-        let mut indices = (0..parttypes.len()).collect_vec();
-        indices.shuffle(rand);
+        //Only the parttypes with the highest priority are eligible
+        let max_priority = parttypes.iter().map(|pt| pt.priority()).max().unwrap();
+        let mut indices = (0..parttypes.len()).filter(|i| parttypes[*i].priority() == max_priority).collect_vec();
+        let perimeter = |pt: &PartType| 2 * (pt.width() + pt.height());
+        match config.insertion_order.unwrap_or_default() {
+            InsertionOrder::FewestOptions => {
+                indices.shuffle(rand);
 
-        let n_options: Vec<usize> = indices.iter().map(|i| {
-            let parttype = parttypes[*i];
-            insertion_option_cache.get_for_parttype(parttype).map_or(0, |options| options.len())
-        }).collect();
+                let n_options: Vec<usize> = indices.iter().map(|i| {
+                    let parttype = parttypes[*i];
+                    insertion_option_cache.get_for_parttype(parttype).map_or(0, |options| options.len())
+                }).collect();
 
-        let blink = blink::select_lowest_entry(&n_options, config.blink_rate, rand);
-        let parttype_index = indices[blink];
-        parttypes[parttype_index]
+                let blink = blink::select_lowest_entry(&n_options, config.blink_rate, rand);
+                return parttypes[indices[blink]];
+            }
+            InsertionOrder::Random => return parttypes[*indices.choose(rand).unwrap()],
+            InsertionOrder::LargestAreaFirst => indices.sort_by_key(|&i| (std::cmp::Reverse(parttypes[i].area()), parttypes[i].id())),
+            InsertionOrder::LargestPerimeterFirst => indices.sort_by_key(|&i| (std::cmp::Reverse(perimeter(parttypes[i])), parttypes[i].id())),
+            InsertionOrder::ByPriority => indices.sort_by_key(|&i| parttypes[i].id()),
+        }
+        let blink = blink::select_lowest_in_range(0..indices.len(), config.blink_rate, rand);
+        parttypes[indices[blink]]
+    }
+
+    /// Replaces the leftover value of the blueprints by the change in leftover value of their layout according to the custom cost function,
+    /// evaluated by implementing every blueprint on a copy of its layout
+    fn apply_cost_function(blueprints: Vec<InsertionBlueprint<'a>>, problem: &Problem<'a>) -> Vec<InsertionBlueprint<'a>> {
+        blueprints.into_iter().map(|bp| {
+            let layout = problem.get_layout(bp.layout_index());
+            let delta = layout.cost_after_insertion(&bp, problem.instance()).leftover_value - layout.cost_immut(false).leftover_value;
+            let leftover_value = bp.cost().leftover_value;
+            bp.add_leftover_value(delta - leftover_value)
+        }).collect_vec()
+    }
+
+    /// Generates (and thereby evaluates the cost of) the blueprints of all options on the threads of EVALUATION_POOL.
+    /// The result is in the same order as the options.
+    fn generate_blueprints_parallel<'o>(options: &[&'o InsertionOption<'a>], problem: &Problem) -> Vec<(&'o InsertionOption<'a>, Vec<InsertionBlueprint<'a>>)> {
+        //the valuation power is thread local, and can differ between the searches sharing the pool
+        let valuation_power = leftover_valuator::power().expect("valuation power not set for this thread!");
+        EVALUATION_POOL.install(|| {
+            options.par_iter()
+                .map(|&option| {
+                    leftover_valuator::set_power(valuation_power);
+                    (option, option.generate_blueprints(problem))
+                })
+                .collect()
+        })
+    }
+
+    /// Splits the options into the max_candidates options in existing layouts with the tightest fit, i.e. the smallest empty nodes
+    /// (the options all belong to the same parttype), followed by the options to open a new layout (one per sheettype), and the remaining options.
+    /// Equal fits, and the remaining options, are ordered randomly, except for greedy recreates.
+    fn most_promising_options<'o>(options: &'o [Rc<InsertionOption<'a>>], max_candidates: usize, problem: &mut Problem, greedy: bool) -> (Vec<&'o InsertionOption<'a>>, Vec<&'o InsertionOption<'a>>) {
+        let (mut existing_layout_options, new_layout_options): (Vec<&InsertionOption>, Vec<&InsertionOption>) = options.iter().map(|o| o.as_ref())
+            .partition(|o| matches!(o.layout_index(), LayoutIndex::Existing(_)));
+        if !greedy {
+            existing_layout_options.shuffle(problem.rng());
+        }
+        let node_area = |option: &InsertionOption| problem.get_layout(option.layout_index()).nodes()[*option.original_node_index()].area();
+        if existing_layout_options.len() > max_candidates {
+            existing_layout_options.select_nth_unstable_by_key(max_candidates, |o| node_area(o));
+        }
+        let remaining_options = existing_layout_options.split_off(usize::min(max_candidates, existing_layout_options.len()));
+        existing_layout_options.sort_by_key(|o| node_area(o));
+        existing_layout_options.extend(new_layout_options);
+        (existing_layout_options, remaining_options)
     }
 
-    fn select_insertion_blueprint(parttype: &'a PartType, insertion_option_cache: &InsertionOptionCache<'a>, mat_limit_budget: i128, problem: &mut Problem, config: &Config, cost_comparator: &fn(&Cost, &Cost) -> Ordering) -> Option<InsertionBlueprint<'a>> {
+    /// Subtracts the increase of the urgency penalty (see Problem::urgency_penalty) from the leftover value of the blueprints,
+    /// assuming the ranking of the layouts does not change. A new layout is ranked after all layouts containing urgent parts.
+    fn add_urgency_penalty(blueprints: Vec<InsertionBlueprint<'a>>, ranking: &[(Index, u64)], instance: &Instance) -> Vec<InsertionBlueprint<'a>> {
+        blueprints.into_iter().map(|bp| {
+            let (rank, urgency_area) = match bp.layout_index() {
+                LayoutIndex::Existing(index) => ranking.iter().enumerate()
+                    .find(|(_, (i, _))| i == index)
+                    .map(|(rank, (_, urgency_area))| (rank, *urgency_area))
+                    .expect("layout missing in the urgency ranking"),
+                LayoutIndex::Empty(_) => (ranking.iter().filter(|(_, urgency_area)| *urgency_area > 0).count(), 0),
+            };
+            let part_urgency_area = bp.parttype().urgency() as u64 * bp.parttype().area();
+            let increase = leftover_valuator::valuate(urgency_area + part_urgency_area) - leftover_valuator::valuate(urgency_area);
+            bp.add_leftover_value(-instance.urgency_weight() * rank as f32 * increase)
+        }).collect_vec()
+    }
+
+    fn select_insertion_blueprint(parttype: &'a PartType, insertion_option_cache: &InsertionOptionCache<'a>, mat_limit_budget: i128, problem: &mut Problem<'a>, config: &Config, cost_comparator: &fn(&Cost, &Cost) -> Ordering, greedy: bool) -> Option<InsertionBlueprint<'a>> {
         let insertion_options = insertion_option_cache.get_for_parttype(parttype);
         match insertion_options {
             Some(options) => {
+                //The options beyond max_candidates are only evaluated if none of the most promising ones results in an insertion in an existing layout
+                let (first_options, remaining_options) = match config.max_candidates_per_step {
+                    Some(max_candidates) if options.len() > max_candidates => GDRR::most_promising_options(options, max_candidates, problem, greedy),
+                    _ => (options.iter().map(|o| o.as_ref()).collect_vec(), vec![]),
+                };
                 //Collect the blueprints
                 let mut existing_layout_blueprints: Vec<InsertionBlueprint<'a>> = Vec::new();
                 let mut new_layout_blueprints: Vec<InsertionBlueprint<'a>> = Vec::new();
 
-                for option in options {
-                    if existing_layout_blueprints.len() > 20 {
-                        break; //enough blueprints to consider
+                //Bounds on the number of layouts
+                let n_layouts = problem.layouts().len();
+                let new_layout_allowed = n_layouts < config.max_layouts.unwrap_or(usize::MAX);
+                let new_layout_preferred = n_layouts < config.min_layouts.unwrap_or(0)
+                    && config.no_insertion_policy != Some(NoInsertionPolicy::DropPart);
+                //With initial sheets, the parts are spread over all existing layouts, which requires the blueprints of all of them
+                let spread = config.initial_sheets.as_ref().is_some_and(|sheets| !sheets.is_empty());
+                let enough_blueprints = |n_blueprints: usize| n_blueprints > 20 && !new_layout_preferred && !spread;
+
+                let option_considered = |option: &InsertionOption| match option.layout_index() {
+                    LayoutIndex::Existing(_) => true,
+                    LayoutIndex::Empty(i) => new_layout_allowed && mat_limit_budget >= problem.layout_material_cost(problem.empty_layouts()[*i].sheettype()) as i128,
+                };
+                let parallel_threshold = config.parallel_evaluation_threshold.unwrap_or(usize::MAX);
+                for (i, options) in [first_options, remaining_options].iter().enumerate() {
+                    if i > 0 && !existing_layout_blueprints.is_empty() {
+                        break;
                     }
-                    match option.layout_index() {
-                        LayoutIndex::Existing(_) => {
-                            existing_layout_blueprints.extend(option.generate_blueprints(problem))
+                    match options.len() > parallel_threshold {
+                        false => {
+                            for &option in options {
+                                if enough_blueprints(existing_layout_blueprints.len()) {
+                                    break; //enough blueprints to consider
+                                }
+                                if option_considered(option) {
+                                    match option.layout_index() {
+                                        LayoutIndex::Existing(_) => existing_layout_blueprints.extend(option.generate_blueprints(problem)),
+                                        LayoutIndex::Empty(_) => new_layout_blueprints.extend(option.generate_blueprints(problem)),
+                                    }
+                                }
+                            }
                         }
-                        LayoutIndex::Empty(i) => {
-                            if mat_limit_budget >= problem.empty_layouts()[*i].sheettype().value() as i128 {
-                                new_layout_blueprints.extend(option.generate_blueprints(problem));
+                        true => {
+                            //Generate the blueprints of one batch of options in parallel at a time, collected in the same order as the sequential loop.
+                            //Once there are enough blueprints, at most the rest of the last batch was generated in vain.
+                            let options = options.iter().copied().filter(|o| option_considered(o)).collect_vec();
+                            'batches: for batch in options.chunks(EVALUATION_POOL.current_num_threads()) {
+                                if enough_blueprints(existing_layout_blueprints.len()) {
+                                    break;
+                                }
+                                for (option, blueprints) in GDRR::generate_blueprints_parallel(batch, problem) {
+                                    if enough_blueprints(existing_layout_blueprints.len()) {
+                                        break 'batches;
+                                    }
+                                    match option.layout_index() {
+                                        LayoutIndex::Existing(_) => existing_layout_blueprints.extend(blueprints),
+                                        LayoutIndex::Empty(_) => new_layout_blueprints.extend(blueprints),
+                                    }
+                                }
                             }
                         }
                     }
                 }
+                if problem.instance().cost_function().is_some() {
+                    existing_layout_blueprints = GDRR::apply_cost_function(existing_layout_blueprints, problem);
+                    new_layout_blueprints = GDRR::apply_cost_function(new_layout_blueprints, problem);
+                }
+                if problem.instance().urgency_weight() > 0.0 && parttype.urgency() > 0 {
+                    let ranking = problem.urgency_ranking();
+                    existing_layout_blueprints = GDRR::add_urgency_penalty(existing_layout_blueprints, &ranking, problem.instance());
+                    new_layout_blueprints = GDRR::add_urgency_penalty(new_layout_blueprints, &ranking, problem.instance());
+                }
+                if new_layout_preferred && !new_layout_blueprints.is_empty() {
+                    //Fewer layouts than the configured minimum, spread the parts by opening a new layout
+                    existing_layout_blueprints.clear();
+                }
                 match existing_layout_blueprints.is_empty() {
                     false => {
-                        //Sort the blueprints by cost
+                        //Sort the blueprints by cost, optionally preferring shallower nesting between equal costs
+                        let prefer_shallow = config.prefer_shallow_insertions.unwrap_or(false);
                         existing_layout_blueprints.sort_by(|a, b| {
                             cost_comparator(a.cost(), b.cost())
+                                .then_with(|| match prefer_shallow {
+                                    true => a.max_level().cmp(&b.max_level()),
+                                    false => Ordering::Equal,
+                                })
                         });
+                        if let Some(fit_metric) = config.fit_metric {
+                            //Rank by the fit metric instead, the (stable) sort keeps the cost order between equal scores
+                            let min_part_dimension = problem.instance().parts().iter()
+                                .map(|(pt, _)| u64::min(pt.width(), pt.height()))
+                                .min().unwrap_or(0);
+                            let node_size = |bp: &InsertionBlueprint| {
+                                let node = &problem.get_layout(bp.layout_index()).nodes()[*bp.original_node_index()];
+                                (node.width(), node.height())
+                            };
+                            existing_layout_blueprints.sort_by_cached_key(|bp| fit_metric.score(node_size(bp), bp, min_part_dimension));
+                        }
+                        if let (Some(max_aspect_ratio), Some(WasteAspectRatioMode::Soft)) = (config.max_waste_aspect_ratio, config.waste_aspect_ratio_mode) {
+                            //Move the blueprints creating too elongated waste to the back, keeping the order otherwise
+                            existing_layout_blueprints.sort_by_cached_key(|bp| {
+                                let node = &problem.get_layout(bp.layout_index()).nodes()[*bp.original_node_index()];
+                                node.creates_elongated_waste(bp.replacements(), max_aspect_ratio)
+                            });
+                        }
+                        if spread {
+                            //Prefer the emptiest layout, keeping the order of the blueprints within a layout
+                            existing_layout_blueprints.sort_by_cached_key(|bp| problem.get_layout(bp.layout_index()).used_area());
+                        }
+                        if spread {
+                            //Prefer the emptiest layout, keeping the order of the blueprints within a layout
+                            existing_layout_blueprints.sort_by_cached_key(|bp| problem.get_layout(bp.layout_index()).used_area());
+                        }
                         //Select the best (blinked) one
-                        let selected_blinked_index = blink::select_lowest_in_range(0..existing_layout_blueprints.len(), config.blink_rate, problem.rng());
+                        let selected_blinked_index = match greedy {
+                            true => 0,
+                            false => blink::select_lowest_in_range(0..existing_layout_blueprints.len(), config.blink_rate, problem.rng()),
+                        };
                         Some(existing_layout_blueprints.remove(selected_blinked_index))
                     }
                     true => {
-                        //No blueprints for existing layouts, try new layouts
+                        //No blueprints for existing layouts, the policy decides whether to try new layouts
+                        if config.no_insertion_policy == Some(NoInsertionPolicy::DropPart) {
+                            return None;
+                        }
+                        if config.no_insertion_policy == Some(NoInsertionPolicy::OpenNewSheet) {
+                            //Only consider the blueprints of the cheapest sheettype
+                            let cheapest_value = new_layout_blueprints.iter()
+                                .map(|bp| problem.layout_material_cost(problem.get_layout(bp.layout_index()).sheettype()))
+                                .min();
+                            new_layout_blueprints.retain(|bp| Some(problem.layout_material_cost(problem.get_layout(bp.layout_index()).sheettype())) == cheapest_value);
+                        }
+                        //A pinned first sheettype is opened before all others, as long as the problem does not contain a layout of it
+                        let of_sheettype = |bp: &InsertionBlueprint, id: usize| problem.get_layout(bp.layout_index()).sheettype().id() == id;
+                        let first_sheettype = GDRR::pending_first_sheettype(problem)
+                            .filter(|id| new_layout_blueprints.iter().any(|bp| of_sheettype(bp, *id)));
+                        if let Some(first_sheettype) = first_sheettype {
+                            new_layout_blueprints.retain(|bp| of_sheettype(bp, first_sheettype));
+                        }
+                        //Break ties between the remaining sheettypes using the configured priority order
+                        let best_priority = new_layout_blueprints.iter()
+                            .map(|bp| config.sheettype_priority(problem.get_layout(bp.layout_index()).sheettype().id()))
+                            .min();
+                        new_layout_blueprints.retain(|bp| Some(config.sheettype_priority(problem.get_layout(bp.layout_index()).sheettype().id())) == best_priority);
                         match new_layout_blueprints.is_empty() {
                             true => {
                                 //No insertion blueprint available
                                 None
                             }
                             false => {
-                                //Select a random blueprint from the new layout blueprints
-                                let selected_index = problem.rng().random_range(0..new_layout_blueprints.len());
+                                //Select a random blueprint from the new layout blueprints, the cheapest one if greedy
+                                let selected_index = match greedy {
+                                    true => (0..new_layout_blueprints.len())
+                                        .min_by(|&a, &b| cost_comparator(new_layout_blueprints[a].cost(), new_layout_blueprints[b].cost()))
+                                        .unwrap(),
+                                    false => problem.rng().random_range(0..new_layout_blueprints.len()),
+                                };
                                 Some(new_layout_blueprints.remove(selected_index))
                             }
                         }
@@ -330,4 +928,858 @@ impl<'a> GDRR<'a> {
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Fast feasible solution for previews, see GDRR::first_feasible.
+/// Runs on the calling thread, no solution collectors are involved.
+pub fn optimize_first_feasible(instance: Arc<Instance>, config: &Config) -> Option<SendableSolution> {
+    let (_tx_sync, rx_sync) = channel();
+    let (tx_solution_report, _rx_solution_report) = channel();
+    let local_sol_collector = LocalSolCollector::new(instance.clone(), rx_sync, tx_solution_report, crate::COST_COMPARATOR);
+    let mut gdrr = GDRR::new(&instance, config, local_sol_collector);
+
+    gdrr.first_feasible().map(|solution| SendableSolution::new(instance.clone(), &solution))
+}
+
+/// Runs the search on the calling thread and returns up to k structurally distinct complete solutions encountered during the search,
+/// in which at least `diverseMinDistance` (default 1) parts are placed differently between every pair of solutions,
+/// sorted by cost and with a material cost within `diverseCostTolerance` (default 5%) of the best one, see DiverseArchive.
+/// Fails if the search is not limited by the config, see search_with_archive.
+pub fn optimize_diverse(instance: Arc<Instance>, config: &Config, k: usize) -> Result<Vec<SendableSolution>, ConfigError> {
+    let archive = Rc::new(RefCell::new(DiverseArchive::new(instance.clone(), k, config.diverse_cost_tolerance.unwrap_or(0.05), config.diverse_min_distance.unwrap_or(1))));
+    search_with_archive(&instance, config, archive.clone())?;
+
+    let mut archive = archive.borrow_mut();
+    Ok(archive.take_solutions())
+}
+
+/// Runs the search on the calling thread and returns the complete solutions encountered during the search
+/// which are not dominated on the `paretoObjectives` (default: minimal material cost and cut count), see ParetoArchive.
+/// The solutions are sorted by the values of the objectives, in order of the objectives.
+/// Fails if the list of objectives is empty, or if the search is not limited by the config, see search_with_archive.
+pub fn optimize_pareto(instance: Arc<Instance>, config: &Config) -> Result<Vec<SendableSolution>, ConfigError> {
+    let objectives = match config.pareto_objectives.clone() {
+        Some(objectives) if objectives.is_empty() => return Err(ConfigError::NoParetoObjectives),
+        Some(objectives) => objectives,
+        None => vec![
+            Objective::new(Metric::MaterialCost, Direction::Minimize),
+            Objective::new(Metric::CutCount, Direction::Minimize),
+        ],
+    };
+    let archive = Rc::new(RefCell::new(ParetoArchive::new(instance.clone(), objectives)));
+    search_with_archive(&instance, config, archive.clone())?;
+
+    let mut archive = archive.borrow_mut();
+    Ok(archive.take_solutions())
+}
+
+/// Runs the search on the calling thread, offering every accepted solution to the archive, and returns the statistics of the search.
+/// As no global solution collector is involved, the search only terminates through maxRRIterations, maxNodeOps,
+/// targetUsage or stopOnStagnation, one of which needs to be configured.
+fn search_with_archive(instance: &Arc<Instance>, config: &Config, archive: Rc<RefCell<dyn SolutionArchive>>) -> Result<SearchStats, ConfigError> {
+    search_on_calling_thread(instance, config, |gdrr| gdrr.set_archive(archive))
+}
+
+/// Runs the search on the calling thread after `setup` configured it, with a local solution collector which is not connected to a global one.
+/// Fails if the search is not limited by the config, see search_with_archive.
+fn search_on_calling_thread(instance: &Arc<Instance>, config: &Config, setup: impl FnOnce(&mut GDRR)) -> Result<SearchStats, ConfigError> {
+    if config.max_rr_iterations.is_none() && config.max_node_ops.is_none() && config.target_usage.is_none() && config.stop_on_stagnation != Some(true) {
+        return Err(ConfigError::UnboundedSearch);
+    }
+    let (_tx_sync, rx_sync) = channel();
+    let (tx_solution_report, _rx_solution_report) = channel();
+    let local_sol_collector = LocalSolCollector::new(instance.clone(), rx_sync, tx_solution_report, crate::COST_COMPARATOR);
+    let mut gdrr = GDRR::new(instance, config, local_sol_collector);
+    setup(&mut gdrr);
+    Ok(gdrr.lahc())
+}
+
+/// Runs the search on the calling thread and returns the best complete solution encountered for every number of sheets, see SheetCountArchive.
+/// As the search lowers its material limit after every improvement, mostly solutions with fewer sheets than the best one found so far are encountered,
+/// so the solutions with more sheets than the first complete solution are typically missing.
+/// Fails if the search is not limited by the config, see search_with_archive.
+pub fn optimize_by_sheet_count(instance: Arc<Instance>, config: &Config) -> Result<BTreeMap<usize, SendableSolution>, ConfigError> {
+    let archive = Rc::new(RefCell::new(SheetCountArchive::new(instance.clone())));
+    search_with_archive(&instance, config, archive.clone())?;
+
+    let mut archive = archive.borrow_mut();
+    Ok(archive.take_solutions())
+}
+
+/// Runs the search on the calling thread and returns the move log of its best solution (complete if one was found), see MoveLog.
+/// MoveLog::replay reproduces the best solution from the moves, MoveLog::best_solution is the solution reported by the search.
+/// Fails if the search is not limited by the config, see search_with_archive.
+pub fn optimize_with_move_log(instance: Arc<Instance>, config: &Config) -> Result<MoveLog, ConfigError> {
+    let move_log = Rc::new(RefCell::new(MoveLog::new(instance.clone())));
+    search_on_calling_thread(&instance, config, |gdrr| gdrr.set_move_log(move_log.clone()))?;
+
+    Ok(move_log.replace(MoveLog::new(instance.clone())))
+}
+
+/// Solves instances with a single parttype and a single sheettype directly, without search.
+/// Every sheet is cut into strips across the sheet (in either direction, unless the first cut orientation is fixed),
+/// each filled with as many parts as fit in one rotation, using the numbers of strips of both rotations which fit the most parts on a sheet.
+/// Sizes include the kerf and the strips lie inside the margins. All sheets are cut alike, the last one only contains the first parts of the pattern.
+/// The strip pattern is not necessarily the densest guillotine pattern (e.g. rotations are not mixed within a strip), so the search can occasionally do better.
+/// Returns None for instances with composite parts, edge affinities or waste constraints, a stage limit below 2 or insufficient stock,
+/// and when the config bounds the number of sheets.
+pub fn optimize_single_parttype(instance: Arc<Instance>, config: &Config) -> Option<SendableSolution> {
+    let ([(parttype, demand)], [(sheettype, stock)]) = (instance.parts().as_slice(), instance.sheets().as_slice()) else {
+        return None;
+    };
+    if parttype.composite().is_some() || parttype.edge_affinity().is_some() || instance.min_waste_dimension().is_some() || instance.max_waste_aspect_ratio().is_some()
+        || sheettype.max_stages() < 2 || config.min_layouts.is_some() || config.max_layouts.is_some() {
+        return None;
+    }
+
+    let rotations = match parttype.fixed_rotation() {
+        Some(rotation) => vec![*rotation],
+        None => vec![Rotation::Default, Rotation::Rotated],
+    };
+    let orientations = match instance.forced_first_cut().or(sheettype.fixed_first_cut_orientation()) {
+        Some(orientation) => vec![orientation],
+        None => vec![Orientation::Horizontal, Orientation::Vertical],
+    };
+    let pattern = orientations.into_iter()
+        .map(|orientation| strip_pattern(parttype, &rotations, &sheettype.layout_size(), orientation))
+        .reduce(|best, pattern| if pattern.len() > best.len() { pattern } else { best })
+        .unwrap();
+    if pattern.is_empty() || demand.div_ceil(pattern.len()) > *stock {
+        return None;
+    }
+
+    leftover_valuator::set_power(config.leftover_valuation_power);
+    let empty_node_order = config.empty_node_order.unwrap_or_default();
+    let mut problem = Problem::new(&instance, empty_node_order);
+    let margins = sheettype.margins();
+    let placements = pattern.iter()
+        .map(|&(x, y, rotation)| {
+            let size = parttype.size_for(rotation);
+            PlacedPart { parttype_id: parttype.id(), x: margins.left + x, y: margins.top + y, width: size.width(), height: size.height(), rotation }
+        })
+        .collect_vec();
+    let build_layout = |id: usize, n_parts: usize| Layout::try_from_placements(id, sheettype, &placements[..n_parts], empty_node_order, &instance)
+        .expect("strip patterns can be cut by guillotine cuts");
+
+    let full_layout = build_layout(problem.next_layout_id(), pattern.len());
+    for _ in 0..demand / pattern.len() {
+        let id = problem.next_layout_id();
+        problem.register_layout(full_layout.clone_with_id(id));
+    }
+    if demand % pattern.len() > 0 {
+        let id = problem.next_layout_id();
+        problem.register_layout(build_layout(id, demand % pattern.len()));
+    }
+
+    let solution = problem.create_solution(&None, None);
+    Some(SendableSolution::new(instance.clone(), &solution))
+}
+
+/// Positions (relative to the top node) and rotations of the parts in the strip pattern of optimize_single_parttype with the given first cut orientation,
+/// in order of the strips
+fn strip_pattern(parttype: &PartType, rotations: &[Rotation], layout_size: &Size, orientation: Orientation) -> Vec<(u64, u64, Rotation)> {
+    //dimensions along and across the strips
+    let oriented = |width: u64, height: u64| match orientation {
+        Orientation::Horizontal => (width, height),
+        Orientation::Vertical => (height, width),
+    };
+    let (strip_length, total_breadth) = oriented(layout_size.width(), layout_size.height());
+    let part_dims = |rotation: Rotation| oriented(parttype.size_for(rotation).width(), parttype.size_for(rotation).height());
+    let parts_per_strip = |rotation: Rotation| (strip_length / part_dims(rotation).0) as usize;
+
+    //strips of the first rotation, followed by as many strips of the last rotation as fit in the remaining breadth
+    let (first, last) = (rotations[0], rotations[rotations.len() - 1]);
+    let (n_first, n_last) = (0..=total_breadth / part_dims(first).1)
+        .map(|n_first| (n_first, (total_breadth - n_first * part_dims(first).1) / part_dims(last).1))
+        .max_by_key(|&(n_first, n_last)| (n_first as usize * parts_per_strip(first) + n_last as usize * parts_per_strip(last), Reverse(n_first + n_last)))
+        .unwrap();
+
+    let strips = itertools::repeat_n(first, n_first as usize).chain(itertools::repeat_n(last, n_last as usize));
+    let mut breadth_offset = 0;
+    let mut pattern = vec![];
+    for rotation in strips {
+        let (part_length, part_breadth) = part_dims(rotation);
+        for i in 0..parts_per_strip(rotation) as u64 {
+            let (x, y) = oriented(i * part_length, breadth_offset);
+            pattern.push((x, y, rotation));
+        }
+        breadth_offset += part_breadth;
+    }
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::path::PathBuf;
+    use std::rc::Rc;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+
+    use itertools::Itertools;
+    use serde_json::json;
+
+    use crate::core::cost::Cost;
+    use crate::core::entities::layout::Layout;
+    use crate::core::insertion::insertion_option::InsertionOption;
+    use crate::core::insertion::node_blueprint::NodeBlueprint;
+    use crate::core::kerf::Kerf;
+    use crate::core::layout_index::LayoutIndex;
+    use crate::core::leftover_valuator;
+    use crate::core::orientation::Orientation;
+    use crate::io::parser;
+    use crate::optimization::config::{Config, ConfigError};
+    use crate::optimization::gdrr::{self, GDRR};
+    use crate::optimization::instance::Instance;
+    use crate::optimization::rr::insertion_option_cache::InsertionOptionCache;
+    use crate::optimization::search_stats::SearchStats;
+    use crate::optimization::sol_collectors::pareto_archive::ParetoArchive;
+    use crate::optimization::solutions::objective::{self, Direction, Metric, Objective};
+    use crate::optimization::solutions::sendable_solution::SendableSolution;
+    use crate::optimization::solutions::solution::Solution;
+    use crate::optimization::solutions::solution_diff::{Placement, SolutionDiff};
+    use crate::util::assertions;
+    use crate::util::test_util;
+
+    fn sheettype_ids(gdrr: &GDRR) -> Vec<usize> {
+        gdrr.problem.layouts().iter().map(|(_, l)| l.sheettype().id()).sorted().collect_vec()
+    }
+
+    //after the 70x70 part, the 60x60 part does not fit in the open (70x70) sheet
+
+    #[test]
+    fn open_new_sheet_policy_opens_the_cheapest_sheettype() {
+        let instance = Arc::new(test_util::instance(&[(70, 70, 1), (60, 60, 1)], &[(200, 200, 5), (70, 70, 5)]));
+        let config = test_util::config_with(json!({"noInsertionPolicy": "openNewSheet"}));
+        let (collector, _tx, _rx) = test_util::local_sol_collector(&instance);
+        let mut gdrr = GDRR::new(&instance, &config, collector);
+
+        gdrr.recreate(i128::MAX, u64::MAX);
+        assert_eq!(sheettype_ids(&gdrr), vec![1, 1]);
+        assert_eq!(gdrr.problem.cost().part_area_excluded, 0);
+    }
+
+    #[test]
+    fn sheettype_priority_breaks_ties_between_sheettypes() {
+        //both sheettypes are equally suitable for the part
+        let instance = Arc::new(test_util::instance(&[(50, 50, 1)], &[(100, 100, 5), (100, 100, 5)]));
+        for (priority, expected) in [(json!([1]), 1), (json!([0, 1]), 0), (json!([2, 1, 0]), 1)] {
+            let config = test_util::config_with(json!({"sheettypePriority": priority}));
+            let (collector, _tx, _rx) = test_util::local_sol_collector(&instance);
+            let mut gdrr = GDRR::new(&instance, &config, collector);
+
+            gdrr.recreate(i128::MAX, u64::MAX);
+            assert_eq!(sheettype_ids(&gdrr), vec![expected]);
+        }
+    }
+
+    #[test]
+    fn max_layouts_caps_the_number_of_sheets() {
+        //every 60x60 part needs its own sheet
+        let instance = Arc::new(test_util::instance(&[(60, 60, 5)], &[(100, 100, 10)]));
+        let config = test_util::config_with(json!({"maxLayouts": 2}));
+        let (collector, _tx, _rx) = test_util::local_sol_collector(&instance);
+        let mut gdrr = GDRR::new(&instance, &config, collector);
+
+        gdrr.recreate(i128::MAX, u64::MAX);
+        assert_eq!(gdrr.problem.layouts().len(), 2);
+        assert_eq!(gdrr.problem.cost().part_area_excluded, 3 * 60 * 60);
+    }
+
+    #[test]
+    fn min_layouts_spreads_the_parts_over_the_sheets() {
+        //all parts fit together in a single sheet
+        let instance = Arc::new(test_util::instance(&[(20, 20, 4)], &[(100, 100, 10)]));
+        let config = test_util::config_with(json!({"minLayouts": 3}));
+        let (collector, _tx, _rx) = test_util::local_sol_collector(&instance);
+        let mut gdrr = GDRR::new(&instance, &config, collector);
+
+        gdrr.recreate(i128::MAX, u64::MAX);
+        assert_eq!(gdrr.problem.layouts().len(), 3);
+        assert_eq!(gdrr.problem.cost().part_area_excluded, 0);
+    }
+
+    #[test]
+    fn drop_part_policy_leaves_the_part_unplaced() {
+        let instance = Arc::new(test_util::instance(&[(70, 70, 1), (60, 60, 1)], &[(200, 200, 5), (70, 70, 5)]));
+        let config = test_util::config_with(json!({"noInsertionPolicy": "dropPart"}));
+        let (collector, _tx, _rx) = test_util::local_sol_collector(&instance);
+        let mut gdrr = GDRR::new(&instance, &config, collector);
+
+        gdrr.problem.register_layout(test_util::layout(&instance, 1, &[(0, 0, 0)]));
+        gdrr.recreate(i128::MAX, u64::MAX);
+        assert_eq!(sheettype_ids(&gdrr), vec![1]);
+        assert_eq!(gdrr.problem.cost().part_area_excluded, 60 * 60);
+        assert_eq!(gdrr.insertion_failure, None);
+    }
+
+    #[test]
+    fn fail_policy_stops_the_search() {
+        //the 150x150 part fits no sheettype at all
+        let instance = Arc::new(test_util::instance(&[(70, 70, 1), (150, 150, 1)], &[(100, 100, 5)]));
+        let config = test_util::config_with(json!({"noInsertionPolicy": "fail"}));
+        let (collector, _tx, _rx) = test_util::local_sol_collector(&instance);
+        let mut gdrr = GDRR::new(&instance, &config, collector);
+
+        let stats = gdrr.lahc();
+        assert_eq!(stats.insertion_failure, Some(1));
+        assert_eq!(stats.n_iterations, 0);
+    }
+
+    #[test]
+    fn fail_policy_opens_new_sheets_as_usual() {
+        //needs two sheets, after the first complete solution the material budget excludes new sheets, which is not a failure
+        let instance = Arc::new(test_util::instance(&[(70, 70, 1), (60, 60, 1)], &[(100, 100, 5)]));
+        let config = test_util::config_with(json!({"noInsertionPolicy": "fail", "maxRRIterations": 50}));
+        let (collector, _tx, _rx) = test_util::local_sol_collector(&instance);
+        let mut gdrr = GDRR::new(&instance, &config, collector);
+
+        let stats = gdrr.lahc();
+        assert_eq!(stats.insertion_failure, None);
+        assert_eq!(stats.n_iterations, 50);
+    }
+
+    #[test]
+    fn sheettype_penalty_prefers_a_single_material() {
+        //the 90x90 part needs a 100x100 sheet, the 50x50 part fits the cheaper 60x60 sheet or another 100x100 sheet
+        let distinct_sheettypes = |sheettype_penalty: u64| {
+            leftover_valuator::set_power(2.0);
+            let parts = vec![(test_util::parttype(0, 90, 90), 1), (test_util::parttype(1, 50, 50), 1)];
+            let sheets = vec![(test_util::sheettype(0, 100, 100), 5), (test_util::sheettype(1, 60, 60), 5)];
+            let instance = Arc::new(Instance::new(parts, sheets, sheettype_penalty, false));
+            let solution = test_util::solve(instance, &test_util::config());
+            solution.layouts().iter().map(|l| l.sheettype_id()).sorted().dedup().collect_vec()
+        };
+
+        //100x100 + 60x60 (13600) is cheaper than 2 x 100x100 (20000)
+        assert_eq!(distinct_sheettypes(0), vec![0, 1]);
+        //unless every distinct sheettype costs an additional 10000
+        assert_eq!(distinct_sheettypes(10_000), vec![0]);
+    }
+
+    #[test]
+    fn low_priority_parts_are_dropped_first() {
+        //only one of the parts fits in the single sheet, the larger one would be kept without priorities
+        let included_parts = |priorities: [u32; 2]| {
+            leftover_valuator::set_power(2.0);
+            let parts = vec![
+                (test_util::parttype(0, 70, 100).with_priority(priorities[0]), 1),
+                (test_util::parttype(1, 60, 100).with_priority(priorities[1]), 1),
+            ];
+            let instance = Arc::new(Instance::new(parts, vec![(test_util::sheettype(0, 100, 100), 1)], 0, false));
+            let solution = test_util::solve(instance, &test_util::config());
+            solution.layouts().iter().flat_map(|l| Placement::extract(0, l.sheettype_id(), l.top_node())).map(|p| p.parttype_id).collect_vec()
+        };
+
+        assert_eq!(included_parts([0, 0]), vec![0]);
+        assert_eq!(included_parts([0, 1]), vec![1]);
+    }
+
+    fn search_stats(instance: &Arc<Instance>, config: &Config, seed: u64) -> SearchStats {
+        let (collector, _tx, _rx) = test_util::local_sol_collector(instance);
+        let mut gdrr = GDRR::new(instance, config, collector);
+        gdrr.reseed(seed);
+        gdrr.lahc()
+    }
+
+    #[test]
+    fn node_op_limit_is_honored_within_one_iteration() {
+        let instance = Arc::new(test_util::instance(&[(30, 20, 8), (25, 45, 6), (60, 15, 5)], &[(100, 100, 10)]));
+        let max_node_ops = 500;
+        let config = test_util::config_with(json!({"maxRRIterations": null, "maxNodeOps": max_node_ops}));
+        let stats = search_stats(&instance, &config, 7);
+        assert!(stats.n_node_ops >= max_node_ops);
+        assert_eq!(stats.insertion_failure, None);
+
+        //the same search stopped one iteration earlier has not reached the limit yet
+        let config = test_util::config_with(json!({"maxRRIterations": stats.n_iterations - 1}));
+        let earlier_stats = search_stats(&instance, &config, 7);
+        assert_eq!(earlier_stats.n_iterations, stats.n_iterations - 1);
+        assert!(earlier_stats.n_node_ops < max_node_ops);
+    }
+
+    #[test]
+    fn target_usage_stops_the_search_early() {
+        let instance = Arc::new(test_util::instance(&[(50, 50, 8)], &[(100, 100, 4)]));
+        let config = test_util::config_with(json!({"maxRRIterations": 100_000, "targetUsage": 0.5}));
+        let (collector, _tx_sync, rx_solution_report) = test_util::local_sol_collector(&instance);
+        let mut gdrr = GDRR::new(&instance, &config, collector);
+        let stats = gdrr.lahc();
+
+        assert!(stats.n_iterations < 1000);
+        assert!(test_util::best_solution(&rx_solution_report).unwrap().usage() >= 0.5);
+    }
+
+    #[test]
+    fn strip_recreate_packs_uniform_heights_into_full_rows() {
+        //every row of 100 holds one part of each length, four rows fill a sheet
+        let instance = Arc::new(test_util::instance(&[(60, 25, 4), (40, 25, 4)], &[(100, 100, 10)]));
+        let config = test_util::config_with(json!({"recreateStrategy": "strip"}));
+        let solution = test_util::solve(instance, &config);
+
+        assert_eq!(solution.n_layouts(), 1);
+        assert!(solution.usage() >= 0.95);
+    }
+
+    fn part_sizes(node: &NodeBlueprint) -> Vec<(u64, u64)> {
+        match node.parttype_id() {
+            Some(_) => vec![(node.width(), node.height())],
+            None => node.children().iter().flat_map(part_sizes).collect_vec(),
+        }
+    }
+
+    #[test]
+    fn homogeneous_strips_only_contain_parts_of_the_same_dimension() {
+        let parts = [(30, 20), (40, 30), (25, 20), (35, 15)].iter().enumerate()
+            .map(|(id, &(w, h))| (test_util::parttype(id, w, h), 4))
+            .collect();
+        leftover_valuator::set_power(2.0);
+        let instance = Arc::new(Instance::new(parts, vec![(test_util::sheettype(0, 100, 100), 10)], 0, true));
+        let solution = test_util::solve(instance, &test_util::config());
+
+        for layout in solution.layouts() {
+            let top_node = layout.top_node();
+            for strip in top_node.children() {
+                let strip_dimensions = part_sizes(strip).into_iter()
+                    .map(|(w, h)| match top_node.next_cut_orient() {
+                        Orientation::Horizontal => h,
+                        Orientation::Vertical => w,
+                    })
+                    .unique()
+                    .collect_vec();
+                assert!(strip_dimensions.len() <= 1, "strip with parts of dimensions {:?}", strip_dimensions);
+            }
+        }
+        assert_eq!(solution.cost().part_area_excluded, 0);
+    }
+
+    #[test]
+    fn stagnation_is_detected_and_stops_the_search() {
+        //a single part leaves nothing to improve after the first solution
+        let instance = Arc::new(test_util::instance(&[(50, 50, 1)], &[(100, 100, 1)]));
+        let stats = |config: serde_json::Value| {
+            let config = test_util::config_with(config);
+            let (collector, _tx, _rx) = test_util::local_sol_collector(&instance);
+            GDRR::new(&instance, &config, collector).lahc()
+        };
+
+        let stats_without_stop = stats(json!({"maxRRIterations": 2000, "stagnationWindow": 50}));
+        assert!(stats_without_stop.stagnated);
+        assert_eq!(stats_without_stop.n_iterations, 2000);
+
+        let stats = stats(json!({"maxRRIterations": 2000, "stagnationWindow": 50, "stopOnStagnation": true}));
+        assert!(stats.stagnated);
+        assert!(stats.n_iterations < 200);
+        assert_eq!(stats.iterations_since_improvement, 51);
+    }
+
+    #[test]
+    fn restarts_change_the_trajectory_deterministically() {
+        let instance = Arc::new(test_util::instance(&[(40, 30, 8), (25, 60, 6), (70, 20, 4), (15, 15, 10)], &[(100, 100, 10)]));
+        let trajectory = |config: &Config| {
+            let stats = search_stats(&instance, config, 1);
+            assert_eq!(stats.n_iterations, 100);
+            (stats.n_accepted, stats.n_improved, stats.n_node_ops)
+        };
+        let continuous = trajectory(&test_util::config_with(json!({"maxRRIterations": 100})));
+        let restarting_config = test_util::config_with(json!({"maxRRIterations": 100, "restartEvery": 25, "restartSeeds": [7, 8, 9]}));
+        let restarting = trajectory(&restarting_config);
+
+        assert_ne!(restarting, continuous);
+        assert_eq!(restarting, trajectory(&restarting_config));
+    }
+
+    #[test]
+    fn shallow_insertions_are_preferred_between_equal_costs() {
+        //the 30x30 part fits exactly in two 30x30 leftovers: next to the 70x30 part (level 2) and below the 30x40 part (level 3)
+        let instance = Arc::new(test_util::instance(&[(70, 70, 1), (30, 40, 1), (70, 30, 1), (30, 30, 1)], &[(100, 100, 1)]));
+        let part_level = |prefer_shallow: bool| {
+            let config = test_util::config_with(json!({"preferShallowInsertions": prefer_shallow, "blinkRate": 0.0}));
+            let (collector, _tx, _rx) = test_util::local_sol_collector(&instance);
+            let mut gdrr = GDRR::new(&instance, &config, collector);
+            let layout = test_util::layout(&instance, 0, &[(2, 0, 0), (0, 0, 30), (1, 70, 30)]);
+            gdrr.problem.register_layout(layout);
+            gdrr.recreate(i128::MAX, u64::MAX);
+            assert_eq!(gdrr.problem.cost().part_area_excluded, 0);
+            let (_, layout) = gdrr.problem.layouts().iter().next().unwrap();
+            layout.nodes().iter().find(|(_, n)| n.part_id() == Some(3)).unwrap().1.level()
+        };
+        assert_eq!(part_level(true), 2);
+        assert_eq!(part_level(false), 3);
+    }
+
+    #[test]
+    fn first_feasible_places_all_parts_without_search() {
+        let instance = Arc::new(test_util::instance(&[(40, 30, 10), (25, 60, 6), (70, 20, 4)], &[(100, 100, 10)]));
+        let config = test_util::config();
+        let (collector, _tx, _rx) = test_util::local_sol_collector(&instance);
+        let mut gdrr = GDRR::new(&instance, &config, collector);
+
+        let solution = gdrr.first_feasible().unwrap();
+        assert_eq!(solution.cost().part_area_excluded, 0);
+        assert!(solution.parttype_qtys().iter().all(|qty| *qty == 0));
+        //a single insertion per part, nothing was ruined
+        assert_eq!(gdrr.problem.n_node_ops(), 20);
+
+        let solution = gdrr::optimize_first_feasible(instance.clone(), &config).unwrap();
+        assert_eq!(solution.cost().part_area_excluded, 0);
+
+        //the stock cannot hold all parts
+        let infeasible = Arc::new(test_util::instance(&[(60, 60, 3)], &[(100, 100, 2)]));
+        assert!(gdrr::optimize_first_feasible(infeasible, &config).is_none());
+    }
+
+    #[test]
+    fn custom_cost_function_changes_the_chosen_solution() {
+        fn max_waste_dimension(pattern: &NodeBlueprint) -> u64 {
+            match pattern.is_empty() {
+                true => pattern.width().max(pattern.height()),
+                false => pattern.children().iter().map(max_waste_dimension).max().unwrap_or(0),
+            }
+        }
+        //minimizes the longest side of the leftovers instead of concentrating them
+        fn objective(layout: &Layout) -> Cost {
+            let pattern = NodeBlueprint::from_node(*layout.top_node_index(), layout.nodes());
+            Cost { leftover_value: -(max_waste_dimension(&pattern) as f32), ..layout.builtin_cost() }
+        }
+        let max_waste_dimensions = |instance: Instance| {
+            let solution = test_util::solve(Arc::new(instance), &test_util::config());
+            solution.layouts().iter().map(|l| max_waste_dimension(l.top_node())).collect_vec()
+        };
+
+        //the built-in cost keeps the leftover of two parts in a strip as a single 100x50 rectangle,
+        //the custom objective places them diagonally, leaving two 50x50 leftovers
+        let parts = [(50, 50, 2)];
+        assert_eq!(max_waste_dimensions(test_util::instance(&parts, &[(100, 100, 1)])), vec![100]);
+        assert_eq!(max_waste_dimensions(test_util::instance(&parts, &[(100, 100, 1)]).with_cost_function(objective)), vec![50]);
+    }
+
+    #[test]
+    fn optimize_diverse_returns_distinct_solutions_of_a_limited_search() {
+        let instance = Arc::new(test_util::instance(&[(50, 50, 2), (30, 20, 4)], &[(100, 100, 5)]));
+        let config = test_util::config_with(json!({"diverseMinDistance": 2}));
+        let solutions = gdrr::optimize_diverse(instance.clone(), &config, 3).unwrap();
+        assert!(!solutions.is_empty() && solutions.len() <= 3);
+        assert!(solutions.iter().all(|s| s.is_complete()));
+        for (a, b) in solutions.iter().tuple_combinations() {
+            assert!(SolutionDiff::new(&a.layout_patterns(), &b.layout_patterns()).n_changed_parts() >= 2);
+        }
+
+        //without any limit, the search would never terminate
+        let mut unbounded = test_util::config();
+        unbounded.max_rr_iterations = None;
+        assert_eq!(gdrr::optimize_diverse(instance, &unbounded, 3).err(), Some(ConfigError::UnboundedSearch));
+    }
+
+    #[test]
+    fn optimize_by_sheet_count_keys_complete_solutions_by_their_sheet_count() {
+        let instance = Arc::new(test_util::instance(&[(50, 50, 2), (30, 20, 4)], &[(100, 100, 5)]));
+        let solutions = gdrr::optimize_by_sheet_count(instance.clone(), &test_util::config()).unwrap();
+        assert!(!solutions.is_empty());
+        assert!(solutions.iter().all(|(n_sheets, s)| s.is_complete() && s.n_layouts() == *n_sheets));
+
+        let mut unbounded = test_util::config();
+        unbounded.max_rr_iterations = None;
+        assert_eq!(gdrr::optimize_by_sheet_count(instance, &unbounded).err(), Some(ConfigError::UnboundedSearch));
+    }
+
+    #[test]
+    fn warm_start_converges_in_fewer_iterations() {
+        let instance = Arc::new(test_util::instance(&[(40, 30, 9), (25, 60, 7), (70, 20, 5), (15, 45, 8), (55, 35, 4)], &[(100, 100, 50)]));
+        let best_material_cost = |warm_start: bool, n_iterations: usize| {
+            let config = test_util::config_with(json!({"maxRRIterations": n_iterations, "warmStart": warm_start}));
+            let (collector, _tx, rx) = test_util::local_sol_collector(&instance);
+            let mut gdrr = GDRR::new(&instance, &config, collector);
+            gdrr.reseed(0);
+            gdrr.lahc();
+            test_util::best_solution(&rx).unwrap().cost().material_cost
+        };
+
+        //the searches are deterministic, so a search with more iterations continues a shorter one
+        let iterations_to_reach = |warm_start: bool, target: u64| (1..=40).find(|&n| best_material_cost(warm_start, n) <= target).unwrap();
+
+        //the warm start reaches the best material cost of the cold start in fewer iterations
+        let target = best_material_cost(false, 40);
+        assert!(iterations_to_reach(true, target) < iterations_to_reach(false, target));
+    }
+
+    #[test]
+    fn perfect_layouts_are_never_ruined() {
+        //the 100x100 part fills its sheet, the 40x30 parts leave plenty of waste on theirs
+        let instance = Arc::new(test_util::instance(&[(100, 100, 1), (40, 30, 3)], &[(100, 100, 5)]));
+        let config = test_util::config();
+        let perfect_parts = test_util::layout(&instance, 0, &[(0, 0, 0)]).placed_parts();
+        for seed in 0..20 {
+            let (collector, _tx, _rx) = test_util::local_sol_collector(&instance);
+            let mut gdrr = GDRR::new(&instance, &config, collector);
+            gdrr.reseed(seed);
+            for parts in [vec![(0, 0, 0)], vec![(1, 0, 0), (1, 40, 0), (1, 0, 30)]] {
+                let layout = test_util::layout(&instance, 0, &parts).clone_with_id(gdrr.problem.next_layout_id());
+                gdrr.problem.register_layout(layout);
+            }
+            assert_eq!(gdrr.problem.layouts().iter().filter(|(_, l)| l.is_perfect()).count(), 1);
+
+            gdrr.ruin(0);
+            let perfect = gdrr.problem.layouts().iter().find(|(_, l)| l.is_perfect()).map(|(_, l)| l.placed_parts());
+            assert_eq!(perfect, Some(perfect_parts.clone()));
+        }
+    }
+
+    #[test]
+    fn the_pinned_first_sheettype_is_cut_first() {
+        //four parts fit on either sheettype, the pinned one is the more expensive
+        let mut json_instance = test_util::json_instance(&[(50, 50, 8)], &[(100, 100, 10), (120, 120, 10)]);
+        for first_sheettype in [0, 1] {
+            let config = test_util::config_with(json!({"firstSheettype": first_sheettype}));
+            let instance = Arc::new(parser::generate_instance(&mut json_instance, &config).unwrap());
+            let solution = test_util::solve(instance.clone(), &config);
+            assert!(solution.parttype_qtys().iter().all(|q| *q == 0));
+            assert!(solution.layouts().iter().any(|l| l.sheettype_id() == first_sheettype));
+
+            let json_solution = parser::generate_json_solution(&json_instance, &solution, &PathBuf::from("config.json"));
+            assert_eq!(json_solution.cutting_patterns[0].object, first_sheettype);
+        }
+    }
+
+    #[test]
+    fn cancellation_from_another_thread_stops_the_search_promptly() {
+        let instance = Arc::new(test_util::instance(&[(40, 30, 9), (25, 60, 7), (70, 20, 5)], &[(100, 100, 50)]));
+        let max_rr_iterations = 1_000_000;
+        let config = test_util::config_with(json!({"maxRRIterations": max_rr_iterations}));
+        let (collector, _tx, rx) = test_util::local_sol_collector(&instance);
+        let mut gdrr = GDRR::new(&instance, &config, collector);
+        let flag = Arc::new(AtomicBool::new(false));
+        gdrr.set_cancellation_flag(flag.clone());
+
+        //the client cancels as soon as the warm start solution is reported, the receiver is handed back to keep the channel open
+        let canceller = std::thread::spawn(move || {
+            rx.recv().unwrap();
+            flag.store(true, AtomicOrdering::Relaxed);
+            rx
+        });
+        let stats = gdrr.lahc();
+        let _rx = canceller.join().unwrap();
+        assert!(stats.n_iterations < 10_000, "{} iterations after cancellation", stats.n_iterations);
+    }
+
+    #[test]
+    fn a_search_cancelled_in_advance_keeps_the_warm_start_solution() {
+        let instance = Arc::new(test_util::instance(&[(40, 30, 9), (25, 60, 7), (70, 20, 5)], &[(100, 100, 50)]));
+        let config = test_util::config();
+        let (collector, _tx, rx) = test_util::local_sol_collector(&instance);
+        let mut gdrr = GDRR::new(&instance, &config, collector);
+        gdrr.set_cancellation_flag(Arc::new(AtomicBool::new(true)));
+
+        //the flag is checked before every iteration, the solution reported by the warm start remains valid
+        assert_eq!(gdrr.lahc().n_iterations, 0);
+        let solution = test_util::best_solution(&rx).unwrap();
+        assert!(solution.parttype_qtys().iter().all(|q| *q == 0));
+        assert!(solution.layouts().iter().all(|l| assertions::children_node_blueprints_fit(l.top_node())));
+    }
+
+    #[test]
+    fn pareto_solutions_are_mutually_non_dominated() {
+        let mut json_instance = test_util::json_instance(&[(45, 30, 10), (30, 20, 10)], &[(100, 100, 10), (100, 50, 10)]);
+        let config = test_util::config();
+        let instance = Arc::new(parser::generate_instance(&mut json_instance, &config).unwrap());
+        //the default objectives of optimize_pareto
+        let objectives = vec![Objective::new(Metric::MaterialCost, Direction::Minimize), Objective::new(Metric::CutCount, Direction::Minimize)];
+        let assert_non_dominated = |solutions: &[SendableSolution]| {
+            let values = solutions.iter().map(|s| objectives.iter().map(|o| o.evaluate(s)).collect_vec()).collect_vec();
+            assert!(solutions.iter().all(|s| s.is_complete()));
+            for (a, b) in values.iter().tuple_combinations() {
+                assert!(a != b && !objective::dominates(a, b) && !objective::dominates(b, a));
+            }
+            //sorted by the first objective
+            assert!(values.windows(2).all(|w| w[0][0] <= w[1][0]));
+        };
+
+        let solutions = gdrr::optimize_pareto(instance.clone(), &config).unwrap();
+        assert!(!solutions.is_empty());
+        assert_non_dominated(&solutions);
+
+        //the same search with a seed for which it encounters a trade-off between both objectives
+        let archive = Rc::new(RefCell::new(ParetoArchive::new(instance.clone(), objectives.clone())));
+        let (collector, _tx, _rx) = test_util::local_sol_collector(&instance);
+        let mut gdrr = GDRR::new(&instance, &config, collector);
+        gdrr.reseed(0);
+        gdrr.set_archive(archive.clone());
+        gdrr.lahc();
+        let solutions = archive.borrow_mut().take_solutions();
+        assert!(solutions.len() > 1);
+        assert_non_dominated(&solutions);
+
+        let without_objectives = test_util::config_with(json!({"paretoObjectives": []}));
+        assert_eq!(gdrr::optimize_pareto(instance, &without_objectives).err(), Some(ConfigError::NoParetoObjectives));
+    }
+
+    #[test]
+    fn parallel_evaluation_selects_the_same_insertions() {
+        let instance = Arc::new(test_util::instance(&[(30, 20, 12), (25, 45, 8), (60, 15, 6), (10, 10, 20)], &[(100, 100, 10)]));
+        let placements = |parallel_evaluation_threshold: Option<usize>| {
+            let config = test_util::config_with(json!({"maxRRIterations": 100, "parallelEvaluationThreshold": parallel_evaluation_threshold}));
+            let (collector, _tx, rx) = test_util::local_sol_collector(&instance);
+            let mut gdrr = GDRR::new(&instance, &config, collector);
+            gdrr.reseed(3);
+            gdrr.lahc();
+            let solution = test_util::best_solution(&rx).unwrap();
+            (solution.cost().clone(), solution.layouts().iter().enumerate()
+                .flat_map(|(i, l)| Placement::extract(i, l.sheettype_id(), l.top_node()))
+                .collect_vec())
+        };
+
+        let sequential = placements(None);
+        assert_eq!(placements(Some(0)), sequential);
+        assert_eq!(placements(Some(20)), sequential);
+    }
+
+    #[test]
+    fn initial_sheets_spread_the_parts_evenly() {
+        //36 parts need three sheets, which fit 16 each
+        let instance = Arc::new(test_util::instance(&[(5, 5, 36)], &[(20, 20, 5)]));
+        let layout_usages = |overrides: serde_json::Value| {
+            let config = test_util::config_with(overrides);
+            let (collector, _tx, rx) = test_util::local_sol_collector(&instance);
+            let mut gdrr = GDRR::new(&instance, &config, collector);
+            gdrr.reseed(0);
+            gdrr.lahc();
+            let solution = test_util::best_solution(&rx).unwrap();
+            solution.layout_usages().into_iter().sorted_by(|a, b| a.total_cmp(b)).collect_vec()
+        };
+
+        assert_eq!(layout_usages(json!({})), vec![0.25, 1.0, 1.0]);
+        assert_eq!(layout_usages(json!({"initialSheets": [0, 0, 0]})), vec![0.75, 0.75, 0.75]);
+    }
+
+    #[test]
+    fn insertion_orders_recreate_the_ruined_state_differently() {
+        //the 35x35 parts have the largest area, the 80x10 parts the largest perimeter
+        let instance = Arc::new(test_util::instance(&[(80, 10, 4), (35, 35, 4), (20, 20, 4)], &[(100, 100, 5)]));
+        let recreate = |insertion_order: &str, seed: u64| {
+            let config = test_util::config_with(json!({"insertionOrder": insertion_order, "blinkRate": 0.0}));
+            let (collector, _tx, _rx) = test_util::local_sol_collector(&instance);
+            let mut gdrr = GDRR::new(&instance, &config, collector);
+            gdrr.reseed(seed);
+            let layout = test_util::layout(&instance, 0, &[(0, 0, 0), (1, 0, 10), (2, 35, 10)]);
+            gdrr.problem.register_layout(layout);
+            gdrr.recreate(i128::MAX, u64::MAX);
+            assert_eq!(gdrr.problem.cost().part_area_excluded, 0);
+            gdrr.problem.layouts().iter().map(|(_, l)| l.placed_parts()).collect_vec()
+        };
+
+        assert_ne!(recreate("largestAreaFirst", 0), recreate("largestPerimeterFirst", 0));
+        assert_ne!(recreate("largestAreaFirst", 0), recreate("byPriority", 0));
+        //the random order is determined by the seed
+        assert_eq!(recreate("random", 4), recreate("random", 4));
+    }
+
+    #[test]
+    fn single_parttype_fast_path_matches_the_search_without_inserting_parts() {
+        let config = test_util::config_with(json!({"maxRRIterations": 100}));
+        for kerf in [Kerf::default(), Kerf::uniform(2)] {
+            let instance = Arc::new(test_util::instance_with_kerf(&[(30, 20, 20)], &[(100, 100, 10)], kerf));
+            let fast_path = gdrr::optimize_single_parttype(instance.clone(), &config).unwrap();
+
+            let (collector, _tx, rx) = test_util::local_sol_collector(&instance);
+            let mut gdrr = GDRR::new(&instance, &config, collector);
+            let stats = gdrr.lahc();
+            let searched = test_util::best_solution(&rx).unwrap();
+
+            assert!(fast_path.is_complete());
+            assert!(fast_path.n_layouts() <= searched.n_layouts(), "{} > {} sheets", fast_path.n_layouts(), searched.n_layouts());
+            //the fast path builds the pattern once, the search inserts every part many times over
+            assert!(stats.n_node_ops > 10 * 20, "{} node operations", stats.n_node_ops);
+        }
+
+        //a second parttype requires the search
+        let instance = Arc::new(test_util::instance(&[(30, 20, 40), (20, 20, 1)], &[(100, 100, 10)]));
+        assert!(gdrr::optimize_single_parttype(instance, &config).is_none());
+    }
+
+    #[test]
+    fn clustering_weight_reduces_the_spread_of_the_parttypes() {
+        let json_instance = test_util::json_instance(&[(30, 20, 5), (20, 20, 6), (25, 15, 6), (15, 40, 4)], &[(100, 100, 10)]);
+        //average over all sheets and parttypes of the area of the bounding box of the parts of a parttype on a sheet not covered by them
+        let average_spread = |clustering_weight: f32, seed: u64| {
+            let config = test_util::config_with(json!({"clusteringWeight": clustering_weight, "maxRRIterations": 100}));
+            let instance = Arc::new(parser::generate_instance(&mut json_instance.clone(), &config).unwrap());
+            let (collector, _tx_sync, rx_solution_report) = test_util::local_sol_collector(&instance);
+            let mut gdrr = GDRR::new(&instance, &config, collector);
+            gdrr.reseed(seed);
+            gdrr.lahc();
+            let solution = test_util::best_solution(&rx_solution_report).unwrap();
+            let spreads = solution.layouts().iter().enumerate()
+                .flat_map(|(i, l)| Placement::extract(i, l.sheettype_id(), l.top_node()))
+                .into_group_map_by(|p| (p.layout, p.parttype_id))
+                .into_values()
+                .map(|placements| {
+                    let (min_x, min_y) = (placements.iter().map(|p| p.x).min().unwrap(), placements.iter().map(|p| p.y).min().unwrap());
+                    let (max_x, max_y) = (placements.iter().map(|p| p.x + p.width).max().unwrap(), placements.iter().map(|p| p.y + p.height).max().unwrap());
+                    ((max_x - min_x) * (max_y - min_y) - placements.iter().map(|p| p.width * p.height).sum::<u64>()) as f64
+                })
+                .collect_vec();
+            spreads.iter().sum::<f64>() / spreads.len() as f64
+        };
+
+        for seed in 0..3 {
+            let (unclustered, clustered) = (average_spread(0.0, seed), average_spread(1.0, seed));
+            assert!(clustered < unclustered, "seed {}: {} >= {}", seed, clustered, unclustered);
+        }
+    }
+
+    #[test]
+    fn limiting_the_candidates_speeds_up_the_iterations() {
+        //a grid of small parts leaves hundreds of empty nodes to insert the other part in
+        let instance = Arc::new(test_util::instance(&[(20, 20, 400), (15, 15, 1)], &[(1000, 1000, 2)]));
+        let grid = (0..20).cartesian_product(0..20).map(|(i, j)| (0, 50 * i, 50 * j)).collect_vec();
+        let parttype = instance.get_parttype(1);
+        let config = test_util::config_with(json!({"maxCandidatesPerStep": 5}));
+        let (collector, _tx_sync, _rx_solution_report) = test_util::local_sol_collector(&instance);
+        let mut gdrr = GDRR::new(&instance, &config, collector);
+        gdrr.problem.register_layout(test_util::layout(&instance, 0, &grid));
+        let layouts = gdrr.problem.layouts().iter().map(|(i, l)| (LayoutIndex::Existing(i), l)).collect_vec();
+        let mut insertion_option_cache = InsertionOptionCache::new(&instance);
+        insertion_option_cache.add_for_parttypes(&[parttype], &layouts);
+        let options = insertion_option_cache.get_for_parttype(parttype).unwrap();
+        assert!(options.len() > 400);
+
+        //only the 5 options with the smallest empty nodes are evaluated first
+        gdrr.reseed(0);
+        let (first_options, remaining_options) = GDRR::most_promising_options(options, 5, &mut gdrr.problem, false);
+        assert_eq!((first_options.len(), remaining_options.len()), (5, options.len() - 5));
+        let node_area = |option: &InsertionOption| gdrr.problem.get_layout(option.layout_index()).nodes()[*option.original_node_index()].area();
+        let largest_candidate = first_options.iter().map(|o| node_area(o)).max().unwrap();
+        assert!(remaining_options.iter().all(|o| node_area(o) >= largest_candidate));
+
+        //one of them is selected, as the candidates are sampled with the seeded rng of the problem
+        gdrr.reseed(0);
+        let blueprint = GDRR::select_insertion_blueprint(parttype, &insertion_option_cache, i128::MAX, &mut gdrr.problem, &config, &gdrr.cost_comparator, false).unwrap();
+        assert!(first_options.iter().any(|o| o.original_node_index() == blueprint.original_node_index()));
+    }
+
+    #[test]
+    fn urgency_weight_moves_the_urgent_parts_to_the_first_sheets() {
+        leftover_valuator::set_power(2.0);
+        //urgent parts per layout (in cutting order) after all parts are placed on the two sheets, the first one already contains an urgent part and the second one is fuller
+        let urgent_parts = |urgency_weight: f32| {
+            let parts = vec![(test_util::parttype(0, 60, 60), 2), (test_util::parttype(1, 40, 40), 1), (test_util::parttype(2, 20, 20).with_urgency(1), 8)];
+            let instance = Arc::new(Instance::new(parts, vec![(test_util::sheettype(0, 100, 100), 2)], 0, false).with_urgency_weight(urgency_weight));
+            let config = test_util::config();
+            let (collector, _tx_sync, _rx_solution_report) = test_util::local_sol_collector(&instance);
+            let mut gdrr = GDRR::new(&instance, &config, collector);
+            for parts in [[(0, 0, 0), (2, 60, 0)], [(0, 0, 0), (1, 60, 0)]] {
+                gdrr.problem.register_layout(test_util::layout(&instance, 0, &parts));
+            }
+            gdrr.recreate_with(0, u64::MAX, true);
+            assert_eq!(gdrr.problem.cost().part_area_excluded, 0);
+            assert_eq!(gdrr.problem.layouts().len(), 2);
+            gdrr.problem.urgency_ranking().iter()
+                .map(|(i, _)| gdrr.problem.layouts()[*i].placed_parts().iter().filter(|p| p.parttype_id == 2).count())
+                .collect_vec()
+        };
+
+        let (unweighted, weighted) = (urgent_parts(0.0), urgent_parts(100.0));
+        assert!(unweighted[1] > 0, "{:?}", unweighted);
+        assert_eq!(weighted, vec![8, 0]);
+    }
+}
@@ -1,5 +1,21 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use itertools::Itertools;
+
+use crate::core::empty_node_order::EmptyNodeOrder;
+use crate::core::entities::layout::{CostFunction, Layout};
 use crate::core::entities::parttype::PartType;
 use crate::core::entities::sheettype::SheetType;
+use crate::core::kerf::Kerf;
+use crate::core::orientation::Orientation;
+use crate::core::size::Size;
+use crate::io::instance_error::InstanceError;
+use crate::io::packlib;
+use crate::optimization::config::Config;
+use crate::optimization::gdrr;
+use crate::optimization::precheck::PrecheckReport;
+use crate::optimization::solutions::solution::Solution;
 use crate::util::assertions;
 
 #[derive(Debug)]
@@ -9,10 +25,22 @@ pub struct Instance {
     sheets: Vec<(SheetType, usize)>,
     total_part_area: u64,
     total_part_qty: usize,
+    sheettype_penalty: u64,
+    sheet_open_penalty: u64,
+    clustering_weight: f32,
+    urgency_weight: f32,
+    homogeneous_strips: bool,
+    min_waste_dimension: Option<u64>,
+    max_waste_aspect_ratio: Option<f64>,
+    max_parttypes_per_sheet: Option<usize>,
+    first_sheettype: Option<usize>,
+    forced_first_cut: Option<Orientation>,
+    unit: Option<String>,
+    cost_function: Option<CostFunction>,
 }
 
 impl Instance {
-    pub fn new(parts: Vec<(PartType, usize)>, sheets: Vec<(SheetType, usize)>) -> Self {
+    pub fn new(parts: Vec<(PartType, usize)>, sheets: Vec<(SheetType, usize)>, sheettype_penalty: u64, homogeneous_strips: bool) -> Self {
         // The ID's of parts and sheets must match their respective indices in the vectors.
         assert!(assertions::instance_parttypes_and_sheettypes_ids_correct(&parts, &sheets));
 
@@ -24,9 +52,278 @@ impl Instance {
             sheets,
             total_part_area,
             total_part_qty,
+            sheettype_penalty,
+            sheet_open_penalty: 0,
+            clustering_weight: 0.0,
+            urgency_weight: 0.0,
+            homogeneous_strips,
+            min_waste_dimension: None,
+            max_waste_aspect_ratio: None,
+            max_parttypes_per_sheet: None,
+            first_sheettype: None,
+            forced_first_cut: None,
+            unit: None,
+            cost_function: None,
+        }
+    }
+
+    /// Replaces the built-in cost of every layout by a custom objective, which is used wherever the cost of a layout is evaluated.
+    /// The cost of a layout is cached and only recalculated after it is modified, but this still happens at least once every iteration
+    /// for every changed layout. During recreate, the function is also evaluated for every candidate insertion, on a copy of its layout,
+    /// so an expensive function severely reduces the number of iterations per second.
+    pub fn with_cost_function(mut self, cost_function: CostFunction) -> Self {
+        self.cost_function = Some(cost_function);
+        self
+    }
+
+    /// Adds a fixed cost to the material cost of a solution for every sheet it uses, on top of the value of the sheet.
+    /// Unlike the value, the penalty does not depend on the sheettype, making the search prefer filling the open sheets before opening additional ones.
+    pub fn with_sheet_open_penalty(mut self, sheet_open_penalty: u64) -> Self {
+        self.sheet_open_penalty = sheet_open_penalty;
+        self
+    }
+
+    /// Rewards grouping the parts of the same parttype on a sheet, see Layout::clustering_penalty
+    pub fn with_clustering_weight(mut self, clustering_weight: f32) -> Self {
+        assert!(clustering_weight >= 0.0, "the clustering weight cannot be negative");
+        self.clustering_weight = clustering_weight;
+        self
+    }
+
+    /// Rewards consolidating the urgent parts on the first layouts to be cut, see Problem::urgency_penalty
+    pub fn with_urgency_weight(mut self, urgency_weight: f32) -> Self {
+        assert!(urgency_weight >= 0.0, "the urgency weight cannot be negative");
+        self.urgency_weight = urgency_weight;
+        self
+    }
+
+    /// Forbids insertions creating empty nodes thinner than the given dimension, see Node::waste_allows
+    pub fn with_min_waste_dimension(mut self, min_waste_dimension: u64) -> Self {
+        self.min_waste_dimension = Some(min_waste_dimension);
+        self
+    }
+
+    /// Forbids insertions creating empty nodes more elongated than the given aspect ratio (long side / short side), see Node::creates_elongated_waste
+    pub fn with_max_waste_aspect_ratio(mut self, max_waste_aspect_ratio: f64) -> Self {
+        assert!(max_waste_aspect_ratio >= 1.0, "an aspect ratio is at least 1.0");
+        self.max_waste_aspect_ratio = Some(max_waste_aspect_ratio);
+        self
+    }
+
+    /// Limits the number of different parttypes on a single sheet, see Layout::parttype_allowed
+    pub fn with_max_parttypes_per_sheet(mut self, max_parttypes_per_sheet: usize) -> Self {
+        self.max_parttypes_per_sheet = Some(max_parttypes_per_sheet);
+        self
+    }
+
+    /// Pins the sheettype of the first sheet to be cut (e.g. the one already loaded in the machine):
+    /// as long as no layout of this sheettype exists, it is preferred over all others when a new layout is opened, if it is in stock and fits the part.
+    /// Once opened, ruin never releases the last layout of this sheettype unless it is the only layout left.
+    /// Layouts of this sheettype come first in the exported cutting patterns.
+    pub fn with_first_sheettype(mut self, sheettype_id: usize) -> Self {
+        assert!(sheettype_id < self.sheets.len(), "first sheettype {} does not exist", sheettype_id);
+        self.first_sheettype = Some(sheettype_id);
+        self
+    }
+
+    /// Forces the orientation of the first cut of every layout, overriding the fixed first cut orientation of the sheettypes
+    pub fn with_forced_first_cut(mut self, orientation: Orientation) -> Self {
+        self.forced_first_cut = Some(orientation);
+        self
+    }
+
+    /// Labels the unit of all dimensions (e.g. "mm"), which is only metadata echoed in the exports
+    pub fn with_unit(mut self, unit: String) -> Self {
+        self.unit = Some(unit);
+        self
+    }
+
+    /// Reads an instance in the 2DPackLib format, see io::packlib::read_2dpacklib.
+    /// Rotation is allowed and no kerf or other constraints are applied,
+    /// use io::parser::generate_instance on the result of read_2dpacklib to apply a config instead.
+    pub fn from_2dpacklib(path: &Path) -> Result<Instance, InstanceError> {
+        let json_instance = packlib::read_2dpacklib(path)?;
+
+        let parts = json_instance.parttypes.iter().enumerate()
+            .map(|(id, p)| (PartType::new(id, p.length, p.height, None, None, 0.0, Kerf::default()), p.demand))
+            .collect_vec();
+        let sheets = json_instance.sheettypes.iter().enumerate()
+            .map(|(id, s)| (SheetType::new(id, s.length, s.height, s.cost, None, u8::MAX, Kerf::default()), s.stock.unwrap_or(usize::MAX)))
+            .collect_vec();
+
+        Ok(Instance::new(parts, sheets, 0, false))
+    }
+
+    /// Checks the instance for obvious infeasibilities, without running any optimization
+    pub fn precheck(&self) -> PrecheckReport {
+        PrecheckReport::new(self)
+    }
+
+    /// Hash of the contents of the instance, independent of the order in which the parts and sheets are defined.
+    /// Ids are not included, all other properties of the parttypes and sheettypes, their quantities and the sheettype penalty are.
+    /// Identical instances result in the same hash across runs.
+    pub fn content_hash(&self) -> u64 {
+        let part_hashes = self.parts.iter()
+            .map(|(pt, qty)| {
+                fxhash::hash64(&(pt.width(), pt.height(), pt.fixed_rotation(), pt.preferred_rotation(), pt.preferred_rotation_penalty().to_bits(), pt.priority(), pt.kerf(), pt.composite(), pt.edge_affinity(), pt.urgency(), qty))
+            })
+            .sorted_unstable()
+            .collect_vec();
+        let sheet_hashes = self.sheets.iter()
+            .map(|(st, qty)| {
+                let density = st.density().map(|d| d.to_bits());
+                //the pinned first sheettype is identified by its properties, independent of its id
+                let pinned = self.first_sheettype == Some(st.id());
+                fxhash::hash64(&(st.width(), st.height(), st.value(), st.fixed_first_cut_orientation(), st.max_stages(), st.kerf(), st.margins(), density, st.corner_cut(), st.usable_regions(), pinned, qty))
+            })
+            .sorted_unstable()
+            .collect_vec();
+
+        //the tuple of a hash holds at most 12 elements, the penalties and weights of the cost are nested
+        let penalties = (self.sheettype_penalty, self.sheet_open_penalty, self.clustering_weight.to_bits(), self.urgency_weight.to_bits());
+        //a custom cost function is identified by its address
+        let cost_function = self.cost_function.map(|f| f as usize);
+        fxhash::hash64(&(part_hashes, sheet_hashes, penalties, self.homogeneous_strips, self.min_waste_dimension,
+            self.max_waste_aspect_ratio.map(f64::to_bits), self.max_parttypes_per_sheet, self.forced_first_cut, cost_function, &self.unit))
+    }
+
+    /// Trivial upper bound on the usage of a solution using only sheets of this sheettype (in unlimited supply):
+    /// the total part area divided by the (usage) area of the minimum number of sheets whose placeable area can contain it.
+    /// Parts which do not fit in the sheettype (in any of their allowed rotations) are excluded.
+    /// Returns 0.0 if none of the parts fit.
+    pub fn single_sheet_density_bound(&self, sheettype_id: usize) -> f64 {
+        let sheettype = self.get_sheettype(sheettype_id);
+        let sheet_size = sheettype.layout_size();
+        let fits = |size: &Size| size.width() <= sheet_size.width() && size.height() <= sheet_size.height();
+
+        let part_area = self.parts.iter()
+            .filter(|(pt, _)| fits(pt.size()) || (pt.fixed_rotation().is_none() && fits(pt.rotated_size())))
+            .map(|(pt, qty)| pt.area() * *qty as u64)
+            .sum::<u64>();
+        if part_area == 0 {
+            return 0.0;
+        }
+        let n_sheets = part_area.div_ceil(sheettype.placeable_area());
+
+        part_area as f64 / (n_sheets * sheettype.usage_area()) as f64
+    }
+
+    /// Heuristic estimate of the smallest sheet (width, height) which can contain all parts, not an exact minimum.
+    /// The parts are shelf packed (see Layout::strip_insert) by descending height into a sheet of unlimited height.
+    /// With a fixed width, the height needed for this width is returned.
+    /// Otherwise, a range of widths (between the widest part and all parts side by side) is tried and the sheet with the smallest area is returned.
+    /// Parttypes with a demand of 0 are ignored. Returns None if there are no parts, or if a part does not fit in the fixed width.
+    pub fn min_enclosing_sheet(&self, fixed_width: Option<u64>) -> Option<(u64, u64)> {
+        let min_dimension = |pt: &PartType| match pt.fixed_rotation() {
+            Some(_) => pt.width(),
+            None => u64::min(pt.width(), pt.height()),
+        };
+        //also returns None if no part is in demand, the widths below are bounded by the parts in demand
+        let min_width = self.parts.iter().filter(|(_, qty)| *qty > 0).map(|(pt, _)| min_dimension(pt)).max()?;
+        match fixed_width {
+            Some(width) => self.strip_pack_height(width).map(|height| (width, height)),
+            None => {
+                let max_width = self.parts.iter().map(|(pt, qty)| u64::max(pt.width(), pt.height()) * *qty as u64).sum::<u64>();
+                let step = ((max_width - min_width) / Instance::N_ENCLOSING_WIDTHS).max(1);
+                (min_width..=max_width).step_by(step as usize)
+                    .filter_map(|width| self.strip_pack_height(width).map(|height| (width, height)))
+                    .min_by_key(|(width, height)| (width * height, *height))
+            }
         }
     }
 
+    const N_ENCLOSING_WIDTHS: u64 = 64;
+
+    /// Heuristic search for a small set of sheettypes (ids, ascending) with which all parts can still be placed, not necessarily the smallest one.
+    /// Starting from all sheettypes in stock, the sheettypes are dropped one by one, those with the least capacity (placeable area times stock) first,
+    /// as long as a feasible solution (GDRR::first_feasible with the config) without them places all parts.
+    /// The result is a cover from which no single sheettype can be dropped, but the feasibility test is heuristic as well:
+    /// a sheettype is kept if the greedy solution fails to place all parts without it, even if a complete solution exists.
+    /// If not all parts can be placed with all sheettypes, all sheettypes in stock are returned.
+    pub fn min_sheettype_cover(&self, config: &Config) -> Vec<usize> {
+        let mut cover = self.sheets.iter()
+            .filter(|(_, qty)| *qty > 0)
+            .map(|(st, _)| st.id())
+            .collect_vec();
+        let feasible = |sheettype_ids: &[usize]| {
+            let instance = Arc::new(self.with_sheettypes(sheettype_ids));
+            gdrr::optimize_first_feasible(instance, config).is_some_and(|solution| solution.is_complete())
+        };
+        if !feasible(&cover) {
+            return cover;
+        }
+
+        let candidates = cover.iter().copied()
+            .sorted_by_key(|&id| {
+                let (sheettype, qty) = &self.sheets[id];
+                (sheettype.placeable_area().saturating_mul(*qty as u64), id)
+            })
+            .collect_vec();
+        for id in candidates {
+            let without = cover.iter().copied().filter(|&c| c != id).collect_vec();
+            if !without.is_empty() && feasible(&without) {
+                cover = without;
+            }
+        }
+        cover
+    }
+
+    /// Copy of the instance in which only the given sheettypes are in stock, all parttypes and sheettypes are kept unchanged
+    fn with_sheettypes(&self, sheettype_ids: &[usize]) -> Instance {
+        let parts = self.parts.iter().map(|(pt, qty)| (pt.clone(), *qty)).collect_vec();
+        let sheets = self.sheets.iter()
+            .map(|(st, qty)| match sheettype_ids.contains(&st.id()) {
+                true => (st.clone(), *qty),
+                false => (st.clone(), 0),
+            })
+            .collect_vec();
+
+        Instance {
+            parts,
+            sheets,
+            total_part_area: self.total_part_area,
+            total_part_qty: self.total_part_qty,
+            sheettype_penalty: self.sheettype_penalty,
+            sheet_open_penalty: self.sheet_open_penalty,
+            clustering_weight: self.clustering_weight,
+            urgency_weight: self.urgency_weight,
+            homogeneous_strips: self.homogeneous_strips,
+            min_waste_dimension: self.min_waste_dimension,
+            max_waste_aspect_ratio: self.max_waste_aspect_ratio,
+            max_parttypes_per_sheet: self.max_parttypes_per_sheet,
+            first_sheettype: self.first_sheettype.filter(|id| sheettype_ids.contains(id)),
+            forced_first_cut: self.forced_first_cut,
+            unit: self.unit.clone(),
+            cost_function: self.cost_function,
+        }
+    }
+
+    /// Height needed to shelf pack all parts in a sheet of the given width
+    fn strip_pack_height(&self, width: u64) -> Option<u64> {
+        let (first_parttype, _) = self.parts.first()?;
+        let kerf = *first_parttype.kerf();
+        let unlimited_height = self.parts.iter().map(|(pt, qty)| u64::max(pt.width(), pt.height()) * *qty as u64).sum::<u64>();
+        let sheettype = SheetType::new(0, width, unlimited_height, 0, None, u8::MAX, kerf);
+        let mut layout = Layout::new(0, &sheettype, Orientation::Horizontal, EmptyNodeOrder::default());
+
+        let strip_height = |pt: &PartType| match pt.fixed_rotation() {
+            Some(_) => pt.size().height(),
+            None => u64::min(pt.size().width(), pt.size().height()),
+        };
+        let parttypes = self.parts.iter()
+            .sorted_by_key(|(pt, _)| (std::cmp::Reverse(strip_height(pt)), std::cmp::Reverse(pt.area()), pt.id()));
+        for (parttype, qty) in parttypes {
+            for _ in 0..*qty {
+                if !layout.strip_insert(parttype, self) {
+                    return None;
+                }
+            }
+        }
+
+        let height = layout.placed_parts().iter().map(|p| p.y + p.height).max().unwrap_or(0);
+        Some(height.saturating_sub(kerf.horizontal))
+    }
+
     pub fn parts(&self) -> &Vec<(PartType, usize)> {
         &self.parts
     }
@@ -68,4 +365,178 @@ impl Instance {
     pub fn total_part_qty(&self) -> usize {
         self.total_part_qty
     }
-}
\ No newline at end of file
+
+    /// Additional material cost for every distinct sheettype used in a solution
+    pub fn sheettype_penalty(&self) -> u64 {
+        self.sheettype_penalty
+    }
+
+    /// Additional material cost for every sheet used in a solution
+    pub fn sheet_open_penalty(&self) -> u64 {
+        self.sheet_open_penalty
+    }
+
+    /// Weight of the clustering penalty of every layout, 0.0 if same parttypes do not need to be grouped
+    pub fn clustering_weight(&self) -> f32 {
+        self.clustering_weight
+    }
+
+    /// Weight of the urgency penalty of the solutions, 0.0 if the order of the layouts does not matter
+    pub fn urgency_weight(&self) -> f32 {
+        self.urgency_weight
+    }
+
+    /// Custom objective replacing the built-in cost of the layouts, if any
+    pub fn cost_function(&self) -> Option<CostFunction> {
+        self.cost_function
+    }
+
+    /// Whether all parts within a first-level strip of a layout need to share the dimension across the strip
+    pub fn homogeneous_strips(&self) -> bool {
+        self.homogeneous_strips
+    }
+
+    /// Maximum number of different parttypes on a single sheet, if any
+    pub fn max_parttypes_per_sheet(&self) -> Option<usize> {
+        self.max_parttypes_per_sheet
+    }
+
+    /// Sheettype of the first sheet to be cut, if pinned
+    pub fn first_sheettype(&self) -> Option<usize> {
+        self.first_sheettype
+    }
+
+    /// Orientation of the first cut of every layout, if forced
+    pub fn forced_first_cut(&self) -> Option<Orientation> {
+        self.forced_first_cut
+    }
+
+    /// Unit of all dimensions, if declared
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+
+    /// Minimum width and height of the empty nodes created by an insertion, if any
+    pub fn min_waste_dimension(&self) -> Option<u64> {
+        self.min_waste_dimension
+    }
+
+    /// Maximum aspect ratio of the empty nodes created by an insertion, if any
+    pub fn max_waste_aspect_ratio(&self) -> Option<f64> {
+        self.max_waste_aspect_ratio
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::edge_affinity::{Edge, EdgeAffinity};
+    use crate::core::entities::composite_structure::CompositeStructure;
+    use crate::core::entities::parttype::PartType;
+    use crate::core::entities::sheettype::SheetType;
+    use crate::core::kerf::Kerf;
+    use crate::core::margins::Margins;
+    use crate::core::orientation::Orientation;
+    use crate::core::rotation::Rotation;
+    use crate::optimization::instance::Instance;
+    use crate::util::test_util;
+
+    #[test]
+    fn content_hash_is_independent_of_the_order() {
+        let parts = [(40, 30, 5), (25, 60, 3), (70, 20, 2)];
+        let sheets = [(100, 100, 10), (200, 50, 4)];
+        let reversed = |items: &[(u64, u64, usize)]| items.iter().rev().copied().collect::<Vec<_>>();
+
+        let instance = test_util::instance(&parts, &sheets);
+        let reordered = test_util::instance(&reversed(&parts), &reversed(&sheets));
+        let other = test_util::instance(&[(40, 30, 5), (25, 60, 3), (70, 20, 1)], &sheets);
+
+        assert_eq!(instance.content_hash(), reordered.content_hash());
+        assert_ne!(instance.content_hash(), other.content_hash());
+    }
+
+    #[test]
+    fn content_hash_changes_with_every_property() {
+        let parttype = |priority: u32, penalty: f32| PartType::new(0, 40, 30, None, Some(Rotation::Rotated), penalty, Kerf::default()).with_priority(priority);
+        let sheettype = |max_stages: u8| SheetType::new(0, 100, 100, 100 * 100, None, max_stages, Kerf::default());
+        let instance = |parttype: PartType, sheettype: SheetType, sheettype_penalty: u64, homogeneous_strips: bool| {
+            Instance::new(vec![(parttype, 5)], vec![(sheettype, 10)], sheettype_penalty, homogeneous_strips)
+        };
+
+        let composite = CompositeStructure::new(40, 30, Orientation::Vertical, vec![
+            CompositeStructure::new(10, 30, Orientation::Horizontal, vec![]),
+            CompositeStructure::new(30, 30, Orientation::Horizontal, vec![]),
+        ]);
+
+        let hash = instance(parttype(0, 0.5), sheettype(3), 0, false).content_hash();
+        let changed = [
+            instance(parttype(1, 0.5), sheettype(3), 0, false),
+            instance(parttype(0, 0.5).with_composite(composite), sheettype(3), 0, false),
+            instance(parttype(0, 0.5).with_edge_affinity(EdgeAffinity::new(Edge::Top, 50)), sheettype(3), 0, false),
+            instance(parttype(0, 0.5).with_urgency(2), sheettype(3), 0, false),
+            instance(parttype(0, 1.0), sheettype(3), 0, false),
+            instance(parttype(0, 0.5), sheettype(2), 0, false),
+            instance(parttype(0, 0.5), sheettype(3).with_density(0.5), 0, false),
+            instance(parttype(0, 0.5), sheettype(3).with_corner_cut(20, 10), 0, false),
+            instance(parttype(0, 0.5), sheettype(3).with_usable_regions(vec![(0, 0, 100, 40), (0, 40, 50, 60)]).unwrap(), 0, false),
+            instance(parttype(0, 0.5), sheettype(3).with_margins(Margins::new(0, 0, 20, 0)), 0, false),
+            instance(parttype(0, 0.5), sheettype(3), 100, false),
+            instance(parttype(0, 0.5), sheettype(3), 0, false).with_sheet_open_penalty(100),
+            instance(parttype(0, 0.5), sheettype(3), 0, false).with_clustering_weight(0.5),
+            instance(parttype(0, 0.5), sheettype(3), 0, false).with_urgency_weight(0.5),
+            instance(parttype(0, 0.5), sheettype(3), 0, true),
+            instance(parttype(0, 0.5), sheettype(3), 0, false).with_min_waste_dimension(20),
+            instance(parttype(0, 0.5), sheettype(3), 0, false).with_max_waste_aspect_ratio(2.0),
+            instance(parttype(0, 0.5), sheettype(3), 0, false).with_max_parttypes_per_sheet(2),
+            instance(parttype(0, 0.5), sheettype(3), 0, false).with_first_sheettype(0),
+            instance(parttype(0, 0.5), sheettype(3), 0, false).with_forced_first_cut(Orientation::Vertical),
+            instance(parttype(0, 0.5), sheettype(3), 0, false).with_cost_function(|layout| layout.builtin_cost()),
+            instance(parttype(0, 0.5), sheettype(3), 0, false).with_unit("mm".to_string()),
+        ];
+        assert!(changed.iter().all(|other| other.content_hash() != hash));
+    }
+
+    #[test]
+    fn single_sheet_density_bound_excludes_parts_which_do_not_fit() {
+        //40x30x5 + 25x60x3 = 10500 on two 100x100 sheets, the 200x10 part does not fit in either rotation
+        let instance = test_util::instance(&[(40, 30, 5), (25, 60, 3), (200, 10, 1)], &[(100, 100, 10), (50, 50, 10)]);
+        assert_eq!(instance.single_sheet_density_bound(0), 10500.0 / 20000.0);
+
+        //only the 40x30 parts fit on the 50x50 sheets: 6000 on three sheets of 2500
+        assert_eq!(instance.single_sheet_density_bound(1), 6000.0 / 7500.0);
+
+        let nothing_fits = test_util::instance(&[(200, 10, 1)], &[(100, 100, 10)]);
+        assert_eq!(nothing_fits.single_sheet_density_bound(0), 0.0);
+    }
+
+    #[test]
+    fn min_enclosing_sheet_stacks_strips_of_parts() {
+        //three 50x20 strips, the 30x30 parts fit side by side in one 30 high strip of width 60
+        let instance = test_util::instance(&[(50, 20, 3), (30, 30, 2), (300, 300, 0)], &[(100, 100, 1)]);
+        assert_eq!(instance.min_enclosing_sheet(Some(60)), Some((60, 90)));
+        assert_eq!(instance.min_enclosing_sheet(Some(20)), None);
+
+        let (width, height) = instance.min_enclosing_sheet(None).unwrap();
+        assert!(width * height <= 60 * 90);
+        assert!(width * height >= 50 * 20 * 3 + 30 * 30 * 2);
+
+        //parttypes without demand are ignored
+        assert_eq!(test_util::instance(&[(80, 80, 0)], &[(100, 100, 1)]).min_enclosing_sheet(None), None);
+    }
+
+    #[test]
+    fn redundant_sheettypes_are_excluded_from_the_cover() {
+        let config = test_util::config();
+        //the small sheets are not needed, the large parts only fit on the large sheets
+        let instance = test_util::instance(&[(80, 80, 2), (40, 40, 3)], &[(100, 100, 10), (50, 50, 10), (120, 120, 0)]);
+        assert_eq!(instance.min_sheettype_cover(&config), vec![0]);
+
+        //a single large sheet only holds the large part, the small parts need the small sheets
+        let instance = test_util::instance(&[(80, 80, 1), (40, 40, 3)], &[(100, 100, 1), (50, 50, 10)]);
+        assert_eq!(instance.min_sheettype_cover(&config), vec![0, 1]);
+
+        //the corner cut leaves no room for the part on the large sheet
+        let sheets = vec![(test_util::sheettype(0, 100, 100).with_corner_cut(60, 60), 1), (test_util::sheettype(1, 60, 60), 1)];
+        let instance = Instance::new(vec![(test_util::parttype(0, 50, 50), 1)], sheets, 0, false);
+        assert_eq!(instance.min_sheettype_cover(&config), vec![1]);
+    }
+}
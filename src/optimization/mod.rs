@@ -5,3 +5,7 @@ pub mod sol_collectors;
 pub mod instance;
 pub mod config;
 pub mod solutions;
+pub mod search_stats;
+pub mod move_log;
+pub mod precheck;
+pub mod benchmark;
\ No newline at end of file
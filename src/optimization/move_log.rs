@@ -0,0 +1,181 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use generational_arena::Index;
+
+use crate::core::cost::Cost;
+use crate::core::insertion::insertion_blueprint::InsertionBlueprint;
+use crate::core::insertion::node_blueprint::NodeBlueprint;
+use crate::core::layout_index::LayoutIndex;
+use crate::core::leftover_valuator;
+use crate::optimization::config::Config;
+use crate::optimization::instance::Instance;
+use crate::optimization::problem::Problem;
+use crate::optimization::solutions::problem_solution::ProblemSolution;
+use crate::optimization::solutions::sendable_solution::SendableSolution;
+
+/// Trajectory of the search leading to its best solution (complete if one was found, incomplete otherwise), see GDRR::set_move_log.
+/// Contains the accepted moves in order, starting from an empty problem. Rejected moves are not included, as they were undone.
+/// Layouts are referred to by their id in the search, nodes by their index in the layout,
+/// which are reproduced exactly when the moves are replayed in order.
+/// All accepted moves are kept in memory, as it is unknown which solution will remain the best one.
+#[derive(Debug, Clone)]
+pub struct MoveLog {
+    instance: Arc<Instance>,
+    moves: Vec<RRMove>,
+    n_best_moves: usize,
+    best_solution: Option<SendableSolution>,
+}
+
+/// Accepted move of the search: the ruined nodes and the recreated parts, in the order they were modified
+#[derive(Debug, Clone, PartialEq)]
+pub struct RRMove {
+    /// Iteration of the search, None for the warm start. The first move also opens the initial sheets.
+    pub iteration: Option<usize>,
+    pub steps: Vec<MoveStep>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MoveStep {
+    /// A layout of the sheettype was opened without parts (initialSheets)
+    OpenSheet { sheettype_id: usize, layout_id: usize },
+    /// The node (and all parts below it) was removed from the layout, the top node removes the entire layout
+    Remove { layout_id: usize, node: Index },
+    /// A part was inserted by replacing the empty node with the replacements
+    Insert { target: InsertionTarget, parttype_id: usize, original_node: Index, replacements: Vec<NodeBlueprint> },
+    /// All layouts without parts were released
+    ReleaseEmptyLayouts,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertionTarget {
+    Existing { layout_id: usize },
+    /// A copy of the empty layout (by its index in Problem::empty_layouts) was opened and received this id
+    New { empty_layout: usize, layout_id: usize },
+}
+
+impl MoveLog {
+    pub fn new(instance: Arc<Instance>) -> Self {
+        Self {
+            instance,
+            moves: vec![],
+            n_best_moves: 0,
+            best_solution: None,
+        }
+    }
+
+    /// The accepted moves up to (and including) the one resulting in the best solution
+    pub fn moves(&self) -> &[RRMove] {
+        &self.moves[..self.n_best_moves]
+    }
+
+    /// The best solution, as reported by the search
+    pub fn best_solution(&self) -> Option<&SendableSolution> {
+        self.best_solution.as_ref()
+    }
+
+    pub(crate) fn push_move(&mut self, rr_move: RRMove) {
+        self.moves.push(rr_move);
+    }
+
+    /// The last pushed move resulted in the best solution so far
+    pub(crate) fn mark_best(&mut self, solution: &ProblemSolution) {
+        self.n_best_moves = self.moves.len();
+        self.best_solution = Some(SendableSolution::new(self.instance.clone(), solution));
+    }
+
+    /// Implements the moves on an empty problem (with the config of the search) and returns the resulting solution,
+    /// which has the same cost and placements as the best solution of the search. The layouts receive new ids.
+    pub fn replay(&self, config: &Config) -> SendableSolution {
+        let instance = self.instance.clone();
+        leftover_valuator::set_power(config.leftover_valuation_power);
+        let mut problem = Problem::new(&instance, config.empty_node_order.unwrap_or_default());
+        //layout ids of the search mapped to the layouts of the replay
+        let mut layouts: HashMap<usize, Index> = HashMap::new();
+
+        for step in self.moves().iter().flat_map(|m| m.steps.iter()) {
+            match step {
+                MoveStep::OpenSheet { sheettype_id, layout_id } => {
+                    let index = problem.open_layout(*sheettype_id).expect("sheettype out of stock during replay");
+                    layouts.insert(*layout_id, index);
+                }
+                MoveStep::Remove { layout_id, node } => {
+                    if problem.remove_node(*node, LayoutIndex::Existing(layouts[layout_id])).is_some() {
+                        layouts.remove(layout_id);
+                    }
+                }
+                MoveStep::Insert { target, parttype_id, original_node, replacements } => {
+                    let layout_index = match target {
+                        InsertionTarget::Existing { layout_id } => LayoutIndex::Existing(layouts[layout_id]),
+                        InsertionTarget::New { empty_layout, .. } => LayoutIndex::Empty(*empty_layout),
+                    };
+                    let parttype = instance.get_parttype(*parttype_id);
+                    let original = &problem.get_layout(&layout_index).nodes()[*original_node];
+                    let blueprint = InsertionBlueprint::new(layout_index, *original_node, replacements.clone(), parttype, Cost::empty(), original.level());
+                    let updates = problem.implement_insertion_blueprint(&blueprint).expect("move log does not match the instance");
+                    if let (InsertionTarget::New { layout_id, .. }, LayoutIndex::Existing(index)) = (target, updates.layout_index()) {
+                        layouts.insert(*layout_id, *index);
+                    }
+                }
+                MoveStep::ReleaseEmptyLayouts => {
+                    problem.release_empty_layouts();
+                    layouts.retain(|_, index| problem.layouts().contains(*index));
+                }
+            }
+        }
+
+        let solution = problem.create_solution(&None, None);
+        SendableSolution::new(instance.clone(), &solution)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use itertools::Itertools;
+    use serde_json::json;
+
+    use crate::optimization::config::ConfigError;
+    use crate::optimization::gdrr;
+    use crate::optimization::solutions::sendable_solution::SendableSolution;
+    use crate::optimization::solutions::solution::Solution;
+    use crate::optimization::solutions::solution_diff::Placement;
+    use crate::util::test_util;
+
+    /// x, y, width, height and parttype id of a placed part
+    type PartRect = (u64, u64, u64, u64, usize);
+
+    /// Sheettype and placed parts of every layout, independent of the order of the layouts
+    fn placements(solution: &SendableSolution) -> Vec<(usize, Vec<PartRect>)> {
+        solution.layouts().iter().enumerate()
+            .map(|(i, l)| {
+                let placements = Placement::extract(i, l.sheettype_id(), l.top_node()).into_iter()
+                    .map(|p| (p.x, p.y, p.width, p.height, p.parttype_id))
+                    .sorted()
+                    .collect_vec();
+                (l.sheettype_id(), placements)
+            })
+            .sorted()
+            .collect_vec()
+    }
+
+    #[test]
+    fn replaying_the_move_log_reproduces_the_best_solution() {
+        let instance = Arc::new(test_util::instance(&[(40, 30, 8), (25, 60, 5), (70, 20, 4), (15, 15, 10)], &[(100, 100, 10), (120, 80, 10)]));
+        for overrides in [json!({}), json!({"initialSheets": [1, 1]})] {
+            let config = test_util::config_with(overrides);
+            let move_log = gdrr::optimize_with_move_log(instance.clone(), &config).unwrap();
+            let best_solution = move_log.best_solution().unwrap();
+            assert!(!move_log.moves().is_empty());
+
+            let replayed = move_log.replay(&config);
+            assert_eq!(replayed.cost(), best_solution.cost());
+            assert_eq!(placements(&replayed), placements(best_solution));
+        }
+
+        let mut unbounded = test_util::config();
+        unbounded.max_rr_iterations = None;
+        assert_eq!(gdrr::optimize_with_move_log(instance, &unbounded).err(), Some(ConfigError::UnboundedSearch));
+    }
+}
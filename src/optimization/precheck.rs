@@ -0,0 +1,129 @@
+use itertools::Itertools;
+
+use crate::optimization::instance::Instance;
+
+/// Result of a quick feasibility check of an instance, performed without running the optimization.
+/// Errors mean that no complete solution exists, warnings point at issues which likely are mistakes in the input.
+#[derive(Debug, Clone)]
+pub struct PrecheckReport {
+    pub errors: Vec<PrecheckIssue>,
+    pub warnings: Vec<PrecheckIssue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrecheckIssue {
+    /// The part does not fit in any of the sheettypes
+    PartFitsNoSheet { parttype_id: usize },
+    /// The part only fits in some sheettype when rotated, but its rotation is fixed
+    PartOnlyFitsRotated { parttype_id: usize },
+    /// The demanded area of the parts which only fit in these sheettypes exceeds the (usable) area of their stock
+    InsufficientArea { sheettype_ids: Vec<usize>, demanded_area: u64, available_area: u64 },
+    /// The sheettype has no stock
+    NoStock { sheettype_id: usize },
+    /// The part has no demand
+    NoDemand { parttype_id: usize },
+}
+
+impl PrecheckReport {
+    pub fn new(instance: &Instance) -> Self {
+        let mut errors = vec![];
+        let mut warnings = vec![];
+
+        for (sheettype, stock) in instance.sheets() {
+            if *stock == 0 {
+                warnings.push(PrecheckIssue::NoStock { sheettype_id: sheettype.id() });
+            }
+        }
+
+        //For every parttype, collect the sheettypes (in stock) it fits in
+        let mut compatible_sheettypes = vec![];
+        for (parttype, demand) in instance.parts() {
+            if *demand == 0 {
+                warnings.push(PrecheckIssue::NoDemand { parttype_id: parttype.id() });
+                continue;
+            }
+            let fits = |rotated: bool| {
+                let part_size = match rotated {
+                    false => parttype.size(),
+                    true => parttype.rotated_size(),
+                };
+                instance.sheets().iter()
+                    .filter(|(_, stock)| *stock > 0)
+                    .map(|(sheettype, _)| sheettype)
+                    .filter(|sheettype| {
+                        let sheet_size = sheettype.layout_size();
+                        part_size.width() <= sheet_size.width() && part_size.height() <= sheet_size.height()
+                    })
+                    .map(|sheettype| sheettype.id())
+                    .collect_vec()
+            };
+            let compatible = match parttype.fixed_rotation() {
+                Some(_) => fits(false),
+                None => fits(false).into_iter().chain(fits(true)).sorted().dedup().collect_vec()
+            };
+            if compatible.is_empty() {
+                errors.push(PrecheckIssue::PartFitsNoSheet { parttype_id: parttype.id() });
+                if parttype.fixed_rotation().is_some() && !fits(true).is_empty() {
+                    warnings.push(PrecheckIssue::PartOnlyFitsRotated { parttype_id: parttype.id() });
+                }
+            } else {
+                compatible_sheettypes.push((parttype.area() * *demand as u64, compatible));
+            }
+        }
+
+        //All parts which only fit in a set of sheettypes need to fit in the combined (supply-limited) area of those sheettypes
+        for sheettype_ids in compatible_sheettypes.iter().map(|(_, c)| c).unique() {
+            let demanded_area = compatible_sheettypes.iter()
+                .filter(|(_, c)| c.iter().all(|id| sheettype_ids.contains(id)))
+                .map(|(area, _)| *area)
+                .sum::<u64>();
+            let available_area = sheettype_ids.iter()
+                .map(|id| instance.get_sheettype(*id).placeable_area().saturating_mul(instance.get_sheettype_qty(*id).unwrap() as u64))
+                .fold(0u64, |acc, area| acc.saturating_add(area));
+
+            if demanded_area > available_area {
+                errors.push(PrecheckIssue::InsufficientArea { sheettype_ids: sheettype_ids.clone(), demanded_area, available_area });
+            }
+        }
+
+        Self { errors, warnings }
+    }
+
+    /// Whether the instance passed all checks which would rule out a complete solution
+    pub fn is_feasible(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::optimization::instance::Instance;
+    use crate::optimization::precheck::PrecheckIssue;
+    use crate::util::test_util;
+
+    #[test]
+    fn feasible_instance_passes() {
+        let instance = test_util::instance(&[(50, 50, 8), (100, 20, 2)], &[(100, 100, 3)]);
+        let report = instance.precheck();
+        assert!(report.is_feasible());
+        assert!(report.warnings.is_empty());
+    }
+
+    #[test]
+    fn demand_beyond_the_stock_area_is_infeasible() {
+        //the parts would fit in 3 sheets, but only 2 are in stock
+        let instance = test_util::instance(&[(50, 50, 12)], &[(100, 100, 2)]);
+        let report = instance.precheck();
+        assert!(!report.is_feasible());
+        assert_eq!(report.errors, vec![PrecheckIssue::InsufficientArea { sheettype_ids: vec![0], demanded_area: 12 * 50 * 50, available_area: 2 * 100 * 100 }]);
+    }
+
+    #[test]
+    fn only_the_usable_regions_of_the_stock_are_available() {
+        //the parts would fit in the area of the 2 sheets, but only 7000 of each is usable
+        let sheettype = test_util::sheettype(0, 100, 100).with_usable_regions(vec![(0, 0, 100, 40), (0, 40, 50, 60)]).unwrap();
+        let instance = Instance::new(vec![(test_util::parttype(0, 50, 50), 6)], vec![(sheettype, 2)], 0, false);
+        let report = instance.precheck();
+        assert_eq!(report.errors, vec![PrecheckIssue::InsufficientArea { sheettype_ids: vec![0], demanded_area: 6 * 50 * 50, available_area: 2 * 7000 }]);
+    }
+}
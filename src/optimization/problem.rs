@@ -1,11 +1,17 @@
+use std::cmp::Reverse;
+
 use generational_arena::{Arena, Index};
+use itertools::Itertools;
 use rand::SeedableRng;
 use rand::rngs::SmallRng;
 
 use crate::core::cost::Cost;
-use crate::core::entities::layout::Layout;
-use crate::core::insertion::insertion_blueprint::InsertionBlueprint;
+use crate::core::empty_node_order::EmptyNodeOrder;
+use crate::core::entities::layout::{Layout, LayoutError};
+use crate::core::entities::sheettype::SheetType;
+use crate::core::insertion::insertion_blueprint::{InsertionBlueprint, InsertionBlueprintError};
 use crate::core::layout_index::LayoutIndex;
+use crate::core::leftover_valuator;
 use crate::core::orientation::Orientation;
 use crate::DETERMINISTIC_MODE;
 use crate::optimization::instance::Instance;
@@ -30,10 +36,11 @@ pub struct Problem<'a> {
     solution_id_changed_layouts: Option<usize>,
     solution_id_counter: usize,
     layout_id_counter: usize,
+    n_node_ops: u64,
 }
 
 impl<'a> Problem<'a> {
-    pub fn new(instance: &'a Instance) -> Self {
+    pub fn new(instance: &'a Instance, empty_node_order: EmptyNodeOrder) -> Self {
         let parttype_qtys = instance.parts().iter().map(|(_, qty)| *qty).collect::<Vec<_>>();
         let sheettype_qtys = instance.sheets().iter().map(|(_, qty)| *qty).collect::<Vec<_>>();
         let random = match DETERMINISTIC_MODE {
@@ -52,18 +59,25 @@ impl<'a> Problem<'a> {
             rng: random,
             solution_id_counter : 0,
             layout_id_counter : 0,
+            n_node_ops : 0,
         };
 
         //Initiate the empty layouts
         for (sheettype, _) in instance.sheets() {
-            match sheettype.fixed_first_cut_orientation() {
+            let new_layout = |id: usize, orientation: Orientation| {
+                let mut layout = Layout::new(id, sheettype, orientation, empty_node_order);
+                layout.set_cost_function(instance.cost_function());
+                layout.set_clustering_weight(instance.clustering_weight());
+                layout
+            };
+            match instance.forced_first_cut().or(sheettype.fixed_first_cut_orientation()) {
                 Some(orientation) => {
-                    let empty_layout = Layout::new(problem.next_layout_id(), sheettype, orientation);
+                    let empty_layout = new_layout(problem.next_layout_id(), orientation);
                     problem.empty_layouts.push(empty_layout);
                 }
                 None => {
-                    let empty_layout_h = Layout::new(problem.next_layout_id(), sheettype, Orientation::Horizontal);
-                    let empty_layout_v = Layout::new(problem.next_layout_id(), sheettype, Orientation::Vertical);
+                    let empty_layout_h = new_layout(problem.next_layout_id(), Orientation::Horizontal);
+                    let empty_layout_v = new_layout(problem.next_layout_id(), Orientation::Vertical);
                     problem.empty_layouts.extend([empty_layout_h, empty_layout_v]);
                 }
             }
@@ -73,19 +87,23 @@ impl<'a> Problem<'a> {
 
     /// Modifies the problem by inserting an part according to the InsertionBlueprint.
     /// It returns which updates should be made to the InsertionOptionCache and whether or not a new layout was created.
-    pub fn implement_insertion_blueprint(&mut self, blueprint: &InsertionBlueprint<'a>) -> IOCUpdates {
+    /// If the blueprint is invalid, an error is returned and the problem is left untouched.
+    pub fn implement_insertion_blueprint(&mut self, blueprint: &InsertionBlueprint<'a>) -> Result<IOCUpdates, InsertionBlueprintError> {
+        self.get_layout(blueprint.layout_index()).validate_insertion_blueprint(blueprint)?;
+
+        self.n_node_ops += 1;
         self.register_part(blueprint.parttype().id(), 1);
 
         match blueprint.layout_index() {
             LayoutIndex::Existing(index) => {
                 let blueprint_layout = &mut self.layouts[*index];
                 let mut cache_updates = IOCUpdates::new(*blueprint.layout_index());
-                blueprint_layout.implement_insertion_blueprint(blueprint, self.instance, &mut cache_updates);
+                blueprint_layout.apply_and_cost_delta(blueprint, self.instance, &mut cache_updates);
 
                 let blueprint_layout_id = blueprint_layout.id();
                 self.layout_has_changed(blueprint_layout_id);
 
-                cache_updates
+                Ok(cache_updates)
             }
             LayoutIndex::Empty(index) => {
                 let next_layout_id = self.next_layout_id();
@@ -95,16 +113,73 @@ impl<'a> Problem<'a> {
                 let empty_layout_clone = empty_layout.clone_with_id(next_layout_id);
                 let clone_index = self.register_layout(empty_layout_clone);
 
-                //Implement the blueprint
+                //Implement the blueprint, the other empty nodes of the copy (around a corner cut) are new to the cache as well
                 let mut cache_updates = IOCUpdates::new(LayoutIndex::Existing(clone_index));
-                self.layouts[clone_index].implement_insertion_blueprint(blueprint, self.instance, &mut cache_updates);
+                let other_empty_nodes = self.layouts[clone_index].sorted_empty_nodes().iter()
+                    .filter(|n| *n != blueprint.original_node_index())
+                    .copied().collect_vec();
+                cache_updates.extend_new(other_empty_nodes);
+                self.layouts[clone_index].apply_and_cost_delta(blueprint, self.instance, &mut cache_updates);
 
-                cache_updates
+                Ok(cache_updates)
             }
         }
     }
 
+    /// Flips the cut orientation of an empty node in an existing layout, see Layout::flip_node_orientation.
+    /// As this also flips the first cut, it is not possible when the first cut orientation is fixed by the instance or the sheettype.
+    pub fn flip_node_orientation(&mut self, node_index: Index, layout_index: Index) -> Result<(), LayoutError> {
+        let layout = &mut self.layouts[layout_index];
+        if self.instance.forced_first_cut().or(layout.sheettype().fixed_first_cut_orientation()).is_some() {
+            return Err(LayoutError::FirstCutOrientationFixed);
+        }
+        layout.flip_node_orientation(node_index)?;
+        let layout_id = layout.id();
+        self.layout_has_changed(layout_id);
+        Ok(())
+    }
+
+    /// Moves a part to an empty node within an existing layout, see Layout::relocate_part
+    pub fn relocate_part(&mut self, node_index: Index, target_empty: Index, layout_index: Index) -> Result<(), LayoutError> {
+        let layout = &mut self.layouts[layout_index];
+        layout.relocate_part(node_index, target_empty, self.instance)?;
+        let layout_id = layout.id();
+        self.n_node_ops += 2;
+        self.layout_has_changed(layout_id);
+        Ok(())
+    }
+
+    /// Exchanges two parts, given as (layout, node), between two existing layouts.
+    /// Both parts are removed and each is inserted into the space freed by the other one, see Layout::replace_part.
+    /// The swap is atomic: if either part does not fit, both layouts remain unchanged.
+    pub fn swap_parts(&mut self, a: (Index, Index), b: (Index, Index)) -> Result<(), LayoutError> {
+        let ((layout_a, node_a), (layout_b, node_b)) = (a, b);
+        if layout_a == layout_b {
+            return Err(LayoutError::SameLayout);
+        }
+        let part_id = |layout: &Layout, node: Index| {
+            layout.nodes().get(node).ok_or(LayoutError::NodeNotFound)?.part_id().ok_or(LayoutError::NotAPart)
+        };
+        let parttype_a = self.instance.get_parttype(part_id(&self.layouts[layout_a], node_a)?);
+        let parttype_b = self.instance.get_parttype(part_id(&self.layouts[layout_b], node_b)?);
+
+        let checkpoint_a = self.layouts[layout_a].checkpoint();
+        self.layouts[layout_a].replace_part(node_a, parttype_b, self.instance)?;
+        if let Err(error) = self.layouts[layout_b].replace_part(node_b, parttype_a, self.instance) {
+            self.layouts[layout_a].restore(checkpoint_a);
+            return Err(error);
+        }
+
+        //every parttype is removed from one layout and inserted in the other, so the quantities of the problem are unchanged
+        self.n_node_ops += 4;
+        let (id_a, id_b) = (self.layouts[layout_a].id(), self.layouts[layout_b].id());
+        self.layout_has_changed(id_a);
+        self.layout_has_changed(id_b);
+        Ok(())
+    }
+
     pub fn remove_node(&mut self, node_index: Index, layout_index: LayoutIndex) -> Option<Layout<'a>> {
+        self.n_node_ops += 1;
         self.layout_has_changed(self.get_layout(&layout_index).id());
         match layout_index {
             LayoutIndex::Empty(_) => panic!("Cannot remove a node from an empty layout"),
@@ -133,8 +208,38 @@ impl<'a> Problem<'a> {
         }
     }
 
+    /// Opens a new layout of the sheettype without any parts, None if the sheettype is out of stock.
+    /// Its first cut orientation is that of the first empty layout of the sheettype.
+    pub fn open_layout(&mut self, sheettype_id: usize) -> Option<Index> {
+        if self.sheettype_qtys[sheettype_id] == 0 {
+            return None;
+        }
+        let id = self.next_layout_id();
+        let layout = self.empty_layouts.iter()
+            .find(|l| l.sheettype().id() == sheettype_id)
+            .expect("no empty layout for sheettype")
+            .clone_with_id(id);
+        Some(self.register_layout(layout))
+    }
+
+    /// Unregisters all layouts without parts, e.g. those opened with open_layout which remained unused.
+    /// Returns the number of released layouts.
+    pub fn release_empty_layouts(&mut self) -> usize {
+        let empty = self.layouts.iter()
+            .filter(|(_, l)| l.included_parts_unordered().is_empty())
+            .map(|(i, _)| i)
+            .collect::<Vec<_>>();
+        for &index in &empty {
+            self.layout_has_changed(self.layouts[index].id());
+            self.unregister_layout(LayoutIndex::Existing(index));
+        }
+        empty.len()
+    }
+
     pub fn cost(&mut self) -> Cost {
+        //summed in order of the layout ids, as the order in the arena depends on the restores and the leftover value is a float
         let mut cost = self.layouts.iter_mut()
+            .sorted_by_key(|(_, l)| l.id())
             .fold(Cost::empty(), |acc, (_,l)| acc + l.cost(false));
 
         cost.part_area_excluded = self.parttype_qtys.iter().enumerate()
@@ -142,9 +247,61 @@ impl<'a> Problem<'a> {
 
         cost.part_area_included = self.instance.total_part_area() - cost.part_area_excluded;
 
+        cost.excluded_priority = self.parttype_qtys.iter().enumerate()
+            .filter(|(_, qty)| **qty > 0)
+            .map(|(id, _)| self.instance().get_parttype(id).priority())
+            .max();
+
+        //Solution-level penalty for every distinct sheettype in use
+        cost.material_cost += self.instance.sheettype_penalty() * self.n_sheettypes_used() as u64;
+        //and for every sheet in use
+        cost.material_cost += self.instance.sheet_open_penalty() * self.layouts.len() as u64;
+
+        if self.instance.urgency_weight() > 0.0 {
+            cost.leftover_value -= self.urgency_penalty();
+        }
+
         cost
     }
 
+    /// Urgent parts should be cut first, so on as few layouts as possible, which are cut before the others.
+    /// The layouts are cut in order of decreasing urgency area (see Layout::urgency_area and SendableSolution::new),
+    /// and every layout adds its rank in this order (0 for the first) times the value a leftover of its urgency area would have,
+    /// multiplied by the urgency weight of the instance.
+    pub fn urgency_penalty(&self) -> f32 {
+        let penalty: f32 = self.urgency_ranking().into_iter().enumerate()
+            .map(|(rank, (_, urgency_area))| rank as f32 * leftover_valuator::valuate(urgency_area))
+            .sum();
+        self.instance.urgency_weight() * penalty
+    }
+
+    /// The layouts and their urgency area, in the order in which they are cut: by decreasing urgency area, then by id
+    pub fn urgency_ranking(&self) -> Vec<(Index, u64)> {
+        self.layouts.iter()
+            .map(|(i, l)| (i, l.id(), l.urgency_area(self.instance)))
+            .sorted_by_key(|(_, id, urgency_area)| (Reverse(*urgency_area), *id))
+            .map(|(i, _, urgency_area)| (i, urgency_area))
+            .collect_vec()
+    }
+
+    pub fn sheettype_in_use(&self, sheettype_id: usize) -> bool {
+        self.sheettype_qtys[sheettype_id] < self.instance.get_sheettype_qty(sheettype_id).unwrap()
+    }
+
+    pub fn n_sheettypes_used(&self) -> usize {
+        (0..self.sheettype_qtys.len()).filter(|id| self.sheettype_in_use(*id)).count()
+    }
+
+    /// Material cost of a layout of this sheettype, including the sheet open penalty and the sheettype penalty if no other layout uses this sheettype.
+    /// Used to predict the cost of opening a new layout, or the savings of removing one (after it has been removed).
+    pub fn layout_material_cost(&self, sheettype: &SheetType) -> u64 {
+        let material_cost = sheettype.value() + self.instance.sheet_open_penalty();
+        match self.sheettype_in_use(sheettype.id()) {
+            true => material_cost,
+            false => material_cost + self.instance.sheettype_penalty()
+        }
+    }
+
     pub fn create_solution(&mut self, old_solution: &Option<ProblemSolution<'a>>, cached_cost: Option<Cost>) -> ProblemSolution<'a> {
         //TODO: implement cached cost for problem
 
@@ -238,7 +395,7 @@ impl<'a> Problem<'a> {
             |(parttype, qty)| { parttype.area() * (*qty - self.parttype_qtys.get(parttype.id()).unwrap()) as u64 }
         ).sum::<u64>();
         let total_used_sheet_area = self.layouts().iter().map(
-            |(_, layout)| { layout.sheettype().area() }
+            |(_, layout)| { layout.sheettype().usage_area() }
         ).sum::<u64>();
 
         total_included_part_area as f64 / total_used_sheet_area as f64
@@ -260,6 +417,11 @@ impl<'a> Problem<'a> {
         &mut self.rng
     }
 
+    /// Replaces the random number generator by one seeded with the given seed
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = SmallRng::seed_from_u64(seed);
+    }
+
     pub fn layouts(&self) -> &Arena<Layout<'a>> {
         &self.layouts
     }
@@ -277,7 +439,7 @@ impl<'a> Problem<'a> {
 
     pub fn register_layout(&mut self, layout: Layout<'a>) -> Index {
         self.register_sheet(layout.sheettype().id(), 1);
-        layout.get_included_parts().iter().for_each(
+        layout.included_parts_unordered().iter().for_each(
             |p_id| {
                 self.register_part(*p_id, 1);
             });
@@ -292,7 +454,7 @@ impl<'a> Problem<'a> {
                 let layout = self.layouts.remove(li).expect("Layout not found");
 
                 self.unregister_sheet(layout.sheettype().id(), 1);
-                layout.get_included_parts().iter().for_each(
+                layout.included_parts_unordered().iter().for_each(
                     |p_id| { self.unregister_part(*p_id, 1) });
                 layout
             }
@@ -327,7 +489,7 @@ impl<'a> Problem<'a> {
         self.sheettype_qtys[sheettype_id] += qty;
     }
 
-    fn next_layout_id(&mut self) -> usize {
+    pub fn next_layout_id(&mut self) -> usize {
         self.layout_id_counter += 1;
         self.layout_id_counter
     }
@@ -344,10 +506,120 @@ impl<'a> Problem<'a> {
     pub fn changed_layouts(&self) -> &Vec<usize> {
         &self.changed_layouts
     }
+
+    /// Total number of node operations (insertions and removals) performed on the problem.
+    /// Not affected by restoring the problem to a prior solution.
+    pub fn n_node_ops(&self) -> u64 {
+        self.n_node_ops
+    }
 }
 
 impl<'a> PartialEq for Problem<'a> {
     fn eq(&self, other: &Problem<'a>) -> bool {
         std::ptr::eq(self, other)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use std::sync::Arc;
+
+    use generational_arena::Index;
+    use itertools::Itertools;
+    use serde_json::json;
+
+    use crate::core::entities::layout::LayoutError;
+    use crate::core::entities::placed_part::PlacedPart;
+    use crate::core::orientation::Orientation;
+    use crate::io::parser;
+    use crate::optimization::problem::Problem;
+    use crate::optimization::solutions::solution::Solution;
+    use crate::util::test_util;
+
+    fn layout_indices(problem: &Problem) -> Vec<Index> {
+        problem.layouts().iter().map(|(i, _)| i).collect_vec()
+    }
+
+    fn part_node(problem: &Problem, layout: Index, parttype_id: usize) -> (Index, Index) {
+        let node = problem.layouts()[layout].nodes().iter().find(|(_, n)| n.part_id() == Some(parttype_id)).unwrap().0;
+        (layout, node)
+    }
+
+    fn placed_parts(problem: &Problem, layouts: &[Index]) -> Vec<Vec<PlacedPart>> {
+        layouts.iter().map(|&l| problem.layouts()[l].placed_parts()).collect_vec()
+    }
+
+    #[test]
+    fn swapping_parts_between_layouts_concentrates_the_leftovers() {
+        //the 70x70 part leaves a 30x70 leftover on the first sheet, the 30x70 part one of 40x70 on the second sheet.
+        //After the swap, the leftover of the first sheet is 70x70 and the strip of the second sheet is full.
+        let instance = test_util::instance(&[(30, 70, 1), (30, 70, 1), (70, 70, 1), (100, 30, 1)], &[(100, 100, 2)]);
+        let mut problem = test_util::problem(&instance, &[(0, &[(2, 0, 0), (3, 0, 70)]), (0, &[(0, 0, 0), (1, 70, 0)])]);
+        let layouts = layout_indices(&problem);
+        let cost_before = problem.cost();
+
+        let (a, b) = (part_node(&problem, layouts[0], 2), part_node(&problem, layouts[1], 0));
+        problem.swap_parts(a, b).unwrap();
+
+        let cost_after = problem.cost();
+        assert_eq!(cost_after.material_cost, cost_before.material_cost);
+        assert_eq!(crate::COST_COMPARATOR(&cost_after, &cost_before), Ordering::Less);
+        let part_ids = |l: Index| problem.layouts()[l].placed_parts().iter().map(|p| p.parttype_id).sorted().collect_vec();
+        assert_eq!((part_ids(layouts[0]), part_ids(layouts[1])), (vec![0, 3], vec![1, 2]));
+        assert!(problem.parttype_qtys().iter().all(|q| *q == 0));
+    }
+
+    #[test]
+    fn infeasible_swaps_leave_both_layouts_unchanged() {
+        //the space freed by part 0 is too low for part 2
+        let instance = test_util::instance(&[(30, 30, 1), (30, 30, 1), (70, 70, 1)], &[(100, 100, 2)]);
+        let mut problem = test_util::problem(&instance, &[(0, &[(0, 0, 0), (1, 0, 40)]), (0, &[(2, 0, 0)])]);
+        let layouts = layout_indices(&problem);
+        let (cost_before, parts_before) = (problem.cost(), placed_parts(&problem, &layouts));
+
+        //the indices of the nodes around the parts can change, even if the swap fails
+        for (first, second) in [(0, 1), (1, 0)] {
+            let parts = [part_node(&problem, layouts[0], 0), part_node(&problem, layouts[1], 2)];
+            assert_eq!(problem.swap_parts(parts[first], parts[second]), Err(LayoutError::PartDoesNotFit));
+        }
+
+        assert_eq!(problem.cost(), cost_before);
+        assert_eq!(placed_parts(&problem, &layouts), parts_before);
+    }
+
+    #[test]
+    fn sheet_open_penalty_reduces_the_number_of_sheets() {
+        //two narrow sheets are cheaper than the square one holding all parts, until every sheet is charged the penalty
+        let mut json_instance = test_util::json_instance(&[(50, 50, 4)], &[(50, 100, 10), (100, 100, 1)]);
+        json_instance.sheettypes[1].cost = 10500;
+        let cheapest_plan = |sheet_open_penalty: u64| {
+            let config = test_util::config_with(json!({"sheetOpenPenalty": sheet_open_penalty, "sheetValuationMode": "cost"}));
+            let instance = parser::generate_instance(&mut json_instance.clone(), &config).unwrap();
+            let narrow_sheets: [(usize, &test_util::Parts); 2] = [(0, &[(0, 0, 0), (0, 0, 50)]), (0, &[(0, 0, 0), (0, 0, 50)])];
+            let square_sheet: [(usize, &test_util::Parts); 1] = [(1, &[(0, 0, 0), (0, 50, 0), (0, 0, 50), (0, 50, 50)])];
+            [&narrow_sheets[..], &square_sheet[..]].iter()
+                .map(|plan| {
+                    let solution = test_util::problem_solution(&instance, plan);
+                    (solution.cost().material_cost, solution.layouts().len())
+                })
+                .min().unwrap()
+        };
+
+        assert_eq!(cheapest_plan(0), (10000, 2));
+        assert_eq!(cheapest_plan(1000), (11500, 1));
+    }
+
+    #[test]
+    fn forced_first_cut_fixes_the_first_cut_of_every_layout() {
+        let mut json_instance = test_util::json_instance(&[(60, 30, 6), (35, 70, 4), (20, 20, 8)], &[(100, 100, 10)]);
+        for orientation in [Orientation::Horizontal, Orientation::Vertical] {
+            let config = test_util::config_with(json!({"forcedFirstCut": orientation}));
+            let instance = Arc::new(parser::generate_instance(&mut json_instance, &config).unwrap());
+            let solution = test_util::solve(instance, &config);
+
+            assert!(solution.layouts().len() > 1);
+            assert!(solution.layouts().iter().all(|l| l.top_node().next_cut_orient() == orientation));
+        }
+    }
+}
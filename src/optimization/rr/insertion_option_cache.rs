@@ -3,6 +3,7 @@ use std::rc::{Rc};
 use generational_arena::{Index};
 use itertools::Itertools;
 
+use crate::core::empty_node_order::EmptyNodeOrder;
 use crate::core::entities::layout::Layout;
 use crate::core::entities::node::Node;
 use crate::core::entities::parttype::PartType;
@@ -56,21 +57,28 @@ impl<'a : 'b, 'b> InsertionOptionCache<'a> {
 
         for (layout_i, layout) in layouts {
             let sorted_empty_nodes = layout.sorted_empty_nodes();
+            //Skipping based on area is only possible if the empty nodes are sorted by descending area
+            let sorted_by_area = layout.empty_node_order() == EmptyNodeOrder::Area;
             let mut starting_index = 0;
 
             for empty_node_i in sorted_empty_nodes.iter() {
                 let mut generated_insertion_options = Vec::new();
                 let empty_node = &layout.nodes()[*empty_node_i];
                 if sorted_parttypes[sorted_parttypes.len() - 1].area() > empty_node.area() {
-                    //The smallest parttype is larger than this node, there are no possible insertion options left.
-                    break;
+                    if sorted_by_area {
+                        //The smallest parttype is larger than this node, there are no possible insertion options left.
+                        break;
+                    }
+                    continue;
                 }
                 for i in starting_index..sorted_parttypes.len() {
                     let parttype = *sorted_parttypes.get(i).unwrap();
 
                     if empty_node.area() < parttype.area() {
-                        //The empty node is smaller than this parttype. For the next (smaller) empty node, start searching from next index
-                        starting_index = i + 1;
+                        if sorted_by_area {
+                            //The empty node is smaller than this parttype. For the next (smaller) empty node, start searching from next index
+                            starting_index = i + 1;
+                        }
                     } else {
                         let insertion_option = InsertionOptionCache::generate_insertion_option(empty_node, parttype, *layout_i, *empty_node_i);
                         match insertion_option {
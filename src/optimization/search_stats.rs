@@ -0,0 +1,20 @@
+/// Statistics about the search process of a single GDRR run
+#[derive(Debug, Clone)]
+pub struct SearchStats {
+    pub n_iterations: usize,
+    pub n_accepted: usize,
+    pub n_improved: usize,
+    pub n_node_ops: u64,
+    /// With the Fail no-insertion policy: the parttype which could neither be placed in an existing layout nor in a new one, which stopped the search
+    pub insertion_failure: Option<usize>,
+    /// Number of iterations since the last improvement (or since the last reset due to a lower material limit)
+    pub iterations_since_improvement: usize,
+    /// Whether the iterations since the last improvement exceeded the configured stagnation window
+    pub stagnated: bool,
+}
+
+impl SearchStats {
+    pub fn new(n_iterations: usize, n_accepted: usize, n_improved: usize, n_node_ops: u64, insertion_failure: Option<usize>, iterations_since_improvement: usize, stagnated: bool) -> Self {
+        Self { n_iterations, n_accepted, n_improved, n_node_ops, insertion_failure, iterations_since_improvement, stagnated }
+    }
+}
@@ -0,0 +1,121 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use crate::core::insertion::node_blueprint::NodeBlueprint;
+use crate::optimization::instance::Instance;
+use crate::optimization::sol_collectors::solution_archive::SolutionArchive;
+use crate::optimization::solutions::problem_solution::ProblemSolution;
+use crate::optimization::solutions::sendable_solution::SendableSolution;
+use crate::optimization::solutions::solution::Solution;
+use crate::optimization::solutions::solution_diff::SolutionDiff;
+
+/// Archive of up to `capacity` complete solutions which are structurally distinct
+/// and whose material cost is within a relative tolerance of the best archived solution.
+/// The distance between two solutions is the number of parts which are placed differently (see SolutionDiff::n_changed_parts),
+/// solutions are distinct when their distance is at least `min_distance`. Of every group of solutions closer than this, only the cheapest one is kept.
+pub struct DiverseArchive {
+    instance: Arc<Instance>,
+    capacity: usize,
+    cost_tolerance: f64,
+    min_distance: usize,
+    //solutions with the cutting patterns of their layouts, sorted by cost
+    solutions: Vec<(SendableSolution, Vec<(usize, NodeBlueprint)>)>,
+}
+
+impl DiverseArchive {
+    pub fn new(instance: Arc<Instance>, capacity: usize, cost_tolerance: f64, min_distance: usize) -> Self {
+        Self {
+            instance,
+            capacity,
+            cost_tolerance,
+            min_distance,
+            solutions: vec![],
+        }
+    }
+
+    /// Archived solutions, sorted by cost (material cost, then leftover value)
+    pub fn solutions(&self) -> Vec<&SendableSolution> {
+        self.solutions.iter().map(|(s, _)| s).collect()
+    }
+
+    /// Removes all solutions from the archive, sorted by cost
+    pub fn take_solutions(&mut self) -> Vec<SendableSolution> {
+        std::mem::take(&mut self.solutions).into_iter().map(|(s, _)| s).collect()
+    }
+
+    fn compare(a: &dyn Solution, b: &dyn Solution) -> Ordering {
+        a.cost().material_cost.cmp(&b.cost().material_cost)
+            .then_with(|| crate::COST_COMPARATOR(a.cost(), b.cost()))
+    }
+}
+
+impl SolutionArchive for DiverseArchive {
+    /// Adds the solution to the archive if it is complete, and either distinct from all archived solutions
+    /// or cheaper than all archived solutions it is too close to, which it replaces.
+    fn offer(&mut self, solution: &ProblemSolution) {
+        if !solution.is_complete() {
+            return;
+        }
+        let patterns = solution.layout_patterns();
+        let close = (0..self.solutions.len())
+            .filter(|&i| SolutionDiff::new(&self.solutions[i].1, &patterns).n_changed_parts() < self.min_distance)
+            .collect::<Vec<_>>();
+        if close.iter().any(|&i| DiverseArchive::compare(solution, &self.solutions[i].0) != Ordering::Less) {
+            return;
+        }
+        for i in close.into_iter().rev() {
+            self.solutions.remove(i);
+        }
+        let insert_i = self.solutions.partition_point(|(s, _)| DiverseArchive::compare(s, solution) != Ordering::Greater);
+        if insert_i >= self.capacity {
+            return;
+        }
+        self.solutions.insert(insert_i, (SendableSolution::new(self.instance.clone(), solution), patterns));
+        self.solutions.truncate(self.capacity);
+
+        let max_material_cost = self.solutions[0].0.cost().material_cost as f64 * (1.0 + self.cost_tolerance);
+        self.solutions.retain(|(s, _)| s.cost().material_cost as f64 <= max_material_cost);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use itertools::Itertools;
+
+    use crate::optimization::sol_collectors::diverse_archive::DiverseArchive;
+    use crate::optimization::sol_collectors::solution_archive::SolutionArchive;
+    use crate::optimization::solutions::solution::Solution;
+    use crate::util::test_util;
+
+    #[test]
+    fn only_distant_solutions_within_the_tolerance_are_archived() {
+        let instance = Arc::new(test_util::instance(&[(50, 50, 1), (50, 50, 1), (50, 50, 1), (50, 50, 1)], &[(100, 100, 10)]));
+        let mut archive = DiverseArchive::new(instance.clone(), 3, 0.5, 3);
+        let grid = |ids: [usize; 4]| [(ids[0], 0, 0), (ids[1], 50, 0), (ids[2], 0, 50), (ids[3], 50, 50)];
+        let n_sheets_archived = |archive: &DiverseArchive| archive.solutions().iter().map(|s| s.layouts().len()).collect_vec();
+
+        //incomplete solutions are never archived
+        archive.offer(&test_util::problem_solution(&instance, &[(0, &grid([0, 1, 2, 3])[..3])]));
+        assert!(archive.solutions().is_empty());
+
+        //a cheaper solution replaces the close one, one part placed on another sheet
+        archive.offer(&test_util::problem_solution(&instance, &[(0, &grid([0, 1, 2, 3])[..3]), (0, &grid([3, 0, 0, 0])[..1])]));
+        archive.offer(&test_util::problem_solution(&instance, &[(0, &grid([0, 1, 2, 3]))]));
+        assert_eq!(n_sheets_archived(&archive), vec![1]);
+
+        //two parts swapped is too close to an equally expensive solution
+        archive.offer(&test_util::problem_solution(&instance, &[(0, &grid([1, 0, 2, 3]))]));
+        assert_eq!(n_sheets_archived(&archive).len(), 1);
+
+        //all parts placed differently is distinct
+        archive.offer(&test_util::problem_solution(&instance, &[(0, &grid([3, 2, 1, 0]))]));
+        assert_eq!(n_sheets_archived(&archive), vec![1, 1]);
+
+        //distinct, but too expensive
+        archive.offer(&test_util::problem_solution(&instance, &[(0, &grid([2, 3, 0, 1])[..3]), (0, &grid([1, 0, 0, 0])[..1])]));
+        assert_eq!(n_sheets_archived(&archive), vec![1, 1]);
+        assert!(archive.solutions().iter().all(|s| s.cost().material_cost == 10000));
+    }
+}
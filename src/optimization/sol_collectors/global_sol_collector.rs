@@ -10,9 +10,11 @@ use colored::*;
 use crate::core::cost::Cost;
 use crate::optimization::config::Config;
 use crate::optimization::instance::Instance;
+use crate::optimization::search_stats::SearchStats;
 use crate::optimization::solutions::sendable_solution::SendableSolution;
 use crate::optimization::solutions::solution::Solution;
 use crate::optimization::solutions::solution_stats::SolutionStats;
+use crate::optimization::solutions::tiebreak;
 use crate::timed_println;
 use crate::util::messages::{SolutionReportMessage, SyncMessage};
 use crate::util::util;
@@ -33,6 +35,7 @@ pub struct GlobalSolCollector {
     material_limit: Option<u64>,
     tx_syncs: Vec<Sender<SyncMessage>>,
     rx_solution_report: Receiver<SolutionReportMessage>,
+    search_stats: Vec<SearchStats>,
 }
 
 impl GlobalSolCollector {
@@ -52,10 +55,12 @@ impl GlobalSolCollector {
             material_limit : None,
             tx_syncs,
             rx_solution_report,
+            search_stats: vec![],
         }
     }
 
-    pub fn monitor(&mut self, gdrr_thread_handlers: Vec<thread::JoinHandle<()>>) {
+    /// Monitors the GDRR threads until one of the termination conditions is met, then terminates them and collects their search statistics
+    pub fn monitor(&mut self, gdrr_thread_handlers: Vec<thread::JoinHandle<SearchStats>>) {
         let start_time = time::Instant::now();
         let max_run_time = self.config.max_run_time.unwrap_or(usize::MAX);
         let running = Arc::new(AtomicBool::new(true));
@@ -87,6 +92,11 @@ impl GlobalSolCollector {
                 break;
             }
 
+            if self.best_complete_solution.as_ref().is_some_and(|s| s.usage() >= self.config.target_usage.unwrap_or(f64::INFINITY)) {
+                timed_println!("Target usage reached");
+                break;
+            }
+
             if gdrr_thread_handlers.iter().all(|h| h.is_finished()) {
                 timed_println!("All GDRR threads have finished execution");
                 break;
@@ -102,7 +112,7 @@ impl GlobalSolCollector {
         }
         //Wait for them to finish
         for handler in gdrr_thread_handlers {
-            handler.join().expect("Error joining GDRR thread");
+            self.search_stats.push(handler.join().expect("Error joining GDRR thread"));
         }
 
         match (self.best_complete_solution.as_ref(), self.best_incomplete_cost.as_ref()) {
@@ -137,13 +147,28 @@ impl GlobalSolCollector {
                     }
                 }
             }
+        } else if self.wins_tiebreak(&solution) {
+            //Same material cost, the material limit remains unchanged
+            timed_println!("[{}]\t{}{}", thread_name, "<complete>\t".cyan(), util::solution_stats_string(&solution).cyan());
+            self.best_complete_solution = Some(solution);
+        }
+    }
+
+    /// Whether the solution has the same material cost as the best complete solution, but is preferred by the configured tiebreaks
+    fn wins_tiebreak(&self, solution: &SendableSolution) -> bool {
+        let tiebreaks = self.config.tiebreaks.as_deref().unwrap_or_default();
+        match self.best_complete_solution.as_ref() {
+            Some(best) => best.cost().material_cost == solution.cost().material_cost
+                && tiebreak::compare_chain(tiebreaks, solution, best) == Ordering::Less,
+            None => false,
         }
     }
 
     fn report_new_incomplete_solution(&mut self, thread_name: String, solution: SendableSolution) {
         if self.best_complete_solution.is_none() {
             if self.best_incomplete_solution.is_none()
-                || (self.cost_comparator)(&solution.cost(), &self.best_incomplete_solution.as_ref().unwrap().cost()) == Ordering::Less {
+                || (self.cost_comparator)(solution.cost(), self.best_incomplete_solution.as_ref().unwrap().cost())
+                    .then_with(|| tiebreak::compare_chain(self.config.tiebreaks.as_deref().unwrap_or_default(), &solution, self.best_incomplete_solution.as_ref().unwrap())) == Ordering::Less {
                 timed_println!("[{}]\t{}{}", thread_name, "<incomplete>\t".bright_green(), util::solution_stats_string(&solution));
                 self.best_incomplete_solution = Some(solution.clone());
             }
@@ -174,4 +199,9 @@ impl GlobalSolCollector {
     pub fn material_limit(&self) -> Option<u64> {
         self.material_limit
     }
+
+    /// Statistics of the searches of all threads, in order of the threads, available once monitor() returns
+    pub fn search_stats(&self) -> &[SearchStats] {
+        &self.search_stats
+    }
 }
\ No newline at end of file
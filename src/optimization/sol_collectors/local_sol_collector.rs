@@ -10,6 +10,7 @@ use crate::optimization::solutions::problem_solution::ProblemSolution;
 use crate::optimization::solutions::sendable_solution::SendableSolution;
 use crate::optimization::solutions::solution::Solution;
 use crate::optimization::solutions::solution_stats::SolutionStats;
+use crate::optimization::solutions::tiebreak::{self, Tiebreak};
 use crate::timed_thread_println;
 use crate::util::messages::{SolutionReportMessage, SyncMessage};
 
@@ -22,6 +23,7 @@ pub struct LocalSolCollector<'a> {
     best_complete_solution: Option<ProblemSolution<'a>>,
     best_incomplete_solution: Option<ProblemSolution<'a>>,
     cost_comparator: fn(&Cost, &Cost) -> Ordering,
+    tiebreaks: Vec<Tiebreak>,
     material_limit: Option<u64>,
     rx_sync: Receiver<SyncMessage>,
     tx_solution_report: Sender<SolutionReportMessage>,
@@ -43,6 +45,7 @@ impl<'a> LocalSolCollector<'a> {
             best_complete_solution : None,
             best_incomplete_solution : None,
             cost_comparator,
+            tiebreaks: vec![],
             material_limit : None,
             rx_sync,
             tx_solution_report,
@@ -54,21 +57,23 @@ impl<'a> LocalSolCollector<'a> {
 
     pub fn report_problem_solution(&mut self, solution: &ProblemSolution<'a>) {
         self.rx_sync();
-        match &self.best_incomplete_solution {
-            None => {
-                if solution.cost().material_cost < self.material_limit.unwrap_or(u64::MAX) {
-                    self.accept_solution(solution);
-                    self.tx_solution_report();
-                }
+        let material_cost = solution.cost().material_cost;
+        let improves = match (&self.best_complete_solution, &self.best_incomplete_solution) {
+            //a complete solution at the material limit set by the best complete solution can only win by the tiebreaks
+            (Some(best_complete_solution), _) if solution.is_complete() && material_cost == self.material_limit() && material_cost == best_complete_solution.cost().material_cost => {
+                tiebreak::compare_chain(&self.tiebreaks, solution, best_complete_solution) == Ordering::Less
             }
-            Some(best_incomplete_solution) => {
-                debug_assert!(solution.cost().material_cost < self.material_limit.unwrap_or(u64::MAX));
-                if (self.cost_comparator)(&solution.cost(), &best_incomplete_solution.cost()) == Ordering::Less {
-                    self.accept_solution(solution);
-                    self.tx_solution_report();
-                }
+            _ if material_cost >= self.material_limit() => false,
+            (_, None) => true,
+            (_, Some(best_incomplete_solution)) => {
+                (self.cost_comparator)(solution.cost(), best_incomplete_solution.cost())
+                    .then_with(|| tiebreak::compare_chain(&self.tiebreaks, solution, best_incomplete_solution)) == Ordering::Less
             }
         };
+        if improves {
+            self.accept_solution(solution);
+            self.tx_solution_report();
+        }
     }
 
     fn accept_solution(&mut self, solution: &ProblemSolution<'a>) {
@@ -85,6 +90,11 @@ impl<'a> LocalSolCollector<'a> {
         };
     }
 
+    /// Breaks ties between solutions of equal cost by the criteria in order, see Tiebreak
+    pub fn set_tiebreaks(&mut self, tiebreaks: Vec<Tiebreak>) {
+        self.tiebreaks = tiebreaks;
+    }
+
     pub fn rx_sync(&mut self) {
         while let Ok(message) = self.rx_sync.try_recv() {
             match message {
@@ -166,4 +176,43 @@ impl<'a> LocalSolCollector<'a> {
     pub fn terminate(&self) -> bool {
         self.terminate
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use std::sync::Arc;
+
+    use crate::optimization::solutions::solution::Solution;
+    use crate::optimization::solutions::tiebreak::{self, Tiebreak};
+    use crate::util::messages::SolutionReportMessage;
+    use crate::util::test_util;
+
+    #[test]
+    fn complete_solutions_of_equal_material_cost_are_ordered_by_the_tiebreaks() {
+        //both solutions use a single sheet, the first has less fragmented waste, the second fewer cuts
+        let instance = Arc::new(test_util::instance(&[(30, 30, 1), (50, 20, 1), (20, 60, 1)], &[(100, 100, 2)]));
+        let consolidated = test_util::problem_solution(&instance, &[(0, &[(0, 0, 0), (1, 0, 70), (2, 30, 0)])]);
+        let fewer_cuts = test_util::problem_solution(&instance, &[(0, &[(0, 0, 0), (1, 0, 30), (2, 50, 30)])]);
+        let chains = [
+            (vec![Tiebreak::MinFragmentation, Tiebreak::MinCutCount], Ordering::Less),
+            (vec![Tiebreak::MinCutCount, Tiebreak::MinFragmentation], Ordering::Greater),
+            (vec![], Ordering::Equal),
+        ];
+        for (tiebreaks, ordering) in chains {
+            assert_eq!(tiebreak::compare_chain(&tiebreaks, &consolidated, &fewer_cuts), ordering);
+        }
+
+        //the collector replaces its best complete solution by one of equal material cost which wins the tiebreaks, but not the other way around
+        let (mut collector, _tx, rx) = test_util::local_sol_collector(&instance);
+        collector.set_tiebreaks(vec![Tiebreak::MinCutCount]);
+        let mut reported_cut_counts = vec![];
+        for solution in [&consolidated, &fewer_cuts, &consolidated] {
+            collector.report_problem_solution(solution);
+            while let Ok(SolutionReportMessage::NewCompleteSolution(_, s)) = rx.try_recv() {
+                reported_cut_counts.push(s.cut_count());
+            }
+        }
+        assert_eq!(reported_cut_counts, vec![7, 6]);
+        assert_eq!(collector.best_complete_solution().as_ref().unwrap().cut_count(), 6);
+    }
+}
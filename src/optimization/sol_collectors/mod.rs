@@ -1,2 +1,6 @@
 pub mod local_sol_collector;
-pub mod global_sol_collector;
\ No newline at end of file
+pub mod global_sol_collector;
+pub mod diverse_archive;
+pub mod pareto_archive;
+pub mod solution_archive;
+pub mod sheet_count_archive;
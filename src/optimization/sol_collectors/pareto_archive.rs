@@ -0,0 +1,60 @@
+use std::cmp::Ordering;
+use std::sync::Arc;
+
+use itertools::Itertools;
+
+use crate::optimization::instance::Instance;
+use crate::optimization::sol_collectors::solution_archive::SolutionArchive;
+use crate::optimization::solutions::objective::{self, Objective};
+use crate::optimization::solutions::problem_solution::ProblemSolution;
+use crate::optimization::solutions::sendable_solution::SendableSolution;
+use crate::optimization::solutions::solution::Solution;
+
+/// Archive of the complete solutions which are not dominated by any other offered solution on the objectives.
+/// Of every group of solutions with identical objective values, only the first one offered is kept.
+pub struct ParetoArchive {
+    instance: Arc<Instance>,
+    objectives: Vec<Objective>,
+    //solutions with their objective values
+    solutions: Vec<(SendableSolution, Vec<f64>)>,
+}
+
+impl ParetoArchive {
+    pub fn new(instance: Arc<Instance>, objectives: Vec<Objective>) -> Self {
+        assert!(!objectives.is_empty(), "at least one objective is required");
+        Self {
+            instance,
+            objectives,
+            solutions: vec![],
+        }
+    }
+
+    /// Archived solutions, in the order they were added
+    pub fn solutions(&self) -> Vec<&SendableSolution> {
+        self.solutions.iter().map(|(s, _)| s).collect()
+    }
+
+    /// Removes all solutions from the archive, sorted by the values of the objectives (in order of the objectives)
+    pub fn take_solutions(&mut self) -> Vec<SendableSolution> {
+        std::mem::take(&mut self.solutions).into_iter()
+            .sorted_by(|(_, a), (_, b)| a.iter().zip(b).map(|(x, y)| x.total_cmp(y)).find(|o| o.is_ne()).unwrap_or(Ordering::Equal))
+            .map(|(s, _)| s)
+            .collect()
+    }
+}
+
+impl SolutionArchive for ParetoArchive {
+    /// Adds the solution to the archive if it is complete and not dominated by (or equivalent to) any archived solution,
+    /// removing all archived solutions it dominates.
+    fn offer(&mut self, solution: &ProblemSolution) {
+        if !solution.is_complete() {
+            return;
+        }
+        let values = self.objectives.iter().map(|o| o.evaluate(solution)).collect_vec();
+        if self.solutions.iter().any(|(_, v)| *v == values || objective::dominates(v, &values)) {
+            return;
+        }
+        self.solutions.retain(|(_, v)| !objective::dominates(&values, v));
+        self.solutions.push((SendableSolution::new(self.instance.clone(), solution), values));
+    }
+}
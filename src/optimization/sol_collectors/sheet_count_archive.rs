@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::optimization::instance::Instance;
+use crate::optimization::sol_collectors::solution_archive::SolutionArchive;
+use crate::optimization::solutions::problem_solution::ProblemSolution;
+use crate::optimization::solutions::sendable_solution::SendableSolution;
+use crate::optimization::solutions::solution::Solution;
+
+/// Archive of the best complete solution for every number of sheets, according to the cost comparator.
+/// Between solutions of equal cost, the first one offered is kept.
+pub struct SheetCountArchive {
+    instance: Arc<Instance>,
+    solutions: BTreeMap<usize, SendableSolution>,
+}
+
+impl SheetCountArchive {
+    pub fn new(instance: Arc<Instance>) -> Self {
+        Self {
+            instance,
+            solutions: BTreeMap::new(),
+        }
+    }
+
+    /// Best archived solution for every number of sheets
+    pub fn solutions(&self) -> &BTreeMap<usize, SendableSolution> {
+        &self.solutions
+    }
+
+    /// Removes all solutions from the archive
+    pub fn take_solutions(&mut self) -> BTreeMap<usize, SendableSolution> {
+        std::mem::take(&mut self.solutions)
+    }
+}
+
+impl SolutionArchive for SheetCountArchive {
+    /// Keeps the solution if it is complete and better than the archived solution with the same number of sheets
+    fn offer(&mut self, solution: &ProblemSolution) {
+        if !solution.is_complete() {
+            return;
+        }
+        let improves = match self.solutions.get(&solution.n_layouts()) {
+            Some(archived) => crate::COST_COMPARATOR(solution.cost(), archived.cost()).is_lt(),
+            None => true,
+        };
+        if improves {
+            self.solutions.insert(solution.n_layouts(), SendableSolution::new(self.instance.clone(), solution));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use itertools::Itertools;
+
+    use crate::optimization::sol_collectors::sheet_count_archive::SheetCountArchive;
+    use crate::optimization::sol_collectors::solution_archive::SolutionArchive;
+    use crate::optimization::solutions::solution::Solution;
+    use crate::util::test_util;
+
+    #[test]
+    fn the_best_solution_of_every_sheet_count_is_archived() {
+        let instance = Arc::new(test_util::instance(&[(50, 100, 1), (25, 100, 2)], &[(100, 100, 10)]));
+        let mut archive = SheetCountArchive::new(instance.clone());
+        let one_sheet = test_util::problem_solution(&instance, &[(0, &[(0, 0, 0), (1, 50, 0), (1, 75, 0)])]);
+        //the same parts on two sheets, with the leftovers split differently
+        let two_sheets = [
+            test_util::problem_solution(&instance, &[(0, &[(0, 0, 0)]), (0, &[(1, 0, 0), (1, 25, 0)])]),
+            test_util::problem_solution(&instance, &[(0, &[(0, 0, 0), (1, 50, 0)]), (0, &[(1, 0, 0)])]),
+        ];
+        let best_two_sheets = two_sheets.iter().map(|s| s.cost()).min_by(|a, b| crate::COST_COMPARATOR(a, b)).unwrap();
+        assert_ne!(two_sheets[0].cost(), two_sheets[1].cost());
+
+        //incomplete solutions are never archived
+        archive.offer(&test_util::problem_solution(&instance, &[(0, &[(0, 0, 0)])]));
+        assert!(archive.solutions().is_empty());
+
+        for solution in two_sheets.iter().chain([&one_sheet]) {
+            archive.offer(solution);
+        }
+        assert_eq!(archive.solutions().keys().copied().collect_vec(), vec![1, 2]);
+        assert_eq!(archive.solutions()[&1].cost(), one_sheet.cost());
+        assert_eq!(archive.solutions()[&2].cost(), best_two_sheets);
+
+        //offering them again in the reverse order keeps the same solutions
+        for solution in two_sheets.iter().rev() {
+            archive.offer(solution);
+        }
+        assert_eq!(archive.solutions()[&2].cost(), best_two_sheets);
+        assert_eq!(archive.take_solutions().len(), 2);
+        assert!(archive.solutions().is_empty());
+    }
+}
@@ -0,0 +1,7 @@
+use crate::optimization::solutions::problem_solution::ProblemSolution;
+
+/// Collects solutions encountered during the search beyond the single best one, see GDRR::set_archive
+pub trait SolutionArchive {
+    /// Offers a solution accepted by the search, the archive decides whether to keep a copy of it
+    fn offer(&mut self, solution: &ProblemSolution);
+}
@@ -0,0 +1,20 @@
+use crate::core::rotation::Rotation;
+
+/// Label of a single part placed in a solution, see Solution::to_label_list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelEntry {
+    /// Index of the layout in the solution
+    pub layout: usize,
+    pub sheettype_id: usize,
+    /// Position (starting at 1) of the part in the extraction order of its layout
+    pub sequence: usize,
+    pub parttype_id: usize,
+    /// Position of the part relative to the top-left corner of the sheet (including its margins)
+    pub x: u64,
+    pub y: u64,
+    /// Dimensions of the part as placed (swapped if it is rotated), excluding kerf
+    pub width: u64,
+    pub height: u64,
+    pub rotation: Rotation,
+    pub label: String,
+}
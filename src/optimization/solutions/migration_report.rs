@@ -0,0 +1,14 @@
+/// Outcome of SendableSolution::apply_instance_delta, all parts are listed by parttype id
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MigrationReport {
+    /// Parts removed from the layouts
+    pub removed_parts: Vec<usize>,
+    /// Parts inserted in the free space of the existing layouts
+    pub inserted_in_existing: Vec<usize>,
+    /// Parts inserted in newly opened sheets
+    pub inserted_in_new: Vec<usize>,
+    /// Parts which could not be placed in any existing or new sheet
+    pub unplaced_parts: Vec<usize>,
+    /// Number of layouts which were released because all of their parts were removed
+    pub n_released_layouts: usize,
+}
@@ -1,4 +1,9 @@
 pub mod sendable_solution;
 pub mod problem_solution;
 pub mod solution;
-pub mod solution_stats;
\ No newline at end of file
+pub mod solution_stats;
+pub mod solution_diff;
+pub mod tiebreak;
+pub mod objective;
+pub mod migration_report;
+pub mod label_entry;
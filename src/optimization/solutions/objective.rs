@@ -0,0 +1,73 @@
+use serde::{Deserialize, Serialize};
+
+use crate::optimization::solutions::solution::Solution;
+
+/// Metrics of a solution which can be used as objectives of a multi-objective search, see ParetoArchive
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Metric {
+    /// Material cost, including all penalties
+    MaterialCost,
+    /// Total value of the leftover pieces, see leftover_valuator::valuate
+    LeftoverValue,
+    /// Fraction of the sheets covered by parts
+    Usage,
+    /// Number of sheets used
+    NLayouts,
+    /// Total number of cuts, see Solution::cut_count
+    CutCount,
+    /// How scattered the waste is, see Solution::fragmentation_index
+    FragmentationIndex,
+}
+
+impl Metric {
+    pub fn evaluate<S: Solution + ?Sized>(&self, solution: &S) -> f64 {
+        match self {
+            Metric::MaterialCost => solution.cost().material_cost as f64,
+            Metric::LeftoverValue => solution.cost().leftover_value as f64,
+            Metric::Usage => solution.usage(),
+            Metric::NLayouts => solution.n_layouts() as f64,
+            Metric::CutCount => solution.cut_count() as f64,
+            Metric::FragmentationIndex => solution.fragmentation_index(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum Direction {
+    #[default]
+    Minimize,
+    Maximize,
+}
+
+/// A metric to be minimized or maximized
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub struct Objective {
+    pub metric: Metric,
+    #[serde(default)]
+    pub direction: Direction,
+}
+
+impl Objective {
+    pub fn new(metric: Metric, direction: Direction) -> Self {
+        Self { metric, direction }
+    }
+
+    /// Value of the objective for the solution, negated for maximized metrics so lower values are always better
+    pub fn evaluate<S: Solution + ?Sized>(&self, solution: &S) -> f64 {
+        let value = self.metric.evaluate(solution);
+        match self.direction {
+            Direction::Minimize => value,
+            Direction::Maximize => -value,
+        }
+    }
+}
+
+/// Whether the objective values a are at least as good as b for every objective, and strictly better for at least one,
+/// with the values as returned by Objective::evaluate
+pub fn dominates(a: &[f64], b: &[f64]) -> bool {
+    debug_assert!(a.len() == b.len());
+    a.iter().zip(b).all(|(x, y)| x <= y) && a.iter().zip(b).any(|(x, y)| x < y)
+}
@@ -1,11 +1,14 @@
+use std::fmt;
 use std::rc::Rc;
 
 use indexmap::IndexMap;
 
 use crate::core::cost::Cost;
 use crate::core::entities::layout::Layout;
+use crate::core::insertion::node_blueprint::NodeBlueprint;
 use crate::optimization::instance::Instance;
 use crate::optimization::problem::Problem;
+use crate::optimization::solutions::solution;
 use crate::optimization::solutions::solution::Solution;
 use crate::util::assertions;
 
@@ -93,6 +96,9 @@ impl<'a> ProblemSolution<'a> {
 }
 
 impl<'a> Solution for ProblemSolution<'a> {
+    fn instance(&self) -> &Instance {
+        self.instance
+    }
     fn cost(&self) -> &Cost {
         &self.cost
     }
@@ -108,4 +114,18 @@ impl<'a> Solution for ProblemSolution<'a> {
     fn usage(&self) -> f64 {
         self.usage
     }
+    fn layout_usages(&self) -> Vec<f64> {
+        self.layouts.values().map(|l| l.usage_immut(false)).collect()
+    }
+    fn layout_patterns(&self) -> Vec<(usize, NodeBlueprint)> {
+        self.layouts.values()
+            .map(|l| (l.sheettype().id(), NodeBlueprint::from_node(*l.top_node_index(), l.nodes())))
+            .collect()
+    }
+}
+
+impl<'a> fmt::Display for ProblemSolution<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        solution::fmt_summary(self, f)
+    }
 }
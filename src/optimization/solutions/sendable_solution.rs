@@ -1,8 +1,20 @@
+use std::cmp::Reverse;
+use std::fmt;
 use std::sync::Arc;
+
+use itertools::Itertools;
+
 use crate::core::cost::Cost;
+use crate::core::empty_node_order::EmptyNodeOrder;
 use crate::core::entities::sendable_layout::SendableLayout;
+use crate::core::insertion::insertion_blueprint::InsertionBlueprint;
+use crate::core::insertion::node_blueprint::NodeBlueprint;
+use crate::core::layout_index::LayoutIndex;
 use crate::optimization::instance::Instance;
+use crate::optimization::problem::Problem;
+use crate::optimization::solutions::migration_report::MigrationReport;
 use crate::optimization::solutions::problem_solution::ProblemSolution;
+use crate::optimization::solutions::solution;
 use crate::optimization::solutions::solution::Solution;
 
 /// Representation of a solution, based on ProblemSolution, but that can be sent across threads
@@ -21,7 +33,14 @@ impl SendableSolution {
     pub fn new(instance: Arc<Instance>, problem_solution: &ProblemSolution) -> SendableSolution {
         debug_assert!(instance.as_ref() as *const _ == problem_solution.instance() as *const _);
 
-        let layouts = problem_solution.layouts().iter().map(|(_id, l)| SendableLayout::new(l)).collect();
+        let layouts = match instance.urgency_weight() > 0.0 {
+            //the layouts with the most urgent parts are cut first, see Problem::urgency_penalty
+            true => problem_solution.layouts().iter()
+                .sorted_by_key(|(id, l)| (Reverse(l.urgency_area(&instance)), **id))
+                .map(|(_, l)| SendableLayout::new(l))
+                .collect(),
+            false => problem_solution.layouts().iter().map(|(_id, l)| SendableLayout::new(l)).collect(),
+        };
         let cost = problem_solution.cost().clone();
         let usage = problem_solution.usage();
         let parttype_qtys = problem_solution.parttype_qtys().clone();
@@ -44,10 +63,94 @@ impl SendableSolution {
     pub fn instance(&self) -> &Arc<Instance> {
         &self.instance
     }
+
+    /// Migrates the solution to an edited version of its instance, without solving it again.
+    /// The edited instance needs to contain the sheettypes and parttypes of the current one under the same ids,
+    /// while the demands may differ and additional parttypes can be appended.
+    /// First, the parts listed in `removed` (by parttype id, once per copy) are taken out of the layouts, as well as any copies exceeding the new demand.
+    /// Then, the missing parts are inserted (by descending area) in the free space of the existing layouts,
+    /// or in newly opened sheets if they do not fit. All other parts remain in place.
+    /// Removed parts still demanded by the edited instance are inserted again, which allows to re-place specific parts.
+    pub fn apply_instance_delta(&mut self, instance: Arc<Instance>, removed: &[usize]) -> MigrationReport {
+        assert!(self.instance.sheets().len() == instance.sheets().len() && self.instance.parts().len() <= instance.parts().len(),
+            "the edited instance needs to contain all sheettypes and parttypes of the current one");
+        let mut report = MigrationReport::default();
+        let mut problem = Problem::new(&instance, EmptyNodeOrder::default());
+
+        //Copies of every parttype to remove, including the ones exceeding the new demand
+        let mut to_remove = vec![0; instance.parts().len()];
+        for &id in removed {
+            to_remove[id] += 1;
+        }
+        for (id, (_, demand)) in self.instance.parts().iter().enumerate() {
+            let placed = demand - self.parttype_qtys[id];
+            let remaining = placed.saturating_sub(to_remove[id]);
+            to_remove[id] = placed - remaining + remaining.saturating_sub(instance.parts()[id].1);
+        }
+
+        for sendable_layout in &self.layouts {
+            let id = problem.next_layout_id();
+            let mut layout = sendable_layout.convert_to_layout(id, &instance, EmptyNodeOrder::default());
+            let part_nodes = layout.nodes().iter()
+                .filter_map(|(i, n)| n.part_id().map(|part_id| (i, part_id)))
+                .collect_vec();
+            //removing a part only affects empty nodes, the other part nodes remain valid
+            for (node_index, part_id) in part_nodes {
+                if to_remove[part_id] > 0 {
+                    layout.remove_node(node_index);
+                    to_remove[part_id] -= 1;
+                    report.removed_parts.push(part_id);
+                }
+            }
+            match layout.included_parts_unordered().is_empty() {
+                true => report.n_released_layouts += 1,
+                false => { problem.register_layout(layout); }
+            }
+        }
+
+        let missing_parts = (0..instance.parts().len())
+            .flat_map(|id| std::iter::repeat_n(id, problem.parttype_qtys()[id]))
+            .sorted_by_key(|id| Reverse(instance.get_parttype(*id).area()))
+            .collect_vec();
+        for part_id in missing_parts {
+            let parttype = instance.get_parttype(part_id);
+            let existing_option = problem.layouts().iter()
+                .flat_map(|(i, l)| l.insertion_options(LayoutIndex::Existing(i), parttype, &instance))
+                .min_by(|a, b| crate::COST_COMPARATOR(a.cost(), b.cost()));
+            let option = existing_option.or_else(|| {
+                problem.empty_layouts().iter().enumerate()
+                    .filter(|(_, l)| problem.sheettype_qtys()[l.sheettype().id()] > 0)
+                    .flat_map(|(i, l)| l.insertion_options(LayoutIndex::Empty(i), parttype, &instance))
+                    .min_by(|a, b| {
+                        let material_cost = |bp: &InsertionBlueprint| problem.layout_material_cost(problem.get_layout(bp.layout_index()).sheettype());
+                        material_cost(a).cmp(&material_cost(b))
+                            .then_with(|| crate::COST_COMPARATOR(a.cost(), b.cost()))
+                    })
+            });
+            match option {
+                Some(option) => {
+                    let in_existing = matches!(option.layout_index(), LayoutIndex::Existing(_));
+                    problem.implement_insertion_blueprint(&option).expect("insertion option is valid");
+                    match in_existing {
+                        true => report.inserted_in_existing.push(part_id),
+                        false => report.inserted_in_new.push(part_id),
+                    }
+                }
+                None => report.unplaced_parts.push(part_id),
+            }
+        }
+
+        let problem_solution = problem.create_solution(&None, None);
+        *self = SendableSolution::new(instance.clone(), &problem_solution);
+        report
+    }
 }
 
 
 impl Solution for SendableSolution {
+    fn instance(&self) -> &Instance {
+        &self.instance
+    }
     fn cost(&self) -> &Cost {
         &self.cost
     }
@@ -63,4 +166,67 @@ impl Solution for SendableSolution {
     fn usage(&self) -> f64 {
         self.usage
     }
-}
\ No newline at end of file
+    fn layout_usages(&self) -> Vec<f64> {
+        self.layouts.iter().map(|l| l.usage()).collect()
+    }
+    fn layout_patterns(&self) -> Vec<(usize, NodeBlueprint)> {
+        self.layouts.iter()
+            .map(|l| (l.sheettype_id(), l.top_node().clone()))
+            .collect()
+    }
+}
+
+impl fmt::Display for SendableSolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        solution::fmt_summary(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use itertools::Itertools;
+
+    use crate::optimization::solutions::sendable_solution::SendableSolution;
+    use crate::optimization::solutions::solution::Solution;
+    use crate::optimization::solutions::solution_diff::Placement;
+    use crate::util::test_util;
+
+    fn placements(solution: &SendableSolution) -> Vec<Placement> {
+        solution.layouts().iter().enumerate()
+            .flat_map(|(i, l)| Placement::extract(i, l.sheettype_id(), l.top_node()))
+            .collect_vec()
+    }
+
+    #[test]
+    fn an_added_part_fills_the_free_space_without_moving_the_others() {
+        let instance = Arc::new(test_util::instance(&[(50, 50, 3)], &[(100, 100, 2)]));
+        let mut solution = SendableSolution::new(instance.clone(), &test_util::problem_solution(&instance, &[(0, &[(0, 0, 0), (0, 50, 0), (0, 0, 50)])]));
+        let before = placements(&solution);
+
+        //one more copy of the part and a part which only fits on a new sheet
+        let edited = Arc::new(test_util::instance(&[(50, 50, 4), (100, 100, 1)], &[(100, 100, 2)]));
+        let report = solution.apply_instance_delta(edited, &[]);
+
+        assert_eq!((report.inserted_in_existing, report.inserted_in_new), (vec![0], vec![1]));
+        assert!(report.removed_parts.is_empty() && report.unplaced_parts.is_empty());
+        assert!(solution.is_complete());
+        let after = placements(&solution);
+        assert_eq!(after.len(), 5);
+        assert!(before.iter().all(|p| after.contains(p)));
+    }
+
+    #[test]
+    fn removed_parts_release_their_layout() {
+        let instance = Arc::new(test_util::instance(&[(50, 50, 3), (100, 100, 1)], &[(100, 100, 2)]));
+        let mut solution = SendableSolution::new(instance.clone(), &test_util::problem_solution(&instance, &[(0, &[(0, 0, 0), (0, 50, 0), (0, 0, 50)]), (0, &[(1, 0, 0)])]));
+
+        let edited = Arc::new(test_util::instance(&[(50, 50, 2), (100, 100, 0)], &[(100, 100, 2)]));
+        let report = solution.apply_instance_delta(edited, &[]);
+
+        assert_eq!((report.removed_parts, report.n_released_layouts), (vec![0, 1], 1));
+        assert_eq!(solution.layouts().len(), 1);
+        assert!(solution.is_complete());
+    }
+}
@@ -1,6 +1,22 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use itertools::Itertools;
+
 use crate::core::cost::Cost;
+use crate::core::entities::parttype::PartType;
+use crate::core::insertion::node_blueprint::NodeBlueprint;
+use crate::core::origin_corner::OriginCorner;
+use crate::core::reading_order::ReadingOrder;
+use crate::core::rotation::Rotation;
+use crate::core::entities::placed_part::PlacedPart;
+use crate::optimization::instance::Instance;
+use crate::optimization::solutions::label_entry::LabelEntry;
+use crate::optimization::solutions::solution_diff::{Placement, SolutionDiff};
 
 pub trait Solution {
+    fn instance(&self) -> &Instance;
+
     fn cost(&self) -> &Cost;
 
     fn n_layouts(&self) -> usize;
@@ -14,4 +30,377 @@ pub trait Solution {
     }
 
     fn usage(&self) -> f64;
-}
\ No newline at end of file
+
+    /// Number of parts (of all parttypes) which are not included in the solution
+    fn n_unplaced_parts(&self) -> usize {
+        self.parttype_qtys().iter().sum()
+    }
+
+    /// Fraction of the total demanded part area which is placed in the solution, 1.0 if nothing is demanded.
+    /// Independent of the cost, e.g. for reporting the progress of the search.
+    fn placed_area_fraction(&self) -> f64 {
+        let total_part_area = self.instance().total_part_area();
+        let unplaced_part_area: u64 = self.parttype_qtys().iter().enumerate()
+            .map(|(id, qty)| self.instance().get_parttype(id).area() * *qty as u64)
+            .sum();
+        match total_part_area {
+            0 => 1.0,
+            _ => (total_part_area - unplaced_part_area) as f64 / total_part_area as f64
+        }
+    }
+
+    /// Usage of every layout in the solution
+    fn layout_usages(&self) -> Vec<f64>;
+
+    /// Distribution of the usage of the layouts over equally sized buckets in [0, 1].
+    /// Every bucket is returned as (low, high, number of layouts), including its low bound but excluding its high bound,
+    /// except for the final bucket, which also includes 1.0.
+    fn usage_histogram(&self, buckets: usize) -> Vec<(f64, f64, usize)> {
+        assert!(buckets > 0, "at least one bucket is required");
+        let mut counts = vec![0; buckets];
+        for usage in self.layout_usages() {
+            let bucket = ((usage * buckets as f64) as usize).min(buckets - 1);
+            counts[bucket] += 1;
+        }
+        counts.into_iter().enumerate()
+            .map(|(i, count)| (i as f64 / buckets as f64, (i + 1) as f64 / buckets as f64, count))
+            .collect()
+    }
+
+    /// Sheettype id and cutting pattern of every layout in the solution
+    fn layout_patterns(&self) -> Vec<(usize, NodeBlueprint)>;
+
+    /// Sum of the values of the sheets of all layouts containing parts, once per sheet.
+    /// Equal to the material cost, except for the sheettype and sheet open penalties of the instance, which are only included in the latter.
+    fn material_value(&self) -> u64 {
+        self.layout_patterns().iter()
+            .filter(|(_, pattern)| pattern.waste_area() < pattern.area())
+            .map(|(sheettype_id, _)| self.instance().get_sheettype(*sheettype_id).value())
+            .sum()
+    }
+
+    /// Total weight of the waste (empty nodes) in all layouts, None if the density of any of the used sheettypes is unknown
+    fn total_scrap_weight(&self) -> Option<f64> {
+        self.layout_patterns().iter()
+            .map(|(sheettype_id, pattern)| {
+                self.instance().get_sheettype(*sheettype_id).density()
+                    .map(|density| density * pattern.waste_area() as f64)
+            })
+            .sum()
+    }
+
+    /// How scattered the waste of the solution is: 1 - (largest empty area / total empty area) over all layouts, see Layout::fragmentation_index
+    fn fragmentation_index(&self) -> f64 {
+        let patterns = self.layout_patterns();
+        let total: u64 = patterns.iter().map(|(_, p)| p.waste_area()).sum();
+        match total {
+            0 => 0.0,
+            _ => {
+                let largest = patterns.iter().map(|(_, p)| p.largest_waste_area()).max().unwrap_or(0);
+                1.0 - largest as f64 / total as f64
+            }
+        }
+    }
+
+    /// Heuristic attribution of the waste to the parttypes causing it, as the attributed area per parttype id.
+    /// The area of every empty node is split over the parts bordering it, in proportion to the length of the boundary they share with it
+    /// (touching corners do not count), and summed per parttype. The area of empty nodes which border no part is not attributed.
+    fn waste_attribution(&self) -> HashMap<usize, f64> {
+        let mut attribution = HashMap::new();
+        for (i, (sheettype_id, pattern)) in self.layout_patterns().iter().enumerate() {
+            let placements = Placement::extract(i, *sheettype_id, pattern);
+            for (x, y, width, height) in pattern.waste_rects() {
+                let shared_lengths = placements.iter()
+                    .map(|p| {
+                        let x_overlap = (x + width).min(p.x + p.width) as i128 - x.max(p.x) as i128;
+                        let y_overlap = (y + height).min(p.y + p.height) as i128 - y.max(p.y) as i128;
+                        let length = match (x_overlap, y_overlap) {
+                            (0, overlap) | (overlap, 0) if overlap > 0 => overlap as u64,
+                            _ => 0,
+                        };
+                        (p.parttype_id, length)
+                    })
+                    .filter(|(_, length)| *length > 0)
+                    .collect_vec();
+                let total_length: u64 = shared_lengths.iter().map(|(_, length)| length).sum();
+                for (parttype_id, length) in shared_lengths {
+                    *attribution.entry(parttype_id).or_insert(0.0) += (width * height) as f64 * length as f64 / total_length as f64;
+                }
+            }
+        }
+        attribution
+    }
+
+    /// Total number of cuts of all layouts, see Layout::cut_count
+    fn cut_count(&self) -> usize {
+        self.layout_patterns().iter().map(|(_, p)| p.cut_count()).sum()
+    }
+
+    /// Deepest level of nesting in any of the layouts
+    fn max_level(&self) -> u8 {
+        self.layout_patterns().iter().map(|(_, p)| p.depth()).max().unwrap_or(0)
+    }
+
+    /// Estimate of how many more copies of the parttype fit in the empty space of the existing layouts, without modifying the solution.
+    /// Every empty node is filled independently with a grid of copies in one of the allowed rotations, see NodeBlueprint::grid_capacity.
+    /// Stage limits and the waste and parttype constraints of the instance are not considered,
+    /// so the estimate can slightly exceed the number of copies an actual insertion (e.g. Layout::fill_with) manages to place.
+    fn additional_capacity(&self, parttype: &PartType) -> usize {
+        let part_sizes = match parttype.fixed_rotation() {
+            Some(rotation) => vec![parttype.size_for(*rotation)],
+            None => vec![parttype.size(), parttype.rotated_size()],
+        };
+        self.layout_patterns().iter()
+            .map(|(_, pattern)| pattern.grid_capacity(&part_sizes))
+            .sum()
+    }
+
+    /// Reports what changed going from this solution to the other one
+    fn diff(&self, other: &dyn Solution) -> SolutionDiff {
+        SolutionDiff::new(&self.layout_patterns(), &other.layout_patterns())
+    }
+
+    /// One label for every placed part, labeled by label_fn from its parttype id.
+    /// Ordered by layout and, within a layout, by extraction order (row major, see Layout::extraction_order).
+    fn to_label_list(&self, label_fn: &dyn Fn(usize) -> String) -> Vec<LabelEntry> {
+        let instance = self.instance();
+        self.layout_patterns().iter().enumerate().flat_map(|(i, (sheettype_id, pattern))| {
+            let margins = instance.get_sheettype(*sheettype_id).margins();
+            Placement::extract(i, *sheettype_id, pattern).into_iter()
+                .map(|p| {
+                    let parttype = instance.get_parttype(p.parttype_id);
+                    let rotation = match (p.width, p.height) == (parttype.size().width(), parttype.size().height()) {
+                        true => Rotation::Default,
+                        false => Rotation::Rotated,
+                    };
+                    PlacedPart { parttype_id: p.parttype_id, x: p.x + margins.left, y: p.y + margins.top, width: p.width, height: p.height, rotation }
+                })
+                .sorted_by_key(|p| ReadingOrder::RowMajor.key(p))
+                .enumerate()
+                .map(|(j, p)| {
+                    let parttype = instance.get_parttype(p.parttype_id);
+                    let (width, height) = match p.rotation {
+                        Rotation::Default => (parttype.width(), parttype.height()),
+                        Rotation::Rotated => (parttype.height(), parttype.width()),
+                    };
+                    LabelEntry {
+                        layout: i,
+                        sheettype_id: *sheettype_id,
+                        sequence: j + 1,
+                        parttype_id: p.parttype_id,
+                        x: p.x,
+                        y: p.y,
+                        width,
+                        height,
+                        rotation: p.rotation,
+                        label: label_fn(p.parttype_id),
+                    }
+                })
+                .collect_vec()
+        }).collect_vec()
+    }
+
+    /// Cutting patterns in the format of external saw control software, with the parts labeled by their id
+    /// and coordinates relative to the top-left corner of the sheet. See to_cutting_json_with.
+    fn to_cutting_json(&self) -> serde_json::Value {
+        self.to_cutting_json_with(&|parttype| parttype.id().to_string(), OriginCorner::TopLeft)
+    }
+
+    /// Cutting patterns in the format of external saw control software: an array of panels, one per layout,
+    /// each with the dimensions of the sheet and a list of the pieces placed on it.
+    /// Coordinates are relative to the origin corner of the sheet (including its margins), the dimensions of the pieces exclude kerf
+    /// and are given as placed (swapped if the piece is rotated).
+    fn to_cutting_json_with(&self, label: &dyn Fn(&PartType) -> String, origin: OriginCorner) -> serde_json::Value {
+        let instance = self.instance();
+        let panels = self.layout_patterns().iter().enumerate().map(|(i, (sheettype_id, pattern))| {
+            let sheettype = instance.get_sheettype(*sheettype_id);
+            let margins = sheettype.margins();
+            let sheet_size = (pattern.width() + margins.horizontal(), pattern.height() + margins.vertical());
+            let pieces = Placement::extract(i, *sheettype_id, pattern).into_iter()
+                .map(|p| {
+                    let (x, y) = origin.transform((p.x + margins.left, p.y + margins.top), (p.width, p.height), sheet_size);
+                    Placement { x, y, ..p }
+                })
+                .sorted_by_key(|p| (p.y, p.x))
+                .map(|p| {
+                    let parttype = instance.get_parttype(p.parttype_id);
+                    let rotated = (p.width, p.height) != (parttype.size().width(), parttype.size().height());
+                    let (length, width) = match rotated {
+                        true => (parttype.height(), parttype.width()),
+                        false => (parttype.width(), parttype.height()),
+                    };
+                    serde_json::json!({
+                        "x": p.x,
+                        "y": p.y,
+                        "length": length,
+                        "width": width,
+                        "label": label(parttype),
+                        "rotated": rotated,
+                    })
+                })
+                .collect_vec();
+            serde_json::json!({
+                "length": sheettype.width(),
+                "width": sheettype.height(),
+                "pieces": pieces,
+            })
+        }).collect_vec();
+
+        serde_json::Value::Array(panels)
+    }
+}
+
+/// Concise human-readable summary of a solution, used by the Display implementations of the solutions
+pub fn fmt_summary(solution: &dyn Solution, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    writeln!(f, "{:<16}{}", "Sheets:", solution.n_layouts())?;
+    writeln!(f, "{:<16}{}", "Material cost:", solution.cost().material_cost)?;
+    writeln!(f, "{:<16}{:.3}%", "Usage:", solution.usage() * 100.0)?;
+    write!(f, "{:<16}{}", "Unplaced parts:", solution.n_unplaced_parts())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use itertools::Itertools;
+    use serde_json::json;
+
+    use crate::core::leftover_valuator;
+    use crate::core::origin_corner::OriginCorner;
+    use crate::core::rotation::Rotation;
+    use crate::io::parser;
+    use crate::optimization::instance::Instance;
+    use crate::optimization::solutions::sendable_solution::SendableSolution;
+    use crate::optimization::solutions::solution::Solution;
+    use crate::util::test_util;
+
+    #[test]
+    fn display_summarizes_the_solution() {
+        let instance = test_util::instance(&[(50, 100, 1), (50, 50, 2)], &[(100, 100, 2)]);
+        let solution = test_util::problem_solution(&instance, &[(0, &[(0, 0, 0)])]);
+
+        assert_eq!(solution.to_string(), "Sheets:         1\nMaterial cost:  10000\nUsage:          50.000%\nUnplaced parts: 2");
+    }
+
+    #[test]
+    fn usage_histogram_counts_every_layout_once() {
+        let instance = test_util::instance(&[(30, 100, 1), (60, 100, 1), (90, 100, 1)], &[(100, 100, 3)]);
+        let solution = test_util::problem_solution(&instance, &[(0, &[(0, 0, 0)]), (0, &[(1, 0, 0)]), (0, &[(2, 0, 0)])]);
+
+        let histogram = solution.usage_histogram(3);
+        assert_eq!(histogram.iter().map(|(_, _, count)| *count).collect::<Vec<_>>(), vec![1, 1, 1]);
+        assert_eq!((histogram[0].0, histogram[2].1), (0.0, 1.0));
+        //a full sheet belongs to the final bucket
+        let full = test_util::problem_solution(&instance, &[(0, &[(0, 0, 0), (1, 30, 0)])]);
+        assert_eq!(full.usage_histogram(3)[2].2, 1);
+    }
+
+    #[test]
+    fn cutting_json_lists_the_pieces_of_every_panel() {
+        let instance = test_util::instance(&[(40, 30, 1), (20, 50, 1)], &[(100, 100, 1)]);
+        let solution = test_util::problem_solution(&instance, &[(0, &[(0, 0, 0), (1, 40, 0)])]);
+
+        assert_eq!(solution.to_cutting_json(), json!([{
+            "length": 100,
+            "width": 100,
+            "pieces": [
+                {"x": 0, "y": 0, "length": 40, "width": 30, "label": "0", "rotated": false},
+                {"x": 40, "y": 0, "length": 20, "width": 50, "label": "1", "rotated": false},
+            ],
+        }]));
+
+        let labeled = solution.to_cutting_json_with(&|parttype| format!("part {}", parttype.id() + 1), OriginCorner::TopLeft);
+        assert_eq!(labeled[0]["pieces"][1]["label"], "part 2");
+    }
+
+    #[test]
+    fn scrap_weight_is_the_density_times_the_waste_area() {
+        let parts = vec![(test_util::parttype(0, 40, 30), 2), (test_util::parttype(1, 30, 50), 1)];
+        let sheets = vec![(test_util::sheettype(0, 100, 100).with_density(0.5), 2)];
+        let instance = Instance::new(parts, sheets, 0, false);
+        leftover_valuator::set_power(2.0);
+        //waste of 10000 - 1200 - 1500 = 7300 and 10000 - 1200 = 8800
+        let solution = test_util::problem_solution(&instance, &[(0, &[(0, 0, 0), (1, 40, 0)]), (0, &[(0, 0, 0)])]);
+        assert_eq!(solution.total_scrap_weight(), Some(0.5 * 7300.0 + 0.5 * 8800.0));
+
+        let without_density = test_util::instance(&[(40, 30, 1)], &[(100, 100, 1)]);
+        assert_eq!(test_util::problem_solution(&without_density, &[(0, &[(0, 0, 0)])]).total_scrap_weight(), None);
+    }
+
+    #[test]
+    fn additional_capacity_counts_the_copies_fitting_in_the_empty_space() {
+        //40x100 and 10x100 of empty space, a 20x30 part fits 2x3 times in the first in its default rotation
+        let instance = test_util::instance(&[(60, 100, 1), (90, 100, 1), (20, 30, 1), (30, 30, 1)], &[(100, 100, 2)]);
+        let solution = test_util::problem_solution(&instance, &[(0, &[(0, 0, 0)]), (0, &[(1, 0, 0)])]);
+
+        assert_eq!(solution.additional_capacity(instance.get_parttype(2)), 6);
+        assert_eq!(solution.additional_capacity(instance.get_parttype(3)), 3);
+        assert_eq!(solution.additional_capacity(instance.get_parttype(0)), 0);
+    }
+
+    #[test]
+    fn placed_area_fraction_is_the_share_of_the_demanded_area_placed() {
+        //half of the demanded area, but only a third of the parts
+        let instance = test_util::instance(&[(50, 100, 1), (25, 100, 2)], &[(100, 100, 1)]);
+        assert_eq!(test_util::problem_solution(&instance, &[(0, &[(0, 0, 0)])]).placed_area_fraction(), 0.5);
+        assert_eq!(test_util::problem_solution(&instance, &[(0, &[(0, 0, 0), (1, 50, 0), (1, 75, 0)])]).placed_area_fraction(), 1.0);
+
+        let nothing_demanded = test_util::instance(&[(50, 100, 0)], &[(100, 100, 1)]);
+        assert_eq!(test_util::problem_solution(&nothing_demanded, &[]).placed_area_fraction(), 1.0);
+    }
+
+    #[test]
+    fn label_list_has_one_label_per_placed_part_in_extraction_order() {
+        let instance = test_util::instance(&[(40, 30, 3), (20, 50, 1)], &[(100, 100, 2)]);
+        let solution = test_util::problem_solution(&instance, &[(0, &[(1, 40, 0), (0, 0, 0), (0, 0, 50)]), (0, &[(0, 0, 0)])]);
+
+        let labels = solution.to_label_list(&|parttype_id| format!("order {}", parttype_id + 1));
+        //all parts are placed, each is labeled once
+        assert!(solution.is_complete());
+        let entries = labels.iter().map(|l| (l.layout, l.sequence, l.parttype_id, l.x, l.y, l.width, l.height, l.label.as_str())).collect_vec();
+        assert_eq!(entries, vec![
+            (0, 1, 0, 0, 0, 40, 30, "order 1"),
+            (0, 2, 1, 40, 0, 20, 50, "order 2"),
+            (0, 3, 0, 0, 50, 40, 30, "order 1"),
+            (1, 1, 0, 0, 0, 40, 30, "order 1"),
+        ]);
+        assert!(labels.iter().all(|l| l.sheettype_id == 0 && l.rotation == Rotation::Default));
+    }
+
+    #[test]
+    fn material_value_sums_the_values_of_the_used_sheets() {
+        let mut json_instance = test_util::json_instance(&[(50, 50, 3)], &[(100, 100, 2), (50, 100, 2)]);
+        json_instance.sheettypes[0].cost = 700;
+        json_instance.sheettypes[1].cost = 300;
+        let instance = Arc::new(parser::generate_instance(&mut json_instance, &test_util::config_with(json!({"sheetValuationMode": "cost"}))).unwrap());
+        leftover_valuator::set_power(2.0);
+
+        let solution = test_util::problem_solution(&instance, &[(0, &[(0, 0, 0), (0, 50, 0)]), (1, &[(0, 0, 0)])]);
+        assert_eq!(solution.material_value(), 700 + 300);
+        assert_eq!(solution.material_value(), solution.cost().material_cost);
+        assert_eq!(SendableSolution::new(instance.clone(), &solution).material_value(), 1000);
+
+        //a sheet without parts is not counted, although it is charged in the material cost
+        let solution = test_util::problem_solution(&instance, &[(0, &[(0, 0, 0), (0, 50, 0)]), (1, &[(0, 0, 0)]), (0, &[])]);
+        assert_eq!((solution.material_value(), solution.cost().material_cost), (1000, 1700));
+    }
+
+    #[test]
+    fn waste_attribution_splits_a_sliver_over_the_bordering_parts() {
+        let instance = test_util::instance(&[(95, 50, 2), (100, 50, 1)], &[(100, 100, 2)]);
+        //a 5x50 sliver next to a part, bordering another part along its bottom edge
+        let solution = test_util::problem_solution(&instance, &[(0, &[(0, 0, 0), (1, 0, 50)])]);
+        let attribution = solution.waste_attribution();
+        assert_eq!(attribution.keys().sorted().collect_vec(), vec![&0, &1]);
+        assert!((attribution[&0] - 250.0 * 50.0 / 55.0).abs() < 1e-9);
+        assert!((attribution[&1] - 250.0 * 5.0 / 55.0).abs() < 1e-9);
+        assert!((attribution.values().sum::<f64>() - 250.0).abs() < 1e-9);
+
+        //a sliver bordering a single part is attributed to it entirely, over all layouts
+        let solution = test_util::problem_solution(&instance, &[(0, &[(0, 0, 0), (0, 0, 50)]), (0, &[(1, 0, 0)])]);
+        let attribution = solution.waste_attribution();
+        assert_eq!(attribution.len(), 2);
+        assert!((attribution[&0] - 500.0).abs() < 1e-9);
+        assert!((attribution[&1] - 5000.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,161 @@
+use itertools::Itertools;
+
+use crate::core::insertion::node_blueprint::NodeBlueprint;
+use crate::core::orientation::Orientation;
+
+/// Differences between two solutions (an old and a new one).
+///
+/// Layouts which are structurally identical in both solutions are reported as unchanged.
+/// The parts in all other layouts are compared by their position (layout index, sheettype, coordinates and size):
+/// parts at identical positions in both solutions are not reported,
+/// for the remaining parts of every parttype, as many as possible are reported as moved, the others as added or removed.
+#[derive(Debug, Clone)]
+pub struct SolutionDiff {
+    /// Pairs of (index in old solution, index in new solution) of identical layouts
+    pub unchanged_layouts: Vec<(usize, usize)>,
+    pub added: Vec<Placement>,
+    pub removed: Vec<Placement>,
+    /// Pairs of (old placement, new placement) of the same parttype
+    pub moved: Vec<(Placement, Placement)>,
+}
+
+/// Position of a single part in a solution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    pub layout: usize,
+    pub sheettype_id: usize,
+    pub parttype_id: usize,
+    pub x: u64,
+    pub y: u64,
+    pub width: u64,
+    pub height: u64,
+}
+
+impl SolutionDiff {
+    /// Both arguments contain the sheettype id and cutting pattern of every layout of the solutions
+    pub fn new(old: &[(usize, NodeBlueprint)], new: &[(usize, NodeBlueprint)]) -> Self {
+        //Match structurally identical layouts
+        let mut unchanged_layouts = vec![];
+        let mut new_matched = vec![false; new.len()];
+        for (i, old_layout) in old.iter().enumerate() {
+            let match_index = (0..new.len()).find(|&j| !new_matched[j] && new[j] == *old_layout);
+            if let Some(j) = match_index {
+                new_matched[j] = true;
+                unchanged_layouts.push((i, j));
+            }
+        }
+
+        let old_placements = old.iter().enumerate()
+            .filter(|(i, _)| !unchanged_layouts.iter().any(|(o, _)| o == i))
+            .flat_map(|(i, (sheettype_id, pattern))| Placement::extract(i, *sheettype_id, pattern))
+            .collect_vec();
+        let new_placements = new.iter().enumerate()
+            .filter(|(j, _)| !new_matched[*j])
+            .flat_map(|(j, (sheettype_id, pattern))| Placement::extract(j, *sheettype_id, pattern))
+            .collect_vec();
+
+        //Remove the placements which occur at the same position in both solutions
+        let mut removed = vec![];
+        let mut added = new_placements;
+        for old_placement in old_placements {
+            match added.iter().position(|p| p.same_position(&old_placement)) {
+                Some(index) => {
+                    added.remove(index);
+                }
+                None => removed.push(old_placement),
+            }
+        }
+
+        //Pair up the remaining placements of the same parttype as moved
+        let mut moved = vec![];
+        removed.retain(|old_placement| {
+            match added.iter().position(|p| p.parttype_id == old_placement.parttype_id) {
+                Some(index) => {
+                    moved.push((old_placement.clone(), added.remove(index)));
+                    false
+                }
+                None => true
+            }
+        });
+
+        Self {
+            unchanged_layouts,
+            added,
+            removed,
+            moved,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.moved.is_empty()
+    }
+
+    /// Number of parts which are placed differently: added, removed or moved
+    pub fn n_changed_parts(&self) -> usize {
+        self.added.len() + self.removed.len() + self.moved.len()
+    }
+}
+
+impl Placement {
+    /// Collects the placements of all parts in a cutting pattern
+    pub fn extract(layout: usize, sheettype_id: usize, pattern: &NodeBlueprint) -> Vec<Placement> {
+        let mut placements = vec![];
+        Placement::extract_from_node(layout, sheettype_id, pattern, (0, 0), &mut placements);
+        placements
+    }
+
+    fn extract_from_node(layout: usize, sheettype_id: usize, node: &NodeBlueprint, (x, y): (u64, u64), placements: &mut Vec<Placement>) {
+        if let Some(parttype_id) = node.parttype_id() {
+            placements.push(Placement { layout, sheettype_id, parttype_id, x, y, width: node.width(), height: node.height() });
+        }
+        let (mut child_x, mut child_y) = (x, y);
+        for child in node.children() {
+            Placement::extract_from_node(layout, sheettype_id, child, (child_x, child_y), placements);
+            match node.next_cut_orient() {
+                Orientation::Horizontal => child_y += child.height(),
+                Orientation::Vertical => child_x += child.width(),
+            }
+        }
+    }
+
+    /// Whether both placements are of the same parttype, at the same position in the layout with the same index and sheettype
+    pub fn same_position(&self, other: &Placement) -> bool {
+        self.layout == other.layout && self.sheettype_id == other.sheettype_id && self.parttype_id == other.parttype_id &&
+            self.x == other.x && self.y == other.y &&
+            self.width == other.width && self.height == other.height
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::core::insertion::node_blueprint::NodeBlueprint;
+    use crate::optimization::solutions::solution_diff::{Placement, SolutionDiff};
+    use crate::util::test_util;
+
+    #[test]
+    fn one_moved_part_is_reported_as_a_single_move() {
+        let instance = test_util::instance(&[(50, 50, 1), (50, 50, 1), (20, 20, 1)], &[(100, 100, 2)]);
+        let pattern = |parts: &[(usize, u64, u64)]| {
+            let layout = test_util::layout(&instance, 0, parts);
+            (0, NodeBlueprint::from_node(*layout.top_node_index(), layout.nodes()))
+        };
+        let unchanged = pattern(&[(2, 0, 0)]);
+        let old = [unchanged.clone(), pattern(&[(0, 0, 0), (1, 50, 0)])];
+        let new = [unchanged, pattern(&[(0, 0, 0), (1, 0, 50)])];
+
+        let diff = SolutionDiff::new(&old, &new);
+        assert_eq!(diff.unchanged_layouts, vec![(0, 0)]);
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        assert_eq!(diff.moved.len(), 1);
+        let (from, to) = &diff.moved[0];
+        assert_eq!((from.parttype_id, from.x, from.y), (1, 50, 0));
+        assert_eq!((to.parttype_id, to.x, to.y), (1, 0, 50));
+    }
+
+    #[test]
+    fn placements_in_other_layouts_are_not_at_the_same_position() {
+        let placement = Placement { layout: 0, sheettype_id: 0, parttype_id: 0, x: 0, y: 0, width: 50, height: 50 };
+        assert!(placement.same_position(&placement.clone()));
+        assert!(!placement.same_position(&Placement { layout: 1, ..placement.clone() }));
+    }
+}
@@ -0,0 +1,37 @@
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+use crate::optimization::solutions::solution::Solution;
+
+/// Secondary criteria to rank solutions which are equally good according to the primary cost
+/// (the material cost for complete solutions, the cost comparator for incomplete ones).
+/// Lower values are preferred for all criteria.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Tiebreak {
+    /// Waste concentrated in fewer, larger pieces, see Solution::fragmentation_index
+    MinFragmentation,
+    /// Fewer cuts in total, see Solution::cut_count
+    MinCutCount,
+    /// Shallower nesting of the cutting patterns, see Solution::max_level
+    MinMaxLevel,
+}
+
+impl Tiebreak {
+    pub fn compare<S: Solution + ?Sized>(&self, a: &S, b: &S) -> Ordering {
+        match self {
+            Tiebreak::MinFragmentation => a.fragmentation_index().total_cmp(&b.fragmentation_index()),
+            Tiebreak::MinCutCount => a.cut_count().cmp(&b.cut_count()),
+            Tiebreak::MinMaxLevel => a.max_level().cmp(&b.max_level()),
+        }
+    }
+}
+
+/// Compares two solutions by the tiebreaks in order, the first one which differs decides
+pub fn compare_chain<S: Solution + ?Sized>(tiebreaks: &[Tiebreak], a: &S, b: &S) -> Ordering {
+    tiebreaks.iter()
+        .map(|tiebreak| tiebreak.compare(a, b))
+        .find(|ordering| *ordering != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
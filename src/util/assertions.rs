@@ -5,6 +5,8 @@ use std::rc::Weak;
 use generational_arena::{Arena, Index};
 use itertools::Itertools;
 
+use crate::core::cost::Cost;
+use crate::core::empty_node_order::EmptyNodeOrder;
 use crate::core::entities::layout::Layout;
 use crate::core::entities::node::Node;
 use crate::core::entities::parttype::PartType;
@@ -123,6 +125,7 @@ pub fn nodes_match(n_i_1: &Index, n_i_2: &Index, nodes_1 : &Arena<Node>, nodes_2
         node1.height() != node2.height() ||
         node1.children().len() != node2.children().len() ||
         node1.parttype() != node2.parttype() ||
+        node1.is_unusable() != node2.is_unusable() ||
         node1.next_cut_orient() != node2.next_cut_orient() ||
         node1.parent().is_some() != node2.parent().is_some() {
         return false;
@@ -230,7 +233,7 @@ pub fn insertion_option_cache_is_valid<'a>(problem: &Problem<'a>, ioc: &Insertio
     return true;
 }
 
-pub fn cached_sorted_empty_nodes_correct(nodes: &Arena<Node>, cached_sorted_empty_nodes: &Vec<Index>) -> bool {
+pub fn cached_sorted_empty_nodes_correct(nodes: &Arena<Node>, cached_sorted_empty_nodes: &[Index], empty_node_order: EmptyNodeOrder) -> bool {
     let all_empty_nodes = nodes.iter().filter(|(_i,n)| n.is_empty()).map(|(i,_n)| i).collect_vec();
 
     if all_empty_nodes.len() != cached_sorted_empty_nodes.len() {
@@ -242,11 +245,11 @@ pub fn cached_sorted_empty_nodes_correct(nodes: &Arena<Node>, cached_sorted_empt
         return false;
     }
 
-    //ensure that the sorted list is sorted in descending area
-    let correctly_sorted = cached_sorted_empty_nodes.iter().tuples().all(|(a,b)|{
+    //ensure that the sorted list is sorted in descending order
+    let correctly_sorted = cached_sorted_empty_nodes.iter().tuple_windows().all(|(a,b)|{
         let a = &nodes[*a];
         let b = &nodes[*b];
-        a.area().cmp(&b.area()) != Ordering::Less
+        empty_node_order.compare(a, b) != Ordering::Less
     });
 
     if !correctly_sorted {
@@ -256,6 +259,24 @@ pub fn cached_sorted_empty_nodes_correct(nodes: &Arena<Node>, cached_sorted_empt
     return true;
 }
 
+/// Either no node has a cached position, or all of them have the position found by walking down the tree from the top node
+pub fn cached_positions_correct(nodes: &Arena<Node>, top_node: &Index) -> bool {
+    fn walk(node_i: Index, position: (u64, u64), nodes: &Arena<Node>) -> bool {
+        let node = &nodes[node_i];
+        let (mut x, mut y) = position;
+        node.cached_position() == Some(position) && node.children().iter().all(|&child| {
+            let child_correct = walk(child, (x, y), nodes);
+            match node.next_cut_orient() {
+                Orientation::Horizontal => y += nodes[child].height(),
+                Orientation::Vertical => x += nodes[child].width(),
+            }
+            child_correct
+        })
+    }
+
+    nodes.iter().all(|(_, n)| n.cached_position().is_none()) || walk(*top_node, (0, 0), nodes)
+}
+
 pub fn instance_parttypes_and_sheettypes_ids_correct(parttypes: &Vec<(PartType, usize)>, sheettypes: &Vec<(SheetType, usize)>) -> bool {
     parttypes.iter().enumerate().all(|(i, (p, _qty))| {
         p.id() == i
@@ -300,4 +321,16 @@ pub fn node_arena_valid(nodes: &Arena<Node>, top_node: &Index) -> bool {
     assert!(node_child_parent_relations_valid(nodes, top_node));
 
     true
-}
\ No newline at end of file
+}
+
+/// Whether the cost before plus the delta equals the cost after, the cached cost of a layout is maintained this way
+pub fn cost_delta_reconciles(cost_before: &Cost, cost_delta: &Cost, cost_after: &Cost) -> bool {
+    //leftover values are floating point sums, allow for a small relative rounding error
+    let expected_leftover_value = cost_before.leftover_value + cost_delta.leftover_value;
+    let tolerance = 1e-4 * f32::max(1.0, f32::max(cost_before.leftover_value.abs(), cost_after.leftover_value.abs()));
+
+    cost_before.material_cost + cost_delta.material_cost == cost_after.material_cost &&
+        cost_before.part_area_excluded + cost_delta.part_area_excluded == cost_after.part_area_excluded &&
+        cost_before.part_area_included + cost_delta.part_area_included == cost_after.part_area_included &&
+        (expected_leftover_value - cost_after.leftover_value).abs() <= tolerance
+}
@@ -0,0 +1,226 @@
+use std::sync::Arc;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use itertools::Itertools;
+
+use crate::core::cost::Cost;
+use crate::core::empty_node_order::EmptyNodeOrder;
+use crate::core::entities::layout::Layout;
+use crate::core::entities::parttype::PartType;
+use crate::core::entities::placed_part::PlacedPart;
+use crate::core::entities::sheettype::SheetType;
+use crate::core::insertion::insertion_blueprint::InsertionBlueprint;
+use crate::core::insertion::node_blueprint::NodeBlueprint;
+use crate::core::kerf::Kerf;
+use crate::core::layout_index::LayoutIndex;
+use crate::core::leftover_valuator;
+use crate::core::orientation::Orientation;
+use crate::core::rotation::Rotation;
+use crate::io::json_format::{JsonInstance, JsonPartType, JsonSheetType};
+use crate::optimization::config::Config;
+use crate::optimization::gdrr::GDRR;
+use crate::optimization::instance::Instance;
+use crate::optimization::problem::Problem;
+use crate::optimization::sol_collectors::local_sol_collector::LocalSolCollector;
+use crate::optimization::rr::cache_updates::IOCUpdates;
+use crate::optimization::solutions::problem_solution::ProblemSolution;
+use crate::optimization::solutions::sendable_solution::SendableSolution;
+use crate::util::messages::{SolutionReportMessage, SyncMessage};
+
+//Small instances, layouts and configs shared by the unit tests
+
+pub fn parttype(id: usize, width: u64, height: u64) -> PartType {
+    PartType::new(id, width, height, None, None, 0.0, Kerf::default())
+}
+
+pub fn sheettype(id: usize, width: u64, height: u64) -> SheetType {
+    SheetType::new(id, width, height, width * height, None, u8::MAX, Kerf::default())
+}
+
+/// Instance with parts (width, height, demand) and sheets (width, height, stock), ids in order
+pub fn instance(parts: &[(u64, u64, usize)], sheets: &[(u64, u64, usize)]) -> Instance {
+    instance_with_kerf(parts, sheets, Kerf::default())
+}
+
+/// Also sets the valuation power of the calling (test) thread to 2, as the search would
+pub fn instance_with_kerf(parts: &[(u64, u64, usize)], sheets: &[(u64, u64, usize)], kerf: Kerf) -> Instance {
+    leftover_valuator::set_power(2.0);
+    let parts = parts.iter().enumerate()
+        .map(|(id, &(w, h, demand))| (PartType::new(id, w, h, None, None, 0.0, kerf), demand))
+        .collect();
+    let sheets = sheets.iter().enumerate()
+        .map(|(id, &(w, h, stock))| (SheetType::new(id, w, h, w * h, None, u8::MAX, kerf), stock))
+        .collect();
+    Instance::new(parts, sheets, 0, false)
+}
+
+/// JSON instance with parts (length, height, demand) and sheets (length, height, stock), valued by their area
+pub fn json_instance(parts: &[(u64, u64, usize)], sheets: &[(u64, u64, usize)]) -> JsonInstance {
+    JsonInstance {
+        name: "test".to_string(),
+        unit: None,
+        sheettypes: sheets.iter().map(|&(length, height, stock)| JsonSheetType {
+            length, height, stock: Some(stock), cost: length * height, density: None, corner_cut: None, usable_regions: None, unit: None, margins: None, reference: None,
+        }).collect(),
+        parttypes: parts.iter().map(|&(length, height, demand)| JsonPartType {
+            length, height, demand, value: length * height, preferred_rotation: None, priority: None, half_turn_only: None, composite: None, unit: None, edge_affinity: None, urgency: None, reference: None,
+        }).collect(),
+    }
+}
+
+/// Parts (parttype id, x, y) placed in their default rotation
+pub type Parts = [(usize, u64, u64)];
+
+/// Layout on the sheettype with the parts, the positions include the kerf of the preceding parts.
+/// The nodes are the strips between all cuts along the edges of the parts which no part straddles, the first cuts are horizontal if possible.
+pub fn layout<'a>(instance: &'a Instance, sheettype_id: usize, parts: &Parts) -> Layout<'a> {
+    layout_with_id(0, instance, sheettype_id, parts)
+}
+
+/// Problem with a layout for every sheettype id and list of parts, see layout. The layouts are registered in order.
+pub fn problem<'a>(instance: &'a Instance, layouts: &[(usize, &Parts)]) -> Problem<'a> {
+    let mut problem = Problem::new(instance, EmptyNodeOrder::default());
+    for &(sheettype_id, parts) in layouts {
+        let layout = layout_with_id(problem.next_layout_id(), instance, sheettype_id, parts);
+        problem.register_layout(layout);
+    }
+    problem
+}
+
+/// Solution of the problem with the layouts, see problem
+pub fn problem_solution<'a>(instance: &'a Instance, layouts: &[(usize, &Parts)]) -> ProblemSolution<'a> {
+    problem(instance, layouts).create_solution(&None, None)
+}
+
+fn layout_with_id<'a>(id: usize, instance: &'a Instance, sheettype_id: usize, parts: &Parts) -> Layout<'a> {
+    let sheettype = instance.get_sheettype(sheettype_id);
+    let rects = parts.iter()
+        .map(|&(id, x, y)| (x, y, instance.get_parttype(id).size().width(), instance.get_parttype(id).size().height()))
+        .collect_vec();
+    let region = (0, 0, sheettype.layout_size().width(), sheettype.layout_size().height());
+    let all_parts = (0..parts.len()).collect_vec();
+    let orientation = match strips(region, &rects, &all_parts, Orientation::Horizontal).len() {
+        1 => Orientation::Vertical,
+        _ => Orientation::Horizontal,
+    };
+    let mut layout = Layout::new(id, sheettype, orientation, EmptyNodeOrder::default());
+    if parts.is_empty() {
+        return layout;
+    }
+
+    //the top node can have a single child, in which the first cuts run in the other direction
+    let top_strips = strips(region, &rects, &all_parts, orientation);
+    let replacements = match top_strips.len() {
+        1 => vec![blueprint(instance, parts, region, &rects, &all_parts, orientation.rotate())],
+        _ => top_strips.into_iter()
+            .map(|(strip, strip_parts)| blueprint(instance, parts, strip, &rects, &strip_parts, orientation.rotate()))
+            .collect_vec(),
+    };
+    let placeholder = layout.sorted_empty_nodes()[0];
+    let layout_i = LayoutIndex::Empty(0);
+    let insertion = InsertionBlueprint::new(layout_i, placeholder, replacements, instance.get_parttype(parts[0].0), Cost::empty(), layout.nodes()[placeholder].level());
+    layout.implement_insertion_blueprint(&insertion, instance, &mut IOCUpdates::new(layout_i)).unwrap();
+    layout
+}
+
+/// The part placed at (x, y) of the sheet in its default rotation
+pub fn placed(instance: &Instance, parttype_id: usize, x: u64, y: u64) -> PlacedPart {
+    let size = instance.get_parttype(parttype_id).size();
+    PlacedPart { parttype_id, x, y, width: size.width(), height: size.height(), rotation: Rotation::Default }
+}
+
+type Rect = (u64, u64, u64, u64);
+
+/// (start, end) of the rectangle along the axis in which the cuts of the orientation separate rectangles
+fn span(&(x, y, width, height): &Rect, orientation: Orientation) -> (u64, u64) {
+    match orientation {
+        Orientation::Horizontal => (y, y + height),
+        Orientation::Vertical => (x, x + width),
+    }
+}
+
+/// The strips of the region between the cuts of the orientation along part edges which no part straddles, with the parts they contain
+fn strips(region: Rect, rects: &[Rect], parts: &[usize], orientation: Orientation) -> Vec<(Rect, Vec<usize>)> {
+    let (start, end) = span(&region, orientation);
+    let cuts = parts.iter()
+        .flat_map(|&i| { let (a, b) = span(&rects[i], orientation); [a, b] })
+        .filter(|&c| c > start && c < end)
+        .filter(|&c| parts.iter().all(|&i| { let (a, b) = span(&rects[i], orientation); c <= a || c >= b }))
+        .sorted_unstable()
+        .dedup();
+
+    [start].into_iter().chain(cuts).chain([end])
+        .tuple_windows()
+        .map(|(a, b)| {
+            let strip = match orientation {
+                Orientation::Horizontal => (region.0, a, region.2, b - a),
+                Orientation::Vertical => (a, region.1, b - a, region.3),
+            };
+            let strip_parts = parts.iter().copied().filter(|&i| { let (p_a, p_b) = span(&rects[i], orientation); p_a >= a && p_b <= b }).collect_vec();
+            (strip, strip_parts)
+        })
+        .collect_vec()
+}
+
+fn blueprint(instance: &Instance, parts: &Parts, region: Rect, rects: &[Rect], region_parts: &[usize], orientation: Orientation) -> NodeBlueprint {
+    match region_parts {
+        [] => return NodeBlueprint::new(region.2, region.3, None, orientation),
+        [i] if rects[*i] == region => return NodeBlueprint::new(region.2, region.3, Some(instance.get_parttype(parts[*i].0)), orientation),
+        _ => {}
+    }
+    let strips = strips(region, rects, region_parts, orientation);
+    assert!(strips.len() > 1, "the parts in {:?} cannot be separated by guillotine cuts", region);
+    let mut node = NodeBlueprint::new(region.2, region.3, None, orientation);
+    for (strip, strip_parts) in strips {
+        node.add_child(blueprint(instance, parts, strip, rects, &strip_parts, orientation.rotate()));
+    }
+    node
+}
+
+/// Local solution collector which is not connected to a global one, the channels need to outlive it
+pub fn local_sol_collector<'a>(instance: &Arc<Instance>) -> (LocalSolCollector<'a>, Sender<SyncMessage>, Receiver<SolutionReportMessage>) {
+    let (tx_sync, rx_sync) = channel();
+    let (tx_solution_report, rx_solution_report) = channel();
+    (LocalSolCollector::new(instance.clone(), rx_sync, tx_solution_report, crate::COST_COMPARATOR), tx_sync, rx_solution_report)
+}
+
+/// Single threaded config with a limited number of iterations, with the fields of `overrides` (camelCase, as in a config file) replaced
+pub fn config_with(overrides: serde_json::Value) -> Config {
+    let mut config = serde_json::json!({
+        "maxRRIterations": 300,
+        "nThreads": 1,
+        "rotationAllowed": true,
+        "avgNodesRemoved": 6,
+        "blinkRate": 0.01,
+        "leftoverValuationPower": 2,
+        "historyLength": 100,
+        "sheetValuationMode": "area"
+    });
+    config.as_object_mut().unwrap().extend(overrides.as_object().unwrap().clone());
+    serde_json::from_value(config).unwrap()
+}
+
+pub fn config() -> Config {
+    config_with(serde_json::json!({}))
+}
+
+/// Best solution sent to the receiver of a local solution collector, which is incomplete if no complete solution was sent
+pub fn best_solution(rx_solution_report: &Receiver<SolutionReportMessage>) -> Option<SendableSolution> {
+    //the last solution sent is the best one of its kind, and any complete solution is better than the incomplete ones
+    rx_solution_report.try_iter()
+        .filter_map(|message| match message {
+            SolutionReportMessage::NewCompleteSolution(_, solution) => Some((true, solution)),
+            SolutionReportMessage::NewIncompleteSolution(_, solution) => Some((false, solution)),
+            _ => None
+        })
+        .max_by_key(|(complete, _)| *complete)
+        .map(|(_, solution)| solution)
+}
+
+/// Best solution of a single threaded search, see best_solution
+pub fn solve(instance: Arc<Instance>, config: &Config) -> SendableSolution {
+    let (collector, _tx_sync, rx_solution_report) = local_sol_collector(&instance);
+    let mut gdrr = GDRR::new(&instance, config, collector);
+    gdrr.lahc();
+    best_solution(&rx_solution_report).expect("no solution found")
+}
@@ -0,0 +1,63 @@
+//! The log records of the search, in a test binary of their own as the logger is global to the process
+
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::channel;
+
+use gdrr_2bp::core::entities::parttype::PartType;
+use gdrr_2bp::core::entities::sheettype::SheetType;
+use gdrr_2bp::core::kerf::Kerf;
+use gdrr_2bp::optimization::config::Config;
+use gdrr_2bp::optimization::gdrr::GDRR;
+use gdrr_2bp::optimization::instance::Instance;
+use gdrr_2bp::optimization::sol_collectors::local_sol_collector::LocalSolCollector;
+
+static RECORDS: Mutex<Vec<(log::Level, String)>> = Mutex::new(vec![]);
+
+struct RecordingLogger;
+
+impl log::Log for RecordingLogger {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        RECORDS.lock().unwrap().push((record.level(), record.args().to_string()));
+    }
+
+    fn flush(&self) {}
+}
+
+#[test]
+fn moves_and_improvements_are_logged() {
+    static LOGGER: RecordingLogger = RecordingLogger;
+    log::set_logger(&LOGGER).unwrap();
+    log::set_max_level(log::LevelFilter::Trace);
+
+    let parts = [(40, 30, 5), (25, 60, 3), (70, 20, 2)].iter().enumerate()
+        .map(|(id, &(w, h, demand))| (PartType::new(id, w, h, None, None, 0.0, Kerf::default()), demand))
+        .collect();
+    let sheets = vec![(SheetType::new(0, 100, 100, 100 * 100, None, u8::MAX, Kerf::default()), 10)];
+    let instance = Arc::new(Instance::new(parts, sheets, 0, false));
+    let config: Config = serde_json::from_value(serde_json::json!({
+        "maxRRIterations": 50,
+        "nThreads": 1,
+        "rotationAllowed": true,
+        "avgNodesRemoved": 6,
+        "blinkRate": 0.01,
+        "leftoverValuationPower": 2,
+        "historyLength": 100,
+        "sheetValuationMode": "area"
+    })).unwrap();
+
+    let (_tx_sync, rx_sync) = channel();
+    let (tx_solution_report, _rx_solution_report) = channel();
+    let local_sol_collector = LocalSolCollector::new(instance.clone(), rx_sync, tx_solution_report, gdrr_2bp::COST_COMPARATOR);
+    let stats = GDRR::new(&instance, &config, local_sol_collector).lahc();
+
+    let records = RECORDS.lock().unwrap();
+    let moves = records.iter().filter(|(level, message)| *level == log::Level::Trace && message.starts_with("move ")).collect::<Vec<_>>();
+    assert_eq!(moves.len(), stats.n_iterations);
+    assert_eq!(moves.iter().filter(|(_, message)| message.contains("accepted")).count(), stats.n_accepted);
+    let improvements = records.iter().filter(|(level, message)| *level == log::Level::Debug && message.starts_with("improvement ")).count();
+    assert_eq!(improvements, stats.n_improved);
+}